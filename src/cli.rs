@@ -0,0 +1,550 @@
+use crate::core::backtest::{run_backtest, MarketData};
+use crate::core::config::{load_settings, CONFIG_PATH, CREDENTIALS_PATH};
+use crate::core::config_validation::{ensure_valid, validate_settings_files};
+use crate::core::exchanges::common::{CurrencyPair, ExchangeAccountId};
+use crate::core::exchanges::events::{ExchangeEvent, CHANNEL_MAX_EVENTS_COUNT};
+use crate::core::exchanges::general::exchange::Exchange;
+use crate::core::exchanges::general::exchange_creation::{
+    create_exchange_without_connection, create_timeout_manager,
+};
+use crate::core::lifecycle::application_manager::ApplicationManager;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::lifecycle::launcher::{launch_trading_engine, EngineBuildConfig, InitSettings};
+use crate::core::settings::{AppSettings, BaseStrategySettings};
+use crate::strategies::disposition_strategy::DispositionStrategy;
+use anyhow::{anyhow, bail, Context, Result};
+use core::fmt::Debug;
+use futures::future::join_all;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+pub const USAGE: &str = "\
+Usage: mmb [COMMAND] [OPTIONS]
+
+Commands:
+    run                Launch trading engine (default command)
+    validate-config    Check config and credentials without engine launching
+    list-symbols       Print symbols metadata received from exchanges
+    open-orders        Print open orders on exchanges
+    cancel-all         Cancel open orders on exchanges
+    backtest           Run strategy on historical data
+    help               Print this message
+
+Options:
+    --config <PATH>             Path to config file [default: config.toml]
+    --credentials <PATH>        Path to credentials file [default: credentials.toml]
+    --dry-run                   Launch engine without sending orders to exchanges (run)
+    --exchange <ID>             Exchange account id like Binance0, all configured exchanges by default
+                                (list-symbols, open-orders, cancel-all)
+    --currency-pair <PAIR>      Currency pair like eos/btc, pairs of open orders by default (cancel-all)
+    --market-data <PATH>        Recorded market data in JSON Lines format, required (backtest)
+    --speed <N>                 Replay market data N times faster than it was recorded [default: 1]
+                                (backtest)
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsPaths {
+    pub config_path: String,
+    pub credentials_path: String,
+}
+
+impl Default for SettingsPaths {
+    fn default() -> Self {
+        SettingsPaths {
+            config_path: CONFIG_PATH.to_owned(),
+            credentials_path: CREDENTIALS_PATH.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run {
+        paths: SettingsPaths,
+        dry_run: bool,
+    },
+    ValidateConfig {
+        paths: SettingsPaths,
+    },
+    ListSymbols {
+        paths: SettingsPaths,
+        exchange_account_id: Option<ExchangeAccountId>,
+    },
+    OpenOrders {
+        paths: SettingsPaths,
+        exchange_account_id: Option<ExchangeAccountId>,
+    },
+    CancelAll {
+        paths: SettingsPaths,
+        exchange_account_id: Option<ExchangeAccountId>,
+        currency_pair: Option<CurrencyPair>,
+    },
+    Backtest {
+        paths: SettingsPaths,
+        market_data_path: String,
+        speed: u32,
+    },
+    Help,
+}
+
+/// Parse command line arguments without program name
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let mut args = args.into_iter();
+
+    let mut command_name = None;
+    let mut paths = SettingsPaths::default();
+    let mut dry_run = false;
+    let mut exchange_account_id = None;
+    let mut currency_pair = None;
+    let mut market_data_path = None;
+    let mut speed = None;
+    while let Some(arg) = args.next() {
+        let mut next_value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("Option {} requires a value", arg))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--config" => paths.config_path = next_value()?,
+            "--credentials" => paths.credentials_path = next_value()?,
+            "--dry-run" => dry_run = true,
+            "--exchange" => {
+                let value = next_value()?;
+                let id = value
+                    .parse::<ExchangeAccountId>()
+                    .map_err(|err| anyhow!("Invalid exchange account id {}: {:?}", value, err))?;
+                exchange_account_id = Some(id);
+            }
+            "--currency-pair" => currency_pair = Some(parse_currency_pair(&next_value()?)?),
+            "--market-data" => market_data_path = Some(next_value()?),
+            "--speed" => {
+                let value = next_value()?;
+                match value.parse::<u32>() {
+                    Ok(value) if value > 0 => speed = Some(value),
+                    _ => bail!("Invalid speed {}: expected positive integer", value),
+                }
+            }
+            _ if arg.starts_with('-') => bail!("Unknown option {}", arg),
+            _ => match command_name {
+                None => command_name = Some(arg),
+                Some(_) => bail!("Unexpected argument {}", arg),
+            },
+        }
+    }
+
+    let command_name = command_name.unwrap_or_else(|| "run".to_owned());
+    let check_option = |is_set: bool, option: &str| {
+        if is_set {
+            bail!(
+                "Option {} isn't supported by command {}",
+                option,
+                command_name
+            )
+        }
+        Ok(())
+    };
+
+    if command_name != "run" {
+        check_option(dry_run, "--dry-run")?;
+    }
+    if !matches!(
+        command_name.as_str(),
+        "list-symbols" | "open-orders" | "cancel-all"
+    ) {
+        check_option(exchange_account_id.is_some(), "--exchange")?;
+    }
+    if command_name != "cancel-all" {
+        check_option(currency_pair.is_some(), "--currency-pair")?;
+    }
+    if command_name != "backtest" {
+        check_option(market_data_path.is_some(), "--market-data")?;
+        check_option(speed.is_some(), "--speed")?;
+    }
+
+    let command = match command_name.as_str() {
+        "run" => Command::Run { paths, dry_run },
+        "validate-config" => Command::ValidateConfig { paths },
+        "list-symbols" => Command::ListSymbols {
+            paths,
+            exchange_account_id,
+        },
+        "open-orders" => Command::OpenOrders {
+            paths,
+            exchange_account_id,
+        },
+        "cancel-all" => Command::CancelAll {
+            paths,
+            exchange_account_id,
+            currency_pair,
+        },
+        "backtest" => Command::Backtest {
+            paths,
+            market_data_path: market_data_path
+                .ok_or_else(|| anyhow!("Option --market-data is required by command backtest"))?,
+            speed: speed.unwrap_or(1),
+        },
+        "help" => Command::Help,
+        _ => bail!("Unknown command {}", command_name),
+    };
+
+    Ok(command)
+}
+
+fn parse_currency_pair(value: &str) -> Result<CurrencyPair> {
+    match value.split('/').collect_vec()[..] {
+        [base, quote] if !base.is_empty() && !quote.is_empty() => {
+            Ok(CurrencyPair::from_codes(base.into(), quote.into()))
+        }
+        _ => bail!(
+            "Invalid currency pair {}: expected format like eos/btc",
+            value
+        ),
+    }
+}
+
+/// Execute command. Returns `false` if command failed in a way that is already reported to user,
+/// e.g. config is invalid
+pub async fn execute_command<'a, TStrategySettings>(
+    command: Command,
+    engine_config: &EngineBuildConfig,
+    build_strategy: impl Fn(&AppSettings<TStrategySettings>) -> Box<dyn DispositionStrategy + 'static>,
+) -> Result<bool>
+where
+    TStrategySettings:
+        BaseStrategySettings + Clone + Debug + Deserialize<'a> + Serialize + Send + Sync + 'static,
+{
+    match command {
+        Command::Help => print!("{}", USAGE),
+        Command::ValidateConfig { paths } => {
+            let errors = validate_settings_files::<TStrategySettings>(
+                &paths.config_path,
+                &paths.credentials_path,
+                &engine_config.supported_exchange_ids(),
+            );
+            if !errors.is_empty() {
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+                return Ok(false);
+            }
+
+            println!("Config is valid");
+        }
+        Command::Run { paths, dry_run } => {
+            let mut settings = load_valid_settings::<TStrategySettings>(&paths, engine_config)?;
+            settings.core.forced_dry_run = dry_run;
+
+            let init_settings = InitSettings::Directly(settings);
+            let engine =
+                launch_trading_engine(engine_config, init_settings, build_strategy).await?;
+            engine.run().await;
+        }
+        Command::ListSymbols {
+            paths,
+            exchange_account_id,
+        } => {
+            let tools = ExchangeTools::create::<TStrategySettings>(
+                &paths,
+                engine_config,
+                exchange_account_id,
+            )
+            .await?;
+            for exchange in &tools.exchanges {
+                for symbol in exchange.get_supported_symbols() {
+                    println!("{} {:?}", exchange.exchange_account_id, symbol);
+                }
+            }
+        }
+        Command::OpenOrders {
+            paths,
+            exchange_account_id,
+        } => {
+            let tools = ExchangeTools::create::<TStrategySettings>(
+                &paths,
+                engine_config,
+                exchange_account_id,
+            )
+            .await?;
+            for exchange in &tools.exchanges {
+                let orders = exchange.get_open_orders(false).await.with_context(|| {
+                    format!(
+                        "Unable to get open orders on {}",
+                        exchange.exchange_account_id
+                    )
+                })?;
+                for order in orders {
+                    println!(
+                        "{} {}",
+                        exchange.exchange_account_id,
+                        serde_json::to_string(&order)?
+                    );
+                }
+            }
+        }
+        Command::CancelAll {
+            paths,
+            exchange_account_id,
+            currency_pair,
+        } => {
+            let tools = ExchangeTools::create::<TStrategySettings>(
+                &paths,
+                engine_config,
+                exchange_account_id,
+            )
+            .await?;
+            for exchange in &tools.exchanges {
+                cancel_all_orders(exchange, currency_pair.clone()).await?;
+            }
+        }
+        Command::Backtest {
+            paths,
+            market_data_path,
+            speed,
+        } => {
+            let settings = load_valid_settings::<TStrategySettings>(&paths, engine_config)?;
+            let market_data = MarketData::load(&market_data_path)?;
+
+            let results =
+                run_backtest(settings, engine_config, market_data, speed, build_strategy).await?;
+            for result in results {
+                let trading_result = result.trading_result;
+                println!(
+                    "{} {}: orders {}, fills {}, bought {}, sold {}, base change {}, quote change {}, commission {}, pnl {}",
+                    result.exchange_account_id,
+                    result.currency_pair,
+                    trading_result.created_orders_count,
+                    trading_result.fills_count,
+                    trading_result.bought_amount,
+                    trading_result.sold_amount,
+                    trading_result.base_balance_change,
+                    trading_result.quote_balance_change,
+                    trading_result.commission,
+                    trading_result
+                        .pnl()
+                        .map_or_else(|| "unknown".to_owned(), |pnl| pnl.to_string()),
+                );
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn load_valid_settings<'a, TStrategySettings>(
+    paths: &SettingsPaths,
+    engine_config: &EngineBuildConfig,
+) -> Result<AppSettings<TStrategySettings>>
+where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    ensure_valid(validate_settings_files::<TStrategySettings>(
+        &paths.config_path,
+        &paths.credentials_path,
+        &engine_config.supported_exchange_ids(),
+    ))?;
+
+    load_settings(&paths.config_path, &paths.credentials_path)
+}
+
+async fn cancel_all_orders(
+    exchange: &Arc<Exchange>,
+    currency_pair: Option<CurrencyPair>,
+) -> Result<()> {
+    let currency_pairs = match currency_pair {
+        Some(currency_pair) => vec![currency_pair],
+        None => exchange
+            .get_open_orders(false)
+            .await?
+            .into_iter()
+            .map(|order| order.currency_pair)
+            .unique()
+            .collect_vec(),
+    };
+
+    for currency_pair in currency_pairs {
+        exchange
+            .cancel_all_orders(currency_pair.clone())
+            .await
+            .with_context(|| {
+                format!(
+                    "Unable to cancel orders on {} for {}",
+                    exchange.exchange_account_id, currency_pair
+                )
+            })?;
+        println!(
+            "{} orders for {} are canceled",
+            exchange.exchange_account_id, currency_pair
+        );
+    }
+
+    Ok(())
+}
+
+/// Exchanges with metadata for one-off requests without trading engine launching
+struct ExchangeTools {
+    exchanges: Vec<Arc<Exchange>>,
+    // Events about orders are sent to channel and it is failed without receivers
+    _events_receiver: broadcast::Receiver<ExchangeEvent>,
+}
+
+impl ExchangeTools {
+    async fn create<'a, TStrategySettings>(
+        paths: &SettingsPaths,
+        engine_config: &EngineBuildConfig,
+        exchange_account_id: Option<ExchangeAccountId>,
+    ) -> Result<Self>
+    where
+        TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+    {
+        let settings = load_valid_settings::<TStrategySettings>(paths, engine_config)?;
+        let exchanges_settings = settings
+            .core
+            .exchanges
+            .iter()
+            .filter(|x| match &exchange_account_id {
+                Some(id) => &x.exchange_account_id == id,
+                None => true,
+            })
+            .collect_vec();
+        if exchanges_settings.is_empty() {
+            match exchange_account_id {
+                Some(id) => bail!("Exchange {} isn't specified in config", id),
+                None => bail!("There are no exchanges in config"),
+            }
+        }
+
+        let application_manager = ApplicationManager::new(CancellationToken::new());
+        let (events_sender, events_receiver) = broadcast::channel(CHANNEL_MAX_EVENTS_COUNT);
+        let timeout_manager = create_timeout_manager(&settings.core, engine_config);
+        let exchanges = join_all(exchanges_settings.into_iter().map(|x| {
+            create_exchange_without_connection(
+                x,
+                engine_config,
+                events_sender.clone(),
+                application_manager.clone(),
+                timeout_manager.clone(),
+            )
+        }))
+        .await;
+
+        Ok(ExchangeTools {
+            exchanges,
+            _events_receiver: events_receiver,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command> {
+        parse_args(args.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn run_by_default() {
+        assert_eq!(
+            parse(&[]).expect("in test"),
+            Command::Run {
+                paths: SettingsPaths::default(),
+                dry_run: false
+            }
+        );
+        assert_eq!(
+            parse(&["--dry-run", "--config", "my_config.toml"]).expect("in test"),
+            Command::Run {
+                paths: SettingsPaths {
+                    config_path: "my_config.toml".to_owned(),
+                    credentials_path: CREDENTIALS_PATH.to_owned(),
+                },
+                dry_run: true
+            }
+        );
+    }
+
+    #[test]
+    fn cancel_all_with_options() {
+        assert_eq!(
+            parse(&[
+                "cancel-all",
+                "--exchange",
+                "Binance0",
+                "--currency-pair",
+                "EOS/BTC",
+                "--credentials",
+                "creds.toml"
+            ])
+            .expect("in test"),
+            Command::CancelAll {
+                paths: SettingsPaths {
+                    config_path: CONFIG_PATH.to_owned(),
+                    credentials_path: "creds.toml".to_owned(),
+                },
+                exchange_account_id: Some("Binance0".parse().expect("in test")),
+                currency_pair: Some(CurrencyPair::from_codes("eos".into(), "btc".into())),
+            }
+        );
+    }
+
+    #[test]
+    fn backtest_with_options() {
+        assert_eq!(
+            parse(&[
+                "backtest",
+                "--market-data",
+                "eos_btc.jsonl",
+                "--speed",
+                "10"
+            ])
+            .expect("in test"),
+            Command::Backtest {
+                paths: SettingsPaths::default(),
+                market_data_path: "eos_btc.jsonl".to_owned(),
+                speed: 10,
+            }
+        );
+        assert_eq!(
+            parse(&["backtest", "--market-data", "eos_btc.jsonl"]).expect("in test"),
+            Command::Backtest {
+                paths: SettingsPaths::default(),
+                market_data_path: "eos_btc.jsonl".to_owned(),
+                speed: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(parse(&["help"]).expect("in test"), Command::Help);
+        assert_eq!(
+            parse(&["open-orders", "-h"]).expect("in test"),
+            Command::Help
+        );
+    }
+
+    #[test]
+    fn invalid_args() {
+        let errors = [
+            parse(&["unknown"]),
+            parse(&["run", "run"]),
+            parse(&["run", "--unknown"]),
+            parse(&["run", "--config"]),
+            parse(&["validate-config", "--dry-run"]),
+            parse(&["run", "--exchange", "Binance0"]),
+            parse(&["open-orders", "--exchange", "Binance"]),
+            parse(&["open-orders", "--currency-pair", "eos/btc"]),
+            parse(&["cancel-all", "--currency-pair", "eosbtc"]),
+            parse(&["backtest"]),
+            parse(&["backtest", "--market-data", "eos_btc.jsonl", "--speed", "0"]),
+            parse(&["run", "--market-data", "eos_btc.jsonl"]),
+        ];
+
+        for error in errors.iter() {
+            assert!(error.is_err(), "{:?}", error);
+        }
+    }
+}
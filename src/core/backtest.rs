@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use core::fmt::Debug;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::core::exchanges::common::{Amount, CurrencyPair, ExchangeAccountId, Price};
+use crate::core::exchanges::simulated::simulated::{
+    SimulatedBuilder, SimulatedMarket, SimulatedSymbol, SimulatedTradingResult,
+};
+use crate::core::exchanges::traits::ExchangeClientBuilder;
+use crate::core::lifecycle::launcher::{launch_trading_engine, EngineBuildConfig, InitSettings};
+use crate::core::order_book::order_book_data::OrderBookData;
+use crate::core::settings::{AppSettings, BaseStrategySettings};
+use crate::core::DateTime;
+use crate::strategies::disposition_strategy::DispositionStrategy;
+
+/// Time for strategy to react on last replayed order book before shutdown
+const SETTLING_TIME: Duration = Duration::from_secs(1);
+
+/// Line of recorded market data file in JSON Lines format.
+/// Symbols should be declared before order books of their currency pairs, e.g.
+/// {"type":"symbol","base":"eos","quote":"btc","price_tick":"0.0000001","amount_tick":"0.01"}
+/// {"type":"order_book","time":"2021-06-01T00:00:00Z","currency_pair":"eos/btc","asks":[["0.0001","10"]],"bids":[["0.00009","5"]]}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketDataRecord {
+    Symbol(SimulatedSymbol),
+    OrderBook(OrderBookRecord),
+}
+
+/// Snapshot of order book at moment of recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderBookRecord {
+    pub time: DateTime,
+    pub currency_pair: CurrencyPair,
+    pub asks: Vec<(Price, Amount)>,
+    pub bids: Vec<(Price, Amount)>,
+}
+
+impl OrderBookRecord {
+    fn to_order_book_data(&self) -> OrderBookData {
+        OrderBookData::new(
+            self.asks.iter().copied().collect(),
+            self.bids.iter().copied().collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarketData {
+    pub symbols: Vec<SimulatedSymbol>,
+    pub order_books: Vec<OrderBookRecord>,
+}
+
+impl MarketData {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read market data file {}", path))?;
+        Self::parse(&content).with_context(|| format!("Invalid market data file {}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut market_data = MarketData::default();
+        let mut currency_pairs = HashSet::new();
+        for (index, line) in content.lines().enumerate() {
+            let line_number = index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record: MarketDataRecord = serde_json::from_str(line)
+                .with_context(|| format!("Unable to parse line {}", line_number))?;
+            match record {
+                MarketDataRecord::Symbol(symbol) => {
+                    let _ = currency_pairs.insert(symbol.currency_pair());
+                    market_data.symbols.push(symbol);
+                }
+                MarketDataRecord::OrderBook(order_book) => {
+                    if !currency_pairs.contains(&order_book.currency_pair) {
+                        bail!(
+                            "Order book on line {} has undeclared symbol {}",
+                            line_number,
+                            order_book.currency_pair
+                        );
+                    }
+                    if let Some(previous) = market_data.order_books.last() {
+                        if order_book.time < previous.time {
+                            bail!(
+                                "Order book on line {} is earlier than previous one",
+                                line_number
+                            );
+                        }
+                    }
+                    market_data.order_books.push(order_book);
+                }
+            }
+        }
+
+        if market_data.order_books.is_empty() {
+            bail!("There are no order books");
+        }
+
+        Ok(market_data)
+    }
+}
+
+/// Trading results of one currency pair on one simulated exchange account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacktestResult {
+    pub exchange_account_id: ExchangeAccountId,
+    pub currency_pair: CurrencyPair,
+    pub trading_result: SimulatedTradingResult,
+}
+
+/// Runs strategy on simulated exchanges replaying the same market data for every configured
+/// exchange account. Replay is `speed` times faster than market data was recorded
+pub async fn run_backtest<'a, TStrategySettings>(
+    settings: AppSettings<TStrategySettings>,
+    engine_config: &EngineBuildConfig,
+    market_data: MarketData,
+    speed: u32,
+    build_strategy: impl Fn(&AppSettings<TStrategySettings>) -> Box<dyn DispositionStrategy + 'static>,
+) -> Result<Vec<BacktestResult>>
+where
+    TStrategySettings:
+        BaseStrategySettings + Clone + Debug + Deserialize<'a> + Serialize + Send + Sync + 'static,
+{
+    if speed == 0 {
+        bail!("Backtest speed should be positive");
+    }
+
+    // Exchange ids stay the same as in config, but all of them trade on simulated markets
+    let simulated_builder = SimulatedBuilder::new(market_data.symbols.clone());
+    let simulated_config = EngineBuildConfig {
+        supported_exchange_clients: engine_config
+            .supported_exchange_ids()
+            .into_iter()
+            .map(|exchange_id| {
+                let builder = Box::new(simulated_builder.clone()) as Box<dyn ExchangeClientBuilder>;
+                (exchange_id, builder)
+            })
+            .collect(),
+    };
+
+    let mut settings = settings;
+    settings.core.dry_run = false;
+
+    let engine = launch_trading_engine(
+        &simulated_config,
+        InitSettings::Directly(settings),
+        build_strategy,
+    )
+    .await?;
+    let context = engine.context();
+    let markets = simulated_builder.markets();
+
+    info!(
+        "Backtest started with {} order books",
+        market_data.order_books.len()
+    );
+    replay(&markets, &market_data.order_books, speed, || {
+        context
+            .application_manager
+            .stop_token()
+            .is_cancellation_requested()
+    })
+    .await?;
+
+    tokio::time::sleep(SETTLING_TIME).await;
+    let _ = context
+        .application_manager
+        .clone()
+        .spawn_graceful_shutdown("Backtest finished".to_owned());
+    engine.run().await;
+
+    let mut results = Vec::new();
+    for market in &markets {
+        for (currency_pair, trading_result) in market.trading_results() {
+            results.push(BacktestResult {
+                exchange_account_id: market.id.clone(),
+                currency_pair,
+                trading_result,
+            });
+        }
+    }
+    results.sort_by(|a, b| {
+        (a.exchange_account_id.to_string(), a.currency_pair.as_str())
+            .cmp(&(b.exchange_account_id.to_string(), b.currency_pair.as_str()))
+    });
+
+    Ok(results)
+}
+
+async fn replay(
+    markets: &[Arc<SimulatedMarket>],
+    order_books: &[OrderBookRecord],
+    speed: u32,
+    is_stopped: impl Fn() -> bool,
+) -> Result<()> {
+    let mut previous_time: Option<DateTime> = None;
+    for order_book in order_books {
+        let delay = previous_time
+            .and_then(|previous_time| (order_book.time - previous_time).to_std().ok())
+            .unwrap_or_default()
+            / speed;
+        tokio::time::sleep(delay).await;
+        previous_time = Some(order_book.time);
+
+        if is_stopped() {
+            bail!("Trading engine stopped before end of market data");
+        }
+
+        for market in markets {
+            market.replay_order_book(
+                order_book.time,
+                &order_book.currency_pair,
+                order_book.to_order_book_data(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn parse_market_data() {
+        let content = r#"
+{"type":"symbol","base":"eos","quote":"btc","price_tick":"0.0000001","amount_tick":"0.01","fee_rate":"0.001"}
+{"type":"order_book","time":"2021-06-01T00:00:00Z","currency_pair":"eos/btc","asks":[["0.0001","10"]],"bids":[["0.00009","5"]]}
+
+{"type":"order_book","time":"2021-06-01T00:00:01Z","currency_pair":"eos/btc","asks":[],"bids":[]}
+"#;
+
+        let market_data = MarketData::parse(content).expect("in test");
+
+        assert_eq!(market_data.symbols.len(), 1);
+        assert_eq!(market_data.symbols[0].fee_rate, dec!(0.001));
+        assert_eq!(market_data.symbols[0].min_amount, None);
+        assert_eq!(market_data.order_books.len(), 2);
+        let order_book = market_data.order_books[0].to_order_book_data();
+        assert_eq!(order_book.asks[&dec!(0.0001)], dec!(10));
+        assert_eq!(order_book.bids[&dec!(0.00009)], dec!(5));
+    }
+
+    #[test]
+    fn reject_invalid_market_data() {
+        let undeclared_symbol = r#"{"type":"order_book","time":"2021-06-01T00:00:00Z","currency_pair":"eos/btc","asks":[],"bids":[]}"#;
+        let earlier_time = r#"
+{"type":"symbol","base":"eos","quote":"btc","price_tick":"0.0000001","amount_tick":"0.01"}
+{"type":"order_book","time":"2021-06-01T00:00:01Z","currency_pair":"eos/btc","asks":[],"bids":[]}
+{"type":"order_book","time":"2021-06-01T00:00:00Z","currency_pair":"eos/btc","asks":[],"bids":[]}
+"#;
+        let no_order_books = r#"{"type":"symbol","base":"eos","quote":"btc","price_tick":"0.0000001","amount_tick":"0.01"}"#;
+
+        for content in [undeclared_symbol, earlier_time, no_order_books, "{}"].iter() {
+            assert!(MarketData::parse(content).is_err(), "{}", content);
+        }
+    }
+}
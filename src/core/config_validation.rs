@@ -0,0 +1,688 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt::{self, Debug, Display, Formatter};
+
+use anyhow::{bail, Result};
+use awc::http::Uri;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::core::config::{
+    env_var_prefix, load_combined_settings, API_KEY, ENV_VAR_PREFIX, EXCHANGE_ACCOUNT_ID,
+    SECRET_KEY,
+};
+use crate::core::exchanges::common::{ExchangeAccountId, ExchangeId};
+use crate::core::settings::{
+    AppSettings, BaseStrategySettings, ControlPanelSettings, CurrencyPairSetting, ExchangeSettings,
+    LoggingSettings, RiskManagerSettings, SelfTradePrevention,
+};
+
+/// Invalid value in settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// TOML path of invalid value, e.g. `core.exchanges[0].rest_host`
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        ValidationError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Load config and credentials files and check them with `validate_settings`
+pub fn validate_settings_files<'a, TStrategySettings>(
+    config_path: &str,
+    credentials_path: &str,
+    supported_exchanges: &[ExchangeId],
+) -> Vec<ValidationError>
+where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    match load_combined_settings(config_path, credentials_path) {
+        Ok(settings) => validate_settings::<TStrategySettings>(&settings, supported_exchanges),
+        Err(error) => vec![ValidationError::new("", format!("{:#}", error))],
+    }
+}
+
+/// Parse serialized settings with credentials and check them with `validate_settings`
+pub fn validate_serialized_settings<'a, TStrategySettings>(
+    settings: &str,
+    supported_exchanges: &[ExchangeId],
+) -> Vec<ValidationError>
+where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    match toml::from_str(settings) {
+        Ok(settings) => validate_settings::<TStrategySettings>(&settings, supported_exchanges),
+        Err(error) => vec![ValidationError::new("", error.to_string())],
+    }
+}
+
+/// Check settings with credentials before they are used by engine.
+/// All found errors are returned, not only the first one
+pub fn validate_settings<'a, TStrategySettings>(
+    settings: &Value,
+    supported_exchanges: &[ExchangeId],
+) -> Vec<ValidationError>
+where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    validate_settings_with_env::<TStrategySettings>(settings, supported_exchanges, env::vars())
+}
+
+fn validate_settings_with_env<'a, TStrategySettings>(
+    settings: &Value,
+    supported_exchanges: &[ExchangeId],
+    env_vars: impl Iterator<Item = (String, String)>,
+) -> Vec<ValidationError>
+where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    let mut errors = Vec::new();
+
+    let exchange_account_ids = match settings.get("core") {
+        Some(core) => validate_core(core, supported_exchanges, &mut errors),
+        None => {
+            errors.push(ValidationError::new("core", "Missing table"));
+            vec![]
+        }
+    };
+
+    match settings.get("strategy") {
+        Some(strategy) => {
+            validate_strategy::<TStrategySettings>(strategy, &exchange_account_ids, &mut errors)
+        }
+        None => errors.push(ValidationError::new("strategy", "Missing table")),
+    }
+
+    validate_env_vars(settings, env_vars, &mut errors);
+
+    // Checks above should cover all fields, but settings are parsed completely to be sure
+    // that there are no errors at startup
+    if errors.is_empty() {
+        if let Err(error) = settings
+            .clone()
+            .try_into::<AppSettings<TStrategySettings>>()
+        {
+            errors.push(ValidationError::new("", error.to_string()));
+        }
+    }
+
+    errors
+}
+
+/// Returns error with all validation errors if there are any
+pub fn ensure_valid(errors: Vec<ValidationError>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let errors: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+    bail!("Invalid settings:\n{}", errors.join("\n"))
+}
+
+fn validate_core(
+    core: &Value,
+    supported_exchanges: &[ExchangeId],
+    errors: &mut Vec<ValidationError>,
+) -> Vec<ExchangeAccountId> {
+    if !core.is_table() {
+        errors.push(ValidationError::new("core", "Expected table"));
+        return vec![];
+    }
+
+    validate_as::<SelfTradePrevention>(core, "core", "self_trade_prevention", errors);
+    validate_as::<RiskManagerSettings>(core, "core", "risk_manager", errors);
+    validate_as::<ControlPanelSettings>(core, "core", "control_panel", errors);
+    validate_as::<LoggingSettings>(core, "core", "logging", errors);
+
+    let exchanges = match core.get("exchanges") {
+        Some(Value::Array(exchanges)) => exchanges,
+        Some(_) => {
+            errors.push(ValidationError::new("core.exchanges", "Expected array"));
+            return vec![];
+        }
+        None => {
+            errors.push(ValidationError::new("core.exchanges", "Missing array"));
+            return vec![];
+        }
+    };
+
+    let mut exchange_account_ids = Vec::new();
+    let mut paths_by_exchange_account_id = HashMap::new();
+    for (index, exchange) in exchanges.iter().enumerate() {
+        let path = format!("core.exchanges[{}]", index);
+        let exchange_account_id =
+            match validate_exchange(exchange, &path, supported_exchanges, errors) {
+                Some(exchange_account_id) => exchange_account_id,
+                None => continue,
+            };
+
+        match paths_by_exchange_account_id.get(&exchange_account_id) {
+            Some(first_path) => errors.push(ValidationError::new(
+                format!("{}.{}", path, EXCHANGE_ACCOUNT_ID),
+                format!(
+                    "Exchange account {} is already specified in {}",
+                    exchange_account_id, first_path
+                ),
+            )),
+            None => {
+                let _ = paths_by_exchange_account_id.insert(exchange_account_id.clone(), path);
+                exchange_account_ids.push(exchange_account_id);
+            }
+        }
+    }
+
+    exchange_account_ids
+}
+
+fn validate_exchange(
+    exchange: &Value,
+    path: &str,
+    supported_exchanges: &[ExchangeId],
+    errors: &mut Vec<ValidationError>,
+) -> Option<ExchangeAccountId> {
+    if !exchange.is_table() {
+        errors.push(ValidationError::new(path, "Expected table"));
+        return None;
+    }
+
+    let errors_count = errors.len();
+
+    let exchange_account_id_path = format!("{}.{}", path, EXCHANGE_ACCOUNT_ID);
+    let exchange_account_id = match exchange.get(EXCHANGE_ACCOUNT_ID) {
+        Some(Value::String(exchange_account_id)) => {
+            match exchange_account_id.parse::<ExchangeAccountId>() {
+                Ok(exchange_account_id) => {
+                    if !supported_exchanges.contains(&exchange_account_id.exchange_id) {
+                        let supported_exchanges: Vec<_> =
+                            supported_exchanges.iter().map(|x| x.as_str()).collect();
+                        errors.push(ValidationError::new(
+                            &exchange_account_id_path,
+                            format!(
+                                "Exchange {} is not supported. Supported exchanges: {}",
+                                exchange_account_id.exchange_id,
+                                supported_exchanges.join(", ")
+                            ),
+                        ));
+                    }
+
+                    Some(exchange_account_id)
+                }
+                Err(_) => {
+                    errors.push(ValidationError::new(
+                        &exchange_account_id_path,
+                        format!(
+                            "Invalid exchange account id '{}'. Expected exchange name with account number on the tail, e.g. Binance0",
+                            exchange_account_id
+                        ),
+                    ));
+                    None
+                }
+            }
+        }
+        Some(_) => {
+            errors.push(ValidationError::new(
+                &exchange_account_id_path,
+                "Expected string",
+            ));
+            None
+        }
+        None => {
+            errors.push(ValidationError::new(
+                &exchange_account_id_path,
+                "Missing value",
+            ));
+            None
+        }
+    };
+
+    for credential in &[API_KEY, SECRET_KEY] {
+        match exchange.get(credential) {
+            Some(Value::String(_)) => {}
+            Some(_) => errors.push(ValidationError::new(
+                format!("{}.{}", path, credential),
+                "Expected string",
+            )),
+            None => errors.push(ValidationError::new(
+                format!("{}.{}", path, credential),
+                "Missing value. Credentials are specified in credentials file by exchange account id or by environment variable, e.g. MMB_BINANCE0_API_KEY",
+            )),
+        }
+    }
+
+    validate_url(exchange, path, "web_socket_host", &["ws", "wss"], errors);
+    validate_url(exchange, path, "web_socket2_host", &["ws", "wss"], errors);
+    validate_url(exchange, path, "rest_host", &["http", "https"], errors);
+
+    validate_currency_pairs(exchange, path, errors);
+
+    // Report missing and invalid fields that are not checked above
+    if errors.len() == errors_count {
+        if let Err(error) = exchange.clone().try_into::<ExchangeSettings>() {
+            errors.push(ValidationError::new(path, error.to_string()));
+        }
+    }
+
+    exchange_account_id
+}
+
+/// Empty urls are allowed because exchange client may set them itself
+fn validate_url(
+    table: &Value,
+    path: &str,
+    key: &str,
+    schemes: &[&str],
+    errors: &mut Vec<ValidationError>,
+) {
+    let url = match table.get(key) {
+        Some(Value::String(url)) if url.is_empty() => return,
+        Some(Value::String(url)) => url,
+        // Missing value and wrong type are reported by parsing of whole table
+        _ => return,
+    };
+
+    let path = format!("{}.{}", path, key);
+    let uri = match url.parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(error) => {
+            errors.push(ValidationError::new(
+                path,
+                format!("Invalid url '{}': {}", url, error),
+            ));
+            return;
+        }
+    };
+
+    let is_scheme_supported = uri
+        .scheme_str()
+        .map(|scheme| schemes.contains(&scheme))
+        .unwrap_or(false);
+    if !is_scheme_supported || uri.host().is_none() {
+        errors.push(ValidationError::new(
+            path,
+            format!(
+                "Invalid url '{}'. Expected absolute url with scheme {}",
+                url,
+                schemes.join(" or ")
+            ),
+        ));
+    }
+}
+
+fn validate_currency_pairs(exchange: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let currency_pairs = match exchange.get("currency_pairs") {
+        Some(Value::Array(currency_pairs)) => currency_pairs,
+        Some(_) => {
+            errors.push(ValidationError::new(
+                format!("{}.currency_pairs", path),
+                "Expected array",
+            ));
+            return;
+        }
+        None => return,
+    };
+
+    let mut paths_by_pair = HashMap::new();
+    for (index, currency_pair) in currency_pairs.iter().enumerate() {
+        let path = format!("{}.currency_pairs[{}]", path, index);
+        let currency_pair: CurrencyPairSetting = match currency_pair.clone().try_into() {
+            Ok(currency_pair) => currency_pair,
+            Err(error) => {
+                errors.push(ValidationError::new(path, error.to_string()));
+                continue;
+            }
+        };
+
+        let base = currency_pair.base.as_str();
+        let quote = currency_pair.quote.as_str();
+        if base.is_empty() || quote.is_empty() {
+            errors.push(ValidationError::new(
+                path,
+                "Base and quote currency codes should not be empty",
+            ));
+            continue;
+        }
+
+        if base == quote {
+            errors.push(ValidationError::new(
+                path,
+                format!("Base and quote currency codes are the same: {}", base),
+            ));
+            continue;
+        }
+
+        let pair = (base.to_lowercase(), quote.to_lowercase());
+        match paths_by_pair.get(&pair) {
+            Some(first_path) => errors.push(ValidationError::new(
+                &path,
+                format!(
+                    "Currency pair {}/{} is already specified in {}",
+                    base, quote, first_path
+                ),
+            )),
+            None => {
+                let _ = paths_by_pair.insert(pair, path);
+            }
+        }
+    }
+}
+
+fn validate_strategy<'a, TStrategySettings>(
+    strategy: &Value,
+    exchange_account_ids: &[ExchangeAccountId],
+    errors: &mut Vec<ValidationError>,
+) where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    let strategy: TStrategySettings = match strategy.clone().try_into() {
+        Ok(strategy) => strategy,
+        Err(error) => {
+            errors.push(ValidationError::new("strategy", error.to_string()));
+            return;
+        }
+    };
+
+    let exchange_account_id = strategy.exchange_account_id();
+    if !exchange_account_ids.contains(&exchange_account_id) {
+        errors.push(ValidationError::new(
+            "strategy",
+            format!(
+                "Strategy exchange account {} is not specified in core.exchanges",
+                exchange_account_id
+            ),
+        ));
+    }
+
+    let max_amount = strategy.max_amount();
+    if max_amount <= dec!(0) {
+        errors.push(ValidationError::new(
+            "strategy",
+            format!(
+                "Strategy max amount should be positive, but got {}",
+                max_amount
+            ),
+        ));
+    }
+}
+
+/// Environment variable `MMB_{EXCHANGE_ACCOUNT_ID}_{FIELD}` of configured exchange is inserted
+/// to exchange settings as is, so misspelled field would be ignored silently
+fn validate_env_vars(
+    settings: &Value,
+    env_vars: impl Iterator<Item = (String, String)>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let exchanges = match settings
+        .get("core")
+        .and_then(|core| core.get("exchanges"))
+        .and_then(|exchanges| exchanges.as_array())
+    {
+        Some(exchanges) => exchanges,
+        None => return,
+    };
+
+    let mut env_vars: Vec<_> = env_vars
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with(ENV_VAR_PREFIX))
+        .collect();
+    env_vars.sort();
+
+    let fields = exchange_settings_fields();
+    for (index, exchange) in exchanges.iter().enumerate() {
+        let env_var_prefix = match exchange.get(EXCHANGE_ACCOUNT_ID).and_then(|id| id.as_str()) {
+            Some(exchange_account_id) => env_var_prefix(exchange_account_id),
+            None => continue,
+        };
+
+        for env_var in &env_vars {
+            let field = match env_var.strip_prefix(&env_var_prefix) {
+                Some(field) => field.to_lowercase(),
+                None => continue,
+            };
+            if !fields.contains(&field) {
+                errors.push(ValidationError::new(
+                    format!("core.exchanges[{}]", index),
+                    format!(
+                        "Environment variable {} overrides unknown field {}",
+                        env_var, field
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Names of all `ExchangeSettings` fields. JSON is used because TOML omits not specified optional fields
+fn exchange_settings_fields() -> HashSet<String> {
+    match serde_json::to_value(ExchangeSettings::default()) {
+        Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+        _ => HashSet::new(),
+    }
+}
+
+/// Check that optional value can be parsed to its type
+fn validate_as<'a, T: Deserialize<'a>>(
+    table: &Value,
+    path: &str,
+    key: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Some(value) = table.get(key) {
+        if let Err(error) = value.clone().try_into::<T>() {
+            errors.push(ValidationError::new(
+                format!("{}.{}", path, key),
+                error.to_string(),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exchanges::common::{Amount, CurrencyPair};
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct TestStrategySettings {
+        exchange_account_id: ExchangeAccountId,
+        max_amount: Amount,
+    }
+
+    impl BaseStrategySettings for TestStrategySettings {
+        fn exchange_account_id(&self) -> ExchangeAccountId {
+            self.exchange_account_id.clone()
+        }
+
+        fn currency_pair(&self) -> CurrencyPair {
+            CurrencyPair::from_codes("eos".into(), "btc".into())
+        }
+
+        fn max_amount(&self) -> Amount {
+            self.max_amount
+        }
+    }
+
+    fn validate(settings: &str) -> Vec<ValidationError> {
+        validate_serialized_settings::<TestStrategySettings>(settings, &["Binance".into()])
+    }
+
+    fn exchange(exchange_account_id: &str, rest_host: &str, currency_pairs: &str) -> String {
+        format!(
+            r#"
+            [[core.exchanges]]
+            exchange_account_id = "{}"
+            api_key = "api_key"
+            secret_key = "secret_key"
+            is_margin_trading = false
+            web_socket_host = ""
+            web_socket2_host = ""
+            rest_host = "{}"
+            subscribe_to_market_data = true
+            websocket_channels = []
+            currency_pairs = [{}]
+            "#,
+            exchange_account_id, rest_host, currency_pairs
+        )
+    }
+
+    fn paths(errors: &[ValidationError]) -> Vec<&str> {
+        errors.iter().map(|error| error.path.as_str()).collect()
+    }
+
+    #[test]
+    fn valid_settings() {
+        let settings = format!(
+            r#"
+            [strategy]
+            exchange_account_id = "Binance0"
+            max_amount = 1
+            {}
+            "#,
+            exchange(
+                "Binance0",
+                "https://api.binance.com",
+                r#"{ base = "eos", quote = "btc" }"#
+            )
+        );
+
+        assert_eq!(validate(&settings), vec![]);
+    }
+
+    #[test]
+    fn all_errors_reported_with_paths() {
+        let settings = format!(
+            r#"
+            [strategy]
+            exchange_account_id = "Binance1"
+            max_amount = 0
+            {}
+            {}
+            {}
+            {}
+            "#,
+            exchange(
+                "Binance0",
+                "api.binance.com",
+                r#"{ base = "eos", quote = "btc" }, { base = "EOS", quote = "BTC" }"#
+            ),
+            exchange("Binance0", "", ""),
+            exchange("Unknown0", "", ""),
+            exchange("Binance", "", ""),
+        );
+
+        let errors = validate(&settings);
+
+        assert_eq!(
+            paths(&errors),
+            vec![
+                "core.exchanges[0].rest_host",
+                "core.exchanges[0].currency_pairs[1]",
+                "core.exchanges[1].exchange_account_id",
+                "core.exchanges[2].exchange_account_id",
+                "core.exchanges[3].exchange_account_id",
+                "strategy",
+                "strategy",
+            ]
+        );
+        assert!(errors[3].message.contains("Unknown is not supported"));
+    }
+
+    #[test]
+    fn missing_fields_reported() {
+        let settings = r#"
+            [strategy]
+            exchange_account_id = "Binance0"
+            max_amount = 1
+
+            [[core.exchanges]]
+            exchange_account_id = "Binance0"
+            rest_host = ""
+            "#;
+
+        let errors = validate(settings);
+
+        assert_eq!(
+            paths(&errors),
+            vec!["core.exchanges[0].api_key", "core.exchanges[0].secret_key",]
+        );
+    }
+
+    fn valid_settings_with(extra_settings: &str) -> String {
+        format!(
+            r#"
+            [strategy]
+            exchange_account_id = "Binance0"
+            max_amount = 1
+            {}
+            {}
+            "#,
+            exchange("Binance0", "", ""),
+            extra_settings
+        )
+    }
+
+    #[test]
+    fn invalid_logging_reported() {
+        let settings = valid_settings_with(
+            r#"
+            [core.logging]
+            console_level = "loud"
+            "#,
+        );
+
+        let errors = validate(&settings);
+
+        assert_eq!(paths(&errors), vec!["core.logging"]);
+    }
+
+    #[test]
+    fn unknown_env_var_field_reported() {
+        let settings: Value = toml::from_str(&valid_settings_with("")).expect("in test");
+        let env_vars = [
+            ("MMB_BINANCE0_API_KEY", "api_key"),
+            ("MMB_BINANCE0_MARKET_TYPE", "usdm_futures"),
+            ("MMB_BINANCE0_IS_MARGIN_TRADNG", "true"),
+            ("MMB_BINANCE1_UNKNOWN_FIELD", "value"),
+            ("PATH", "/usr/bin"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let errors = validate_settings_with_env::<TestStrategySettings>(
+            &settings,
+            &["Binance".into()],
+            env_vars,
+        );
+
+        assert_eq!(paths(&errors), vec!["core.exchanges[0]"]);
+        assert!(
+            errors[0].message.contains("MMB_BINANCE0_IS_MARGIN_TRADNG"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn invalid_toml() {
+        let errors = validate("[strategy");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "");
+    }
+}
@@ -204,6 +204,15 @@ impl ConnectivityManager {
         let _ = finished_receiver.recv().await;
     }
 
+    pub fn is_websocket_connected(&self, role: WebSocketRole) -> bool {
+        match &self.websockets.get_websocket_state(role).lock().state {
+            WebSocketState::Connected {
+                websocket_actor, ..
+            } => websocket_actor.connected(),
+            WebSocketState::Disconnected | WebSocketState::Connecting { .. } => false,
+        }
+    }
+
     pub fn send(&self, role: WebSocketRole, message: &str) {
         if let WebSocketState::Connected {
             ref websocket_actor,
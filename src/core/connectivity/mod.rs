@@ -1,2 +1,3 @@
 pub mod connectivity_manager;
+pub mod network_connector;
 pub mod websocket_actor;
@@ -1,9 +1,14 @@
 use crate::core::connectivity::connectivity_manager::{ConnectivityManagerNotifier, WebSocketRole};
+use crate::core::connectivity::network_connector::NetworkConnector;
 use crate::core::exchanges::common::ExchangeAccountId;
 use crate::core::nothing_to_do;
 use actix::io::{SinkWrite, WriteHandler};
 use actix::{Actor, ActorContext, Addr, AsyncContext, Context, Handler, Message, StreamHandler};
 use actix_codec::Framed;
+use actix_service::Service;
+use actix_tls::connect::{
+    Connect as TcpConnect, ConnectError as TcpConnectError, Connection as TcpConnection,
+};
 use actix_web::web::Buf;
 use actix_web_actors::ws::ProtocolError;
 use anyhow::{bail, Result};
@@ -13,12 +18,17 @@ use awc::{
     http,
     http::Uri,
     ws::{self, CloseCode, CloseReason, Codec, Frame},
-    BoxedSocket, Client,
+    BoxedSocket, Client, Connector,
 };
 use bytes::Bytes;
+use futures::future::LocalBoxFuture;
 use futures::stream::{SplitSink, StreamExt};
 use log::{error, info, trace, warn};
+use std::convert::TryFrom;
+use std::io;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
 
 /// Time interval between heartbeat pings are sent
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
@@ -31,11 +41,49 @@ const PING_MESSAGE: &'static [u8; 9] = b"heartbeat";
 #[derive(Debug, Clone)]
 pub struct WebSocketParams {
     url: Uri,
+    network_connector: NetworkConnector,
 }
 
 impl WebSocketParams {
-    pub fn new(url: Uri) -> Self {
-        WebSocketParams { url }
+    pub fn new(url: Uri, network_connector: NetworkConnector) -> Self {
+        WebSocketParams {
+            url,
+            network_connector,
+        }
+    }
+}
+
+/// Awc connector which establishes connections through NetworkConnector
+#[derive(Clone)]
+struct TcpConnector(NetworkConnector);
+
+impl Service<TcpConnect<Uri>> for TcpConnector {
+    type Response = TcpConnection<Uri, TcpStream>;
+    type Error = TcpConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: TcpConnect<Uri>) -> Self::Future {
+        let network_connector = self.0.clone();
+        let host = req.hostname().to_owned();
+        let port = req.port();
+        Box::pin(async move {
+            let stream = network_connector
+                .connect(&host, port)
+                .await
+                .map_err(|error| {
+                    TcpConnectError::Io(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("{:?}", error),
+                    ))
+                })?;
+
+            // Host of uri is used by TLS layer as server name
+            let uri = Uri::try_from(format!("{}:{}", host, port))
+                .map_err(|_| TcpConnectError::InvalidInput)?;
+            Ok(TcpConnection::new(stream, uri))
+        })
     }
 }
 
@@ -57,8 +105,23 @@ impl WebSocketActor {
         params: WebSocketParams,
         connectivity_manager_notifier: ConnectivityManagerNotifier,
     ) -> Result<Addr<WebSocketActor>> {
-        let connected_client = Client::builder()
-            .max_http_version(http::Version::HTTP_11)
+        let network_connector = params.network_connector;
+        let mut connector = Connector::new().connector(TcpConnector(network_connector.clone()));
+        if let Some(connect_timeout) = network_connector.connect_timeout() {
+            connector = connector.timeout(connect_timeout);
+        }
+        if let Some(tls_config) = network_connector.create_rustls_config()? {
+            connector = connector.rustls(Arc::new(tls_config));
+        }
+
+        let mut client_builder = Client::builder()
+            .connector(connector)
+            .max_http_version(http::Version::HTTP_11);
+        if let Some(read_timeout) = network_connector.read_timeout() {
+            client_builder = client_builder.timeout(read_timeout);
+        }
+
+        let connected_client = client_builder
             .finish()
             .ws(params.url)
             .header("Accept-Encoding", "gzip")
@@ -328,7 +391,7 @@ mod tests {
             let websocket_addr = WebSocketActor::open_connection(
                 exchange_id,
                 WebSocketRole::Main,
-                WebSocketParams { url },
+                WebSocketParams::new(url, NetworkConnector::default()),
                 Default::default(),
             )
             .await
@@ -5,11 +5,11 @@ use anyhow::{anyhow, bail, Context, Result};
 use chrono::Utc;
 use futures::FutureExt;
 use itertools::Itertools;
-use log::{error, trace, warn};
+use log::{error, info, trace, warn};
 use parking_lot::Mutex;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use tokio::sync::{broadcast, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 use crate::core::disposition_execution::trading_context_calculation::calculate_trading_context;
 use crate::core::exchanges::common::{
@@ -21,6 +21,7 @@ use crate::core::exchanges::general::exchange::Exchange;
 use crate::core::exchanges::general::request_type::RequestType;
 use crate::core::explanation::{Explanation, WithExplanation};
 use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::lifecycle::settings_updater::{SettingsUpdateRequest, StrategySettingsUpdate};
 use crate::core::lifecycle::trading_engine::{EngineContext, Service};
 use crate::core::order_book::local_snapshot_service::LocalSnapshotsService;
 use crate::core::orders::event::OrderEventType;
@@ -29,6 +30,7 @@ use crate::core::orders::order::{
     OrderStatus, OrderType, ReservationId,
 };
 use crate::core::orders::pool::OrderRef;
+use crate::core::risk_manager::RiskCheckOrder;
 use crate::core::{
     disposition_execution::trade_limit::is_enough_amount_and_cost, infrastructure::spawn_future,
 };
@@ -45,7 +47,13 @@ use chrono::Duration;
 static DISPOSITION_EXECUTOR: &str = "DispositionExecutor";
 static DISPOSITION_EXECUTOR_REQUESTS_GROUP: &str = "DispositionExecutorRG";
 const ALLOWED_AMOUNT_DEVIATION_RATE: Decimal = dec!(0.001);
-const GROUP_REQUESTS_COUNT: usize = 4;
+/// Requests for order cancellation reserved in group before order creation
+const GROUP_REQUESTS: [RequestType; 4] = [
+    RequestType::CancelOrder,
+    RequestType::GetOrderInfo,
+    RequestType::CancelOrder,
+    RequestType::GetOrderInfo,
+];
 
 struct DisplaySmallOrder {
     price: Decimal,
@@ -71,6 +79,7 @@ impl DispositionExecutorService {
         currency_pair: CurrencyPair,
         max_amount: Amount,
         strategy: Box<dyn DispositionStrategy>,
+        settings_updates_receiver: mpsc::Receiver<SettingsUpdateRequest>,
         cancellation_token: CancellationToken,
         statistics: Arc<StatisticService>,
     ) -> Arc<Self> {
@@ -85,6 +94,7 @@ impl DispositionExecutorService {
                 currency_pair,
                 max_amount,
                 strategy,
+                settings_updates_receiver,
                 work_finished_sender,
                 cancellation_token,
                 statistics,
@@ -124,6 +134,7 @@ struct DispositionExecutor {
     local_snapshots_service: LocalSnapshotsService,
     orders_state: OrdersState,
     strategy: Box<dyn DispositionStrategy>,
+    settings_updates_receiver: mpsc::Receiver<SettingsUpdateRequest>,
     work_finished_sender: Option<oneshot::Sender<Result<()>>>,
     cancellation_token: CancellationToken,
     statistics: Arc<StatisticService>,
@@ -138,6 +149,7 @@ impl DispositionExecutor {
         currency_pair: CurrencyPair,
         max_amount: Amount,
         strategy: Box<dyn DispositionStrategy>,
+        settings_updates_receiver: mpsc::Receiver<SettingsUpdateRequest>,
         work_finished_sender: oneshot::Sender<Result<()>>,
         cancellation_token: CancellationToken,
         statistics: Arc<StatisticService>,
@@ -158,6 +170,7 @@ impl DispositionExecutor {
             max_amount,
             orders_state: OrdersState::new(),
             strategy,
+            settings_updates_receiver,
             work_finished_sender: Some(work_finished_sender),
             cancellation_token,
             statistics,
@@ -170,6 +183,10 @@ impl DispositionExecutor {
         loop {
             let event = tokio::select! {
                 event_res = self.events_receiver.recv() => event_res.context("Error during receiving event in DispositionExecutor::start()")?,
+                Some(settings_update) = self.settings_updates_receiver.recv() => {
+                    self.handle_settings_update(settings_update);
+                    continue;
+                }
                 _ = self.cancellation_token.when_cancelled() => {
                     let _ = self.work_finished_sender.take().ok_or(anyhow!("Can't take `work_finished_sender` in DispositionExecutor"))?.send(Ok(()));
                     return Ok(());
@@ -180,6 +197,53 @@ impl DispositionExecutor {
         }
     }
 
+    fn handle_settings_update(&mut self, settings_update: SettingsUpdateRequest) {
+        let SettingsUpdateRequest {
+            update,
+            result_sender,
+        } = settings_update;
+
+        let result = self.apply_settings_update(update);
+        if let Err(error) = &result {
+            warn!(
+                "Strategy settings update rejected in DispositionExecutor: {:?}",
+                error
+            );
+        }
+
+        if result_sender.send(result).is_err() {
+            warn!("Unable to send result of settings update because receiver is dropped");
+        }
+    }
+
+    /// Added currency pairs are checked before strategy settings update, so nothing is changed on error
+    fn apply_settings_update(&mut self, update: StrategySettingsUpdate) -> Result<()> {
+        let mut added_symbols = Vec::new();
+        for (exchange_account_id, currency_pairs) in &update.added_currency_pairs {
+            let exchange = self
+                .engine_ctx
+                .exchanges
+                .get(exchange_account_id)
+                .with_context(|| format!("Exchange {} isn't running", exchange_account_id))?
+                .clone();
+            let symbols = exchange.find_new_symbols(currency_pairs)?;
+            added_symbols.push((exchange, symbols));
+        }
+
+        self.strategy.update_settings(&update.strategy_settings)?;
+        self.max_amount = update.max_amount;
+        for (exchange, symbols) in added_symbols {
+            exchange.add_symbols(symbols);
+        }
+
+        info!(
+            "Strategy settings updated in DispositionExecutor, max amount: {}",
+            self.max_amount
+        );
+
+        Ok(())
+    }
+
     fn handle_event(
         &mut self,
         event: ExchangeEvent,
@@ -629,11 +693,52 @@ impl DispositionExecutor {
             );
         }
 
+        if !self.engine_ctx.self_trade_guard.check_order(
+            &self.exchange(),
+            &self.currency_pair_metadata.currency_pair(),
+            side,
+            new_price,
+            explanation,
+        ) {
+            return log_trace(
+                "Finished `try_create_order` because new order would cross own orders",
+                explanation,
+            );
+        }
+
+        let risk_check_order = RiskCheckOrder {
+            currency_pair: self.currency_pair_metadata.currency_pair(),
+            side: new_disposition.side(),
+            price: new_price,
+            amount: new_order_amount,
+        };
+        if !self.engine_ctx.risk_manager.check_order(
+            &self.exchange(),
+            &risk_check_order,
+            explanation,
+        ) {
+            return log_trace(
+                "Finished `try_create_order` because of risk limit breach",
+                explanation,
+            );
+        }
+
+        // Guards above don't cancel real orders in dry run mode
+        if self.engine_ctx.app_settings.is_dry_run() {
+            return log_trace(
+                format!(
+                    "Finished `try_create_order` because of dry run: order {:?} {} {} wasn't sent",
+                    side, new_order_amount, new_price
+                ),
+                explanation,
+            );
+        }
+
         let new_client_order_id = ClientOrderId::unique_id();
 
         let requests_group_id = self.engine_ctx.timeout_manager.try_reserve_group(
             &self.exchange_account_id,
-            GROUP_REQUESTS_COUNT,
+            &GROUP_REQUESTS,
             DISPOSITION_EXECUTOR_REQUESTS_GROUP.to_string(),
         )?;
 
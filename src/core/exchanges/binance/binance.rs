@@ -1,10 +1,14 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
 use dashmap::DashMap;
 use hex;
 use hmac::{Hmac, Mac, NewMac};
+use hyper::header::HeaderValue;
 use log::error;
 use parking_lot::{Mutex, RwLock};
 use serde_json::Value;
@@ -12,8 +16,10 @@ use sha2::Sha256;
 use tokio::sync::broadcast;
 
 use super::support::BinanceOrderInfo;
+use crate::core::connectivity::network_connector::NetworkConnector;
 use crate::core::exchanges::events::ExchangeEvent;
-use crate::core::exchanges::rest_client::RestClient;
+use crate::core::exchanges::general::request_type::RequestType;
+use crate::core::exchanges::rest_client::{RestClient, RestRequest, RestRequestAuthenticator};
 use crate::core::exchanges::traits::ExchangeClientBuilderResult;
 use crate::core::exchanges::{
     common::CurrencyCode,
@@ -28,9 +34,11 @@ use crate::core::exchanges::{
 use crate::core::exchanges::{general::handlers::handle_order_filled::FillEventData, rest_client};
 use crate::core::orders::fill::EventSourceType;
 use crate::core::orders::order::*;
-use crate::core::settings::ExchangeSettings;
+use crate::core::secret::Secret;
+use crate::core::settings::{ExchangeSettings, MarketType};
 use crate::core::{exchanges::traits::ExchangeClientBuilder, orders::fill::OrderFillType};
 use crate::core::{lifecycle::application_manager::ApplicationManager, utils};
+use crate::hashmap;
 
 pub struct Binance {
     pub settings: ExchangeSettings,
@@ -40,6 +48,8 @@ pub struct Binance {
     pub order_cancelled_callback:
         Mutex<Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>>,
     pub handle_order_filled_callback: Mutex<Box<dyn FnMut(FillEventData) + Send + Sync>>,
+    pub websocket_message_lag_callback: Mutex<Box<dyn FnMut(Duration) + Send + Sync>>,
+    pub positions_changed_callback: Mutex<Box<dyn FnMut() + Send + Sync>>,
 
     pub unified_to_specific: RwLock<HashMap<CurrencyPair, SpecificCurrencyPair>>,
     pub specific_to_unified: RwLock<HashMap<SpecificCurrencyPair, CurrencyPair>>,
@@ -54,6 +64,28 @@ pub struct Binance {
     pub(super) subscribe_to_market_data: bool,
 
     pub(super) rest_client: RestClient,
+
+    // Difference between exchange server time and local time in milliseconds
+    pub(super) server_time_offset_ms: AtomicI64,
+
+    // Listen key of current user data stream connection
+    pub(super) listen_key: Mutex<Option<String>>,
+}
+
+/// Binance rejects signed request if it is older than receive window, 5000ms is exchange default
+const DEFAULT_RECV_WINDOW_MS: u64 = 5000;
+
+/// Binance identifies account by api key header, signature is added to request parameters separately
+struct BinanceAuthenticator {
+    api_key: Secret,
+}
+
+impl RestRequestAuthenticator for BinanceAuthenticator {
+    fn authenticate(&self, request: &mut RestRequest) -> Result<()> {
+        let api_key = HeaderValue::from_str(self.api_key.expose()).context("Invalid api key")?;
+        let _ = request.headers.insert("X-MBX-APIKEY", api_key);
+        Ok(())
+    }
 }
 
 impl Binance {
@@ -63,11 +95,22 @@ impl Binance {
         events_channel: broadcast::Sender<ExchangeEvent>,
         application_manager: Arc<ApplicationManager>,
     ) -> Self {
+        let network_connector = NetworkConnector::new(&settings.network)
+            .unwrap_or_else(|error| panic!("Invalid network settings of {}: {:?}", id, error));
+        let authenticator = BinanceAuthenticator {
+            api_key: settings.api_key.clone(),
+        };
+        let rest_client = RestClient::new(network_connector)
+            .unwrap_or_else(|error| panic!("Unable to create RestClient for {}: {:?}", id, error))
+            .with_authenticator(Box::new(authenticator));
+
         Self {
             id,
             order_created_callback: Mutex::new(Box::new(|_, _, _| {})),
             order_cancelled_callback: Mutex::new(Box::new(|_, _, _| {})),
             handle_order_filled_callback: Mutex::new(Box::new(|_| {})),
+            websocket_message_lag_callback: Mutex::new(Box::new(|_| {})),
+            positions_changed_callback: Mutex::new(Box::new(|| {})),
             unified_to_specific: Default::default(),
             specific_to_unified: Default::default(),
             supported_currencies: Default::default(),
@@ -76,27 +119,57 @@ impl Binance {
             settings,
             events_channel,
             application_manager,
-            rest_client: RestClient::new(),
+            rest_client,
+            server_time_offset_ms: AtomicI64::new(0),
+            listen_key: Mutex::new(None),
         }
     }
 
     pub async fn get_listen_key(&self) -> Result<RestRequestOutcome> {
-        let url_path = match self.settings.is_margin_trading {
-            true => "/sapi/v1/userDataStream",
-            false => "/api/v3/userDataStream",
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/userDataStream",
+            MarketType::Margin => "/sapi/v1/userDataStream",
+            MarketType::UsdmFutures => "/fapi/v1/listenKey",
         };
 
         let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &vec![])?;
         let http_params = rest_client::HttpParams::new();
-        self.rest_client
-            .post(full_url, &self.settings.api_key, &http_params)
-            .await
+        self.rest_client.post(full_url, &http_params).await
+    }
+
+    /// Listen key expires in 60 minutes if it isn't prolonged
+    pub async fn keepalive_listen_key(&self, listen_key: &str) -> Result<RestRequestOutcome> {
+        let listen_key_params = vec![("listenKey".to_owned(), listen_key.to_owned())];
+        let (url_path, http_params) = match self.settings.market_type() {
+            MarketType::Spot => ("/api/v3/userDataStream", listen_key_params),
+            MarketType::Margin => ("/sapi/v1/userDataStream", listen_key_params),
+            // Futures account has the only listen key, so it isn't specified
+            MarketType::UsdmFutures => ("/fapi/v1/listenKey", rest_client::HttpParams::new()),
+        };
+
+        let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &vec![])?;
+        self.rest_client.put(full_url, &http_params).await
     }
 
     pub async fn reconnect(&mut self) {
         todo!("reconnect")
     }
 
+    /// Binance specifies event time in milliseconds for most of websocket events
+    pub(super) fn register_websocket_message_lag(&self, data: &Value) {
+        let event_time = data["E"].as_i64().or_else(|| data["data"]["E"].as_i64());
+        let event_time = match event_time {
+            Some(event_time) => event_time,
+            None => return,
+        };
+
+        // Negative lag is possible only because of clocks difference, so skip it
+        let lag_ms = Utc::now().timestamp_millis() - event_time;
+        if lag_ms >= 0 {
+            self.websocket_message_lag_callback.lock()(Duration::from_millis(lag_ms as u64));
+        }
+    }
+
     pub(super) fn get_stream_name(
         specific_currency_pair: &SpecificCurrencyPair,
         channel: &str,
@@ -144,7 +217,7 @@ impl Binance {
     }
 
     fn generate_signature(&self, data: String) -> Result<String> {
-        let mut hmac = Hmac::<Sha256>::new_from_slice(self.settings.secret_key.as_bytes())
+        let mut hmac = Hmac::<Sha256>::new_from_slice(self.settings.secret_key.expose().as_bytes())
             .context("Unable to calculate hmac")?;
         hmac.update(data.as_bytes());
         let result = hex::encode(&hmac.finalize().into_bytes());
@@ -156,7 +229,14 @@ impl Binance {
         &self,
         parameters: &mut rest_client::HttpParams,
     ) -> Result<()> {
-        let time_stamp = utils::get_current_milliseconds();
+        let recv_window = self
+            .settings
+            .recv_window_ms
+            .unwrap_or(DEFAULT_RECV_WINDOW_MS);
+        parameters.push(("recvWindow".to_owned(), recv_window.to_string()));
+
+        let time_stamp = utils::get_current_milliseconds() as i64
+            + self.server_time_offset_ms.load(Ordering::Relaxed);
         parameters.push(("timestamp".to_owned(), time_stamp.to_string()));
 
         let message_to_sign = rest_client::to_http_string(&parameters);
@@ -198,10 +278,10 @@ impl Binance {
         )
     }
 
-    pub(super) fn handle_trade(&self, msg_to_log: &str, json_response: Value) -> Result<()> {
-        let original_client_order_id = json_response["C"]
-            .as_str()
-            .ok_or(anyhow!("Unable to parse original client order id"))?;
+    /// Handles spot executionReport and futures ORDER_TRADE_UPDATE order data
+    pub(super) fn handle_trade(&self, msg_to_log: &str, json_response: &Value) -> Result<()> {
+        // Futures order updates have no original client order id
+        let original_client_order_id = json_response["C"].as_str().unwrap_or_default();
 
         let client_order_id = if original_client_order_id.is_empty() {
             json_response["c"]
@@ -269,7 +349,7 @@ impl Binance {
             },
             "TRADE" | "CALCULATED" => {
                 let event_data = self.prepare_data_for_fill_handler(
-                    &json_response,
+                    json_response,
                     execution_type,
                     client_order_id.into(),
                     exchange_order_id.into(),
@@ -306,15 +386,22 @@ impl Binance {
         let total_filled_amount = json_response["z"]
             .as_str()
             .ok_or(anyhow!("Unable to parse total filled amount"))?;
-        let commission_amount = json_response["n"]
-            .as_str()
-            .ok_or(anyhow!("Unable to parse last commission amount"))?;
-        let commission_currency = json_response["N"]
-            .as_str()
-            .ok_or(anyhow!("Unable to parse last commission currency"))?;
-        let commission_currency_code = self
-            .get_currency_code(&commission_currency.into())
-            .ok_or(anyhow!("There are no suck supported currency code"))?;
+        // Futures trades may have no commission, e.g. liquidation ones
+        let (commission_amount, commission_currency_code) = match json_response["N"].as_str() {
+            Some(commission_currency) => {
+                let commission_amount = json_response["n"]
+                    .as_str()
+                    .ok_or(anyhow!("Unable to parse last commission amount"))?;
+                let commission_currency_code = self
+                    .get_currency_code(&commission_currency.into())
+                    .ok_or(anyhow!("There are no suck supported currency code"))?;
+                (
+                    Some(commission_amount.parse()?),
+                    Some(commission_currency_code),
+                )
+            }
+            None => (None, None),
+        };
         let is_maker = json_response["m"]
             .as_bool()
             .ok_or(anyhow!("Unable to parse trade side"))?;
@@ -331,23 +418,41 @@ impl Binance {
             OrderRole::Taker
         };
 
+        // Liquidation order is created by exchange, so it is identified by currency pair, side and amount
+        let (client_order_id, trade_currency_pair, order_amount) = match fill_type {
+            OrderFillType::Liquidation => {
+                let specific_currency_pair = json_response["s"]
+                    .as_str()
+                    .ok_or(anyhow!("Unable to parse currency pair"))?;
+                let order_amount = json_response["q"]
+                    .as_str()
+                    .ok_or(anyhow!("Unable to parse order amount"))?;
+                (
+                    None,
+                    Some(self.get_unified_currency_pair(&specific_currency_pair.into())?),
+                    Some(order_amount.parse()?),
+                )
+            }
+            _ => (Some(client_order_id), None, None),
+        };
+
         let event_data = FillEventData {
             source_type: EventSourceType::WebSocket,
             trade_id,
-            client_order_id: Some(client_order_id),
+            client_order_id,
             exchange_order_id,
             fill_price: last_filled_price.parse()?,
             fill_amount: last_filled_amount.parse()?,
             is_diff: true,
             total_filled_amount: Some(total_filled_amount.parse()?),
             order_role: Some(order_role),
-            commission_currency_code: Some(commission_currency_code),
+            commission_currency_code,
             commission_rate: None,
-            commission_amount: Some(commission_amount.parse()?),
+            commission_amount,
             fill_type,
-            trade_currency_pair: None,
+            trade_currency_pair,
             order_side: Some(order_side),
-            order_amount: None,
+            order_amount,
         };
 
         Ok(event_data)
@@ -394,7 +499,7 @@ impl ExchangeClientBuilder for BinanceBuilder {
     }
 
     fn extend_settings(&self, settings: &mut ExchangeSettings) {
-        if settings.is_margin_trading {
+        if settings.market_type() == MarketType::UsdmFutures {
             settings.web_socket_host = "wss://fstream.binance.com".to_string();
             settings.web_socket2_host = "wss://fstream3.binance.com".to_string();
             settings.rest_host = "https://fapi.binance.com".to_string();
@@ -405,15 +510,58 @@ impl ExchangeClientBuilder for BinanceBuilder {
         }
     }
 
-    fn get_timeout_argments(&self) -> RequestTimeoutArguments {
-        RequestTimeoutArguments::from_requests_per_minute(1200)
+    fn get_timeout_argments(&self, settings: &ExchangeSettings) -> Vec<RequestTimeoutArguments> {
+        let orders = [RequestType::CreateOrder];
+
+        if settings.market_type() == MarketType::UsdmFutures {
+            // Weights from https://binance-docs.github.io/apidocs/futures/en/#general-info
+            let request_weights = hashmap![
+                RequestType::GetOpenOrders => 40,
+                RequestType::GetBalance => 5,
+                RequestType::GetMyTrades => 5,
+                RequestType::GetActivePositions => 5
+            ];
+
+            return vec![
+                RequestTimeoutArguments::from_requests_per_minute(2400)
+                    .with_request_weights(request_weights),
+                RequestTimeoutArguments::new(300, chrono::Duration::seconds(10))
+                    .for_request_types(&orders),
+                RequestTimeoutArguments::from_requests_per_minute(1200).for_request_types(&orders),
+            ];
+        }
+
+        // Weights from https://binance-docs.github.io/apidocs/spot/en/#general-info
+        let request_weights = hashmap![
+            RequestType::GetOrderInfo => 2,
+            RequestType::GetOpenOrders => 40,
+            RequestType::GetOpenOrdersByCurrencyPair => 3,
+            RequestType::GetBalance => 10,
+            RequestType::GetMarkets => 10,
+            RequestType::GetMyTrades => 10
+        ];
+
+        vec![
+            RequestTimeoutArguments::from_requests_per_minute(1200)
+                .with_request_weights(request_weights),
+            RequestTimeoutArguments::new(50, chrono::Duration::seconds(10))
+                .for_request_types(&orders),
+            RequestTimeoutArguments::from_requests_per_day(160_000).for_request_types(&orders),
+        ]
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::exchanges::common::ExchangeErrorType;
+    use crate::core::exchanges::events::DerivativePosition;
+    use crate::core::exchanges::timeouts::timeout_manager::RequestsUsage;
+    use crate::core::exchanges::traits::{ExchangeClient, Support};
     use crate::core::lifecycle::cancellation_token::CancellationToken;
+    use awc::http::StatusCode;
+    use hyper::header::{HeaderName, HeaderValue};
+    use rust_decimal_macros::dec;
 
     #[test]
     fn generate_signature() {
@@ -459,4 +607,363 @@ mod tests {
         let right_value = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
         assert_eq!(http_string, right_value);
     }
+
+    fn binance(settings_modifier: impl FnOnce(&mut ExchangeSettings)) -> Binance {
+        let exchange_account_id: ExchangeAccountId = "Binance0".parse().expect("in test");
+        let mut settings = ExchangeSettings::new_short(
+            exchange_account_id.clone(),
+            "api_key".into(),
+            "secret_key".into(),
+            false,
+        );
+        settings_modifier(&mut settings);
+
+        let (tx, _) = broadcast::channel(10);
+        Binance::new(
+            exchange_account_id,
+            settings,
+            tx,
+            ApplicationManager::new(CancellationToken::default()),
+        )
+    }
+
+    #[actix_rt::test]
+    async fn no_keepalive_without_listen_key() {
+        let binance = binance(|_| {});
+
+        let response = binance
+            .request_keepalive_user_data_stream()
+            .await
+            .expect("in test");
+
+        assert_eq!(response, None);
+    }
+
+    #[test]
+    fn requests_usages_from_headers() {
+        let binance = binance(|_| {});
+
+        let mut response = RestRequestOutcome::new("{}".to_owned(), StatusCode::OK);
+        for (name, value) in [
+            ("X-MBX-USED-WEIGHT-1M", "45"),
+            ("X-MBX-ORDER-COUNT-10S", "3"),
+            ("X-MBX-ORDER-COUNT-1D", "120"),
+            ("X-MBX-USED-WEIGHT", "45"),
+            ("Content-Type", "application/json"),
+        ] {
+            response.headers.insert(
+                name.parse::<HeaderName>().expect("in test"),
+                HeaderValue::from_static(value),
+            );
+        }
+
+        let mut usages = binance.get_requests_usages(&response);
+        usages.sort_by_key(|usage| usage.period);
+
+        assert_eq!(
+            usages,
+            vec![
+                RequestsUsage::new(chrono::Duration::seconds(10), 3),
+                RequestsUsage::new(chrono::Duration::minutes(1), 45),
+                RequestsUsage::new(chrono::Duration::days(1), 120),
+            ]
+        );
+    }
+
+    #[test]
+    fn authentification_headers_use_server_time_offset_and_recv_window() {
+        let binance = binance(|settings| settings.recv_window_ms = Some(3000));
+        binance.set_server_time_offset(chrono::Duration::seconds(-10));
+
+        let mut parameters = rest_client::HttpParams::new();
+        binance
+            .add_authentification_headers(&mut parameters)
+            .expect("in test");
+
+        let get_parameter = |name: &str| {
+            parameters
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .expect("in test")
+        };
+        assert_eq!(get_parameter("recvWindow"), "3000");
+
+        let timestamp: i64 = get_parameter("timestamp").parse().expect("in test");
+        let expected_timestamp = Utc::now().timestamp_millis() - 10_000;
+        assert!((timestamp - expected_timestamp).abs() < 1000);
+    }
+
+    #[test]
+    fn api_key_header() {
+        let authenticator = BinanceAuthenticator {
+            api_key: Secret::new("api_key".to_owned()),
+        };
+        let uri = "https://api.binance.com/api/v3/order"
+            .parse()
+            .expect("in test");
+        let mut request = RestRequest::new(hyper::Method::GET, uri);
+
+        authenticator.authenticate(&mut request).expect("in test");
+
+        assert_eq!(request.headers["X-MBX-APIKEY"], "api_key");
+    }
+
+    #[test]
+    fn parse_server_time() {
+        let binance = binance(|_| {});
+        let response =
+            RestRequestOutcome::new(r#"{"serverTime":1499827319559}"#.to_owned(), StatusCode::OK);
+
+        let server_time = binance.parse_server_time(&response).expect("in test");
+
+        assert_eq!(server_time.timestamp_millis(), 1499827319559);
+    }
+
+    #[test]
+    fn timestamp_error_type() {
+        let binance = binance(|_| {});
+        let response = RestRequestOutcome::new(
+            r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#
+                .to_owned(),
+            StatusCode::BAD_REQUEST,
+        );
+
+        let mut error = binance.is_rest_error_code(&response).expect_err("in test");
+        binance.clarify_error_type(&mut error);
+
+        assert_eq!(error.error_type, ExchangeErrorType::InvalidTimestamp);
+    }
+
+    #[test]
+    fn timeout_arguments_depend_on_market_type() {
+        let exchange_account_id: ExchangeAccountId = "Binance0".parse().expect("in test");
+        let mut settings = ExchangeSettings::new_short(
+            exchange_account_id,
+            "api_key".into(),
+            "secret_key".into(),
+            false,
+        );
+
+        let spot_arguments = BinanceBuilder.get_timeout_argments(&settings);
+        assert_eq!(spot_arguments[0].requests_per_period, 1200);
+        assert_eq!(spot_arguments[1].requests_per_period, 50);
+
+        settings.is_margin_trading = true;
+        let margin_arguments = BinanceBuilder.get_timeout_argments(&settings);
+        assert_eq!(margin_arguments[0].requests_per_period, 1200);
+
+        settings.market_type = Some(MarketType::UsdmFutures);
+        let futures_arguments = BinanceBuilder.get_timeout_argments(&settings);
+        assert_eq!(futures_arguments[0].requests_per_period, 2400);
+        assert_eq!(futures_arguments[1].requests_per_period, 300);
+        assert_eq!(futures_arguments[2].period, chrono::Duration::minutes(1));
+    }
+
+    #[test]
+    fn hosts_depend_on_market_type() {
+        let exchange_account_id: ExchangeAccountId = "Binance0".parse().expect("in test");
+        let mut settings = ExchangeSettings::new_short(
+            exchange_account_id,
+            "api_key".into(),
+            "secret_key".into(),
+            true,
+        );
+
+        BinanceBuilder.extend_settings(&mut settings);
+        assert_eq!(settings.market_type(), MarketType::Margin);
+        assert_eq!(settings.rest_host, "https://api.binance.com");
+
+        settings.market_type = Some(MarketType::UsdmFutures);
+        BinanceBuilder.extend_settings(&mut settings);
+        assert_eq!(settings.rest_host, "https://fapi.binance.com");
+    }
+
+    fn futures_binance() -> (Binance, broadcast::Receiver<ExchangeEvent>) {
+        let exchange_account_id: ExchangeAccountId = "Binance0".parse().expect("in test");
+        let mut settings = ExchangeSettings::new_short(
+            exchange_account_id.clone(),
+            "api_key".into(),
+            "secret_key".into(),
+            false,
+        );
+        settings.market_type = Some(MarketType::UsdmFutures);
+
+        let (tx, rx) = broadcast::channel(10);
+        let binance = Binance::new(
+            exchange_account_id,
+            settings,
+            tx,
+            ApplicationManager::new(CancellationToken::default()),
+        );
+
+        let currency_pair = CurrencyPair::from_codes("btc".into(), "usdt".into());
+        let _ = binance
+            .specific_to_unified
+            .write()
+            .insert("BTCUSDT".into(), currency_pair);
+        let _ = binance
+            .supported_currencies
+            .insert("USDT".into(), "usdt".into());
+
+        (binance, rx)
+    }
+
+    #[test]
+    fn parse_futures_metadata() {
+        let (binance, _rx) = futures_binance();
+        let response = RestRequestOutcome::new(
+            r#"{"symbols":[{"symbol":"ETHUSDT","status":"TRADING","baseAsset":"ETH","quoteAsset":"USDT","marginAsset":"USDT",
+                "filters":[{"filterType":"PRICE_FILTER","minPrice":"0.01","maxPrice":"100000","tickSize":"0.01"},
+                {"filterType":"LOT_SIZE","minQty":"0.001","maxQty":"10000","stepSize":"0.001"},
+                {"filterType":"MIN_NOTIONAL","notional":"5"}]}]}"#
+                .to_owned(),
+            StatusCode::OK,
+        );
+
+        let symbols = binance.parse_metadata(&response).expect("in test");
+
+        assert_eq!(symbols.len(), 1);
+        let symbol = &symbols[0];
+        assert!(symbol.is_derivative);
+        assert_eq!(symbol.balance_currency_code, Some("usdt".into()));
+        assert_eq!(symbol.min_cost, Some(dec!(5)));
+    }
+
+    #[test]
+    fn parse_active_positions() {
+        let (binance, _rx) = futures_binance();
+        let response = RestRequestOutcome::new(
+            r#"[{"symbol":"BTCUSDT","positionAmt":"-0.5","entryPrice":"30000","markPrice":"30100","unRealizedProfit":"-50",
+                "liquidationPrice":"32900","leverage":"10","marginType":"cross","positionSide":"BOTH"},
+                {"symbol":"BTCUSDT","positionAmt":"0","entryPrice":"0","markPrice":"30100","unRealizedProfit":"0",
+                "liquidationPrice":"0","leverage":"20","marginType":"cross","positionSide":"BOTH"}]"#
+                .to_owned(),
+            StatusCode::OK,
+        );
+
+        let positions = binance.parse_active_positions(&response).expect("in test");
+
+        assert_eq!(
+            positions,
+            vec![DerivativePosition {
+                currency_pair: CurrencyPair::from_codes("btc".into(), "usdt".into()),
+                position: dec!(-0.5),
+                average_entry_price: dec!(30000),
+                liquidation_price: Some(dec!(32900)),
+                leverage: Some(dec!(10)),
+            }]
+        );
+    }
+
+    #[test]
+    fn account_update() {
+        let (binance, mut rx) = futures_binance();
+        let positions_changed = Arc::new(Mutex::new(false));
+        let positions_changed_clone = positions_changed.clone();
+        binance.set_positions_changed_callback(Box::new(move || {
+            *positions_changed_clone.lock() = true
+        }));
+
+        let message = r#"{"e":"ACCOUNT_UPDATE","E":1564745798939,"T":1564745798938,"a":{"m":"ORDER",
+            "B":[{"a":"USDT","wb":"122624.12345678","cw":"100.12345678","bc":"50.12345678"}],
+            "P":[{"s":"BTCUSDT","pa":"-0.5","ep":"30000","cr":"200","up":"0","mt":"isolated","iw":"0","ps":"BOTH"}]}}"#;
+        binance.on_websocket_message(message).expect("in test");
+
+        let event = match rx.try_recv().expect("in test") {
+            ExchangeEvent::BalanceUpdate(event) => event,
+            _ => panic!("Unexpected exchange event"),
+        };
+        let balances_and_positions = event.balances_and_positions;
+        assert_eq!(balances_and_positions.balances.len(), 1);
+        assert_eq!(
+            balances_and_positions.balances[0].balance,
+            dec!(122624.12345678)
+        );
+        let positions = balances_and_positions.positions.expect("in test");
+        assert_eq!(positions[0].position, dec!(-0.5));
+        assert_eq!(positions[0].liquidation_price, None);
+        assert!(*positions_changed.lock());
+    }
+
+    fn order_trade_update(execution_type: &str, order_type: &str) -> String {
+        format!(
+            r#"{{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{{"s":"BTCUSDT","c":"test_client_order_id",
+            "S":"SELL","o":"{}","f":"GTC","q":"0.5","p":"0","ap":"32900","sp":"0","x":"{}","X":"FILLED","i":8886774,
+            "l":"0.5","z":"0.5","L":"32900","T":1568879465650,"t":12345,"b":"0","a":"0","m":false,"R":false,"wt":"CONTRACT_PRICE",
+            "ot":"{}","ps":"BOTH","cp":false,"rp":"0"}}}}"#,
+            order_type, execution_type, order_type
+        )
+    }
+
+    fn handle_fill(binance: &Binance, message: &str) -> FillEventData {
+        let fill = Arc::new(Mutex::new(None));
+        let fill_clone = fill.clone();
+        binance.set_handle_order_filled_callback(Box::new(move |event_data| {
+            *fill_clone.lock() = Some(event_data)
+        }));
+
+        binance.on_websocket_message(message).expect("in test");
+
+        let event_data = fill.lock().take();
+        event_data.expect("in test")
+    }
+
+    #[test]
+    fn order_trade_update_fill() {
+        let (binance, _rx) = futures_binance();
+
+        let event_data = handle_fill(&binance, &order_trade_update("TRADE", "LIMIT"));
+
+        assert_eq!(event_data.fill_type, OrderFillType::UserTrade);
+        assert_eq!(
+            event_data.client_order_id,
+            Some("test_client_order_id".into())
+        );
+        assert_eq!(event_data.fill_amount, dec!(0.5));
+        assert_eq!(event_data.commission_amount, None);
+    }
+
+    #[test]
+    fn order_trade_update_liquidation() {
+        let (binance, _rx) = futures_binance();
+
+        let event_data = handle_fill(&binance, &order_trade_update("CALCULATED", "LIQUIDATION"));
+
+        assert_eq!(event_data.fill_type, OrderFillType::Liquidation);
+        assert_eq!(event_data.client_order_id, None);
+        assert_eq!(
+            event_data.trade_currency_pair,
+            Some(CurrencyPair::from_codes("btc".into(), "usdt".into()))
+        );
+        assert_eq!(event_data.order_side, Some(OrderSide::Sell));
+        assert_eq!(event_data.order_amount, Some(dec!(0.5)));
+    }
+
+    #[test]
+    fn currency_pairs_subscription() {
+        let binance =
+            binance(|settings| settings.websocket_channels = vec!["depth".into(), "trade".into()]);
+
+        let subscriptions = binance
+            .get_currency_pairs_subscriptions(&["EOSBTC".into()])
+            .expect("in test");
+
+        assert_eq!(
+            subscriptions,
+            vec![r#"{"id":1,"method":"SUBSCRIBE","params":["eosbtc@depth","eosbtc@trade"]}"#]
+        );
+    }
+
+    #[test]
+    fn subscription_response_is_handled() {
+        let binance = binance(|_| {});
+
+        binance
+            .on_websocket_message(r#"{"result":null,"id":1}"#)
+            .expect("in test");
+        binance
+            .on_websocket_message(r#"{"error":{"code":2,"msg":"Invalid request"},"id":1}"#)
+            .expect_err("in test");
+    }
 }
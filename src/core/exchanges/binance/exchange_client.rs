@@ -2,21 +2,26 @@ use super::binance::Binance;
 use crate::core::exchanges::rest_client;
 use crate::core::exchanges::traits::{ExchangeClient, Support};
 use crate::core::orders::order::*;
+use crate::core::settings::MarketType;
 use crate::core::{
     exchanges::common::{CurrencyPair, RestRequestOutcome},
     orders::pool::OrderRef,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use rust_decimal::Decimal;
 
 #[async_trait]
 impl ExchangeClient for Binance {
     async fn request_metadata(&self) -> Result<RestRequestOutcome> {
-        // In currenct versions works only with Spot market
-        let url_path = "/api/v3/exchangeInfo";
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/exchangeInfo",
+            MarketType::Margin => "/api/v3/exchangeInfo",
+            MarketType::UsdmFutures => "/fapi/v1/exchangeInfo",
+        };
         let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &vec![])?;
 
-        self.rest_client.get(full_url, &self.settings.api_key).await
+        self.rest_client.get(full_url).await
     }
 
     async fn create_order(&self, order: &OrderCreating) -> Result<RestRequestOutcome> {
@@ -50,24 +55,24 @@ impl ExchangeClient for Binance {
         }
         self.add_authentification_headers(&mut http_params)?;
 
-        let url_path = match self.settings.is_margin_trading {
-            true => "/fapi/v1/order",
-            false => "/api/v3/order",
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/order",
+            MarketType::Margin => "/sapi/v1/margin/order",
+            MarketType::UsdmFutures => "/fapi/v1/order",
         };
 
         let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &vec![])?;
 
-        self.rest_client
-            .post(full_url, &self.settings.api_key, &http_params)
-            .await
+        self.rest_client.post(full_url, &http_params).await
     }
 
     async fn request_cancel_order(&self, order: &OrderCancelling) -> Result<RestRequestOutcome> {
         let specific_currency_pair = self.get_specific_currency_pair(&order.header.currency_pair);
 
-        let url_path = match self.settings.is_margin_trading {
-            true => "/fapi/v1/order",
-            false => "/api/v3/order",
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/order",
+            MarketType::Margin => "/sapi/v1/margin/order",
+            MarketType::UsdmFutures => "/fapi/v1/order",
         };
 
         let mut http_params = vec![
@@ -84,10 +89,7 @@ impl ExchangeClient for Binance {
 
         let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &http_params)?;
 
-        let outcome = self
-            .rest_client
-            .delete(full_url, &self.settings.api_key)
-            .await?;
+        let outcome = self.rest_client.delete(full_url).await?;
 
         Ok(outcome)
     }
@@ -96,7 +98,11 @@ impl ExchangeClient for Binance {
         let specific_currency_pair = self.get_specific_currency_pair(&currency_pair);
 
         let host = &self.settings.rest_host;
-        let path_to_delete = "/api/v3/openOrders";
+        let path_to_delete = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/openOrders",
+            MarketType::Margin => "/sapi/v1/margin/openOrders",
+            MarketType::UsdmFutures => "/fapi/v1/allOpenOrders",
+        };
 
         let mut http_params = vec![(
             "symbol".to_owned(),
@@ -106,10 +112,7 @@ impl ExchangeClient for Binance {
 
         let full_url = rest_client::build_uri(host, path_to_delete, &http_params)?;
 
-        let _cancel_order_outcome = self
-            .rest_client
-            .delete(full_url, &self.settings.api_key)
-            .await;
+        let _cancel_order_outcome = self.rest_client.delete(full_url).await;
 
         Ok(())
     }
@@ -138,9 +141,10 @@ impl ExchangeClient for Binance {
     async fn request_order_info(&self, order: &OrderRef) -> Result<RestRequestOutcome> {
         let specific_currency_pair = self.get_specific_currency_pair(&order.currency_pair());
 
-        let url_path = match self.settings.is_margin_trading {
-            true => "/fapi/v1/order",
-            false => "/api/v3/order",
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/order",
+            MarketType::Margin => "/sapi/v1/margin/order",
+            MarketType::UsdmFutures => "/fapi/v1/order",
         };
 
         let mut http_params = vec![
@@ -157,6 +161,70 @@ impl ExchangeClient for Binance {
 
         let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &http_params)?;
 
-        self.rest_client.get(full_url, &self.settings.api_key).await
+        self.rest_client.get(full_url).await
+    }
+
+    async fn request_server_time(&self) -> Result<RestRequestOutcome> {
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/time",
+            MarketType::Margin => "/api/v3/time",
+            MarketType::UsdmFutures => "/fapi/v1/time",
+        };
+
+        let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &vec![])?;
+
+        self.rest_client.get(full_url).await
+    }
+
+    async fn request_set_leverage(
+        &self,
+        currency_pair: CurrencyPair,
+        leverage: Decimal,
+    ) -> Result<RestRequestOutcome> {
+        if self.settings.market_type() != MarketType::UsdmFutures {
+            bail!("Leverage is available only for Binance futures");
+        }
+
+        let specific_currency_pair = self.get_specific_currency_pair(&currency_pair);
+
+        let mut http_params = vec![
+            (
+                "symbol".to_owned(),
+                specific_currency_pair.as_str().to_owned(),
+            ),
+            ("leverage".to_owned(), leverage.to_string()),
+        ];
+        self.add_authentification_headers(&mut http_params)?;
+
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/fapi/v1/leverage", &vec![])?;
+
+        self.rest_client.post(full_url, &http_params).await
+    }
+
+    async fn request_active_positions(&self) -> Result<RestRequestOutcome> {
+        if self.settings.market_type() != MarketType::UsdmFutures {
+            bail!("Positions are available only for Binance futures");
+        }
+
+        let mut http_params = rest_client::HttpParams::new();
+        self.add_authentification_headers(&mut http_params)?;
+
+        let full_url = rest_client::build_uri(
+            &self.settings.rest_host,
+            "/fapi/v2/positionRisk",
+            &http_params,
+        )?;
+
+        self.rest_client.get(full_url).await
+    }
+
+    async fn request_keepalive_user_data_stream(&self) -> Result<Option<RestRequestOutcome>> {
+        let listen_key = match self.listen_key.lock().clone() {
+            Some(listen_key) => listen_key,
+            None => return Ok(None),
+        };
+
+        self.keepalive_listen_key(&listen_key).await.map(Some)
     }
 }
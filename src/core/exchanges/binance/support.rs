@@ -1,29 +1,38 @@
 use std::str::FromStr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use awc::http::Uri;
-use chrono::Utc;
+use chrono::{TimeZone, Utc};
 use dashmap::DashMap;
 use itertools::Itertools;
 use log::{error, info};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use super::binance::Binance;
+use crate::core::connectivity::network_connector::NetworkConnector;
 use crate::core::exchanges::common::SortedOrderData;
-use crate::core::exchanges::events::ExchangeEvent;
+use crate::core::exchanges::events::{
+    BalanceUpdateEvent, DerivativePosition, ExchangeBalance, ExchangeBalancesAndPositions,
+    ExchangeEvent,
+};
 use crate::core::exchanges::rest_client;
 use crate::core::exchanges::{
     common::CurrencyCode, common::CurrencyId,
     general::currency_pair_metadata::CurrencyPairMetadata,
-    general::handlers::handle_order_filled::FillEventData, traits::Support,
+    general::handlers::handle_order_filled::FillEventData,
+    timeouts::timeout_manager::RequestsUsage, traits::Support,
 };
 use crate::core::order_book::event::{EventType, OrderBookEvent};
 use crate::core::order_book::order_book_data::OrderBookData;
 use crate::core::orders::order::*;
+use crate::core::settings::MarketType;
+use crate::core::DateTime;
 use crate::core::{
     connectivity::connectivity_manager::WebSocketRole,
     exchanges::general::currency_pair_metadata::Precision,
@@ -36,6 +45,9 @@ use crate::core::{
     orders::fill::EventSourceType,
 };
 
+/// Subscriptions are sent without waiting for response, so responses aren't matched by id
+const SUBSCRIPTION_REQUEST_ID: u64 = 1;
+
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct BinanceOrderInfo {
     #[serde(rename = "symbol")]
@@ -53,6 +65,19 @@ pub struct BinanceOrderInfo {
     pub side: String,
 }
 
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct BinancePosition {
+    #[serde(rename = "symbol")]
+    pub specific_currency_pair: SpecificCurrencyPair,
+    #[serde(rename = "positionAmt")]
+    pub position_amount: Amount,
+    #[serde(rename = "entryPrice")]
+    pub entry_price: Price,
+    #[serde(rename = "liquidationPrice")]
+    pub liquidation_price: Price,
+    pub leverage: Decimal,
+}
+
 #[async_trait]
 impl Support for Binance {
     fn is_rest_error_code(&self, response: &RestRequestOutcome) -> Result<(), ExchangeError> {
@@ -110,8 +135,14 @@ impl Support for Binance {
 
     fn clarify_error_type(&self, error: &mut ExchangeError) {
         // -1010 ERROR_MSG_RECEIVED
+        // -1021 INVALID_TIMESTAMP
         // -2010 NEW_ORDER_REJECTED
         // -2011 CANCEL_REJECTED
+        if error.code == Some(-1021) {
+            error.error_type = ExchangeErrorType::InvalidTimestamp;
+            return;
+        }
+
         let error_type = match error.message.as_str() {
             "Unknown order sent." | "Order does not exist." => ExchangeErrorType::OrderNotFound,
             "Account has insufficient balance for requested action." => {
@@ -135,6 +166,8 @@ impl Support for Binance {
 
     fn on_websocket_message(&self, msg: &str) -> Result<()> {
         let data: Value = serde_json::from_str(msg).context("Unable to parse websocket message")?;
+        self.register_websocket_message_lag(&data);
+
         // Public stream
         if let Some(stream) = data.get("stream") {
             let stream = stream
@@ -154,16 +187,25 @@ impl Support for Binance {
             return Ok(());
         }
 
+        // Response to subscription request sent by `get_currency_pairs_subscriptions`
+        if data.get("id").is_some() {
+            if let Some(error) = data.get("error") {
+                bail!("Websocket subscription failed: {}", error);
+            }
+
+            return Ok(());
+        }
+
         // so it is userData stream
         let event_type = data["e"]
             .as_str()
             .ok_or(anyhow!("Unable to parse event_type"))?;
-        if event_type == "executionReport" {
-            self.handle_trade(msg, data)?;
-        } else if false {
-            // TODO something about ORDER_TRADE_UPDATE? There are no info about it in Binance docs
-        } else {
-            self.log_unknown_message(self.id.clone(), msg);
+        match event_type {
+            "executionReport" => self.handle_trade(msg, &data)?,
+            // Futures user data events from https://binance-docs.github.io/apidocs/futures/en/#user-data-streams
+            "ORDER_TRADE_UPDATE" => self.handle_trade(msg, &data["o"])?,
+            "ACCOUNT_UPDATE" => self.handle_account_update(&data["a"])?,
+            _ => self.log_unknown_message(self.id.clone(), msg),
         }
 
         Ok(())
@@ -190,15 +232,28 @@ impl Support for Binance {
         *self.handle_order_filled_callback.lock() = callback;
     }
 
+    fn set_positions_changed_callback(&self, callback: Box<dyn FnMut() + Send + Sync>) {
+        *self.positions_changed_callback.lock() = callback;
+    }
+
+    fn set_websocket_message_lag_callback(&self, callback: Box<dyn FnMut(Duration) + Send + Sync>) {
+        *self.websocket_message_lag_callback.lock() = callback;
+    }
+
     fn set_traded_specific_currencies(&self, currencies: Vec<SpecificCurrencyPair>) {
         *self.traded_specific_currencies.lock() = currencies;
     }
 
+    fn set_server_time_offset(&self, offset: chrono::Duration) {
+        self.server_time_offset_ms
+            .store(offset.num_milliseconds(), Ordering::Relaxed);
+    }
+
     fn is_websocket_enabled(&self, role: WebSocketRole) -> bool {
         match role {
             WebSocketRole::Main => true,
             WebSocketRole::Secondary => {
-                self.settings.api_key != "" && self.settings.secret_key != ""
+                !self.settings.api_key.is_empty() && !self.settings.secret_key.is_empty()
             }
         }
     }
@@ -220,6 +275,25 @@ impl Support for Binance {
             .with_context(|| format!("Unable parse websocket {:?} uri", role))
     }
 
+    fn get_currency_pairs_subscriptions(
+        &self,
+        currency_pairs: &[SpecificCurrencyPair],
+    ) -> Result<Vec<String>> {
+        let stream_names =
+            Self::get_stream_names(currency_pairs, &self.settings.websocket_channels);
+        let subscription = json!({
+            "method": "SUBSCRIBE",
+            "params": stream_names,
+            "id": SUBSCRIPTION_REQUEST_ID,
+        });
+
+        Ok(vec![subscription.to_string()])
+    }
+
+    fn get_network_connector(&self) -> &NetworkConnector {
+        self.rest_client.network_connector()
+    }
+
     fn get_specific_currency_pair(&self, currency_pair: &CurrencyPair) -> SpecificCurrencyPair {
         self.unified_to_specific.read()[currency_pair].clone()
     }
@@ -230,6 +304,8 @@ impl Support for Binance {
 
     fn should_log_message(&self, message: &str) -> bool {
         message.contains("executionReport")
+            || message.contains("ORDER_TRADE_UPDATE")
+            || message.contains("ACCOUNT_UPDATE")
     }
 
     fn log_unknown_message(
@@ -260,6 +336,16 @@ impl Support for Binance {
         Ok(unified_order)
     }
 
+    fn parse_server_time(&self, response: &RestRequestOutcome) -> Result<DateTime> {
+        let data: Value = serde_json::from_str(&response.content)
+            .context("Unable to parse response content for get_server_time request")?;
+        let server_time = data["serverTime"]
+            .as_i64()
+            .ok_or(anyhow!("Unable to parse serverTime field"))?;
+
+        Ok(Utc.timestamp_millis(server_time))
+    }
+
     fn parse_metadata(
         &self,
         response: &RestRequestOutcome,
@@ -275,8 +361,7 @@ impl Support for Binance {
         for symbol in symbols {
             let is_active = symbol["status"] == "TRADING";
 
-            // TODO There is no work with dereivatives in current version
-            let is_derivative = false;
+            let is_derivative = self.settings.market_type() == MarketType::UsdmFutures;
             let base_currency_id = &symbol
                 .get_as_str("baseAsset")
                 .context("Unable to get base currency id from Binance")?;
@@ -302,8 +387,12 @@ impl Support for Binance {
 
             let amount_currency_code = quote_currency_code.clone();
 
-            // TODO There are no balance_currency_code for spot, why does it set here this way?
-            let balance_currency_code = base_currency_code.clone();
+            // Futures positions are kept in margin asset
+            let balance_currency_code = match symbol.get("marginAsset") {
+                Some(_) => CurrencyCode::from(symbol.get_as_str("marginAsset")?.as_str()),
+                // TODO There are no balance_currency_code for spot, why does it set here this way?
+                None => base_currency_code.clone(),
+            };
 
             let mut min_amount = None;
             let mut max_amount = None;
@@ -330,8 +419,11 @@ impl Support for Binance {
                         max_amount = filter.get_as_decimal("maxQty");
                         amount_tick = filter.get_as_decimal("stepSize");
                     }
+                    // Futures use "notional" field name
                     "MIN_NOTIONAL" => {
-                        min_cost = filter.get_as_decimal("minNotional");
+                        min_cost = filter
+                            .get_as_decimal("minNotional")
+                            .or_else(|| filter.get_as_decimal("notional"));
                     }
                     _ => {}
                 }
@@ -378,6 +470,46 @@ impl Support for Binance {
 
         Ok(result)
     }
+
+    fn parse_active_positions(
+        &self,
+        response: &RestRequestOutcome,
+    ) -> Result<Vec<DerivativePosition>> {
+        let positions: Vec<BinancePosition> = serde_json::from_str(&response.content)
+            .context("Unable to parse response content for get_active_positions request")?;
+
+        positions
+            .iter()
+            .filter(|position| !position.position_amount.is_zero())
+            .map(|position| {
+                Ok(DerivativePosition {
+                    currency_pair: self
+                        .get_unified_currency_pair(&position.specific_currency_pair)?,
+                    position: position.position_amount,
+                    average_entry_price: position.entry_price,
+                    liquidation_price: Some(position.liquidation_price),
+                    leverage: Some(position.leverage),
+                })
+            })
+            .collect()
+    }
+
+    fn get_requests_usages(&self, response: &RestRequestOutcome) -> Vec<RequestsUsage> {
+        // Headers look like X-MBX-USED-WEIGHT-1M and X-MBX-ORDER-COUNT-10S
+        response
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let interval = name
+                    .as_str()
+                    .strip_prefix("x-mbx-used-weight-")
+                    .or_else(|| name.as_str().strip_prefix("x-mbx-order-count-"))?;
+                let period = parse_rate_limit_interval(interval)?;
+                let used_weight = value.to_str().ok()?.parse().ok()?;
+                Some(RequestsUsage::new(period, used_weight))
+            })
+            .collect()
+    }
 }
 
 trait GetOrErr {
@@ -408,12 +540,20 @@ impl Binance {
         currency_pair: &CurrencyPair,
         data: &Value,
     ) -> Result<()> {
-        let last_update_id = data["lastUpdateId"].to_string();
+        // Futures partial depth has the same fields as diff depth update
+        let (last_update_id, asks, bids) = match self.settings.market_type() {
+            MarketType::UsdmFutures => (&data["u"], &data["a"], &data["b"]),
+            MarketType::Spot | MarketType::Margin => {
+                (&data["lastUpdateId"], &data["asks"], &data["bids"])
+            }
+        };
+
+        let last_update_id = last_update_id.to_string();
         let last_update_id = last_update_id.trim_matches('"');
-        let raw_asks = data["asks"]
+        let raw_asks = asks
             .as_array()
             .ok_or(anyhow!("Unable to parse 'asks' in Binance"))?;
-        let raw_bids = data["bids"]
+        let raw_bids = bids
             .as_array()
             .ok_or(anyhow!("Unable to parse 'bids' in Binance"))?;
 
@@ -423,6 +563,55 @@ impl Binance {
         let order_book_data = OrderBookData::new(asks, bids);
         self.handle_order_book_snapshot(currency_pair, &last_update_id, order_book_data, None)
     }
+
+    /// Futures balances and positions update. It has no liquidation prices, so positions are requested by callback
+    pub(super) fn handle_account_update(&self, data: &Value) -> Result<()> {
+        let raw_balances = data["B"]
+            .as_array()
+            .ok_or(anyhow!("Unable to parse balances in Binance"))?;
+        let balances = raw_balances
+            .iter()
+            .map(|balance| {
+                Ok(ExchangeBalance {
+                    currency_code: CurrencyCode::from(balance.get_as_str("a")?.as_str()),
+                    balance: balance.get_as_str("wb")?.parse()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let raw_positions = data["P"]
+            .as_array()
+            .ok_or(anyhow!("Unable to parse positions in Binance"))?;
+        let positions = raw_positions
+            .iter()
+            .map(|position| {
+                let specific_currency_pair = position.get_as_str("s")?.as_str().into();
+                Ok(DerivativePosition {
+                    currency_pair: self.get_unified_currency_pair(&specific_currency_pair)?,
+                    position: position.get_as_str("pa")?.parse()?,
+                    average_entry_price: position.get_as_str("ep")?.parse()?,
+                    liquidation_price: None,
+                    leverage: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let is_positions_changed = !positions.is_empty();
+        let event = ExchangeEvent::BalanceUpdate(BalanceUpdateEvent {
+            exchange_account_id: self.id.clone(),
+            balances_and_positions: ExchangeBalancesAndPositions {
+                balances,
+                positions: Some(positions).filter(|positions| !positions.is_empty()),
+            },
+        });
+        self.send_event(event)?;
+
+        if is_positions_changed {
+            self.positions_changed_callback.lock()();
+        }
+
+        Ok(())
+    }
     fn handle_order_book_snapshot(
         &self,
         currency_pair: &CurrencyPair,
@@ -476,21 +665,27 @@ impl Binance {
     }
 
     fn build_ws_main_path(&self, websocket_channels: &[String]) -> String {
-        let stream_names = self
-            .traded_specific_currencies
-            .lock()
+        let stream_names =
+            Self::get_stream_names(&self.traded_specific_currencies.lock(), websocket_channels)
+                .join("/");
+        format!("/stream?streams={}", stream_names)
+    }
+
+    fn get_stream_names(
+        currency_pairs: &[SpecificCurrencyPair],
+        websocket_channels: &[String],
+    ) -> Vec<String> {
+        currency_pairs
             .iter()
             .flat_map(|currency_pair| {
                 let mut results = Vec::new();
                 for channel in websocket_channels {
                     let result = Self::get_stream_name(currency_pair, channel);
-                    results.push(result);
+                    results.push(result.to_lowercase());
                 }
                 results
             })
-            .join("/");
-        let ws_path = format!("/stream?streams={}", stream_names);
-        ws_path.to_lowercase()
+            .collect()
     }
 
     async fn build_ws_secondary_path(&self) -> Result<String> {
@@ -503,6 +698,7 @@ impl Binance {
         let listen_key = data["listenKey"]
             .as_str()
             .context("Unable to parse listen key field for Binance")?;
+        *self.listen_key.lock() = Some(listen_key.to_owned());
 
         let ws_path = format!("{}{}", "/ws/", listen_key);
         Ok(ws_path)
@@ -512,14 +708,15 @@ impl Binance {
         &self,
         http_params: Vec<(String, String)>,
     ) -> Result<RestRequestOutcome> {
-        let url_path = match self.settings.is_margin_trading {
-            true => "/fapi/v1/openOrders",
-            false => "/api/v3/openOrders",
+        let url_path = match self.settings.market_type() {
+            MarketType::Spot => "/api/v3/openOrders",
+            MarketType::Margin => "/sapi/v1/margin/openOrders",
+            MarketType::UsdmFutures => "/fapi/v1/openOrders",
         };
 
         let full_url = rest_client::build_uri(&self.settings.rest_host, url_path, &http_params)?;
 
-        let orders = self.rest_client.get(full_url, &self.settings.api_key).await;
+        let orders = self.rest_client.get(full_url).await;
 
         orders
     }
@@ -541,3 +738,15 @@ fn get_order_book_side(levels: &Vec<Value>) -> Result<SortedOrderData> {
         })
         .try_collect()
 }
+
+fn parse_rate_limit_interval(interval: &str) -> Option<chrono::Duration> {
+    let unit_index = interval.len().checked_sub(1)?;
+    let count: i64 = interval[..unit_index].parse().ok()?;
+    match &interval[unit_index..] {
+        "s" => Some(chrono::Duration::seconds(count)),
+        "m" => Some(chrono::Duration::minutes(count)),
+        "h" => Some(chrono::Duration::hours(count)),
+        "d" => Some(chrono::Duration::days(count)),
+        _ => None,
+    }
+}
@@ -8,3 +8,5 @@ pub static CREATE_ORDER_INSUFFICIENT_FUNDS: BlockReason =
 pub static REST_RATE_LIMIT: BlockReason = BlockReason::new("REST_RATE_LIMIT");
 pub static GRACEFUL_SHUTDOWN: BlockReason = BlockReason::new("GRACEFUL_SHUTDOWN");
 pub static EXCHANGE_UNAVAILABLE: BlockReason = BlockReason::new("EXCHANGE_UNAVAILABLE");
+pub static RISK_MAX_POSITION: BlockReason = BlockReason::new("RISK_MAX_POSITION");
+pub static RISK_MAX_DAILY_LOSS: BlockReason = BlockReason::new("RISK_MAX_DAILY_LOSS");
@@ -6,6 +6,7 @@ use std::str::FromStr;
 use std::{collections::BTreeMap, time::Duration};
 
 use awc::http::StatusCode;
+use hyper::HeaderMap;
 use itertools::Itertools;
 use regex::Regex;
 use rust_decimal::*;
@@ -324,6 +325,8 @@ pub enum ExchangeErrorType {
     ParsingError,
     PendingError(Duration),
     ServiceUnavailable,
+    /// Request timestamp is out of exchange server time window, so server time should be resynchronized
+    InvalidTimestamp,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -336,11 +339,19 @@ pub enum RestRequestError {
 pub struct RestRequestOutcome {
     pub content: String,
     pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// Time from sending of request to receiving of whole response
+    pub latency: Duration,
 }
 
 impl RestRequestOutcome {
     pub fn new(content: String, status: StatusCode) -> Self {
-        Self { content, status }
+        Self {
+            content,
+            status,
+            headers: HeaderMap::new(),
+            latency: Duration::ZERO,
+        }
     }
 }
 
@@ -17,9 +17,32 @@ pub struct ExchangeBalance {
     pub balance: Decimal,
 }
 
+/// Position on derivative market
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivativePosition {
+    pub currency_pair: CurrencyPair,
+    /// Positive for long position and negative for short one
+    pub position: Amount,
+    pub average_entry_price: Price,
+    /// Not every position update contains liquidation price and leverage
+    pub liquidation_price: Option<Price>,
+    pub leverage: Option<Decimal>,
+}
+
+impl DerivativePosition {
+    pub fn side(&self) -> OrderSide {
+        match self.position.is_sign_negative() {
+            true => OrderSide::Sell,
+            false => OrderSide::Buy,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExchangeBalancesAndPositions {
     pub balances: Vec<ExchangeBalance>,
+    /// None if exchange has no derivatives or update doesn't affect positions
+    pub positions: Option<Vec<DerivativePosition>>,
 }
 
 #[derive(Debug, Clone)]
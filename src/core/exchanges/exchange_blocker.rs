@@ -11,9 +11,11 @@ use futures::{
 };
 use itertools::Itertools;
 use log::{error, trace};
+use once_cell::sync::OnceCell;
 use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::iter::FromIterator;
 use std::ops::{Deref, DerefMut};
@@ -40,6 +42,24 @@ impl BlockReason {
     pub const fn new(value: &'static str) -> Self {
         BlockReason(value)
     }
+
+    /// Reason that isn't known at compile time, e.g. specified by operator via control panel.
+    /// Such reasons are interned, so memory is leaked only once for every distinct reason.
+    /// Should be called only for new blockers, use `ExchangeBlocker::find_reason` to get reason
+    /// of existing one
+    pub fn custom(value: &str) -> Self {
+        static CUSTOM_REASONS: OnceCell<Mutex<HashSet<&'static str>>> = OnceCell::new();
+
+        let mut custom_reasons = CUSTOM_REASONS.get_or_init(Default::default).lock();
+        match custom_reasons.get(value) {
+            Some(reason) => BlockReason(reason),
+            None => {
+                let reason: &'static str = Box::leak(value.to_owned().into_boxed_str());
+                let _ = custom_reasons.insert(reason);
+                BlockReason(reason)
+            }
+        }
+    }
 }
 
 impl From<&'static str> for BlockReason {
@@ -68,6 +88,17 @@ pub enum BlockType {
     Timed(Duration),
 }
 
+/// Current state of blocker for inspection from outside
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockerInfo {
+    pub reason: String,
+    /// Manual blocker is unblocked only by explicit `unblock` call
+    pub is_timed: bool,
+    /// Time left until timed blocker will be unblocked
+    pub remaining_timeout_ms: Option<u64>,
+    pub is_unblock_requested: bool,
+}
+
 struct TimeoutInProgress {
     end_time: Instant,
     timer_handle: JoinHandle<FutureOutcome>,
@@ -503,6 +534,21 @@ impl ExchangeBlocker {
             .is_some()
     }
 
+    /// Reason of active blocker with specified name, e.g. for unblocking by name from control panel
+    pub fn find_reason(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+        reason: &str,
+    ) -> Option<BlockReason> {
+        self.blockers
+            .read()
+            .get(exchange_account_id)
+            .expect(EXPECTED_EAI_SHOULD_BE_CREATED)
+            .keys()
+            .find(|blocker_reason| &***blocker_reason == reason)
+            .copied()
+    }
+
     pub fn is_blocked_except_reason(
         &self,
         exchange_account_id: &ExchangeAccountId,
@@ -518,6 +564,41 @@ impl ExchangeBlocker {
         is_blocker_exists && blockers_count > 1 || !is_blocker_exists && blockers_count > 0
     }
 
+    /// Active blockers for every exchange account
+    pub fn get_blockers_info(&self) -> HashMap<ExchangeAccountId, Vec<BlockerInfo>> {
+        let now = Instant::now();
+        self.blockers
+            .read()
+            .iter()
+            .map(|(exchange_account_id, blockers)| {
+                let blockers_info = blockers
+                    .iter()
+                    .map(|(reason, blocker)| {
+                        let remaining_timeout = match &*blocker.timeout.lock() {
+                            Timeout::ReadyUnblock => None,
+                            Timeout::InProgress { in_progress } => {
+                                Some(in_progress.end_time.saturating_duration_since(now))
+                            }
+                        };
+
+                        BlockerInfo {
+                            reason: reason.to_string(),
+                            is_timed: remaining_timeout.is_some(),
+                            remaining_timeout_ms: remaining_timeout.map(|x| x.as_millis() as u64),
+                            is_unblock_requested: blocker
+                                .progress_state
+                                .lock()
+                                .is_unblock_requested,
+                        }
+                    })
+                    .sorted_by(|x, y| x.reason.cmp(&y.reason))
+                    .collect_vec();
+
+                (exchange_account_id.clone(), blockers_info)
+            })
+            .collect()
+    }
+
     pub fn block(
         self: &Arc<Self>,
         exchange_account_id: &ExchangeAccountId,
@@ -830,6 +911,70 @@ mod tests {
         assert_eq!(exchange_blocker.is_blocked(&exchange_account_id()), false);
     }
 
+    #[test]
+    fn custom_reason_equals_static_one() {
+        let reason = BlockReason::custom("test_reason");
+
+        assert_eq!(reason, BlockReason::new("test_reason"));
+        assert_eq!(
+            &*reason as *const str,
+            &*BlockReason::custom("test_reason") as *const str,
+            "custom reason should be interned"
+        );
+    }
+
+    #[tokio::test]
+    async fn find_reason_of_active_blocker() {
+        let exchange_blocker = exchange_blocker();
+        let reason = BlockReason::new("test_reason");
+
+        assert_eq!(
+            exchange_blocker.find_reason(&exchange_account_id(), "test_reason"),
+            None
+        );
+
+        exchange_blocker.block(&exchange_account_id(), reason, Manual);
+        let found_reason = exchange_blocker.find_reason(&exchange_account_id(), "test_reason");
+
+        assert_eq!(found_reason, Some(reason));
+        assert_eq!(
+            found_reason.map(|found_reason| &*found_reason as *const str),
+            Some(&*reason as *const str),
+            "reason of blocker should be returned instead of new one"
+        );
+        assert_eq!(
+            exchange_blocker.find_reason(&exchange_account_id(), "other_reason"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn blockers_info() {
+        let exchange_blocker = exchange_blocker();
+
+        exchange_blocker.block(&exchange_account_id(), "manual_reason".into(), Manual);
+        exchange_blocker.block(
+            &exchange_account_id(),
+            "timed_reason".into(),
+            Timed(Duration::from_secs(60)),
+        );
+
+        let blockers_info = exchange_blocker.get_blockers_info();
+        let blockers_info = &blockers_info[&exchange_account_id()];
+        assert_eq!(blockers_info.len(), 2);
+
+        let manual_blocker = &blockers_info[0];
+        assert_eq!(manual_blocker.reason, "manual_reason");
+        assert_eq!(manual_blocker.is_timed, false);
+        assert_eq!(manual_blocker.remaining_timeout_ms, None);
+
+        let timed_blocker = &blockers_info[1];
+        assert_eq!(timed_blocker.reason, "timed_reason");
+        assert_eq!(timed_blocker.is_timed, true);
+        let remaining_timeout_ms = timed_blocker.remaining_timeout_ms.expect("in test");
+        assert!(remaining_timeout_ms > 50_000 && remaining_timeout_ms <= 60_000);
+    }
+
     #[tokio::test]
     async fn block_unblock_future() {
         let cancellation_token = CancellationToken::new();
@@ -1,4 +1,6 @@
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{bail, Context, Error, Result};
 use awc::http::StatusCode;
@@ -14,6 +16,7 @@ use super::commission::Commission;
 use super::currency_pair_metadata::CurrencyPairMetadata;
 use crate::core::connectivity::connectivity_manager::GetWSParamsCallback;
 use crate::core::exchanges::events::ExchangeEvent;
+use crate::core::exchanges::exchange_blocker::ExchangeBlocker;
 use crate::core::exchanges::general::features::ExchangeFeatures;
 use crate::core::exchanges::general::order::cancel::CancelOrderResult;
 use crate::core::exchanges::general::order::create::CreateOrderResult;
@@ -103,6 +106,11 @@ pub struct Exchange {
     pub(super) wait_cancel_order: DashMap<ClientOrderId, broadcast::Sender<()>>,
     pub(super) orders_finish_events: DashMap<ClientOrderId, oneshot::Sender<()>>,
     pub(super) orders_created_events: DashMap<ClientOrderId, oneshot::Sender<()>>,
+    // Blocker appears with EngineContext, so it is set after exchange creation
+    pub(super) exchange_blocker: Mutex<Option<Arc<ExchangeBlocker>>>,
+    pub(super) rest_request_latency_callback: Mutex<Box<dyn FnMut(Duration) + Send + Sync>>,
+    // Refresh of active positions is scheduled but not started yet
+    pub(super) is_positions_refresh_pending: AtomicBool,
 }
 
 pub type BoxExchangeClient = Box<dyn ExchangeClient + Send + Sync + 'static>;
@@ -138,6 +146,9 @@ impl Exchange {
             wait_cancel_order: DashMap::new(),
             orders_finish_events: DashMap::new(),
             orders_created_events: DashMap::new(),
+            exchange_blocker: Mutex::new(None),
+            rest_request_latency_callback: Mutex::new(Box::new(|_| {})),
+            is_positions_refresh_pending: AtomicBool::new(false),
         });
 
         exchange.clone().setup_connectivity_manager();
@@ -209,6 +220,43 @@ impl Exchange {
                     None => info!("Unable to upgrade weak reference to Exchange instance",),
                 }
             }));
+
+        let exchange_weak = Arc::downgrade(&self);
+        self.exchange_client
+            .set_positions_changed_callback(Box::new(move || match exchange_weak.upgrade() {
+                Some(exchange) => exchange.refresh_active_positions(),
+                None => info!("Unable to upgrade weak reference to Exchange instance",),
+            }));
+    }
+
+    /// None if websocket with specified role isn't used by exchange
+    pub fn is_websocket_connected(&self, role: WebSocketRole) -> Option<bool> {
+        self.exchange_client
+            .is_websocket_enabled(role)
+            .then(|| self.connectivity_manager.is_websocket_connected(role))
+    }
+
+    pub(crate) fn send_websocket_message(&self, role: WebSocketRole, message: &str) {
+        self.connectivity_manager.send(role, message);
+    }
+
+    pub(crate) fn set_websocket_message_lag_callback(
+        &self,
+        callback: Box<dyn FnMut(Duration) + Send + Sync>,
+    ) {
+        self.exchange_client
+            .set_websocket_message_lag_callback(callback);
+    }
+
+    pub(crate) fn set_rest_request_latency_callback(
+        &self,
+        callback: Box<dyn FnMut(Duration) + Send + Sync>,
+    ) {
+        *self.rest_request_latency_callback.lock() = callback;
+    }
+
+    pub(crate) fn set_exchange_blocker(&self, exchange_blocker: Arc<ExchangeBlocker>) {
+        *self.exchange_blocker.lock() = Some(exchange_blocker);
     }
 
     fn on_websocket_message(&self, msg: &str) {
@@ -246,6 +294,14 @@ impl Exchange {
     }
 
     async fn try_connect(self: Arc<Self>) {
+        if !self
+            .exchange_client
+            .is_websocket_enabled(WebSocketRole::Main)
+        {
+            // Exchange works by REST requests only, e.g. simulated one
+            return;
+        }
+
         // TODO IsWebSocketConnecting()
         info!("Websocket: Connecting on {}", "test_exchange_id");
 
@@ -273,10 +329,31 @@ impl Exchange {
 
         if !is_connected {
             // TODO finish_connected
+            return;
+        }
+
+        self.send_websocket_subscriptions(WebSocketRole::Main).await;
+        if is_enabled_secondary_websocket {
+            self.send_websocket_subscriptions(WebSocketRole::Secondary)
+                .await;
         }
         // TODO all other logs and finish_connected
     }
 
+    async fn send_websocket_subscriptions(&self, role: WebSocketRole) {
+        match self.exchange_client.get_websocket_subscriptions(role).await {
+            Ok(subscriptions) => {
+                for subscription in subscriptions {
+                    self.connectivity_manager.send(role, &subscription);
+                }
+            }
+            Err(error) => error!(
+                "Unable to get websocket {:?} subscriptions on {}: {:?}",
+                role, self.exchange_account_id, error
+            ),
+        }
+    }
+
     pub(super) fn get_rest_error(&self, response: &RestRequestOutcome) -> Option<ExchangeError> {
         self.get_rest_error_main(response, None, None)
     }
@@ -299,6 +376,41 @@ impl Exchange {
         response: &RestRequestOutcome,
         log_template: Option<String>,
         args_to_log: Option<Vec<String>>,
+    ) -> Option<ExchangeError> {
+        let result_error = self.classify_rest_error(response)?;
+
+        let mut msg_to_log = format!(
+            "Response has an error {:?}, on {}: {:?}",
+            result_error.error_type, self.exchange_account_id, result_error
+        );
+
+        if let Some(args) = args_to_log {
+            msg_to_log = format!(" {} with args: {:?}", msg_to_log, args);
+        }
+
+        if let Some(template) = log_template {
+            msg_to_log = format!(" {}", template);
+        }
+
+        let log_level = match result_error.error_type {
+            ExchangeErrorType::RateLimit
+            | ExchangeErrorType::Authentication
+            | ExchangeErrorType::InsufficientFunds
+            | ExchangeErrorType::InvalidOrder => Level::Error,
+            _ => Level::Warn,
+        };
+
+        log::log!(log_level, "{}. Response: {:?}", &msg_to_log, &response);
+
+        // TODO some HandleRestError via BotBase
+
+        Some(result_error)
+    }
+
+    /// Error of response without logging, e.g. to decide about retry
+    pub(super) fn classify_rest_error(
+        &self,
+        response: &RestRequestOutcome,
     ) -> Option<ExchangeError> {
         let result_error = match response.status {
             StatusCode::UNAUTHORIZED => ExchangeError::new(
@@ -311,7 +423,7 @@ impl Exchange {
                 response.content.clone(),
                 None,
             ),
-            StatusCode::TOO_MANY_REQUESTS => {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT => {
                 ExchangeError::new(ExchangeErrorType::RateLimit, response.content.clone(), None)
             }
             _ => match Self::check_content(&response.content) {
@@ -341,31 +453,6 @@ impl Exchange {
             },
         };
 
-        let mut msg_to_log = format!(
-            "Response has an error {:?}, on {}: {:?}",
-            result_error.error_type, self.exchange_account_id, result_error
-        );
-
-        if let Some(args) = args_to_log {
-            msg_to_log = format!(" {} with args: {:?}", msg_to_log, args);
-        }
-
-        if let Some(template) = log_template {
-            msg_to_log = format!(" {}", template);
-        }
-
-        let log_level = match result_error.error_type {
-            ExchangeErrorType::RateLimit
-            | ExchangeErrorType::Authentication
-            | ExchangeErrorType::InsufficientFunds
-            | ExchangeErrorType::InvalidOrder => Level::Error,
-            _ => Level::Warn,
-        };
-
-        log::log!(log_level, "{}. Response: {:?}", &msg_to_log, &response);
-
-        // TODO some HandleRestError via BotBase
-
         Some(result_error)
     }
 
@@ -448,7 +535,8 @@ impl Exchange {
         role: WebSocketRole,
     ) -> Result<WebSocketParams> {
         let ws_url = self.exchange_client.create_ws_url(role).await?;
-        Ok(WebSocketParams::new(ws_url))
+        let network_connector = self.exchange_client.get_network_connector().clone();
+        Ok(WebSocketParams::new(ws_url, network_connector))
     }
 
     pub(crate) fn add_event_on_order_change(
@@ -1,7 +1,8 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use itertools::Itertools;
-use log::error;
+use log::{error, warn};
 use tokio::sync::broadcast;
 
 use super::{commission::Commission, currency_pair_metadata::CurrencyPairMetadata};
@@ -11,7 +12,8 @@ use crate::core::lifecycle::launcher::EngineBuildConfig;
 use crate::core::settings::{CurrencyPairSetting, ExchangeSettings};
 use crate::core::{
     exchanges::{
-        general::exchange::Exchange,
+        general::exchange::Exchange, general::server_time::DEFAULT_SERVER_TIME_SYNC_INTERVAL,
+        general::user_data_stream::USER_DATA_STREAM_KEEPALIVE_INTERVAL,
         timeouts::requests_timeout_manager_factory::RequestsTimeoutManagerFactory,
         timeouts::timeout_manager::TimeoutManager,
     },
@@ -22,25 +24,25 @@ pub fn create_timeout_manager(
     core_settings: &CoreSettings,
     build_settings: &EngineBuildConfig,
 ) -> Arc<TimeoutManager> {
-    let request_timeout_managers = core_settings
+    let exchanges_timeout_managers = core_settings
         .exchanges
         .iter()
         .map(|exchange_settings| {
             let timeout_arguments = build_settings.supported_exchange_clients
                 [&exchange_settings.exchange_account_id.exchange_id]
-                .get_timeout_argments();
+                .get_timeout_argments(exchange_settings);
 
             let exchange_account_id = exchange_settings.exchange_account_id.clone();
-            let request_timeout_manager = RequestsTimeoutManagerFactory::from_requests_per_period(
+            let request_timeout_managers = RequestsTimeoutManagerFactory::from_timeout_arguments(
                 timeout_arguments,
                 exchange_account_id.clone(),
             );
 
-            (exchange_account_id, request_timeout_manager)
+            (exchange_account_id, request_timeout_managers)
         })
         .collect();
 
-    TimeoutManager::new(request_timeout_managers)
+    TimeoutManager::new(exchanges_timeout_managers)
 }
 
 pub async fn create_exchange(
@@ -49,6 +51,39 @@ pub async fn create_exchange(
     events_channel: broadcast::Sender<ExchangeEvent>,
     application_manager: Arc<ApplicationManager>,
     timeout_manager: Arc<TimeoutManager>,
+) -> Arc<Exchange> {
+    let exchange = create_exchange_without_connection(
+        user_settings,
+        build_settings,
+        events_channel,
+        application_manager,
+        timeout_manager,
+    )
+    .await;
+
+    exchange.clone().connect().await;
+
+    let server_time_sync_interval = user_settings
+        .server_time_sync_interval_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_SERVER_TIME_SYNC_INTERVAL);
+    exchange
+        .clone()
+        .start_server_time_sync(server_time_sync_interval);
+    exchange
+        .clone()
+        .start_user_data_stream_keepalive(USER_DATA_STREAM_KEEPALIVE_INTERVAL);
+
+    exchange
+}
+
+/// Exchange with metadata but without websocket connections, e.g. for one-off rest requests
+pub async fn create_exchange_without_connection(
+    user_settings: &ExchangeSettings,
+    build_settings: &EngineBuildConfig,
+    events_channel: broadcast::Sender<ExchangeEvent>,
+    application_manager: Arc<ApplicationManager>,
+    timeout_manager: Arc<TimeoutManager>,
 ) -> Arc<Exchange> {
     let exchange_client_builder =
         &build_settings.supported_exchange_clients[&user_settings.exchange_account_id.exchange_id];
@@ -73,12 +108,18 @@ pub async fn create_exchange(
 
     exchange.build_metadata().await;
 
+    // Signed requests are rejected by exchange if local clock is out of sync
+    if let Err(error) = exchange.sync_server_time().await {
+        warn!(
+            "Unable to sync server time on {}: {:?}",
+            exchange.exchange_account_id, error
+        );
+    }
+
     if let Some(currency_pairs) = &user_settings.currency_pairs {
         exchange.set_symbols(get_symbols(&exchange, &currency_pairs[..]))
     }
 
-    exchange.clone().connect().await;
-
     exchange
 }
 
@@ -1,9 +1,14 @@
+use crate::core::connectivity::connectivity_manager::WebSocketRole;
 use crate::core::exchanges::common::{CurrencyCode, CurrencyId};
 use crate::core::exchanges::general::exchange::Exchange;
+use crate::core::exchanges::general::exchange_creation::get_symbols;
+use crate::core::exchanges::general::request_type::RequestType;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::settings::CurrencyPairSetting;
 use anyhow::{bail, Result};
 use dashmap::DashMap;
 use itertools::Itertools;
-use log::warn;
+use log::{error, info, warn};
 use std::sync::Arc;
 
 use super::currency_pair_metadata::CurrencyPairMetadata;
@@ -42,6 +47,11 @@ impl Exchange {
         *self.supported_symbols.lock() = symbols;
     }
 
+    /// All symbols from exchange metadata, not only traded ones
+    pub fn get_supported_symbols(&self) -> Vec<Arc<CurrencyPairMetadata>> {
+        self.supported_symbols.lock().clone()
+    }
+
     fn set_supported_currencies(&self, supported_currencies: DashMap<CurrencyCode, CurrencyId>) {
         for (currency_code, currency_id) in supported_currencies {
             self.exchange_client
@@ -51,7 +61,13 @@ impl Exchange {
     }
 
     async fn build_metadata_core(&self) -> Result<Vec<Arc<CurrencyPairMetadata>>> {
-        let response = self.exchange_client.request_metadata().await?;
+        let response = self
+            .request_with_retries(
+                RequestType::GetMarkets,
+                CancellationToken::default(),
+                || self.exchange_client.request_metadata(),
+            )
+            .await?;
 
         if let Some(error) = self.get_rest_error(&response) {
             bail!(
@@ -83,6 +99,74 @@ impl Exchange {
             .collect()
     }
 
+    /// Symbols of currency pairs which aren't traded yet.
+    /// Returns error if any of currency pairs isn't supported by exchange
+    pub fn find_new_symbols(
+        self: &Arc<Self>,
+        currency_pairs: &[CurrencyPairSetting],
+    ) -> Result<Vec<Arc<CurrencyPairMetadata>>> {
+        let symbols = get_symbols(self, currency_pairs);
+        if symbols.len() != currency_pairs.len() {
+            bail!(
+                "Not all of currency pairs {:?} are supported by {}",
+                currency_pairs,
+                self.exchange_account_id
+            );
+        }
+
+        Ok(symbols
+            .into_iter()
+            .filter(|symbol| !self.symbols.contains_key(&symbol.currency_pair()))
+            .collect())
+    }
+
+    /// Adds symbols to traded ones on running exchange and subscribes connected main websocket
+    /// to their market data. After reconnection websocket is subscribed to all traded symbols anyway
+    pub fn add_symbols(&self, new_symbols: Vec<Arc<CurrencyPairMetadata>>) {
+        if new_symbols.is_empty() {
+            return;
+        }
+
+        let new_currency_pairs = new_symbols
+            .iter()
+            .map(|symbol| {
+                self.exchange_client
+                    .get_specific_currency_pair(&symbol.currency_pair())
+            })
+            .collect_vec();
+
+        let mut symbols = self
+            .symbols
+            .iter()
+            .map(|symbol| symbol.value().clone())
+            .collect_vec();
+        symbols.extend(new_symbols);
+        self.set_symbols(symbols);
+        info!(
+            "Currency pairs {:?} added on {}",
+            new_currency_pairs, self.exchange_account_id
+        );
+
+        if self.is_websocket_connected(WebSocketRole::Main) != Some(true) {
+            return;
+        }
+
+        match self
+            .exchange_client
+            .get_currency_pairs_subscriptions(&new_currency_pairs)
+        {
+            Ok(subscriptions) => {
+                for subscription in subscriptions {
+                    self.send_websocket_message(WebSocketRole::Main, &subscription);
+                }
+            }
+            Err(error) => error!(
+                "Unable to subscribe to added currency pairs on {} until websocket reconnection: {:?}",
+                self.exchange_account_id, error
+            ),
+        }
+    }
+
     pub fn set_symbols(&self, symbols: Vec<Arc<CurrencyPairMetadata>>) {
         let mut currencies = symbols
             .iter()
@@ -1,4 +1,5 @@
 use crate::core::exchanges::events::AllowedEventSourceType;
+use crate::core::exchanges::general::retry_policy::RetryPolicies;
 
 #[derive(Debug)]
 pub enum OpenOrdersType {
@@ -15,6 +16,7 @@ pub struct ExchangeFeatures {
     pub allows_to_get_order_info_by_client_order_id: bool,
     pub allowed_fill_event_source_type: AllowedEventSourceType,
     pub allowed_cancel_event_source_type: AllowedEventSourceType,
+    pub retry_policies: RetryPolicies,
 }
 
 impl ExchangeFeatures {
@@ -31,6 +33,12 @@ impl ExchangeFeatures {
             allows_to_get_order_info_by_client_order_id,
             allowed_fill_event_source_type,
             allowed_cancel_event_source_type,
+            retry_policies: RetryPolicies::default(),
         }
     }
+
+    pub fn with_retry_policies(mut self, retry_policies: RetryPolicies) -> Self {
+        self.retry_policies = retry_policies;
+        self
+    }
 }
@@ -0,0 +1,173 @@
+use awc::http::StatusCode;
+use log::{error, warn};
+
+use crate::core::exchanges::{
+    block_reasons::REST_RATE_LIMIT, common::RestRequestOutcome, exchange_blocker::BlockType,
+    general::exchange::Exchange, general::request_type::RequestType, rest_client,
+};
+
+impl Exchange {
+    /// Keeps requests limits in sync with exchange reported usage, registers request latency
+    /// and blocks exchange when rate limit is exceeded
+    pub(crate) fn handle_rest_response(
+        &self,
+        request_type: RequestType,
+        response: &RestRequestOutcome,
+    ) {
+        // Zero latency means response wasn't received over network (e.g. simulated exchange)
+        if !response.latency.is_zero() {
+            (self.rest_request_latency_callback.lock())(response.latency);
+        }
+
+        let usages = self.exchange_client.get_requests_usages(response);
+        if !usages.is_empty() {
+            if let Err(error) =
+                self.timeout_manager
+                    .resync(&self.exchange_account_id, request_type, &usages)
+            {
+                error!(
+                    "Unable to resync requests usage on {}: {:?}",
+                    self.exchange_account_id, error
+                );
+            }
+        }
+
+        // 418 means IP was banned after ignoring 429 responses
+        if response.status != StatusCode::TOO_MANY_REQUESTS
+            && response.status != StatusCode::IM_A_TEAPOT
+        {
+            return;
+        }
+
+        let retry_after = match rest_client::get_retry_after(&response.headers) {
+            Some(retry_after) => retry_after,
+            None => {
+                warn!(
+                    "Rate limit response {} without Retry-After on {} for {:?}",
+                    response.status, self.exchange_account_id, request_type
+                );
+                return;
+            }
+        };
+
+        warn!(
+            "Rate limit response {} on {} for {:?}, exchange is blocked for {:?}",
+            response.status, self.exchange_account_id, request_type, retry_after
+        );
+
+        match self.exchange_blocker.lock().as_ref() {
+            Some(exchange_blocker) => exchange_blocker.block(
+                &self.exchange_account_id,
+                REST_RATE_LIMIT,
+                BlockType::Timed(retry_after),
+            ),
+            None => error!(
+                "Unable to block {} by rate limit because ExchangeBlocker isn't set",
+                self.exchange_account_id
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::RETRY_AFTER;
+
+    use super::*;
+    use crate::core::exchanges::exchange_blocker::ExchangeBlocker;
+    use crate::core::exchanges::general::test_helper::get_test_exchange;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn rate_limit_response(status: StatusCode, retry_after: Option<&str>) -> RestRequestOutcome {
+        let mut response = RestRequestOutcome::new(
+            r#"{"code":-1003,"msg":"Too many requests."}"#.to_owned(),
+            status,
+        );
+        if let Some(retry_after) = retry_after {
+            response
+                .headers
+                .insert(RETRY_AFTER, retry_after.parse().expect("in test"));
+        }
+
+        response
+    }
+
+    #[tokio::test]
+    async fn block_for_retry_after_duration() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        let exchange_blocker = ExchangeBlocker::new(vec![exchange_account_id.clone()]);
+        exchange.set_exchange_blocker(exchange_blocker.clone());
+
+        exchange.handle_rest_response(
+            RequestType::CreateOrder,
+            &rate_limit_response(StatusCode::TOO_MANY_REQUESTS, Some("30")),
+        );
+
+        assert!(exchange_blocker.is_blocked_by_reason(&exchange_account_id, REST_RATE_LIMIT));
+        let blockers_info = exchange_blocker.get_blockers_info();
+        let remaining_timeout_ms = blockers_info[&exchange_account_id][0]
+            .remaining_timeout_ms
+            .expect("in test");
+        assert!(remaining_timeout_ms <= 30_000 && remaining_timeout_ms > 29_000);
+
+        exchange_blocker.stop_blocker().await;
+    }
+
+    #[tokio::test]
+    async fn ip_ban_blocks_exchange() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        let exchange_blocker = ExchangeBlocker::new(vec![exchange_account_id.clone()]);
+        exchange.set_exchange_blocker(exchange_blocker.clone());
+
+        exchange.handle_rest_response(
+            RequestType::GetOpenOrders,
+            &rate_limit_response(StatusCode::IM_A_TEAPOT, Some("120")),
+        );
+
+        assert!(exchange_blocker.is_blocked_by_reason(&exchange_account_id, REST_RATE_LIMIT));
+
+        exchange_blocker.stop_blocker().await;
+    }
+
+    #[tokio::test]
+    async fn no_block_without_rate_limit_status_or_retry_after() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        let exchange_blocker = ExchangeBlocker::new(vec![exchange_account_id.clone()]);
+        exchange.set_exchange_blocker(exchange_blocker.clone());
+
+        exchange.handle_rest_response(
+            RequestType::CreateOrder,
+            &rate_limit_response(StatusCode::BAD_REQUEST, Some("30")),
+        );
+        exchange.handle_rest_response(
+            RequestType::CreateOrder,
+            &rate_limit_response(StatusCode::TOO_MANY_REQUESTS, None),
+        );
+
+        assert!(!exchange_blocker.is_blocked(&exchange_account_id));
+
+        exchange_blocker.stop_blocker().await;
+    }
+
+    #[test]
+    fn register_latency_of_received_response() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let latencies = Arc::new(Mutex::new(Vec::new()));
+        let latencies_clone = latencies.clone();
+        exchange.set_rest_request_latency_callback(Box::new(move |latency| {
+            latencies_clone.lock().push(latency)
+        }));
+
+        let mut response = RestRequestOutcome::new("{}".to_owned(), StatusCode::OK);
+        exchange.handle_rest_response(RequestType::GetOpenOrders, &response);
+        response.latency = Duration::from_millis(15);
+        exchange.handle_rest_response(RequestType::GetOpenOrders, &response);
+
+        assert_eq!(*latencies.lock(), vec![Duration::from_millis(15)]);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod handle_cancel_order_failed;
 pub mod handle_cancel_order_succeeded;
 pub mod handle_order_filled;
+pub mod handle_rest_response;
@@ -6,6 +6,10 @@ pub mod exchange_metadata;
 pub mod features;
 pub mod handlers;
 pub mod order;
+pub mod positions;
 pub mod request_type;
+pub mod retry_policy;
+pub mod server_time;
 #[cfg(test)]
 pub mod test_helper;
+pub mod user_data_stream;
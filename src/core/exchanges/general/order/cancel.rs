@@ -1,9 +1,10 @@
 use anyhow::{anyhow, Result};
 use chrono::Utc;
 use futures::future::join_all;
-use log::{error, info};
+use log::{error, info, Level};
 use tokio::sync::oneshot;
 
+use crate::core::logger::LogFields;
 use crate::core::{
     exchanges::common::Amount,
     exchanges::common::ExchangeError,
@@ -11,6 +12,7 @@ use crate::core::{
     exchanges::common::RestRequestOutcome,
     exchanges::general::exchange::Exchange,
     exchanges::general::exchange::RequestResult,
+    exchanges::general::request_type::RequestType,
     lifecycle::cancellation_token::CancellationToken,
     orders::order::ClientOrderId,
     orders::order::ExchangeOrderId,
@@ -19,6 +21,7 @@ use crate::core::{
     orders::pool::OrderRef,
     orders::{fill::EventSourceType, order::OrderCancelling},
 };
+use crate::log_with_fields;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct CancelOrderResult {
@@ -152,7 +155,10 @@ impl Exchange {
         self.order_cancellation_events
             .insert(exchange_order_id.clone(), (tx, None));
 
-        let order_cancel_future = self.exchange_client.request_cancel_order(&order);
+        let order_cancel_future =
+            self.request_with_retries(RequestType::CancelOrder, cancellation_token.clone(), || {
+                self.exchange_client.request_cancel_order(&order)
+            });
 
         tokio::select! {
             rest_request_outcome = order_cancel_future => {
@@ -188,9 +194,18 @@ impl Exchange {
         request_outcome: &Result<RestRequestOutcome>,
         order: &OrderCancelling,
     ) -> CancelOrderResult {
-        info!(
+        let log_fields = LogFields::order(
+            &order.header.exchange_account_id,
+            &order.header.currency_pair,
+            &order.header.client_order_id,
+        );
+        log_with_fields!(
+            log_fields,
+            Level::Info,
             "Cancel response for {}, {:?}, {:?}",
-            order.header.client_order_id, order.header.exchange_account_id, request_outcome
+            order.header.client_order_id,
+            order.header.exchange_account_id,
+            request_outcome
         );
 
         match request_outcome {
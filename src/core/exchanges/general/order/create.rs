@@ -1,9 +1,10 @@
 use anyhow::{anyhow, bail, Result};
 use chrono::Utc;
-use log::{error, info, warn};
+use log::{error, info, warn, Level};
 use tokio::sync::oneshot;
 
 use crate::core::exchanges::general::exchange::RequestResult::{Error, Success};
+use crate::core::logger::LogFields;
 use crate::core::nothing_to_do;
 use crate::core::orders::event::OrderEventType;
 use crate::core::{
@@ -20,6 +21,7 @@ use crate::core::{
     orders::pool::OrderRef,
     orders::{fill::EventSourceType, order::OrderCreating},
 };
+use crate::log_with_fields;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct CreateOrderResult {
@@ -49,9 +51,23 @@ impl Exchange {
         order_to_create: &OrderCreating,
         cancellation_token: CancellationToken,
     ) -> Result<OrderRef> {
-        info!("Submitting order {:?}", order_to_create);
-        self.orders
+        let header = &order_to_create.header;
+        let log_fields = LogFields::order(
+            &header.exchange_account_id,
+            &header.currency_pair,
+            &header.client_order_id,
+        );
+        log_with_fields!(
+            log_fields,
+            Level::Info,
+            "Submitting order {:?}",
+            order_to_create
+        );
+        let order = self
+            .orders
             .add_simple_initial(order_to_create.header.clone(), Some(order_to_create.price));
+        // Time of creation request is needed for latency statistics
+        order.fn_mut(|order| order.set_status(OrderStatus::Creating, Utc::now()));
 
         let _linked_cancellation_token = cancellation_token.create_linked_token();
 
@@ -102,7 +118,14 @@ impl Exchange {
                 // TODO DataRecorder.Save(order); Do we really need it here?
                 // Cause it's already performed in handle_create_order_succeeded
 
-                info!(
+                let log_fields = LogFields::order(
+                    &result_order.exchange_account_id(),
+                    &result_order.currency_pair(),
+                    &result_order.client_order_id(),
+                );
+                log_with_fields!(
+                    log_fields,
+                    Level::Info,
                     "Order was submitted {} {:?} {:?} on {}",
                     result_order.client_order_id(),
                     result_order.exchange_order_id(),
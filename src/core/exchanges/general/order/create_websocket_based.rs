@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use log::{error, info};
 use tokio::sync::oneshot;
 
@@ -8,6 +8,7 @@ use crate::core::{
     exchanges::common::RestRequestOutcome,
     exchanges::general::exchange::Exchange,
     exchanges::general::exchange::RequestResult,
+    exchanges::general::request_type::RequestType,
     lifecycle::cancellation_token::CancellationToken,
     orders::order::ClientOrderId,
     orders::order::ExchangeOrderId,
@@ -30,7 +31,12 @@ impl Exchange {
         self.order_creation_events
             .insert(client_order_id.clone(), (tx, None));
 
-        let order_create_future = self.exchange_client.create_order(&order);
+        let order_create_future = self.request_with_checked_retries(
+            RequestType::CreateOrder,
+            cancellation_token.clone(),
+            || self.exchange_client.create_order(&order),
+            || self.get_created_order_response(&client_order_id),
+        );
 
         tokio::select! {
             rest_request_outcome = order_create_future => {
@@ -61,6 +67,48 @@ impl Exchange {
         };
     }
 
+    /// Order info contains exchange order id, so it replaces lost creation response if order was created
+    async fn get_created_order_response(
+        &self,
+        client_order_id: &ClientOrderId,
+    ) -> Result<Option<RestRequestOutcome>> {
+        if !self.features.allows_to_get_order_info_by_client_order_id {
+            bail!(
+                "Unable to get order {} info by client order id on {}",
+                client_order_id,
+                self.exchange_account_id
+            );
+        }
+
+        let order = self
+            .orders
+            .cache_by_client_id
+            .get(client_order_id)
+            .map(|order| order.clone())
+            .with_context(|| format!("Order {} isn't in local orders pool", client_order_id))?;
+
+        self.timeout_manager
+            .reserve_when_available(
+                &self.exchange_account_id,
+                RequestType::GetOrderInfo,
+                None,
+                CancellationToken::default(),
+            )?
+            .await
+            .into_result()?;
+        let response = self.exchange_client.request_order_info(&order).await?;
+
+        match self.classify_rest_error(&response) {
+            None => Ok(Some(response)),
+            Some(error) if error.error_type == ExchangeErrorType::OrderNotFound => Ok(None),
+            Some(error) => bail!(
+                "Unable to get order {} info: {}",
+                client_order_id,
+                error.message
+            ),
+        }
+    }
+
     fn handle_create_order_response(
         &self,
         request_outcome: &Result<RestRequestOutcome>,
@@ -1,6 +1,8 @@
 use crate::core::{
     exchanges::common::ExchangeError, exchanges::common::ExchangeErrorType,
-    exchanges::general::exchange::Exchange, orders::order::OrderInfo, orders::pool::OrderRef,
+    exchanges::general::exchange::Exchange, exchanges::general::request_type::RequestType,
+    lifecycle::cancellation_token::CancellationToken, orders::order::OrderInfo,
+    orders::pool::OrderRef,
 };
 use anyhow::*;
 use log::info;
@@ -29,7 +31,13 @@ impl Exchange {
             order.exchange_order_id(),
             self.exchange_account_id
         );
-        let request_outcome = self.exchange_client.request_order_info(order).await;
+        let request_outcome = self
+            .request_with_retries(
+                RequestType::GetOrderInfo,
+                CancellationToken::default(),
+                || self.exchange_client.request_order_info(order),
+            )
+            .await;
 
         match request_outcome {
             Ok(request_outcome) => {
@@ -11,36 +11,12 @@ use crate::core::{
 };
 use anyhow::bail;
 use anyhow::Error;
-use log::{info, warn};
+use log::info;
 use parking_lot::RwLock;
 
 use std::sync::Arc;
-use tokio::time::Duration;
 
 impl Exchange {
-    pub async fn get_open_orders(
-        &self,
-        add_missing_open_orders: bool,
-    ) -> anyhow::Result<Vec<OrderInfo>> {
-        // Bugs on exchange server can lead to Err even if order was opened
-        const MAX_COUNT: i32 = 5;
-        let mut count = 0;
-        loop {
-            match self.get_open_orders_core(add_missing_open_orders).await {
-                Ok(gotten_orders) => return Ok(gotten_orders),
-                Err(error) => {
-                    count += 1;
-                    if count < MAX_COUNT {
-                        warn!("{}", error);
-                    } else {
-                        return Err(error);
-                    }
-                }
-            }
-            tokio::time::sleep(Duration::from_secs(1)).await;
-        }
-    }
-
     async fn request_when_available_by_currency_pair(
         &self,
         currency_pair: CurrencyPair,
@@ -48,19 +24,29 @@ impl Exchange {
         self.timeout_manager
             .reserve_when_available(
                 &self.exchange_account_id,
-                RequestType::GetOpenOrders,
+                RequestType::GetOpenOrdersByCurrencyPair,
                 None,
                 CancellationToken::default(),
             )?
             .await
             .into_result()?;
-        self.exchange_client
-            .request_open_orders_by_currency_pair(currency_pair)
-            .await
+        let response = self
+            .request_with_retries(
+                RequestType::GetOpenOrdersByCurrencyPair,
+                CancellationToken::default(),
+                || {
+                    self.exchange_client
+                        .request_open_orders_by_currency_pair(currency_pair.clone())
+                },
+            )
+            .await?;
+
+        Ok(response)
     }
 
-    // Bugs on exchange server can lead to Err even if order was opened
-    async fn get_open_orders_core(
+    /// Bugs on exchange server can lead to Err even if order was opened,
+    /// so failed requests are repeated according to retry policy of open orders request type
+    pub async fn get_open_orders(
         &self,
         check_missing_orders: bool,
     ) -> anyhow::Result<Vec<OrderInfo>> {
@@ -76,7 +62,13 @@ impl Exchange {
                     )?
                     .await
                     .into_result()?;
-                let response = self.exchange_client.request_open_orders().await?;
+                let response = self
+                    .request_with_retries(
+                        RequestType::GetOpenOrders,
+                        CancellationToken::default(),
+                        || self.exchange_client.request_open_orders(),
+                    )
+                    .await?;
 
                 info!(
                     "get_open_orders() response on {}: {:?}",
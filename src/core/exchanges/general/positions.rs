@@ -0,0 +1,218 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use futures::FutureExt;
+use log::warn;
+use rust_decimal::Decimal;
+use tokio::time::sleep;
+
+use crate::core::exchanges::{
+    common::CurrencyPair,
+    events::{DerivativePosition, ExchangeEvent, LiquidationPriceEvent},
+    general::exchange::Exchange,
+    general::request_type::RequestType,
+};
+use crate::core::infrastructure::spawn_future;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+
+/// Binance sends ACCOUNT_UPDATE for every balance and position change, so bursts of updates are coalesced
+pub const POSITIONS_REFRESH_DELAY: Duration = Duration::from_secs(1);
+
+impl Exchange {
+    pub async fn set_leverage(
+        &self,
+        currency_pair: CurrencyPair,
+        leverage: Decimal,
+        cancellation_token: CancellationToken,
+    ) -> Result<()> {
+        self.timeout_manager
+            .reserve_when_available(
+                &self.exchange_account_id,
+                RequestType::SetLeverage,
+                None,
+                cancellation_token.clone(),
+            )?
+            .await
+            .into_result()?;
+
+        let response = self
+            .request_with_retries(RequestType::SetLeverage, cancellation_token, || {
+                self.exchange_client
+                    .request_set_leverage(currency_pair.clone(), leverage)
+            })
+            .await?;
+
+        if let Some(error) = self.get_rest_error(&response) {
+            bail!(
+                "Rest error appeared during request set_leverage: {}",
+                error.message
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Liquidation prices of received positions are published as LiquidationPriceEvent
+    pub async fn get_active_positions(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<DerivativePosition>> {
+        self.timeout_manager
+            .reserve_when_available(
+                &self.exchange_account_id,
+                RequestType::GetActivePositions,
+                None,
+                cancellation_token.clone(),
+            )?
+            .await
+            .into_result()?;
+
+        let response = self
+            .request_with_retries(RequestType::GetActivePositions, cancellation_token, || {
+                self.exchange_client.request_active_positions()
+            })
+            .await?;
+
+        if let Some(error) = self.get_rest_error(&response) {
+            bail!(
+                "Rest error appeared during request get_active_positions: {}",
+                error.message
+            );
+        }
+
+        let positions = self.exchange_client.parse_active_positions(&response)?;
+        self.publish_liquidation_prices(&positions)?;
+
+        Ok(positions)
+    }
+
+    /// Positions without liquidation price (e.g. without margin requirements) are skipped
+    pub(super) fn publish_liquidation_prices(
+        &self,
+        positions: &[DerivativePosition],
+    ) -> Result<()> {
+        for position in positions {
+            let liquidation_price = match position.liquidation_price {
+                Some(liquidation_price) if !liquidation_price.is_zero() => liquidation_price,
+                _ => continue,
+            };
+
+            let event = LiquidationPriceEvent::new(
+                Utc::now(),
+                self.exchange_account_id.clone(),
+                position.currency_pair.clone(),
+                liquidation_price,
+                position.average_entry_price,
+                position.side(),
+            );
+            self.events_channel
+                .send(ExchangeEvent::LiquidationPrice(event))
+                .context("Unable to send event. Probably receiver is already dropped")?;
+        }
+
+        Ok(())
+    }
+
+    /// Position updates from websocket have no liquidation price, so positions are requested again.
+    /// Updates received before pending refresh started are handled by that refresh
+    pub(super) fn refresh_active_positions(self: Arc<Self>) {
+        if self
+            .is_positions_refresh_pending
+            .swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+
+        let action = async move {
+            let cancellation_token = self.application_manager.stop_token();
+            tokio::select! {
+                _ = sleep(POSITIONS_REFRESH_DELAY) => {}
+                _ = cancellation_token.when_cancelled() => return Ok(()),
+            }
+
+            // Updates received during request schedule one more refresh
+            self.is_positions_refresh_pending
+                .store(false, Ordering::SeqCst);
+            if let Err(error) = self.get_active_positions(cancellation_token).await {
+                warn!(
+                    "Unable to refresh active positions on {}: {:?}",
+                    self.exchange_account_id, error
+                );
+            }
+
+            Ok(())
+        };
+        spawn_future("Refresh active positions", false, action.boxed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::core::exchanges::general::test_helper::get_test_exchange;
+    use crate::core::orders::order::OrderSide;
+
+    fn position(position: Decimal, liquidation_price: Option<Decimal>) -> DerivativePosition {
+        DerivativePosition {
+            currency_pair: CurrencyPair::from_codes("btc".into(), "usdt".into()),
+            position,
+            average_entry_price: dec!(30000),
+            liquidation_price,
+            leverage: Some(dec!(10)),
+        }
+    }
+
+    #[test]
+    fn publish_liquidation_prices() {
+        let (exchange, mut rx) = get_test_exchange(true);
+
+        exchange
+            .publish_liquidation_prices(&[
+                position(dec!(-0.5), Some(dec!(32900))),
+                position(dec!(1), Some(dec!(0))),
+                position(dec!(1), None),
+            ])
+            .expect("in test");
+
+        let event = match rx.try_recv().expect("in test") {
+            ExchangeEvent::LiquidationPrice(event) => event,
+            _ => panic!("Unexpected exchange event"),
+        };
+        assert_eq!(event.liq_price, dec!(32900));
+        assert_eq!(event.entry_price, dec!(30000));
+        assert_eq!(event.side, OrderSide::Sell);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn positions_refreshes_are_coalesced() {
+        let (exchange, _rx) = get_test_exchange(true);
+
+        exchange.clone().refresh_active_positions();
+        exchange.clone().refresh_active_positions();
+        assert!(exchange.is_positions_refresh_pending.load(Ordering::SeqCst));
+
+        sleep(POSITIONS_REFRESH_DELAY + Duration::from_millis(100)).await;
+        assert!(!exchange.is_positions_refresh_pending.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn positions_are_not_supported_by_spot_exchange() {
+        let (exchange, _rx) = get_test_exchange(false);
+
+        let result = exchange
+            .set_leverage(
+                CurrencyPair::from_codes("btc".into(), "usdt".into()),
+                dec!(10),
+                CancellationToken::default(),
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+}
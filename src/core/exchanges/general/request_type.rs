@@ -1,10 +1,11 @@
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum RequestType {
     CreateOrder,
     CancelOrder,
     GetOrderInfo,
     GetBalance,
     GetOpenOrders,
+    GetOpenOrdersByCurrencyPair,
     GetMarkets,
     GetCurrencies,
     GetOrderBook,
@@ -22,4 +23,5 @@ pub enum RequestType {
     GetProfileId,
     GetMyTrades,
     SetLeverage,
+    GetServerTime,
 }
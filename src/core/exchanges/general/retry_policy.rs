@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use futures::future::ready;
+use log::warn;
+use tokio::time::sleep;
+
+use crate::core::exchanges::{
+    common::ExchangeErrorType, common::RestRequestOutcome, general::exchange::Exchange,
+    general::request_type::RequestType, rest_client,
+};
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+
+/// How request can be repeated after failure
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Idempotency {
+    /// Request can be repeated as is
+    Idempotent,
+    /// Request changes exchange state, so after error which doesn't guarantee that request was rejected
+    /// it is repeated only if check (e.g. by client order id) shows that previous attempt had no effect
+    CheckBeforeRetry,
+    NotRetryable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts count including the first one
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub idempotency: Idempotency,
+}
+
+impl RetryPolicy {
+    pub fn new(
+        max_attempts: u32,
+        initial_delay: Duration,
+        max_delay: Duration,
+        idempotency: Idempotency,
+    ) -> Self {
+        Self {
+            max_attempts,
+            initial_delay,
+            max_delay,
+            idempotency,
+        }
+    }
+
+    /// Delay before the next attempt or None if request shouldn't be repeated after error of given type
+    pub fn get_retry_delay(
+        &self,
+        error_type: ExchangeErrorType,
+        failed_attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if self.idempotency == Idempotency::NotRetryable || failed_attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = match error_type {
+            ExchangeErrorType::PendingError(pending_time) => pending_time,
+            // Server time is resynchronized before retry, so there is nothing to wait for
+            ExchangeErrorType::InvalidTimestamp => Duration::ZERO,
+            ExchangeErrorType::RateLimit => self
+                .get_backoff_delay(failed_attempt)
+                .max(retry_after.unwrap_or_default()),
+            ExchangeErrorType::SendError | ExchangeErrorType::ServiceUnavailable => {
+                self.get_backoff_delay(failed_attempt)
+            }
+            _ => return None,
+        };
+
+        // Don't hold the caller longer than policy allows, e.g. during exchange ban
+        (delay <= self.max_delay).then_some(delay)
+    }
+
+    /// Exponential backoff limited by max_delay
+    fn get_backoff_delay(&self, failed_attempt: u32) -> Duration {
+        let multiplier = 2u32.saturating_pow(failed_attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(multiplier)
+            .min(self.max_delay)
+    }
+}
+
+/// Request could be processed by exchange even though response is an error
+pub fn may_be_applied(error_type: ExchangeErrorType) -> bool {
+    matches!(
+        error_type,
+        ExchangeErrorType::SendError
+            | ExchangeErrorType::ServiceUnavailable
+            | ExchangeErrorType::Unknown
+    )
+}
+
+/// Retry policies of exchange by request type
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicies {
+    default: RetryPolicy,
+    by_request_type: HashMap<RequestType, RetryPolicy>,
+}
+
+impl RetryPolicies {
+    pub fn new(default: RetryPolicy) -> Self {
+        Self {
+            default,
+            by_request_type: HashMap::new(),
+        }
+    }
+
+    pub fn with_policy(mut self, request_type: RequestType, policy: RetryPolicy) -> Self {
+        let _ = self.by_request_type.insert(request_type, policy);
+        self
+    }
+
+    pub fn get(&self, request_type: RequestType) -> &RetryPolicy {
+        self.by_request_type
+            .get(&request_type)
+            .unwrap_or(&self.default)
+    }
+}
+
+impl Default for RetryPolicies {
+    fn default() -> Self {
+        let open_orders_policy = RetryPolicy::new(
+            5,
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+            Idempotency::Idempotent,
+        );
+
+        Self::new(RetryPolicy::new(
+            3,
+            Duration::from_millis(200),
+            Duration::from_secs(5),
+            Idempotency::Idempotent,
+        ))
+        .with_policy(
+            RequestType::CreateOrder,
+            RetryPolicy::new(
+                2,
+                Duration::from_millis(200),
+                Duration::from_secs(1),
+                Idempotency::CheckBeforeRetry,
+            ),
+        )
+        .with_policy(RequestType::GetOpenOrders, open_orders_policy.clone())
+        .with_policy(RequestType::GetOpenOrdersByCurrencyPair, open_orders_policy)
+    }
+}
+
+impl Exchange {
+    pub(super) async fn request_with_retries<F, Fut>(
+        &self,
+        request_type: RequestType,
+        cancellation_token: CancellationToken,
+        request: F,
+    ) -> Result<RestRequestOutcome>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<RestRequestOutcome>>,
+    {
+        self.request_with_checked_retries(request_type, cancellation_token, request, || {
+            ready(Err(anyhow!(
+                "There is no check before retry of {:?}",
+                request_type
+            )))
+        })
+        .await
+    }
+
+    /// First attempt should be reserved in TimeoutManager by caller, retries are reserved here.
+    /// Every response is handled by `handle_rest_response` to keep requests limits in sync.
+    /// `check_before_retry` returns response which replaces failed one if previous attempt took effect
+    pub(super) async fn request_with_checked_retries<F, Fut, C, CFut>(
+        &self,
+        request_type: RequestType,
+        cancellation_token: CancellationToken,
+        request: F,
+        check_before_retry: C,
+    ) -> Result<RestRequestOutcome>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<RestRequestOutcome>>,
+        C: Fn() -> CFut,
+        CFut: Future<Output = Result<Option<RestRequestOutcome>>>,
+    {
+        let policy = self.features.retry_policies.get(request_type);
+        let mut attempt = 1;
+        loop {
+            let outcome = request().await;
+            let (error_type, retry_after) = match &outcome {
+                Ok(response) => {
+                    self.handle_rest_response(request_type, response);
+                    match self.classify_rest_error(response) {
+                        None => return outcome,
+                        Some(error) => (
+                            error.error_type,
+                            rest_client::get_retry_after(&response.headers),
+                        ),
+                    }
+                }
+                Err(_) => (ExchangeErrorType::SendError, None),
+            };
+
+            let delay = match policy.get_retry_delay(error_type, attempt, retry_after) {
+                Some(delay) => delay,
+                None => return outcome,
+            };
+
+            if policy.idempotency == Idempotency::CheckBeforeRetry && may_be_applied(error_type) {
+                match check_before_retry().await {
+                    Ok(None) => {}
+                    Ok(Some(response)) => return Ok(response),
+                    Err(error) => {
+                        warn!(
+                            "Request {:?} on {} isn't retried because previous attempt can't be checked: {:?}",
+                            request_type, self.exchange_account_id, error
+                        );
+                        return outcome;
+                    }
+                }
+            }
+
+            warn!(
+                "Request {:?} on {} failed with {:?} on attempt {}, retry in {:?}",
+                request_type, self.exchange_account_id, error_type, attempt, delay
+            );
+
+            if error_type == ExchangeErrorType::InvalidTimestamp {
+                if let Err(error) = self.sync_server_time().await {
+                    warn!(
+                        "Unable to sync server time on {}: {:?}",
+                        self.exchange_account_id, error
+                    );
+                    return outcome;
+                }
+            }
+
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = cancellation_token.when_cancelled() => return outcome,
+            }
+
+            self.timeout_manager
+                .reserve_when_available(
+                    &self.exchange_account_id,
+                    request_type,
+                    None,
+                    cancellation_token.clone(),
+                )?
+                .await
+                .into_result()?;
+
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use awc::http::StatusCode;
+
+    use super::*;
+    use crate::core::exchanges::general::test_helper::get_test_exchange;
+
+    fn response(status: StatusCode) -> RestRequestOutcome {
+        RestRequestOutcome::new(r#"{"orderId":1}"#.to_owned(), status)
+    }
+
+    fn policy(idempotency: Idempotency) -> RetryPolicy {
+        RetryPolicy::new(
+            3,
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+            idempotency,
+        )
+    }
+
+    #[test]
+    fn backoff_grows_until_max_attempts() {
+        let policy = policy(Idempotency::Idempotent);
+        let error_type = ExchangeErrorType::ServiceUnavailable;
+
+        assert_eq!(
+            policy.get_retry_delay(error_type, 1, None),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.get_retry_delay(error_type, 2, None),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(policy.get_retry_delay(error_type, 3, None), None);
+    }
+
+    #[test]
+    fn delay_depends_on_error_type() {
+        let policy = policy(Idempotency::Idempotent);
+
+        assert_eq!(
+            policy.get_retry_delay(
+                ExchangeErrorType::PendingError(Duration::from_millis(700)),
+                1,
+                None
+            ),
+            Some(Duration::from_millis(700))
+        );
+        assert_eq!(
+            policy.get_retry_delay(ExchangeErrorType::InvalidTimestamp, 1, None),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            policy.get_retry_delay(ExchangeErrorType::InsufficientFunds, 1, None),
+            None
+        );
+        assert_eq!(
+            policy.get_retry_delay(ExchangeErrorType::InvalidOrder, 1, None),
+            None
+        );
+    }
+
+    #[test]
+    fn rate_limit_waits_for_retry_after_within_max_delay() {
+        let policy = policy(Idempotency::Idempotent);
+        let error_type = ExchangeErrorType::RateLimit;
+
+        assert_eq!(
+            policy.get_retry_delay(error_type, 1, Some(Duration::from_millis(500))),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            policy.get_retry_delay(error_type, 1, Some(Duration::from_secs(120))),
+            None
+        );
+    }
+
+    #[test]
+    fn not_retryable_policy() {
+        let policy = policy(Idempotency::NotRetryable);
+
+        assert_eq!(
+            policy.get_retry_delay(ExchangeErrorType::SendError, 1, None),
+            None
+        );
+    }
+
+    #[test]
+    fn policy_by_request_type() {
+        let policies = RetryPolicies::default();
+
+        assert_eq!(
+            policies.get(RequestType::CreateOrder).idempotency,
+            Idempotency::CheckBeforeRetry
+        );
+        assert_eq!(policies.get(RequestType::GetOpenOrders).max_attempts, 5);
+        assert_eq!(
+            policies.get(RequestType::GetOrderInfo),
+            policies.get(RequestType::GetBalance)
+        );
+    }
+
+    #[test]
+    fn only_uncertain_errors_may_be_applied() {
+        assert!(may_be_applied(ExchangeErrorType::SendError));
+        assert!(may_be_applied(ExchangeErrorType::ServiceUnavailable));
+        assert!(!may_be_applied(ExchangeErrorType::RateLimit));
+        assert!(!may_be_applied(ExchangeErrorType::InvalidTimestamp));
+        assert!(!may_be_applied(ExchangeErrorType::InvalidOrder));
+    }
+
+    #[tokio::test]
+    async fn idempotent_request_is_retried_until_success() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let attempts = AtomicU32::new(0);
+
+        let outcome = exchange
+            .request_with_retries(
+                RequestType::GetOrderInfo,
+                CancellationToken::default(),
+                || {
+                    let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                    let status = match attempt {
+                        1 => StatusCode::SERVICE_UNAVAILABLE,
+                        _ => StatusCode::OK,
+                    };
+                    ready(Ok(response(status)))
+                },
+            )
+            .await
+            .expect("in test");
+
+        assert_eq!(outcome.status, StatusCode::OK);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn request_is_not_retried_after_final_error() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let attempts = AtomicU32::new(0);
+
+        let outcome = exchange
+            .request_with_retries(
+                RequestType::CancelOrder,
+                CancellationToken::default(),
+                || {
+                    let _ = attempts.fetch_add(1, Ordering::SeqCst);
+                    ready(Ok(RestRequestOutcome::new(
+                        r#"{"code":-2011,"msg":"Unknown order sent."}"#.to_owned(),
+                        StatusCode::BAD_REQUEST,
+                    )))
+                },
+            )
+            .await
+            .expect("in test");
+
+        assert_eq!(outcome.status, StatusCode::BAD_REQUEST);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn applied_request_is_taken_from_check_instead_of_retry() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let attempts = AtomicU32::new(0);
+
+        let outcome = exchange
+            .request_with_checked_retries(
+                RequestType::CreateOrder,
+                CancellationToken::default(),
+                || {
+                    let _ = attempts.fetch_add(1, Ordering::SeqCst);
+                    ready(Err(anyhow!("Connection reset")))
+                },
+                || ready(Ok(Some(response(StatusCode::OK)))),
+            )
+            .await
+            .expect("in test");
+
+        assert_eq!(outcome.status, StatusCode::OK);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn request_is_not_retried_when_check_failed() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let attempts = AtomicU32::new(0);
+
+        let outcome = exchange
+            .request_with_retries(
+                RequestType::CreateOrder,
+                CancellationToken::default(),
+                || {
+                    let _ = attempts.fetch_add(1, Ordering::SeqCst);
+                    ready(Ok(response(StatusCode::SERVICE_UNAVAILABLE)))
+                },
+            )
+            .await
+            .expect("in test");
+
+        assert_eq!(outcome.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use futures::FutureExt;
+use log::{info, warn};
+use tokio::time::sleep;
+
+use crate::core::exchanges::{general::exchange::Exchange, general::request_type::RequestType};
+use crate::core::infrastructure::spawn_future;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+
+pub const DEFAULT_SERVER_TIME_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Exchange {
+    /// Offset is estimated as difference between server time and the middle of request round trip
+    pub async fn sync_server_time(&self) -> Result<chrono::Duration> {
+        self.timeout_manager
+            .reserve_when_available(
+                &self.exchange_account_id,
+                RequestType::GetServerTime,
+                None,
+                CancellationToken::default(),
+            )?
+            .await
+            .into_result()?;
+
+        let request_start_time = Utc::now();
+        let response = self.exchange_client.request_server_time().await?;
+        let request_end_time = Utc::now();
+        self.handle_rest_response(RequestType::GetServerTime, &response);
+
+        if let Some(error) = self.get_rest_error(&response) {
+            bail!(
+                "Rest error appeared during request get_server_time: {}",
+                error.message
+            );
+        }
+
+        let server_time = self.exchange_client.parse_server_time(&response)?;
+        let local_time = request_start_time + (request_end_time - request_start_time) / 2;
+        let offset = server_time - local_time;
+        self.exchange_client.set_server_time_offset(offset);
+
+        info!(
+            "Server time offset on {} is {}ms",
+            self.exchange_account_id,
+            offset.num_milliseconds()
+        );
+
+        Ok(offset)
+    }
+
+    pub fn start_server_time_sync(self: Arc<Self>, interval: Duration) {
+        let cancellation_token = self.application_manager.stop_token();
+        let action = async move {
+            loop {
+                tokio::select! {
+                    _ = sleep(interval) => {}
+                    _ = cancellation_token.when_cancelled() => return Ok(()),
+                }
+
+                if let Err(error) = self.sync_server_time().await {
+                    warn!(
+                        "Unable to sync server time on {}: {:?}",
+                        self.exchange_account_id, error
+                    );
+                }
+            }
+        };
+        spawn_future("Server time synchronization", false, action.boxed());
+    }
+}
@@ -1,9 +1,11 @@
 #![cfg(test)]
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
+use chrono::Utc;
 use parking_lot::RwLock;
 use rust_decimal_macros::dec;
 use tokio::sync::broadcast;
+use uuid::Uuid;
 
 use super::{
     currency_pair_metadata::CurrencyPairMetadata, currency_pair_metadata::Precision,
@@ -14,12 +16,16 @@ use crate::core::exchanges::events::ExchangeEvent;
 use crate::core::exchanges::traits::ExchangeClientBuilder;
 use crate::core::lifecycle::application_manager::ApplicationManager;
 use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::orders::fill::{OrderFill, OrderFillType};
+use crate::core::orders::order::OrderFillRole;
 use crate::core::{
     exchanges::binance::binance::Binance, exchanges::common::Amount,
     exchanges::common::CurrencyPair, exchanges::common::ExchangeAccountId,
     exchanges::common::Price, exchanges::events::AllowedEventSourceType,
     exchanges::general::commission::Commission, exchanges::general::commission::CommissionForType,
     exchanges::general::features::ExchangeFeatures, exchanges::general::features::OpenOrdersType,
+    exchanges::timeouts::requests_timeout_manager_factory::RequestTimeoutArguments,
+    exchanges::timeouts::requests_timeout_manager_factory::RequestsTimeoutManagerFactory,
     exchanges::timeouts::timeout_manager::TimeoutManager, orders::order::ClientOrderId,
     orders::order::OrderRole, orders::order::OrderSide, orders::order::OrderSnapshot,
     orders::order::OrderType, orders::pool::OrderRef, orders::pool::OrdersPool, settings,
@@ -28,7 +34,17 @@ use crate::core::{
 pub(crate) fn get_test_exchange(
     is_derivative: bool,
 ) -> (Arc<Exchange>, broadcast::Receiver<ExchangeEvent>) {
-    let exchange_account_id = ExchangeAccountId::new("local_exchange_account_id".into(), 0);
+    get_test_exchange_with_id(
+        ExchangeAccountId::new("local_exchange_account_id".into(), 0),
+        is_derivative,
+    )
+}
+
+/// Test exchange with account id which can be parsed, e.g. from requests to control panel
+pub(crate) fn get_test_exchange_with_id(
+    exchange_account_id: ExchangeAccountId,
+    is_derivative: bool,
+) -> (Arc<Exchange>, broadcast::Receiver<ExchangeEvent>) {
     let mut settings = settings::ExchangeSettings::new_short(
         exchange_account_id.clone(),
         "test_api_key".into(),
@@ -55,6 +71,13 @@ pub(crate) fn get_test_exchange(
         CommissionForType::new(dec!(0.2), referral_reward),
     );
 
+    let timeout_manager = TimeoutManager::new(crate::hashmap![
+        exchange_account_id.clone() => RequestsTimeoutManagerFactory::from_timeout_arguments(
+            vec![RequestTimeoutArguments::from_requests_per_minute(1200)],
+            exchange_account_id.clone(),
+        )
+    ]);
+
     let exchange = Exchange::new(
         exchange_account_id,
         binance,
@@ -67,7 +90,7 @@ pub(crate) fn get_test_exchange(
         ),
         tx,
         application_manager,
-        TimeoutManager::new(HashMap::new()),
+        timeout_manager,
         commission,
     );
     let base_currency_code = "PHB";
@@ -103,6 +126,13 @@ pub(crate) fn get_test_exchange(
     (exchange, rx)
 }
 
+/// Replace metadata of currency pair without requests to exchange
+pub(crate) fn set_test_symbol(exchange: &Exchange, symbol: CurrencyPairMetadata) {
+    let _ = exchange
+        .symbols
+        .insert(symbol.currency_pair(), Arc::new(symbol));
+}
+
 pub(crate) fn create_order_ref(
     client_order_id: &ClientOrderId,
     role: Option<OrderRole>,
@@ -143,3 +173,25 @@ pub(crate) fn try_add_snapshot_by_exchange_id(exchange: &Exchange, order_ref: &O
             .insert(exchange_order_id.clone(), order_ref.clone());
     }
 }
+
+pub(crate) fn create_test_fill(price: Price, amount: Amount) -> OrderFill {
+    OrderFill::new(
+        Uuid::new_v4(),
+        Utc::now(),
+        OrderFillType::UserTrade,
+        None,
+        price,
+        amount,
+        price * amount,
+        OrderFillRole::Taker,
+        "usdt".into(),
+        dec!(0),
+        dec!(0),
+        "usdt".into(),
+        dec!(0),
+        dec!(0),
+        false,
+        None,
+        None,
+    )
+}
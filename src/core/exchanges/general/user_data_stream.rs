@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use futures::FutureExt;
+use log::{info, warn};
+use tokio::time::sleep;
+
+use crate::core::exchanges::{general::exchange::Exchange, general::request_type::RequestType};
+use crate::core::infrastructure::spawn_future;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+
+/// Binance closes user data stream in 60 minutes without keepalive and recommends to prolong it every 30 minutes
+pub const USER_DATA_STREAM_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+impl Exchange {
+    pub async fn keepalive_user_data_stream(&self) -> Result<()> {
+        self.timeout_manager
+            .reserve_when_available(
+                &self.exchange_account_id,
+                RequestType::UpdateListenKey,
+                None,
+                CancellationToken::default(),
+            )?
+            .await
+            .into_result()?;
+
+        let response = match self
+            .exchange_client
+            .request_keepalive_user_data_stream()
+            .await?
+        {
+            Some(response) => response,
+            None => return Ok(()),
+        };
+        self.handle_rest_response(RequestType::UpdateListenKey, &response);
+
+        if let Some(error) = self.get_rest_error(&response) {
+            bail!(
+                "Rest error appeared during request keepalive_user_data_stream: {}",
+                error.message
+            );
+        }
+
+        info!("User data stream on {} prolonged", self.exchange_account_id);
+
+        Ok(())
+    }
+
+    pub fn start_user_data_stream_keepalive(self: Arc<Self>, interval: Duration) {
+        let cancellation_token = self.application_manager.stop_token();
+        let action = async move {
+            loop {
+                tokio::select! {
+                    _ = sleep(interval) => {}
+                    _ = cancellation_token.when_cancelled() => return Ok(()),
+                }
+
+                if let Err(error) = self.keepalive_user_data_stream().await {
+                    warn!(
+                        "Unable to keep user data stream alive on {}: {:?}",
+                        self.exchange_account_id, error
+                    );
+                }
+            }
+        };
+        spawn_future("User data stream keepalive", false, action.boxed());
+    }
+}
@@ -0,0 +1,144 @@
+use super::kraken::Kraken;
+use crate::core::exchanges::rest_client;
+use crate::core::exchanges::traits::{ExchangeClient, Support};
+use crate::core::orders::order::*;
+use crate::core::{
+    exchanges::common::{CurrencyPair, RestRequestOutcome},
+    orders::pool::OrderRef,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::warn;
+
+#[async_trait]
+impl ExchangeClient for Kraken {
+    async fn request_metadata(&self) -> Result<RestRequestOutcome> {
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/0/public/AssetPairs", &vec![])?;
+
+        self.rest_client.get(full_url).await
+    }
+
+    async fn create_order(&self, order: &OrderCreating) -> Result<RestRequestOutcome> {
+        let specific_currency_pair = self.get_specific_currency_pair(&order.header.currency_pair);
+
+        let mut http_params = vec![
+            (
+                "pair".to_owned(),
+                specific_currency_pair.as_str().to_owned(),
+            ),
+            (
+                "type".to_owned(),
+                Self::to_server_order_side(order.header.side),
+            ),
+            (
+                "ordertype".to_owned(),
+                Self::to_server_order_type(order.header.order_type),
+            ),
+            ("volume".to_owned(), order.header.amount.to_string()),
+        ];
+
+        if order.header.order_type != OrderType::Market {
+            http_params.push(("price".to_owned(), order.price.to_string()));
+
+            if order.header.execution_type == OrderExecutionType::MakerOnly {
+                http_params.push(("oflags".to_owned(), "post".to_owned()));
+            }
+        }
+
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/0/private/AddOrder", &vec![])?;
+
+        let outcome = self.rest_client.post(full_url, &http_params).await?;
+
+        // Exchange order id is known only from response, so client order id is matched here
+        if self.is_rest_error_code(&outcome).is_ok() {
+            let exchange_order_id = self.get_order_id(&outcome)?;
+            let _ = self
+                .client_order_ids
+                .insert(exchange_order_id, order.header.client_order_id.clone());
+        }
+
+        Ok(outcome)
+    }
+
+    async fn request_cancel_order(&self, order: &OrderCancelling) -> Result<RestRequestOutcome> {
+        let http_params = vec![(
+            "txid".to_owned(),
+            order.exchange_order_id.as_str().to_owned(),
+        )];
+
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/0/private/CancelOrder", &vec![])?;
+
+        self.rest_client.post(full_url, &http_params).await
+    }
+
+    async fn cancel_all_orders(&self, currency_pair: CurrencyPair) -> Result<()> {
+        // Kraken is able to cancel all orders only for all currency pairs at once
+        let open_orders_outcome = self.request_open_orders().await?;
+        let open_orders = self.parse_open_orders(&open_orders_outcome)?;
+
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/0/private/CancelOrder", &vec![])?;
+
+        for order in open_orders
+            .iter()
+            .filter(|order| order.currency_pair == currency_pair)
+        {
+            let http_params = vec![(
+                "txid".to_owned(),
+                order.exchange_order_id.as_str().to_owned(),
+            )];
+
+            let cancel_order_outcome = self.rest_client.post(full_url.clone(), &http_params).await;
+            if let Err(error) = cancel_order_outcome {
+                warn!(
+                    "Unable to cancel order {} on {}: {:?}",
+                    order.exchange_order_id, self.id, error
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn request_open_orders(&self) -> Result<RestRequestOutcome> {
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/0/private/OpenOrders", &vec![])?;
+
+        self.rest_client
+            .post(full_url, &rest_client::HttpParams::new())
+            .await
+    }
+
+    async fn request_open_orders_by_currency_pair(
+        &self,
+        _currency_pair: CurrencyPair,
+    ) -> Result<RestRequestOutcome> {
+        // Kraken doesn't filter open orders by currency pair
+        self.request_open_orders().await
+    }
+
+    async fn request_order_info(&self, order: &OrderRef) -> Result<RestRequestOutcome> {
+        let exchange_order_id = order.exchange_order_id().with_context(|| {
+            format!(
+                "Unable to get info of order {} without exchange order id",
+                order.client_order_id()
+            )
+        })?;
+
+        let http_params = vec![("txid".to_owned(), exchange_order_id.as_str().to_owned())];
+
+        let full_url =
+            rest_client::build_uri(&self.settings.rest_host, "/0/private/QueryOrders", &vec![])?;
+
+        self.rest_client.post(full_url, &http_params).await
+    }
+
+    async fn request_server_time(&self) -> Result<RestRequestOutcome> {
+        let full_url = rest_client::build_uri(&self.settings.rest_host, "/0/public/Time", &vec![])?;
+
+        self.rest_client.get(full_url).await
+    }
+}
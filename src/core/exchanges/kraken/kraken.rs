@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac, NewMac};
+use hyper::header::HeaderValue;
+use log::error;
+use parking_lot::{Mutex, RwLock};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha512};
+use tokio::sync::broadcast;
+
+use super::support::KrakenOrderInfo;
+use crate::core::connectivity::connectivity_manager::WebSocketRole;
+use crate::core::connectivity::network_connector::NetworkConnector;
+use crate::core::exchanges::events::{AllowedEventSourceType, ExchangeEvent};
+use crate::core::exchanges::general::request_type::RequestType;
+use crate::core::exchanges::rest_client::{
+    self, RestClient, RestRequest, RestRequestAuthenticator,
+};
+use crate::core::exchanges::traits::{ExchangeClientBuilder, ExchangeClientBuilderResult, Support};
+use crate::core::exchanges::{
+    common::{
+        CurrencyCode, CurrencyId, CurrencyPair, ExchangeAccountId, RestRequestOutcome,
+        SpecificCurrencyPair,
+    },
+    general::exchange::BoxExchangeClient,
+    general::features::{ExchangeFeatures, OpenOrdersType},
+    general::handlers::handle_order_filled::FillEventData,
+    timeouts::requests_timeout_manager_factory::RequestTimeoutArguments,
+};
+use crate::core::lifecycle::application_manager::ApplicationManager;
+use crate::core::orders::fill::EventSourceType;
+use crate::core::orders::order::*;
+use crate::core::secret::Secret;
+use crate::core::settings::ExchangeSettings;
+use crate::core::utils;
+use crate::hashmap;
+
+/// Requests with this path prefix are signed, public ones are sent as is
+const PRIVATE_PATH_PREFIX: &str = "/0/private/";
+
+/// Number of order book levels in "book" websocket channel
+pub(super) const ORDER_BOOK_DEPTH: u32 = 10;
+
+/// Public websocket channel which data messages are marked by channel id
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SubscribedChannel {
+    /// Channel name with subscription options, e.g. "book-10"
+    pub name: String,
+    pub currency_pair: CurrencyPair,
+}
+
+pub struct Kraken {
+    pub settings: ExchangeSettings,
+    pub id: ExchangeAccountId,
+    pub order_created_callback:
+        Mutex<Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>>,
+    pub order_cancelled_callback:
+        Mutex<Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>>,
+    pub handle_order_filled_callback: Mutex<Box<dyn FnMut(FillEventData) + Send + Sync>>,
+    pub websocket_message_lag_callback: Mutex<Box<dyn FnMut(Duration) + Send + Sync>>,
+
+    pub unified_to_specific: RwLock<HashMap<CurrencyPair, SpecificCurrencyPair>>,
+    pub specific_to_unified: RwLock<HashMap<SpecificCurrencyPair, CurrencyPair>>,
+    // Websocket API names pairs like "XBT/USD" while REST API uses names like "XBTUSD"
+    pub specific_to_websocket_pair: RwLock<HashMap<SpecificCurrencyPair, String>>,
+    pub websocket_pair_to_unified: RwLock<HashMap<String, CurrencyPair>>,
+    pub supported_currencies: DashMap<CurrencyId, CurrencyCode>,
+    // Currencies used for trading according to user settings
+    pub traded_specific_currencies: Mutex<Vec<SpecificCurrencyPair>>,
+
+    // Kraken doesn't support client order ids, so they are matched with created orders locally
+    pub client_order_ids: DashMap<ExchangeOrderId, ClientOrderId>,
+    // Public channels by channel id received in subscription status
+    pub subscribed_channels: RwLock<HashMap<u64, SubscribedChannel>>,
+
+    pub(super) application_manager: Arc<ApplicationManager>,
+
+    pub(super) events_channel: broadcast::Sender<ExchangeEvent>,
+
+    pub(super) subscribe_to_market_data: bool,
+
+    pub(super) rest_client: RestClient,
+}
+
+/// Kraken signs private requests with nonce which should increase with every request
+struct KrakenAuthenticator {
+    api_key: Secret,
+    secret_key: Secret,
+    last_nonce: AtomicU64,
+}
+
+impl KrakenAuthenticator {
+    fn new(api_key: Secret, secret_key: Secret) -> Self {
+        Self {
+            api_key,
+            secret_key,
+            last_nonce: AtomicU64::new(0),
+        }
+    }
+
+    /// Current milliseconds are used as nonce unless previous request had the same or greater one
+    fn next_nonce(&self) -> u64 {
+        let now = utils::get_current_milliseconds() as u64;
+        let last_nonce = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last_nonce| {
+                Some(now.max(last_nonce + 1))
+            })
+            .expect("Nonce update always returns new value");
+
+        now.max(last_nonce + 1)
+    }
+
+    /// HMAC-SHA512 of path and SHA256(nonce + post data) using base64 decoded secret key
+    fn generate_signature(&self, path: &str, nonce: u64, post_data: &str) -> Result<String> {
+        let secret_key =
+            base64::decode(self.secret_key.expose()).context("Secret key isn't valid base64")?;
+
+        let mut sha256 = Sha256::new();
+        sha256.update(nonce.to_string().as_bytes());
+        sha256.update(post_data.as_bytes());
+
+        let mut hmac =
+            Hmac::<Sha512>::new_from_slice(&secret_key).context("Unable to calculate hmac")?;
+        hmac.update(path.as_bytes());
+        hmac.update(&sha256.finalize());
+
+        Ok(base64::encode(hmac.finalize().into_bytes()))
+    }
+}
+
+impl RestRequestAuthenticator for KrakenAuthenticator {
+    fn authenticate(&self, request: &mut RestRequest) -> Result<()> {
+        let path = request.uri.path().to_owned();
+        if !path.starts_with(PRIVATE_PATH_PREFIX) {
+            return Ok(());
+        }
+
+        let nonce = self.next_nonce();
+        request.body = match request.body.is_empty() {
+            true => format!("nonce={}", nonce),
+            false => format!("nonce={}&{}", nonce, request.body),
+        };
+        let signature = self.generate_signature(&path, nonce, &request.body)?;
+
+        let api_key = HeaderValue::from_str(self.api_key.expose()).context("Invalid api key")?;
+        let _ = request.headers.insert("API-Key", api_key);
+        let signature = HeaderValue::from_str(&signature).context("Invalid signature")?;
+        let _ = request.headers.insert("API-Sign", signature);
+
+        Ok(())
+    }
+}
+
+impl Kraken {
+    pub fn new(
+        id: ExchangeAccountId,
+        settings: ExchangeSettings,
+        events_channel: broadcast::Sender<ExchangeEvent>,
+        application_manager: Arc<ApplicationManager>,
+    ) -> Self {
+        let network_connector = NetworkConnector::new(&settings.network)
+            .unwrap_or_else(|error| panic!("Invalid network settings of {}: {:?}", id, error));
+        let authenticator =
+            KrakenAuthenticator::new(settings.api_key.clone(), settings.secret_key.clone());
+        let rest_client = RestClient::new(network_connector)
+            .unwrap_or_else(|error| panic!("Unable to create RestClient for {}: {:?}", id, error))
+            .with_authenticator(Box::new(authenticator));
+
+        Self {
+            id,
+            order_created_callback: Mutex::new(Box::new(|_, _, _| {})),
+            order_cancelled_callback: Mutex::new(Box::new(|_, _, _| {})),
+            handle_order_filled_callback: Mutex::new(Box::new(|_| {})),
+            websocket_message_lag_callback: Mutex::new(Box::new(|_| {})),
+            unified_to_specific: Default::default(),
+            specific_to_unified: Default::default(),
+            specific_to_websocket_pair: Default::default(),
+            websocket_pair_to_unified: Default::default(),
+            supported_currencies: Default::default(),
+            traded_specific_currencies: Default::default(),
+            client_order_ids: Default::default(),
+            subscribed_channels: Default::default(),
+            subscribe_to_market_data: settings.subscribe_to_market_data,
+            settings,
+            events_channel,
+            application_manager,
+            rest_client,
+        }
+    }
+
+    /// Private websocket requires token which should be used within 15 minutes
+    pub async fn get_websocket_token(&self) -> Result<String> {
+        let full_url = rest_client::build_uri(
+            &self.settings.rest_host,
+            "/0/private/GetWebSocketsToken",
+            &vec![],
+        )?;
+        let response = self
+            .rest_client
+            .post(full_url, &rest_client::HttpParams::new())
+            .await?;
+        if let Err(error) = self.is_rest_error_code(&response) {
+            bail!("Unable to get websocket token: {}", error.message);
+        }
+
+        let data = get_result(&response)?;
+        let token = data["token"]
+            .as_str()
+            .context("Unable to parse websocket token")?;
+
+        Ok(token.to_owned())
+    }
+
+    pub(super) async fn build_websocket_subscriptions(
+        &self,
+        role: WebSocketRole,
+    ) -> Result<Vec<String>> {
+        match role {
+            WebSocketRole::Main => {
+                let currency_pairs = self.traded_specific_currencies.lock().clone();
+                Ok(self.build_public_subscriptions(&currency_pairs))
+            }
+            WebSocketRole::Secondary => {
+                let token = self.get_websocket_token().await?;
+                let subscriptions = ["openOrders", "ownTrades"]
+                    .iter()
+                    .map(|name| {
+                        // Snapshot of own trades contains already handled fills
+                        json!({
+                            "event": "subscribe",
+                            "subscription": { "name": name, "token": token, "snapshot": false },
+                        })
+                        .to_string()
+                    })
+                    .collect();
+
+                Ok(subscriptions)
+            }
+        }
+    }
+
+    pub(super) fn build_public_subscriptions(
+        &self,
+        currency_pairs: &[SpecificCurrencyPair],
+    ) -> Vec<String> {
+        let specific_to_websocket_pair = self.specific_to_websocket_pair.read();
+        let pairs: Vec<&String> = currency_pairs
+            .iter()
+            .filter_map(|currency_pair| specific_to_websocket_pair.get(currency_pair))
+            .collect();
+
+        self.settings
+            .websocket_channels
+            .iter()
+            .map(|channel| {
+                let subscription = match channel.as_str() {
+                    "book" => json!({ "name": channel, "depth": ORDER_BOOK_DEPTH }),
+                    _ => json!({ "name": channel }),
+                };
+
+                json!({
+                    "event": "subscribe",
+                    "pair": pairs,
+                    "subscription": subscription,
+                })
+                .to_string()
+            })
+            .collect()
+    }
+
+    pub(super) fn to_server_order_side(side: OrderSide) -> String {
+        match side {
+            OrderSide::Buy => "buy".to_owned(),
+            OrderSide::Sell => "sell".to_owned(),
+        }
+    }
+
+    pub(super) fn to_local_order_side(side: &str) -> Result<OrderSide> {
+        match side {
+            "buy" => Ok(OrderSide::Buy),
+            "sell" => Ok(OrderSide::Sell),
+            _ => bail!("Unexpected order side {}", side),
+        }
+    }
+
+    pub(super) fn to_local_order_status(status: &str) -> Result<OrderStatus> {
+        match status {
+            "pending" | "open" => Ok(OrderStatus::Created),
+            "closed" => Ok(OrderStatus::Completed),
+            "canceled" | "expired" => Ok(OrderStatus::Canceled),
+            _ => bail!("Unexpected order status {}", status),
+        }
+    }
+
+    pub(super) fn to_server_order_type(order_type: OrderType) -> String {
+        match order_type {
+            OrderType::Limit => "limit".to_owned(),
+            OrderType::Market => "market".to_owned(),
+            unexpected_variant => panic!("{:?} are not expected", unexpected_variant),
+        }
+    }
+
+    pub fn get_unified_currency_pair(
+        &self,
+        currency_pair: &SpecificCurrencyPair,
+    ) -> Result<CurrencyPair> {
+        match self.specific_to_unified.read().get(currency_pair) {
+            None => bail!(
+                "Not found currency pair '{:?}' in {}",
+                currency_pair,
+                self.id
+            ),
+            Some(v) => Ok(v.clone()),
+        }
+    }
+
+    pub(super) fn currency_pair_from_web_socket(
+        &self,
+        currency_pair: &str,
+    ) -> Result<CurrencyPair> {
+        match self.websocket_pair_to_unified.read().get(currency_pair) {
+            None => bail!(
+                "Not found websocket currency pair '{}' in {}",
+                currency_pair,
+                self.id
+            ),
+            Some(v) => Ok(v.clone()),
+        }
+    }
+
+    /// Client order id is empty if order wasn't created by this client
+    pub(super) fn get_client_order_id(&self, exchange_order_id: &ExchangeOrderId) -> ClientOrderId {
+        self.client_order_ids
+            .get(exchange_order_id)
+            .map(|client_order_id| client_order_id.value().clone())
+            .unwrap_or_else(|| "".into())
+    }
+
+    pub(super) fn specific_order_info_to_unified(
+        &self,
+        exchange_order_id: &str,
+        specific: &KrakenOrderInfo,
+    ) -> Result<OrderInfo> {
+        let exchange_order_id: ExchangeOrderId = exchange_order_id.into();
+        let currency_pair =
+            self.get_unified_currency_pair(&specific.description.pair.as_str().into())?;
+
+        Ok(OrderInfo::new(
+            currency_pair,
+            exchange_order_id.clone(),
+            self.get_client_order_id(&exchange_order_id),
+            Self::to_local_order_side(&specific.description.side)?,
+            Self::to_local_order_status(&specific.status)?,
+            specific.description.price,
+            specific.amount,
+            specific.average_fill_price,
+            specific.filled_amount,
+            None,
+            None,
+            Some(specific.fee),
+        ))
+    }
+
+    pub(super) fn send_event(&self, event: ExchangeEvent) -> Result<()> {
+        match self.events_channel.send(event) {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                let msg = format!("Unable to send exchange event in {}: {}", self.id, error);
+                error!("{}", msg);
+                self.application_manager
+                    .clone()
+                    .spawn_graceful_shutdown(msg.clone());
+                Err(anyhow!(msg))
+            }
+        }
+    }
+}
+
+/// Successful responses keep data in "result" field
+pub(super) fn get_result(response: &RestRequestOutcome) -> Result<Value> {
+    let mut data: Value =
+        serde_json::from_str(&response.content).context("Unable to parse response content")?;
+    match data.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => bail!("Unable to get result from Kraken response"),
+    }
+}
+
+pub struct KrakenBuilder;
+
+impl ExchangeClientBuilder for KrakenBuilder {
+    fn create_exchange_client(
+        &self,
+        exchange_settings: ExchangeSettings,
+        events_channel: broadcast::Sender<ExchangeEvent>,
+        application_manager: Arc<ApplicationManager>,
+    ) -> ExchangeClientBuilderResult {
+        let exchange_account_id = exchange_settings.exchange_account_id.clone();
+
+        ExchangeClientBuilderResult {
+            client: Box::new(Kraken::new(
+                exchange_account_id,
+                exchange_settings,
+                events_channel.clone(),
+                application_manager,
+            )) as BoxExchangeClient,
+            features: ExchangeFeatures::new(
+                OpenOrdersType::AllCurrencyPair,
+                false,
+                false,
+                AllowedEventSourceType::All,
+                AllowedEventSourceType::All,
+            ),
+        }
+    }
+
+    fn extend_settings(&self, settings: &mut ExchangeSettings) {
+        settings.web_socket_host = "wss://ws.kraken.com".to_string();
+        settings.web_socket2_host = "wss://ws-auth.kraken.com".to_string();
+        settings.rest_host = "https://api.kraken.com".to_string();
+    }
+
+    fn get_timeout_argments(&self, _settings: &ExchangeSettings) -> Vec<RequestTimeoutArguments> {
+        // Limits of Starter tier from https://docs.kraken.com/rest/#section/Rate-Limits
+        // Private requests counter has maximum 15 and decreases by 1 every 3 seconds
+        let private_requests = [
+            RequestType::GetOrderInfo,
+            RequestType::GetOpenOrders,
+            RequestType::GetOpenOrdersByCurrencyPair,
+            RequestType::GetBalance,
+            RequestType::GetMyTrades,
+            RequestType::GetOrderTrades,
+            RequestType::GetListenKey,
+        ];
+        let request_weights = hashmap![
+            RequestType::GetMyTrades => 2,
+            RequestType::GetOrderTrades => 2
+        ];
+
+        // Orders are limited by matching engine separately from other private requests
+        let orders = [RequestType::CreateOrder, RequestType::CancelOrder];
+
+        let public_requests = [
+            RequestType::GetMarkets,
+            RequestType::GetServerTime,
+            RequestType::GetOrderBook,
+            RequestType::GetTrades,
+        ];
+
+        vec![
+            RequestTimeoutArguments::new(15, chrono::Duration::seconds(45))
+                .with_request_weights(request_weights)
+                .for_request_types(&private_requests),
+            RequestTimeoutArguments::from_requests_per_minute(60).for_request_types(&orders),
+            RequestTimeoutArguments::from_requests_per_second(1)
+                .for_request_types(&public_requests),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{Method, Uri};
+
+    // Values from https://docs.kraken.com/rest/#section/Authentication/Headers-and-Signature
+    const SECRET_KEY: &str =
+        "kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==";
+
+    #[test]
+    fn generate_signature() {
+        let right_value =
+            "4/dpxb3iT4tp/ZCVEwSnEsLxx0bqyhLpdfOpc6fn7OR8+UClSV5n9E6aSS8MPtnRfp32bAb0nmbRn6H8ndwLUQ==";
+        let authenticator = KrakenAuthenticator::new("api_key".into(), SECRET_KEY.into());
+
+        let signature = authenticator
+            .generate_signature(
+                "/0/private/AddOrder",
+                1616492376594,
+                "nonce=1616492376594&ordertype=limit&pair=XBTUSD&price=37500&type=buy&volume=1.25",
+            )
+            .expect("in test");
+
+        assert_eq!(signature, right_value);
+    }
+
+    #[test]
+    fn nonce_increases() {
+        let authenticator = KrakenAuthenticator::new("api_key".into(), SECRET_KEY.into());
+
+        let first = authenticator.next_nonce();
+        let second = authenticator.next_nonce();
+
+        assert!(second > first);
+    }
+
+    #[test]
+    fn authenticate_only_private_requests() {
+        let authenticator = KrakenAuthenticator::new("api_key".into(), SECRET_KEY.into());
+
+        let uri: Uri = "https://api.kraken.com/0/public/Time"
+            .parse()
+            .expect("in test");
+        let mut public_request = RestRequest::new(Method::GET, uri);
+        authenticator
+            .authenticate(&mut public_request)
+            .expect("in test");
+        assert!(public_request.headers.is_empty());
+        assert!(public_request.body.is_empty());
+
+        let uri: Uri = "https://api.kraken.com/0/private/CancelOrder"
+            .parse()
+            .expect("in test");
+        let params = vec![("txid".to_owned(), "OQCLML-BW3P3-BUCMWZ".to_owned())];
+        let mut private_request = RestRequest::new(Method::POST, uri).with_form(&params);
+        authenticator
+            .authenticate(&mut private_request)
+            .expect("in test");
+
+        assert!(private_request.body.starts_with("nonce="));
+        assert!(private_request.body.ends_with("&txid=OQCLML-BW3P3-BUCMWZ"));
+        assert_eq!(private_request.headers["API-Key"], "api_key");
+        assert!(private_request.headers.contains_key("API-Sign"));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod exchange_client;
+pub mod kraken;
+pub mod support;
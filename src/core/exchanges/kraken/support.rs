@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use awc::http::Uri;
+use chrono::{TimeZone, Utc};
+use dashmap::DashMap;
+use itertools::Itertools;
+use log::{error, info};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::kraken::{get_result, Kraken, SubscribedChannel};
+use crate::core::connectivity::network_connector::NetworkConnector;
+use crate::core::exchanges::common::SortedOrderData;
+use crate::core::exchanges::events::ExchangeEvent;
+use crate::core::exchanges::{
+    common::CurrencyCode, common::CurrencyId,
+    general::currency_pair_metadata::CurrencyPairMetadata,
+    general::handlers::handle_order_filled::FillEventData, traits::Support,
+};
+use crate::core::order_book::event::{EventType, OrderBookEvent};
+use crate::core::order_book::order_book_data::OrderBookData;
+use crate::core::orders::fill::OrderFillType;
+use crate::core::orders::order::*;
+use crate::core::DateTime;
+use crate::core::{
+    connectivity::connectivity_manager::WebSocketRole,
+    exchanges::general::currency_pair_metadata::Precision,
+};
+use crate::core::{
+    exchanges::common::{
+        Amount, CurrencyPair, ExchangeAccountId, ExchangeError, ExchangeErrorType, Price,
+        RestRequestOutcome, SpecificCurrencyPair,
+    },
+    orders::fill::EventSourceType,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct KrakenOrderDescription {
+    pub pair: String,
+    #[serde(rename = "type")]
+    pub side: String,
+    #[serde(rename = "ordertype")]
+    pub order_type: String,
+    pub price: Price,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct KrakenOrderInfo {
+    pub status: String,
+    #[serde(rename = "descr")]
+    pub description: KrakenOrderDescription,
+    #[serde(rename = "vol")]
+    pub amount: Amount,
+    #[serde(rename = "vol_exec")]
+    pub filled_amount: Amount,
+    // Average price of executed amount
+    #[serde(rename = "price")]
+    pub average_fill_price: Price,
+    pub fee: Amount,
+}
+
+#[async_trait]
+impl Support for Kraken {
+    fn is_rest_error_code(&self, response: &RestRequestOutcome) -> Result<(), ExchangeError> {
+        // Kraken responds with 200 OK and list of errors like "EOrder:Unknown order"
+        if response.content.contains(r#""error":[]"#) {
+            return Ok(());
+        }
+
+        match serde_json::from_str::<Value>(&response.content) {
+            Ok(data) => match data["error"].as_array() {
+                None => Err(ExchangeError::new(
+                    ExchangeErrorType::ParsingError,
+                    "Unable to parse error field".into(),
+                    None,
+                )),
+                Some(errors) if errors.is_empty() => Ok(()),
+                Some(errors) => {
+                    let message = errors.iter().filter_map(|error| error.as_str()).join("; ");
+                    Err(ExchangeError::new(
+                        ExchangeErrorType::Unknown,
+                        message,
+                        None,
+                    ))
+                }
+            },
+            Err(error) => {
+                let error_message = format!("Unable to parse response.content: {}", error);
+                Err(ExchangeError::new(
+                    ExchangeErrorType::ParsingError,
+                    error_message,
+                    None,
+                ))
+            }
+        }
+    }
+
+    fn get_order_id(&self, response: &RestRequestOutcome) -> Result<ExchangeOrderId> {
+        let data = get_result(response)?;
+        let id = data["txid"][0]
+            .as_str()
+            .ok_or(anyhow!("Unable to parse txid of created order"))?;
+        Ok(ExchangeOrderId::new(id.into()))
+    }
+
+    fn clarify_error_type(&self, error: &mut ExchangeError) {
+        let message = error.message.as_str();
+        let error_type = if message.contains("EOrder:Unknown order") {
+            ExchangeErrorType::OrderNotFound
+        } else if message.contains("EOrder:Insufficient funds") {
+            ExchangeErrorType::InsufficientFunds
+        } else if message.contains("EOrder:Orders limit exceeded")
+            || message.contains("EOrder:Order minimum not met")
+            || message.contains("EOrder:Cost minimum not met")
+            || message.contains("EOrder:Tick size check failed")
+            || message.contains("EOrder:Post only order")
+            || message.contains("EGeneral:Invalid arguments")
+        {
+            ExchangeErrorType::InvalidOrder
+        } else if message.contains("EAPI:Invalid key")
+            || message.contains("EAPI:Invalid signature")
+            || message.contains("EGeneral:Permission denied")
+        {
+            ExchangeErrorType::Authentication
+        } else if message.contains("EAPI:Rate limit exceeded")
+            || message.contains("EOrder:Rate limit exceeded")
+        {
+            ExchangeErrorType::RateLimit
+        } else if message.contains("EService:Unavailable") || message.contains("EService:Busy") {
+            ExchangeErrorType::ServiceUnavailable
+        } else {
+            ExchangeErrorType::Unknown
+        };
+
+        error.error_type = error_type;
+    }
+
+    fn on_websocket_message(&self, msg: &str) -> Result<()> {
+        let data: Value = serde_json::from_str(msg).context("Unable to parse websocket message")?;
+
+        let items = match data {
+            Value::Array(items) => items,
+            Value::Object(_) => return self.handle_websocket_event(msg, &data),
+            _ => {
+                self.log_unknown_message(self.id.clone(), msg);
+                return Ok(());
+            }
+        };
+
+        // Public data is [channelID, data..., channelName, pair]
+        if let Some(channel_id) = items.first().and_then(|channel_id| channel_id.as_u64()) {
+            let channel = self
+                .subscribed_channels
+                .read()
+                .get(&channel_id)
+                .cloned()
+                .ok_or(anyhow!("Unknown websocket channel id {}", channel_id))?;
+
+            // Payload is between channel id and trailing channel name and pair
+            let payloads = items
+                .get(1..items.len().saturating_sub(2))
+                .ok_or(anyhow!("Unable to parse websocket channel data"))?;
+
+            if channel.name.starts_with("book") {
+                self.process_order_book(&channel.currency_pair, payloads)?;
+            } else {
+                self.log_unknown_message(self.id.clone(), msg);
+            }
+
+            return Ok(());
+        }
+
+        // So it is private data which is [data, channelName, {"sequence": ...}]
+        let channel_name = items
+            .get(1)
+            .and_then(|channel_name| channel_name.as_str())
+            .ok_or(anyhow!("Unable to parse websocket channel name"))?;
+        let entries = items[0]
+            .as_array()
+            .ok_or(anyhow!("Unable to parse {} data", channel_name))?;
+
+        match channel_name {
+            "openOrders" => self.handle_orders_update(msg, entries),
+            "ownTrades" => self.handle_trades(entries),
+            _ => {
+                self.log_unknown_message(self.id.clone(), msg);
+                Ok(())
+            }
+        }
+    }
+
+    fn set_order_created_callback(
+        &self,
+        callback: Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>,
+    ) {
+        *self.order_created_callback.lock() = callback;
+    }
+
+    fn set_order_cancelled_callback(
+        &self,
+        callback: Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>,
+    ) {
+        *self.order_cancelled_callback.lock() = callback;
+    }
+
+    fn set_handle_order_filled_callback(
+        &self,
+        callback: Box<dyn FnMut(FillEventData) + Send + Sync>,
+    ) {
+        *self.handle_order_filled_callback.lock() = callback;
+    }
+
+    fn set_websocket_message_lag_callback(&self, callback: Box<dyn FnMut(Duration) + Send + Sync>) {
+        *self.websocket_message_lag_callback.lock() = callback;
+    }
+
+    fn set_traded_specific_currencies(&self, currencies: Vec<SpecificCurrencyPair>) {
+        *self.traded_specific_currencies.lock() = currencies;
+    }
+
+    fn set_server_time_offset(&self, _offset: chrono::Duration) {
+        // Kraken signs requests by nonce, so server time doesn't affect them
+    }
+
+    fn is_websocket_enabled(&self, role: WebSocketRole) -> bool {
+        match role {
+            WebSocketRole::Main => true,
+            WebSocketRole::Secondary => {
+                !self.settings.api_key.is_empty() && !self.settings.secret_key.is_empty()
+            }
+        }
+    }
+
+    async fn create_ws_url(&self, role: WebSocketRole) -> Result<Uri> {
+        // Channels are chosen by subscription messages after connection
+        let host = match role {
+            WebSocketRole::Main => &self.settings.web_socket_host,
+            WebSocketRole::Secondary => &self.settings.web_socket2_host,
+        };
+
+        host.parse::<Uri>()
+            .with_context(|| format!("Unable parse websocket {:?} uri", role))
+    }
+
+    async fn get_websocket_subscriptions(&self, role: WebSocketRole) -> Result<Vec<String>> {
+        self.build_websocket_subscriptions(role).await
+    }
+
+    fn get_currency_pairs_subscriptions(
+        &self,
+        currency_pairs: &[SpecificCurrencyPair],
+    ) -> Result<Vec<String>> {
+        Ok(self.build_public_subscriptions(currency_pairs))
+    }
+
+    fn get_network_connector(&self) -> &NetworkConnector {
+        self.rest_client.network_connector()
+    }
+
+    fn get_specific_currency_pair(&self, currency_pair: &CurrencyPair) -> SpecificCurrencyPair {
+        self.unified_to_specific.read()[currency_pair].clone()
+    }
+
+    fn get_supported_currencies(&self) -> &DashMap<CurrencyId, CurrencyCode> {
+        &self.supported_currencies
+    }
+
+    fn should_log_message(&self, message: &str) -> bool {
+        message.contains("openOrders") || message.contains("ownTrades")
+    }
+
+    fn log_unknown_message(&self, exchange_account_id: ExchangeAccountId, message: &str) {
+        info!("Unknown message for {}: {}", exchange_account_id, message);
+    }
+
+    fn parse_open_orders(&self, response: &RestRequestOutcome) -> Result<Vec<OrderInfo>> {
+        let mut data = get_result(response)?;
+        let kraken_orders: HashMap<String, KrakenOrderInfo> =
+            serde_json::from_value(data["open"].take())
+                .context("Unable to parse response content for get_open_orders request")?;
+
+        kraken_orders
+            .iter()
+            .map(|(exchange_order_id, order)| {
+                self.specific_order_info_to_unified(exchange_order_id, order)
+            })
+            .collect()
+    }
+
+    fn parse_order_info(&self, response: &RestRequestOutcome) -> Result<OrderInfo> {
+        let data = get_result(response)?;
+        let kraken_orders: HashMap<String, KrakenOrderInfo> = serde_json::from_value(data)
+            .context("Unable to parse response content for get_order_info request")?;
+
+        let (exchange_order_id, order) = kraken_orders
+            .iter()
+            .next()
+            .ok_or(anyhow!("There is no order in get_order_info response"))?;
+
+        self.specific_order_info_to_unified(exchange_order_id, order)
+    }
+
+    fn parse_server_time(&self, response: &RestRequestOutcome) -> Result<DateTime> {
+        let data = get_result(response)?;
+        let server_time = data["unixtime"]
+            .as_i64()
+            .ok_or(anyhow!("Unable to parse unixtime field"))?;
+
+        Ok(Utc.timestamp(server_time, 0))
+    }
+
+    fn parse_metadata(
+        &self,
+        response: &RestRequestOutcome,
+    ) -> Result<Vec<Arc<CurrencyPairMetadata>>> {
+        let data = get_result(response)?;
+        let pairs = data
+            .as_object()
+            .ok_or(anyhow!("Unable to get asset pairs metadata from Kraken"))?;
+
+        let mut result = Vec::new();
+        for pair in pairs.values() {
+            // Pairs without websocket name like dark pool ones aren't traded
+            let websocket_pair = match pair.get("wsname").and_then(|wsname| wsname.as_str()) {
+                Some(websocket_pair) => websocket_pair,
+                None => continue,
+            };
+
+            let is_active = pair["status"] == "online";
+            let is_derivative = false;
+
+            let base_currency_id = pair
+                .get_as_str("base")
+                .context("Unable to get base currency id from Kraken")?;
+            let quote_currency_id = pair
+                .get_as_str("quote")
+                .context("Unable to get quote currency id from Kraken")?;
+
+            // Currency ids are like "XXBT" and "ZUSD" while websocket pair is "XBT/USD"
+            let (base_currency_code, quote_currency_code) = websocket_pair
+                .split('/')
+                .map(CurrencyCode::from)
+                .collect_tuple()
+                .ok_or(anyhow!(
+                    "Unable to parse websocket pair {} from Kraken",
+                    websocket_pair
+                ))?;
+
+            let specific_currency_pair =
+                SpecificCurrencyPair::from(pair.get_as_str("altname")?.as_str());
+            let unified_currency_pair =
+                CurrencyPair::from_codes(base_currency_code.clone(), quote_currency_code.clone());
+            self.unified_to_specific.write().insert(
+                unified_currency_pair.clone(),
+                specific_currency_pair.clone(),
+            );
+            self.specific_to_unified.write().insert(
+                specific_currency_pair.clone(),
+                unified_currency_pair.clone(),
+            );
+            self.specific_to_websocket_pair
+                .write()
+                .insert(specific_currency_pair.clone(), websocket_pair.to_owned());
+            self.websocket_pair_to_unified
+                .write()
+                .insert(websocket_pair.to_owned(), unified_currency_pair);
+
+            let price_tick = match pair.get_as_decimal("tick_size") {
+                Some(tick) => tick,
+                None => Decimal::new(1, pair.get_as_u32("pair_decimals")?),
+            };
+            let amount_tick = Decimal::new(1, pair.get_as_u32("lot_decimals")?);
+
+            let currency_pair_metadata = CurrencyPairMetadata::new(
+                is_active,
+                is_derivative,
+                base_currency_id.as_str().into(),
+                base_currency_code.clone(),
+                quote_currency_id.as_str().into(),
+                quote_currency_code,
+                None,
+                None,
+                base_currency_code.clone(),
+                pair.get_as_decimal("ordermin"),
+                None,
+                pair.get_as_decimal("costmin"),
+                Some(base_currency_code),
+                Precision::ByTick { tick: price_tick },
+                Precision::ByTick { tick: amount_tick },
+            );
+
+            result.push(Arc::new(currency_pair_metadata))
+        }
+
+        Ok(result)
+    }
+}
+
+trait GetOrErr {
+    fn get_as_str(&self, key: &str) -> Result<String>;
+    fn get_as_u32(&self, key: &str) -> Result<u32>;
+    fn get_as_decimal(&self, key: &str) -> Option<Decimal>;
+}
+
+impl GetOrErr for Value {
+    fn get_as_str(&self, key: &str) -> Result<String> {
+        Ok(self
+            .get(key)
+            .ok_or(anyhow!("Unable to get {} from Kraken", key))?
+            .as_str()
+            .ok_or(anyhow!("Unable to get {} as string from Kraken", key))?
+            .to_string())
+    }
+
+    fn get_as_u32(&self, key: &str) -> Result<u32> {
+        let value = self
+            .get(key)
+            .and_then(|value| value.as_u64())
+            .ok_or(anyhow!("Unable to get {} as number from Kraken", key))?;
+        Ok(value as u32)
+    }
+
+    fn get_as_decimal(&self, key: &str) -> Option<Decimal> {
+        self.get(key)
+            .and_then(|value| value.as_str())
+            .and_then(|value| Decimal::from_str(value).ok())
+    }
+}
+
+impl Kraken {
+    fn handle_websocket_event(&self, msg: &str, data: &Value) -> Result<()> {
+        let event = data["event"]
+            .as_str()
+            .ok_or(anyhow!("Unable to parse websocket event"))?;
+
+        match event {
+            "heartbeat" | "pong" | "systemStatus" => Ok(()),
+            "subscriptionStatus" => self.handle_subscription_status(data),
+            _ => {
+                self.log_unknown_message(self.id.clone(), msg);
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_subscription_status(&self, data: &Value) -> Result<()> {
+        let status = data["status"]
+            .as_str()
+            .ok_or(anyhow!("Unable to parse subscription status"))?;
+        if status == "error" {
+            bail!(
+                "Unable to subscribe on {}: {}",
+                self.id,
+                data["errorMessage"]
+            );
+        }
+
+        // Private channels have no channel id
+        let channel_id = match data["channelID"].as_u64() {
+            Some(channel_id) => channel_id,
+            None => return Ok(()),
+        };
+
+        match status {
+            "subscribed" => {
+                let name = data["channelName"]
+                    .as_str()
+                    .ok_or(anyhow!("Unable to parse subscription channel name"))?;
+                let websocket_pair = data["pair"]
+                    .as_str()
+                    .ok_or(anyhow!("Unable to parse subscription pair"))?;
+                let channel = SubscribedChannel {
+                    name: name.to_owned(),
+                    currency_pair: self.currency_pair_from_web_socket(websocket_pair)?,
+                };
+                let _ = self.subscribed_channels.write().insert(channel_id, channel);
+            }
+            "unsubscribed" => {
+                let _ = self.subscribed_channels.write().remove(&channel_id);
+            }
+            _ => bail!("Unexpected subscription status {}", status),
+        }
+
+        Ok(())
+    }
+
+    /// Snapshot contains "as" and "bs" levels, update contains "a" and/or "b" ones
+    fn process_order_book(&self, currency_pair: &CurrencyPair, payloads: &[Value]) -> Result<()> {
+        if !self.subscribe_to_market_data {
+            return Ok(());
+        }
+
+        for payload in payloads {
+            let (event_type, asks, bids) = if payload.get("as").is_some() {
+                (EventType::Snapshot, &payload["as"], &payload["bs"])
+            } else {
+                (EventType::Update, &payload["a"], &payload["b"])
+            };
+
+            let asks = get_order_book_side(asks)?;
+            let bids = get_order_book_side(bids)?;
+
+            // Kraken has no update id, checksum of book is the closest identifier
+            let event_id = payload["c"].as_str().unwrap_or_default();
+
+            let order_book_event = OrderBookEvent::new(
+                Utc::now(),
+                self.id.clone(),
+                currency_pair.clone(),
+                event_id.to_owned(),
+                event_type,
+                OrderBookData::new(asks, bids),
+            );
+
+            self.send_event(ExchangeEvent::OrderBookEvent(order_book_event))?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_orders_update(&self, msg_to_log: &str, entries: &[Value]) -> Result<()> {
+        for (exchange_order_id, order) in entries
+            .iter()
+            .filter_map(|entry| entry.as_object())
+            .flatten()
+        {
+            let status = match order["status"].as_str() {
+                Some(status) => status,
+                // Updates of filled amount have no status
+                None => continue,
+            };
+
+            let exchange_order_id: ExchangeOrderId = exchange_order_id.as_str().into();
+            let client_order_id = match self.client_order_ids.get(&exchange_order_id) {
+                Some(client_order_id) => client_order_id.value().clone(),
+                None => {
+                    info!(
+                        "Order {} isn't created by {}, status: {}",
+                        exchange_order_id, self.id, status
+                    );
+                    continue;
+                }
+            };
+
+            match status {
+                "pending" => {}
+                "open" => self.order_created_callback.lock()(
+                    client_order_id,
+                    exchange_order_id,
+                    EventSourceType::WebSocket,
+                ),
+                "canceled" | "expired" => {
+                    let _ = self.client_order_ids.remove(&exchange_order_id);
+                    self.order_cancelled_callback.lock()(
+                        client_order_id,
+                        exchange_order_id,
+                        EventSourceType::WebSocket,
+                    );
+                }
+                // Fills are received from ownTrades channel
+                "closed" => {
+                    let _ = self.client_order_ids.remove(&exchange_order_id);
+                }
+                _ => error!(
+                    "Unexpected order status {} for message {}",
+                    status, msg_to_log
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_trades(&self, entries: &[Value]) -> Result<()> {
+        for (trade_id, trade) in entries
+            .iter()
+            .filter_map(|entry| entry.as_object())
+            .flatten()
+        {
+            let event_data = self.prepare_data_for_fill_handler(trade_id, trade)?;
+
+            self.handle_order_filled_callback.lock()(event_data);
+        }
+
+        Ok(())
+    }
+
+    fn prepare_data_for_fill_handler(
+        &self,
+        trade_id: &str,
+        trade: &Value,
+    ) -> Result<FillEventData> {
+        let exchange_order_id: ExchangeOrderId = trade
+            .get_as_str("ordertxid")
+            .context("Unable to parse order id of trade")?
+            .as_str()
+            .into();
+        let websocket_pair = trade.get_as_str("pair")?;
+        // Kraken charges fee in quote currency by default
+        let commission_currency_code = websocket_pair
+            .split('/')
+            .nth(1)
+            .map(CurrencyCode::from)
+            .ok_or(anyhow!("Unable to parse pair {} of trade", websocket_pair))?;
+        let order_side = Self::to_local_order_side(&trade.get_as_str("type")?)?;
+        let order_role = trade["maker"].as_bool().map(|is_maker| match is_maker {
+            true => OrderRole::Maker,
+            false => OrderRole::Taker,
+        });
+
+        let event_data = FillEventData {
+            source_type: EventSourceType::WebSocket,
+            trade_id: trade_id.to_owned(),
+            client_order_id: self
+                .client_order_ids
+                .get(&exchange_order_id)
+                .map(|client_order_id| client_order_id.value().clone()),
+            exchange_order_id,
+            fill_price: trade.get_as_str("price")?.parse()?,
+            fill_amount: trade.get_as_str("vol")?.parse()?,
+            is_diff: true,
+            total_filled_amount: None,
+            order_role,
+            commission_currency_code: Some(commission_currency_code),
+            commission_rate: None,
+            commission_amount: Some(trade.get_as_str("fee")?.parse()?),
+            fill_type: OrderFillType::UserTrade,
+            trade_currency_pair: None,
+            order_side: Some(order_side),
+            order_amount: None,
+        };
+
+        Ok(event_data)
+    }
+}
+
+/// Levels are [price, volume, timestamp], updates may have additional "r" flag of republished level
+fn get_order_book_side(levels: &Value) -> Result<SortedOrderData> {
+    let levels = match levels.as_array() {
+        Some(levels) => levels,
+        None => return Ok(SortedOrderData::new()),
+    };
+
+    levels
+        .iter()
+        .map(|x| {
+            let price = x[0]
+                .as_str()
+                .ok_or(anyhow!("Unable parse price of order book side in Kraken"))?
+                .parse()?;
+            let amount = x[1]
+                .as_str()
+                .ok_or(anyhow!("Unable parse amount of order book side in Kraken"))?
+                .parse()?;
+            Ok((price, amount))
+        })
+        .try_collect()
+}
@@ -1,88 +1,209 @@
 use super::common::*;
-use anyhow::{bail, Context, Result};
-use hyper::client::HttpConnector;
-use hyper::{Body, Client, Error, Request, Response, Uri};
+use crate::core::connectivity::network_connector::NetworkConnector;
+use anyhow::{anyhow, bail, Context, Result};
+use futures::Future;
+use hyper::header::{HeaderName, HeaderValue, CONNECTION, CONTENT_TYPE, RETRY_AFTER};
+use hyper::service::Service;
+use hyper::{Body, Client, Error, HeaderMap, Method, Request, Response, Uri};
 use hyper_tls::HttpsConnector;
+use serde::Serialize;
 use std::convert::TryInto;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
 
 pub type HttpParams = Vec<(String, String)>;
 
+/// Request which is sent by RestClient. Body is kept serialized, so authenticator is able to sign it
+#[derive(Debug, Clone)]
+pub struct RestRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl RestRequest {
+    pub fn new(method: Method, uri: Uri) -> Self {
+        Self {
+            method,
+            uri,
+            headers: HeaderMap::new(),
+            body: String::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        let _ = self.headers.insert(name, value);
+        self
+    }
+
+    pub fn with_form(mut self, http_params: &HttpParams) -> Self {
+        self.body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(http_params)
+            .finish();
+        self.with_header(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        )
+    }
+
+    pub fn with_json<T: Serialize>(mut self, body: &T) -> Result<Self> {
+        self.body = serde_json::to_string(body).context("Unable to serialize request body")?;
+        Ok(self.with_header(CONTENT_TYPE, HeaderValue::from_static("application/json")))
+    }
+}
+
+/// Exchange specific authentication of requests, e.g. api key header or request signature
+pub trait RestRequestAuthenticator: Send + Sync {
+    fn authenticate(&self, request: &mut RestRequest) -> Result<()>;
+}
+
 pub struct RestClient {
-    client: Client<HttpsConnector<HttpConnector>>,
+    client: Client<HttpsConnector<TcpConnector>>,
+    network_connector: NetworkConnector,
+    authenticator: Option<Box<dyn RestRequestAuthenticator>>,
 }
 
 const KEEP_ALIVE: &'static str = "keep-alive";
 
 impl RestClient {
-    pub fn new() -> Self {
-        Self {
-            client: create_client(),
-        }
+    pub fn new(network_connector: NetworkConnector) -> Result<Self> {
+        Ok(Self {
+            client: create_client(&network_connector)?,
+            network_connector,
+            authenticator: None,
+        })
     }
 
-    pub async fn get(&self, url: Uri, api_key: &str) -> Result<RestRequestOutcome> {
-        let req = Request::get(url)
-            .header(hyper::header::CONNECTION, KEEP_ALIVE)
-            .header("X-MBX-APIKEY", api_key)
-            .body(Body::empty())
-            .context("Error during creation of http GET request")?;
-
-        let response = self.client.request(req).await;
+    /// Every request is passed through authenticator before sending
+    pub fn with_authenticator(mut self, authenticator: Box<dyn RestRequestAuthenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
 
-        handle_response(response, "GET").await
+    pub fn network_connector(&self) -> &NetworkConnector {
+        &self.network_connector
     }
 
-    pub async fn post(
-        &self,
-        url: Uri,
-        api_key: &str,
-        http_params: &HttpParams,
-    ) -> Result<RestRequestOutcome> {
-        let form_encoded = form_urlencoded::Serializer::new(String::new())
-            .extend_pairs(http_params)
-            .finish();
+    pub async fn get(&self, url: Uri) -> Result<RestRequestOutcome> {
+        self.send(RestRequest::new(Method::GET, url)).await
+    }
 
-        let req = Request::post(url)
-            .header(hyper::header::CONNECTION, KEEP_ALIVE)
-            .header("X-MBX-APIKEY", api_key)
-            .body(Body::from(form_encoded))
-            .context("Error during creation of http delete request")?;
+    pub async fn post(&self, url: Uri, http_params: &HttpParams) -> Result<RestRequestOutcome> {
+        self.send(RestRequest::new(Method::POST, url).with_form(http_params))
+            .await
+    }
 
-        let response = self.client.request(req).await;
+    pub async fn put(&self, url: Uri, http_params: &HttpParams) -> Result<RestRequestOutcome> {
+        self.send(RestRequest::new(Method::PUT, url).with_form(http_params))
+            .await
+    }
 
-        handle_response(response, "POST").await
+    pub async fn delete(&self, url: Uri) -> Result<RestRequestOutcome> {
+        self.send(RestRequest::new(Method::DELETE, url)).await
     }
 
-    pub async fn delete(&self, url: Uri, api_key: &str) -> Result<RestRequestOutcome> {
-        let req = Request::delete(url)
-            .header(hyper::header::CONNECTION, KEEP_ALIVE)
-            .header("X-MBX-APIKEY", api_key)
-            .body(Body::empty())
-            .context("Error during creation of http delete request")?;
+    /// Read timeout covers waiting of response and reading its body.
+    /// Latency of outcome is measured from sending of request to reading of response body
+    pub async fn send(&self, mut request: RestRequest) -> Result<RestRequestOutcome> {
+        if let Some(authenticator) = &self.authenticator {
+            authenticator
+                .authenticate(&mut request)
+                .context("Unable to authenticate request")?;
+        }
 
-        let response = self.client.request(req).await;
+        let rest_action = request.method.to_string();
+        let mut builder = Request::builder()
+            .method(request.method)
+            .uri(request.uri)
+            .header(CONNECTION, KEEP_ALIVE);
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(request.headers);
+        }
+        let body = match request.body.is_empty() {
+            true => Body::empty(),
+            false => Body::from(request.body),
+        };
+        let req = builder
+            .body(body)
+            .with_context(|| format!("Error during creation of http {} request", rest_action))?;
 
-        handle_response(response, "DELETE").await
+        let started_at = Instant::now();
+        let sending = async {
+            handle_response(self.client.request(req).await, &rest_action, started_at).await
+        };
+        match self.network_connector.read_timeout() {
+            Some(read_timeout) => tokio::time::timeout(read_timeout, sending)
+                .await
+                .with_context(|| format!("Timeout of {} request", rest_action))?,
+            None => sending.await,
+        }
     }
 }
 
-fn create_client() -> Client<HttpsConnector<HttpConnector>> {
-    let https = HttpsConnector::new();
-    Client::builder().build::<_, hyper::Body>(https)
+fn create_client(
+    network_connector: &NetworkConnector,
+) -> Result<Client<HttpsConnector<TcpConnector>>> {
+    let tls_connector = network_connector.create_native_tls_connector()?;
+    let https = HttpsConnector::from((
+        TcpConnector(network_connector.clone()),
+        tls_connector.into(),
+    ));
+    Ok(Client::builder().build::<_, hyper::Body>(https))
+}
+
+/// Hyper connector which establishes connections through NetworkConnector
+#[derive(Clone)]
+pub struct TcpConnector(NetworkConnector);
+
+impl Service<Uri> for TcpConnector {
+    type Response = TcpStream;
+    type Error = anyhow::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let network_connector = self.0.clone();
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| anyhow!("There is no host in uri {}", uri))?;
+            let default_port = match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            };
+            let port = uri.port_u16().unwrap_or(default_port);
+
+            network_connector.connect(host, port).await
+        })
+    }
 }
 
 // Inner Hyper types. Needed just for unified response handling in handle_response()
 type ResponseType = std::result::Result<Response<Body>, Error>;
-async fn handle_response(response: ResponseType, rest_action: &str) -> Result<RestRequestOutcome> {
+async fn handle_response(
+    response: ResponseType,
+    rest_action: &str,
+    started_at: Instant,
+) -> Result<RestRequestOutcome> {
     match response {
-        Ok(response) => Ok(RestRequestOutcome {
-            status: response.status(),
-            content: std::str::from_utf8(
-                hyper::body::to_bytes(response.into_body()).await?.as_ref(),
-            )
-            .context("Unable to parse content string")?
-            .to_owned(),
-        }),
+        Ok(response) => {
+            let (parts, body) = response.into_parts();
+            let content = std::str::from_utf8(hyper::body::to_bytes(body).await?.as_ref())
+                .context("Unable to parse content string")?
+                .to_owned();
+            Ok(RestRequestOutcome {
+                status: parts.status,
+                headers: parts.headers,
+                content,
+                latency: started_at.elapsed(),
+            })
+        }
         Err(error) => bail!("Unable to send {} request: {}", rest_action, error),
     }
 }
@@ -125,10 +246,103 @@ pub fn to_http_string(parameters: &HttpParams) -> String {
     http_string
 }
 
+/// Only delay in seconds is supported because exchanges don't send HTTP-date in Retry-After
+pub fn get_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
 #[cfg(test)]
 mod tests {
+    use hyper::StatusCode;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
     use super::*;
 
+    struct TestAuthenticator;
+
+    impl RestRequestAuthenticator for TestAuthenticator {
+        fn authenticate(&self, request: &mut RestRequest) -> Result<()> {
+            let signature = format!("{}:{}", request.uri.path(), request.body);
+            let _ = request
+                .headers
+                .insert("x-signature", HeaderValue::from_str(&signature)?);
+            Ok(())
+        }
+    }
+
+    /// Serves one request and returns its raw content
+    async fn serve_one_request(listener: TcpListener) -> String {
+        let (mut stream, _) = listener.accept().await.expect("in test");
+        let mut request = vec![0u8; 4096];
+        let len = stream.read(&mut request).await.expect("in test");
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+            .await
+            .expect("in test");
+        String::from_utf8(request[..len].to_vec()).expect("in test")
+    }
+
+    #[test]
+    pub fn request_bodies() {
+        let uri: Uri = "https://host.com/path".try_into().expect("in test");
+        let http_params = vec![("symbol".to_owned(), "BTCUSDT".to_owned())];
+
+        let form = RestRequest::new(Method::POST, uri.clone()).with_form(&http_params);
+        assert_eq!(form.body, "symbol=BTCUSDT");
+        assert_eq!(
+            form.headers[CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+
+        let json = RestRequest::new(Method::POST, uri)
+            .with_json(&json!({ "symbol": "BTCUSDT" }))
+            .expect("in test");
+        assert_eq!(json.body, r#"{"symbol":"BTCUSDT"}"#);
+        assert_eq!(json.headers[CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    pub async fn send_authenticated_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("in test");
+        let address = listener.local_addr().expect("in test");
+        let server = tokio::spawn(serve_one_request(listener));
+
+        let rest_client = RestClient::new(NetworkConnector::default())
+            .expect("in test")
+            .with_authenticator(Box::new(TestAuthenticator));
+        let uri: Uri = format!("http://{}/listenKey", address)
+            .try_into()
+            .expect("in test");
+        let request = RestRequest::new(Method::PUT, uri)
+            .with_header(
+                HeaderName::from_static("x-custom"),
+                HeaderValue::from_static("value"),
+            )
+            .with_json(&json!({ "listenKey": "key" }))
+            .expect("in test");
+
+        let outcome = rest_client.send(request).await.expect("in test");
+        assert_eq!(outcome.status, StatusCode::OK);
+        assert_eq!(outcome.content, "{}");
+        assert!(outcome.latency > Duration::ZERO);
+
+        let raw_request = server.await.expect("in test");
+        assert!(raw_request.starts_with("PUT /listenKey HTTP/1.1\r\n"));
+        assert!(raw_request.contains("x-custom: value\r\n"));
+        assert!(raw_request.contains("content-type: application/json\r\n"));
+        assert!(raw_request.contains(r#"x-signature: /listenKey:{"listenKey":"key"}"#));
+        assert!(raw_request.ends_with(r#"{"listenKey":"key"}"#));
+    }
+
     #[test]
     pub fn full_uri() {
         let host = "https://host.com";
@@ -157,4 +371,19 @@ mod tests {
         let expected: Uri = "https://host.com/path".try_into().expect("in test");
         assert_eq!(uri, expected)
     }
+
+    #[test]
+    pub fn retry_after_in_seconds() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(get_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, "120".parse().expect("in test"));
+        assert_eq!(get_retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().expect("in test"),
+        );
+        assert_eq!(get_retry_after(&headers), None);
+    }
 }
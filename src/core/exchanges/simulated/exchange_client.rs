@@ -0,0 +1,75 @@
+use super::simulated::{error_outcome, Simulated, ORDER_NOT_FOUND};
+use crate::core::exchanges::traits::ExchangeClient;
+use crate::core::orders::order::*;
+use crate::core::{
+    exchanges::common::{CurrencyPair, RestRequestOutcome},
+    orders::pool::OrderRef,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use awc::http::StatusCode;
+use chrono::Utc;
+use serde_json::json;
+
+/// Simulated exchange answers immediately without network, responses are shaped like REST ones
+#[async_trait]
+impl ExchangeClient for Simulated {
+    async fn request_metadata(&self) -> Result<RestRequestOutcome> {
+        let content = json!({ "symbols": self.market.symbols() }).to_string();
+        Ok(RestRequestOutcome::new(content, StatusCode::OK))
+    }
+
+    async fn create_order(&self, order: &OrderCreating) -> Result<RestRequestOutcome> {
+        Ok(self.market.create_order(order))
+    }
+
+    async fn request_cancel_order(&self, order: &OrderCancelling) -> Result<RestRequestOutcome> {
+        Ok(self.market.cancel_order(&order.exchange_order_id))
+    }
+
+    async fn cancel_all_orders(&self, currency_pair: CurrencyPair) -> Result<()> {
+        for order in self.market.open_orders(Some(&currency_pair)) {
+            let _ = self.market.cancel_order(&order.exchange_order_id);
+        }
+
+        Ok(())
+    }
+
+    async fn request_open_orders(&self) -> Result<RestRequestOutcome> {
+        let content = json!({ "orders": self.market.open_orders(None) }).to_string();
+        Ok(RestRequestOutcome::new(content, StatusCode::OK))
+    }
+
+    async fn request_open_orders_by_currency_pair(
+        &self,
+        currency_pair: CurrencyPair,
+    ) -> Result<RestRequestOutcome> {
+        let content =
+            json!({ "orders": self.market.open_orders(Some(&currency_pair)) }).to_string();
+        Ok(RestRequestOutcome::new(content, StatusCode::OK))
+    }
+
+    async fn request_order_info(&self, order: &OrderRef) -> Result<RestRequestOutcome> {
+        let exchange_order_id = order.exchange_order_id().with_context(|| {
+            format!(
+                "Unable to get info of order {} without exchange order id",
+                order.client_order_id()
+            )
+        })?;
+
+        let outcome = match self.market.order_info(&exchange_order_id) {
+            Some(order_info) => RestRequestOutcome::new(
+                serde_json::to_string(&order_info).context("Unable to serialize order info")?,
+                StatusCode::OK,
+            ),
+            None => error_outcome(ORDER_NOT_FOUND),
+        };
+
+        Ok(outcome)
+    }
+
+    async fn request_server_time(&self) -> Result<RestRequestOutcome> {
+        let content = json!({ "server_time": Utc::now().timestamp_millis() }).to_string();
+        Ok(RestRequestOutcome::new(content, StatusCode::OK))
+    }
+}
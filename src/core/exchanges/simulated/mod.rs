@@ -0,0 +1,3 @@
+pub mod exchange_client;
+pub mod simulated;
+pub mod support;
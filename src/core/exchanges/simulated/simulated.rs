@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use awc::http::StatusCode;
+use chrono::Utc;
+use dashmap::DashMap;
+use log::error;
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast;
+
+use crate::core::connectivity::network_connector::NetworkConnector;
+use crate::core::exchanges::common::{
+    Amount, CurrencyCode, CurrencyId, CurrencyPair, ExchangeAccountId, Price, RestRequestOutcome,
+};
+use crate::core::exchanges::events::{AllowedEventSourceType, ExchangeEvent};
+use crate::core::exchanges::general::currency_pair_metadata::{CurrencyPairMetadata, Precision};
+use crate::core::exchanges::general::exchange::BoxExchangeClient;
+use crate::core::exchanges::general::features::{ExchangeFeatures, OpenOrdersType};
+use crate::core::exchanges::general::handlers::handle_order_filled::FillEventData;
+use crate::core::exchanges::timeouts::requests_timeout_manager_factory::RequestTimeoutArguments;
+use crate::core::exchanges::traits::{ExchangeClientBuilder, ExchangeClientBuilderResult};
+use crate::core::lifecycle::application_manager::ApplicationManager;
+use crate::core::order_book::event::{EventType, OrderBookEvent};
+use crate::core::order_book::order_book_data::OrderBookData;
+use crate::core::orders::fill::{EventSourceType, OrderFillType};
+use crate::core::orders::order::*;
+use crate::core::settings::ExchangeSettings;
+use crate::core::DateTime;
+
+pub(super) const ORDER_NOT_FOUND: &str = "Order not found";
+pub(super) const ORDER_WOULD_MATCH: &str = "Order would immediately match";
+pub(super) const UNSUPPORTED_ORDER_TYPE: &str = "Only limit orders are supported";
+pub(super) const UNKNOWN_CURRENCY_PAIR: &str = "Unknown currency pair";
+
+/// Trading rules of currency pair on simulated exchange
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedSymbol {
+    pub base: CurrencyCode,
+    pub quote: CurrencyCode,
+    pub price_tick: Price,
+    pub amount_tick: Amount,
+    #[serde(default)]
+    pub min_amount: Option<Amount>,
+    #[serde(default)]
+    pub min_cost: Option<Price>,
+    /// Commission rate charged in quote currency for every fill
+    #[serde(default)]
+    pub fee_rate: Decimal,
+}
+
+impl SimulatedSymbol {
+    pub fn currency_pair(&self) -> CurrencyPair {
+        CurrencyPair::from_codes(self.base.clone(), self.quote.clone())
+    }
+
+    pub(super) fn to_metadata(&self) -> CurrencyPairMetadata {
+        CurrencyPairMetadata::new(
+            true,
+            false,
+            self.base.as_str().into(),
+            self.base.clone(),
+            self.quote.as_str().into(),
+            self.quote.clone(),
+            None,
+            None,
+            self.base.clone(),
+            // Amount can't be less than one tick anyway
+            Some(self.min_amount.unwrap_or(self.amount_tick)),
+            None,
+            self.min_cost,
+            Some(self.base.clone()),
+            Precision::ByTick {
+                tick: self.price_tick,
+            },
+            Precision::ByTick {
+                tick: self.amount_tick,
+            },
+        )
+    }
+}
+
+/// Trading results of single currency pair on simulated exchange
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimulatedTradingResult {
+    pub created_orders_count: u64,
+    pub fills_count: u64,
+    pub bought_amount: Amount,
+    pub sold_amount: Amount,
+    pub base_balance_change: Amount,
+    /// Includes paid commission
+    pub quote_balance_change: Amount,
+    pub commission: Amount,
+    pub last_mid_price: Option<Price>,
+}
+
+impl SimulatedTradingResult {
+    /// Profit in quote currency with base balance change valued by last mid price
+    pub fn pnl(&self) -> Option<Amount> {
+        self.last_mid_price
+            .map(|mid_price| self.base_balance_change * mid_price + self.quote_balance_change)
+    }
+}
+
+type OrderEventCallback =
+    Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>;
+
+#[derive(Default)]
+struct MarketState {
+    /// Best ask and best bid of last replayed order book
+    top_prices: HashMap<CurrencyPair, (Option<Price>, Option<Price>)>,
+    orders: HashMap<ExchangeOrderId, OrderInfo>,
+    last_order_id: u64,
+    last_trade_id: u64,
+    results: HashMap<CurrencyPair, SimulatedTradingResult>,
+}
+
+/// Matching engine of simulated exchange account driven by replayed order books.
+/// Only limit orders which don't cross the book are accepted, they are filled as maker
+/// at their own price as soon as opposite side of replayed order book reaches them
+pub struct SimulatedMarket {
+    pub id: ExchangeAccountId,
+    symbols: HashMap<CurrencyPair, SimulatedSymbol>,
+    state: Mutex<MarketState>,
+
+    pub(super) order_created_callback: Mutex<OrderEventCallback>,
+    pub(super) order_cancelled_callback: Mutex<OrderEventCallback>,
+    pub(super) handle_order_filled_callback: Mutex<Box<dyn FnMut(FillEventData) + Send + Sync>>,
+
+    events_channel: broadcast::Sender<ExchangeEvent>,
+    application_manager: Arc<ApplicationManager>,
+}
+
+impl SimulatedMarket {
+    pub fn new(
+        id: ExchangeAccountId,
+        symbols: &[SimulatedSymbol],
+        events_channel: broadcast::Sender<ExchangeEvent>,
+        application_manager: Arc<ApplicationManager>,
+    ) -> Self {
+        Self {
+            id,
+            symbols: symbols
+                .iter()
+                .map(|symbol| (symbol.currency_pair(), symbol.clone()))
+                .collect(),
+            state: Default::default(),
+            order_created_callback: Mutex::new(Box::new(|_, _, _| {})),
+            order_cancelled_callback: Mutex::new(Box::new(|_, _, _| {})),
+            handle_order_filled_callback: Mutex::new(Box::new(|_| {})),
+            events_channel,
+            application_manager,
+        }
+    }
+
+    pub fn symbols(&self) -> Vec<SimulatedSymbol> {
+        self.symbols.values().cloned().collect()
+    }
+
+    pub fn trading_results(&self) -> HashMap<CurrencyPair, SimulatedTradingResult> {
+        self.state.lock().results.clone()
+    }
+
+    /// Fills resting orders reached by order book and publishes order book for strategy.
+    /// Recorded time identifies order book event
+    pub fn replay_order_book(
+        &self,
+        time: DateTime,
+        currency_pair: &CurrencyPair,
+        data: OrderBookData,
+    ) -> Result<()> {
+        let symbol = self
+            .symbols
+            .get(currency_pair)
+            .ok_or_else(|| anyhow!("{} {}", UNKNOWN_CURRENCY_PAIR, currency_pair))?;
+
+        let best_ask = data.asks.keys().next().copied();
+        let best_bid = data.bids.keys().next_back().copied();
+
+        let fills = {
+            let mut state = self.state.lock();
+            let _ = state
+                .top_prices
+                .insert(currency_pair.clone(), (best_ask, best_bid));
+
+            let mut fills = Vec::new();
+            let mut last_trade_id = state.last_trade_id;
+            for order in state.orders.values_mut().filter(|order| {
+                order.order_status == OrderStatus::Created && order.currency_pair == *currency_pair
+            }) {
+                if !is_crossing(order.order_side, order.price, best_ask, best_bid) {
+                    continue;
+                }
+
+                order.order_status = OrderStatus::Completed;
+                order.filled_amount = order.amount;
+                order.average_fill_price = order.price;
+
+                last_trade_id += 1;
+                let commission = order.price * order.amount * symbol.fee_rate;
+                fills.push((order.clone(), last_trade_id, commission));
+            }
+            state.last_trade_id = last_trade_id;
+
+            let result = state.results.entry(currency_pair.clone()).or_default();
+            if let (Some(best_ask), Some(best_bid)) = (best_ask, best_bid) {
+                result.last_mid_price = Some((best_ask + best_bid) / Decimal::from(2));
+            }
+
+            for (order, _, commission) in &fills {
+                let cost = order.price * order.amount;
+                result.fills_count += 1;
+                result.commission += commission;
+                match order.order_side {
+                    OrderSide::Buy => {
+                        result.bought_amount += order.amount;
+                        result.base_balance_change += order.amount;
+                        result.quote_balance_change -= cost + commission;
+                    }
+                    OrderSide::Sell => {
+                        result.sold_amount += order.amount;
+                        result.base_balance_change -= order.amount;
+                        result.quote_balance_change += cost - commission;
+                    }
+                }
+            }
+
+            fills
+        };
+
+        for (order, trade_id, commission) in fills {
+            let event_data = FillEventData {
+                source_type: EventSourceType::WebSocket,
+                trade_id: trade_id.to_string(),
+                client_order_id: Some(order.client_order_id),
+                exchange_order_id: order.exchange_order_id,
+                fill_price: order.price,
+                fill_amount: order.amount,
+                is_diff: true,
+                total_filled_amount: None,
+                order_role: Some(OrderRole::Maker),
+                commission_currency_code: Some(symbol.quote.clone()),
+                commission_rate: Some(symbol.fee_rate),
+                commission_amount: Some(commission),
+                fill_type: OrderFillType::UserTrade,
+                trade_currency_pair: None,
+                order_side: Some(order.order_side),
+                order_amount: Some(order.amount),
+            };
+
+            self.handle_order_filled_callback.lock()(event_data);
+        }
+
+        // Strategy skips stale events, so event is created at replay time
+        let order_book_event = OrderBookEvent::new(
+            Utc::now(),
+            self.id.clone(),
+            currency_pair.clone(),
+            time.timestamp_millis().to_string(),
+            EventType::Snapshot,
+            data,
+        );
+        self.send_event(ExchangeEvent::OrderBookEvent(order_book_event))
+    }
+
+    pub(super) fn create_order(&self, order: &OrderCreating) -> RestRequestOutcome {
+        let header = &order.header;
+        if header.order_type != OrderType::Limit {
+            return error_outcome(UNSUPPORTED_ORDER_TYPE);
+        }
+        if !self.symbols.contains_key(&header.currency_pair) {
+            return error_outcome(UNKNOWN_CURRENCY_PAIR);
+        }
+
+        let exchange_order_id = {
+            let mut state = self.state.lock();
+
+            // Taker liquidity isn't known from order book snapshots, so crossing orders are rejected
+            let (best_ask, best_bid) = state
+                .top_prices
+                .get(&header.currency_pair)
+                .copied()
+                .unwrap_or_default();
+            if is_crossing(header.side, order.price, best_ask, best_bid) {
+                return error_outcome(ORDER_WOULD_MATCH);
+            }
+
+            state.last_order_id += 1;
+            let exchange_order_id: ExchangeOrderId =
+                state.last_order_id.to_string().as_str().into();
+            let order_info = OrderInfo::new(
+                header.currency_pair.clone(),
+                exchange_order_id.clone(),
+                header.client_order_id.clone(),
+                header.side,
+                OrderStatus::Created,
+                order.price,
+                header.amount,
+                Decimal::ZERO,
+                Decimal::ZERO,
+                None,
+                None,
+                None,
+            );
+            let _ = state.orders.insert(exchange_order_id.clone(), order_info);
+            state
+                .results
+                .entry(header.currency_pair.clone())
+                .or_default()
+                .created_orders_count += 1;
+
+            exchange_order_id
+        };
+
+        self.order_created_callback.lock()(
+            header.client_order_id.clone(),
+            exchange_order_id.clone(),
+            EventSourceType::WebSocket,
+        );
+
+        order_id_outcome(&exchange_order_id)
+    }
+
+    pub(super) fn cancel_order(&self, exchange_order_id: &ExchangeOrderId) -> RestRequestOutcome {
+        let client_order_id = {
+            let mut state = self.state.lock();
+            match state.orders.get_mut(exchange_order_id) {
+                Some(order) if order.order_status == OrderStatus::Created => {
+                    order.order_status = OrderStatus::Canceled;
+                    order.client_order_id.clone()
+                }
+                _ => return error_outcome(ORDER_NOT_FOUND),
+            }
+        };
+
+        self.order_cancelled_callback.lock()(
+            client_order_id,
+            exchange_order_id.clone(),
+            EventSourceType::WebSocket,
+        );
+
+        order_id_outcome(exchange_order_id)
+    }
+
+    pub(super) fn open_orders(&self, currency_pair: Option<&CurrencyPair>) -> Vec<OrderInfo> {
+        self.state
+            .lock()
+            .orders
+            .values()
+            .filter(|order| order.order_status == OrderStatus::Created)
+            .filter(|order| currency_pair.is_none() || currency_pair == Some(&order.currency_pair))
+            .cloned()
+            .collect()
+    }
+
+    pub(super) fn order_info(&self, exchange_order_id: &ExchangeOrderId) -> Option<OrderInfo> {
+        self.state.lock().orders.get(exchange_order_id).cloned()
+    }
+
+    fn send_event(&self, event: ExchangeEvent) -> Result<()> {
+        match self.events_channel.send(event) {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                let msg = format!("Unable to send exchange event in {}: {}", self.id, error);
+                error!("{}", msg);
+                self.application_manager
+                    .clone()
+                    .spawn_graceful_shutdown(msg.clone());
+                bail!(msg)
+            }
+        }
+    }
+}
+
+/// Whether order price reaches opposite side of order book
+fn is_crossing(
+    side: OrderSide,
+    price: Price,
+    best_ask: Option<Price>,
+    best_bid: Option<Price>,
+) -> bool {
+    match side {
+        OrderSide::Buy => matches!(best_ask, Some(best_ask) if price >= best_ask),
+        OrderSide::Sell => matches!(best_bid, Some(best_bid) if price <= best_bid),
+    }
+}
+
+pub(super) fn error_outcome(message: &str) -> RestRequestOutcome {
+    RestRequestOutcome::new(
+        json!({ "error": message }).to_string(),
+        StatusCode::BAD_REQUEST,
+    )
+}
+
+fn order_id_outcome(exchange_order_id: &ExchangeOrderId) -> RestRequestOutcome {
+    RestRequestOutcome::new(
+        json!({ "order_id": exchange_order_id }).to_string(),
+        StatusCode::OK,
+    )
+}
+
+/// Exchange client which trades on SimulatedMarket instead of real exchange
+pub struct Simulated {
+    pub id: ExchangeAccountId,
+    pub market: Arc<SimulatedMarket>,
+    pub supported_currencies: DashMap<CurrencyId, CurrencyCode>,
+    pub websocket_message_lag_callback: Mutex<Box<dyn FnMut(Duration) + Send + Sync>>,
+    pub(super) network_connector: NetworkConnector,
+}
+
+impl Simulated {
+    pub fn new(settings: &ExchangeSettings, market: Arc<SimulatedMarket>) -> Self {
+        let id = settings.exchange_account_id.clone();
+        let network_connector = NetworkConnector::new(&settings.network)
+            .unwrap_or_else(|error| panic!("Invalid network settings of {}: {:?}", id, error));
+
+        Self {
+            id,
+            market,
+            supported_currencies: Default::default(),
+            websocket_message_lag_callback: Mutex::new(Box::new(|_| {})),
+            network_connector,
+        }
+    }
+}
+
+/// Creates simulated exchange clients for any exchange id and keeps their markets for replay
+#[derive(Clone)]
+pub struct SimulatedBuilder {
+    symbols: Vec<SimulatedSymbol>,
+    markets: Arc<DashMap<ExchangeAccountId, Arc<SimulatedMarket>>>,
+}
+
+impl SimulatedBuilder {
+    pub fn new(symbols: Vec<SimulatedSymbol>) -> Self {
+        Self {
+            symbols,
+            markets: Default::default(),
+        }
+    }
+
+    /// Markets of all created exchange clients
+    pub fn markets(&self) -> Vec<Arc<SimulatedMarket>> {
+        self.markets
+            .iter()
+            .map(|market| market.value().clone())
+            .collect()
+    }
+}
+
+impl ExchangeClientBuilder for SimulatedBuilder {
+    fn create_exchange_client(
+        &self,
+        exchange_settings: ExchangeSettings,
+        events_channel: broadcast::Sender<ExchangeEvent>,
+        application_manager: Arc<ApplicationManager>,
+    ) -> ExchangeClientBuilderResult {
+        let exchange_account_id = exchange_settings.exchange_account_id.clone();
+        let market = Arc::new(SimulatedMarket::new(
+            exchange_account_id.clone(),
+            &self.symbols,
+            events_channel,
+            application_manager,
+        ));
+        let _ = self.markets.insert(exchange_account_id, market.clone());
+
+        ExchangeClientBuilderResult {
+            client: Box::new(Simulated::new(&exchange_settings, market)) as BoxExchangeClient,
+            features: ExchangeFeatures::new(
+                OpenOrdersType::AllCurrencyPair,
+                false,
+                false,
+                AllowedEventSourceType::All,
+                AllowedEventSourceType::All,
+            ),
+        }
+    }
+
+    fn extend_settings(&self, _settings: &mut ExchangeSettings) {
+        // Simulated exchange isn't reachable by network
+    }
+
+    fn get_timeout_argments(&self, _settings: &ExchangeSettings) -> Vec<RequestTimeoutArguments> {
+        vec![RequestTimeoutArguments::from_requests_per_second(1000)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exchanges::common::SortedOrderData;
+    use crate::core::lifecycle::cancellation_token::CancellationToken;
+    use crate::core::orders::order::OrderHeader;
+    use rust_decimal_macros::dec;
+
+    fn symbol() -> SimulatedSymbol {
+        SimulatedSymbol {
+            base: "eos".into(),
+            quote: "btc".into(),
+            price_tick: dec!(0.0000001),
+            amount_tick: dec!(0.01),
+            min_amount: None,
+            min_cost: None,
+            fee_rate: dec!(0.001),
+        }
+    }
+
+    fn market() -> (SimulatedMarket, broadcast::Receiver<ExchangeEvent>) {
+        let (events_sender, events_receiver) = broadcast::channel(10);
+        let market = SimulatedMarket::new(
+            ExchangeAccountId::new("Binance".into(), 0),
+            &[symbol()],
+            events_sender,
+            ApplicationManager::new(CancellationToken::default()),
+        );
+
+        (market, events_receiver)
+    }
+
+    fn order_book(ask: Price, bid: Price) -> OrderBookData {
+        let asks: SortedOrderData = [(ask, dec!(1))].iter().copied().collect();
+        let bids: SortedOrderData = [(bid, dec!(1))].iter().copied().collect();
+        OrderBookData::new(asks, bids)
+    }
+
+    fn order(side: OrderSide, price: Price) -> OrderCreating {
+        let header = OrderHeader::new(
+            ClientOrderId::unique_id(),
+            Utc::now(),
+            ExchangeAccountId::new("Binance".into(), 0),
+            symbol().currency_pair(),
+            OrderType::Limit,
+            side,
+            dec!(2),
+            OrderExecutionType::None,
+            None,
+            None,
+            "FromTest".to_owned(),
+        );
+
+        OrderCreating { header, price }
+    }
+
+    #[test]
+    fn reject_crossing_order() {
+        let (market, _events_receiver) = market();
+        let currency_pair = symbol().currency_pair();
+        market
+            .replay_order_book(Utc::now(), &currency_pair, order_book(dec!(10), dec!(9)))
+            .expect("in test");
+
+        let outcome = market.create_order(&order(OrderSide::Buy, dec!(10)));
+
+        assert_eq!(outcome.status, StatusCode::BAD_REQUEST);
+        assert!(outcome.content.contains(ORDER_WOULD_MATCH));
+        assert!(market.open_orders(None).is_empty());
+    }
+
+    #[test]
+    fn fill_order_when_book_reaches_it() {
+        let (market, _events_receiver) = market();
+        let currency_pair = symbol().currency_pair();
+        market
+            .replay_order_book(Utc::now(), &currency_pair, order_book(dec!(10), dec!(9)))
+            .expect("in test");
+
+        let filled = Arc::new(Mutex::new(Vec::new()));
+        let filled_clone = filled.clone();
+        *market.handle_order_filled_callback.lock() =
+            Box::new(move |event_data| filled_clone.lock().push(event_data));
+
+        let outcome = market.create_order(&order(OrderSide::Buy, dec!(9.5)));
+        assert_eq!(outcome.status, StatusCode::OK);
+        assert_eq!(market.open_orders(Some(&currency_pair)).len(), 1);
+
+        market
+            .replay_order_book(Utc::now(), &currency_pair, order_book(dec!(9.6), dec!(9)))
+            .expect("in test");
+        assert!(filled.lock().is_empty());
+
+        market
+            .replay_order_book(Utc::now(), &currency_pair, order_book(dec!(9.5), dec!(9)))
+            .expect("in test");
+
+        let filled = filled.lock();
+        assert_eq!(filled.len(), 1);
+        assert_eq!(filled[0].fill_price, dec!(9.5));
+        assert_eq!(filled[0].fill_amount, dec!(2));
+        assert_eq!(filled[0].commission_amount, Some(dec!(0.019)));
+        assert!(market.open_orders(None).is_empty());
+
+        let result = market.trading_results()[&currency_pair].clone();
+        assert_eq!(result.created_orders_count, 1);
+        assert_eq!(result.fills_count, 1);
+        assert_eq!(result.bought_amount, dec!(2));
+        assert_eq!(result.base_balance_change, dec!(2));
+        assert_eq!(result.quote_balance_change, dec!(-19.019));
+        assert_eq!(result.last_mid_price, Some(dec!(9.25)));
+        assert_eq!(result.pnl(), Some(dec!(-0.519)));
+    }
+
+    #[test]
+    fn cancel_order() {
+        let (market, _events_receiver) = market();
+        let outcome = market.create_order(&order(OrderSide::Sell, dec!(11)));
+        let exchange_order_id = market.open_orders(None)[0].exchange_order_id.clone();
+        assert!(outcome.content.contains(exchange_order_id.as_str()));
+
+        let outcome = market.cancel_order(&exchange_order_id);
+        assert_eq!(outcome.status, StatusCode::OK);
+        assert!(market.open_orders(None).is_empty());
+
+        let outcome = market.cancel_order(&exchange_order_id);
+        assert_eq!(outcome.status, StatusCode::BAD_REQUEST);
+        assert!(outcome.content.contains(ORDER_NOT_FOUND));
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use awc::http::Uri;
+use chrono::{TimeZone, Utc};
+use dashmap::DashMap;
+use serde_json::Value;
+
+use super::simulated::{
+    Simulated, SimulatedSymbol, ORDER_NOT_FOUND, ORDER_WOULD_MATCH, UNKNOWN_CURRENCY_PAIR,
+    UNSUPPORTED_ORDER_TYPE,
+};
+use crate::core::connectivity::connectivity_manager::WebSocketRole;
+use crate::core::connectivity::network_connector::NetworkConnector;
+use crate::core::exchanges::common::{
+    CurrencyPair, ExchangeError, ExchangeErrorType, RestRequestOutcome, SpecificCurrencyPair,
+};
+use crate::core::exchanges::{
+    common::CurrencyCode, common::CurrencyId,
+    general::currency_pair_metadata::CurrencyPairMetadata,
+    general::handlers::handle_order_filled::FillEventData, traits::Support,
+};
+use crate::core::orders::fill::EventSourceType;
+use crate::core::orders::order::*;
+use crate::core::DateTime;
+
+#[async_trait]
+impl Support for Simulated {
+    fn is_rest_error_code(&self, response: &RestRequestOutcome) -> Result<(), ExchangeError> {
+        let data: Value = serde_json::from_str(&response.content).map_err(|_| {
+            ExchangeError::new(
+                ExchangeErrorType::ParsingError,
+                "Unable to parse response".into(),
+                None,
+            )
+        })?;
+
+        match data["error"].as_str() {
+            Some(message) => Err(ExchangeError::new(
+                ExchangeErrorType::Unknown,
+                message.to_owned(),
+                None,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn get_order_id(&self, response: &RestRequestOutcome) -> Result<ExchangeOrderId> {
+        let data: Value = serde_json::from_str(&response.content)?;
+        let id = data["order_id"]
+            .as_str()
+            .ok_or(anyhow!("Unable to parse id of created order"))?;
+        Ok(id.into())
+    }
+
+    fn clarify_error_type(&self, error: &mut ExchangeError) {
+        let message = error.message.as_str();
+        error.error_type = if message == ORDER_NOT_FOUND {
+            ExchangeErrorType::OrderNotFound
+        } else if message == ORDER_WOULD_MATCH
+            || message == UNSUPPORTED_ORDER_TYPE
+            || message == UNKNOWN_CURRENCY_PAIR
+        {
+            ExchangeErrorType::InvalidOrder
+        } else {
+            ExchangeErrorType::Unknown
+        };
+    }
+
+    fn on_websocket_message(&self, msg: &str) -> Result<()> {
+        // Events of simulated exchange are raised directly by SimulatedMarket
+        self.log_unknown_message(self.id.clone(), msg);
+        Ok(())
+    }
+
+    fn set_order_created_callback(
+        &self,
+        callback: Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>,
+    ) {
+        *self.market.order_created_callback.lock() = callback;
+    }
+
+    fn set_order_cancelled_callback(
+        &self,
+        callback: Box<dyn FnMut(ClientOrderId, ExchangeOrderId, EventSourceType) + Send + Sync>,
+    ) {
+        *self.market.order_cancelled_callback.lock() = callback;
+    }
+
+    fn set_handle_order_filled_callback(
+        &self,
+        callback: Box<dyn FnMut(FillEventData) + Send + Sync>,
+    ) {
+        *self.market.handle_order_filled_callback.lock() = callback;
+    }
+
+    fn set_websocket_message_lag_callback(&self, callback: Box<dyn FnMut(Duration) + Send + Sync>) {
+        *self.websocket_message_lag_callback.lock() = callback;
+    }
+
+    fn set_traded_specific_currencies(&self, _currencies: Vec<SpecificCurrencyPair>) {
+        // Market data of all symbols is replayed regardless of traded currencies
+    }
+
+    fn set_server_time_offset(&self, _offset: chrono::Duration) {
+        // Requests aren't signed, so server time doesn't affect them
+    }
+
+    fn is_websocket_enabled(&self, _role: WebSocketRole) -> bool {
+        false
+    }
+
+    async fn create_ws_url(&self, role: WebSocketRole) -> Result<Uri> {
+        Err(anyhow!("Simulated exchange has no {:?} websocket", role))
+    }
+
+    fn get_network_connector(&self) -> &NetworkConnector {
+        &self.network_connector
+    }
+
+    fn get_specific_currency_pair(&self, currency_pair: &CurrencyPair) -> SpecificCurrencyPair {
+        currency_pair.as_str().into()
+    }
+
+    fn get_supported_currencies(&self) -> &DashMap<CurrencyId, CurrencyCode> {
+        &self.supported_currencies
+    }
+
+    fn should_log_message(&self, _message: &str) -> bool {
+        false
+    }
+
+    fn parse_open_orders(&self, response: &RestRequestOutcome) -> Result<Vec<OrderInfo>> {
+        let mut data: Value = serde_json::from_str(&response.content)?;
+        serde_json::from_value(data["orders"].take())
+            .context("Unable to parse response content for get_open_orders request")
+    }
+
+    fn parse_order_info(&self, response: &RestRequestOutcome) -> Result<OrderInfo> {
+        serde_json::from_str(&response.content)
+            .context("Unable to parse response content for get_order_info request")
+    }
+
+    fn parse_server_time(&self, response: &RestRequestOutcome) -> Result<DateTime> {
+        let data: Value = serde_json::from_str(&response.content)?;
+        let server_time = data["server_time"]
+            .as_i64()
+            .ok_or(anyhow!("Unable to parse server_time field"))?;
+
+        Ok(Utc.timestamp_millis(server_time))
+    }
+
+    fn parse_metadata(
+        &self,
+        response: &RestRequestOutcome,
+    ) -> Result<Vec<Arc<CurrencyPairMetadata>>> {
+        let mut data: Value = serde_json::from_str(&response.content)?;
+        let symbols: Vec<SimulatedSymbol> = serde_json::from_value(data["symbols"].take())
+            .context("Unable to parse symbols of simulated exchange")?;
+
+        Ok(symbols
+            .iter()
+            .map(|symbol| Arc::new(symbol.to_metadata()))
+            .collect())
+    }
+}
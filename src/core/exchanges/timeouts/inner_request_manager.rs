@@ -16,6 +16,7 @@ use log::info;
 pub(super) struct InnerRequestsTimeoutManager {
     pub(super) requests_per_period: usize,
     pub(super) period_duration: Duration,
+    pub(super) request_weights: HashMap<RequestType, usize>,
     pub(super) exchange_account_id: ExchangeAccountId,
     pub(super) requests: Vec<Request>,
     pub(super) pre_reserved_groups: Vec<PreReservedGroup>,
@@ -44,7 +45,7 @@ impl InnerRequestsTimeoutManager {
         let _all_available_requests_count = self.get_all_available_requests_count();
         let available_requests_count = self.get_available_requests_count_at_present(current_time);
 
-        if available_requests_count == 0 {
+        if available_requests_count < self.get_request_weight(request_type) {
             // TODO save to DataRecorder
 
             return Ok(false);
@@ -65,6 +66,65 @@ impl InnerRequestsTimeoutManager {
         Ok(true)
     }
 
+    pub(super) fn get_request_weight(&self, request_type: RequestType) -> usize {
+        self.request_weights
+            .get(&request_type)
+            .copied()
+            .unwrap_or(1)
+    }
+
+    pub(super) fn get_rest_requests_count_in_group(
+        &self,
+        group: &PreReservedGroup,
+        current_time: DateTime,
+    ) -> usize {
+        let reserved_requests_count_for_group =
+            self.get_reserved_request_count_for_group_to_now(group.id, current_time);
+
+        group
+            .pre_reserved_requests_count
+            .saturating_sub(reserved_requests_count_for_group)
+    }
+
+    pub(super) fn get_request_start_time(
+        &self,
+        request_type: RequestType,
+        current_time: DateTime,
+    ) -> Result<DateTime> {
+        // Note: calculation doesnt' support request cancellation
+        // Note: suppose that exchange restriction work as your have n request on period and n request from beginning of next period and so on
+
+        // Algorithm:
+        // 1. We check: can we do request now
+        // 2. if not form schedule for request where put at start period by requestsPerPeriod requests
+
+        let request_weight = self.get_request_weight(request_type);
+        if request_weight > self.requests_per_period {
+            bail!(
+                "Request {:?} with weight {} can't be reserved because limit is {} for {}",
+                request_type,
+                request_weight,
+                self.requests_per_period,
+                self.exchange_account_id
+            );
+        }
+
+        if self.requests.is_empty() {
+            return Ok(current_time);
+        }
+
+        let last_requests_start_time = self.get_last_request()?.allowed_start_time;
+        let available_requests_count_for_period = self.get_available_requests_in_last_period()?;
+        let request_start_time = if available_requests_count_for_period < request_weight {
+            last_requests_start_time + self.period_duration + self.delay_to_next_time_period
+        } else {
+            last_requests_start_time
+        };
+
+        Ok(std::cmp::max(request_start_time, current_time))
+    }
+
+    /// Total weight of group requests started before current time
     pub(super) fn get_reserved_request_count_for_group_to_now(
         &self,
         group_id: RequestGroupId,
@@ -75,7 +135,7 @@ impl InnerRequestsTimeoutManager {
         let group_id = Some(group_id);
         for request in &self.requests {
             if request.allowed_start_time <= current_time && request.group_id == group_id {
-                count += 1;
+                count += request.weight;
             }
         }
 
@@ -88,8 +148,43 @@ impl InnerRequestsTimeoutManager {
         current_time: DateTime,
         group_id: Option<RequestGroupId>,
     ) -> Result<Request> {
-        let request = Request::new(request_type, current_time, group_id);
+        let weight = self.get_request_weight(request_type);
+        let request = Request::new(request_type, current_time, group_id, weight);
+        self.insert_request(request.clone())?;
+
+        Ok(request)
+    }
+
+    /// Server reported usage can only increase local usage: missing weight is added as a request at current time
+    pub(super) fn resync_used_weight(
+        &mut self,
+        request_type: RequestType,
+        server_used_weight: usize,
+        current_time: DateTime,
+    ) -> Result<usize> {
+        let current_time = self.get_non_decreasing_time(current_time);
+        self.remove_outdated_requests(current_time)?;
+
+        let local_used_weight = self
+            .get_reserved_requests_count_at_present(current_time)
+            .requests_count;
+        if server_used_weight <= local_used_weight {
+            return Ok(0);
+        }
+
+        let missing_weight = server_used_weight - local_used_weight;
+        self.insert_request(Request::new(
+            request_type,
+            current_time,
+            None,
+            missing_weight,
+        ))?;
+        self.last_time = Some(current_time);
 
+        Ok(missing_weight)
+    }
+
+    fn insert_request(&mut self, request: Request) -> Result<()> {
         let request_index = self
             .requests
             .binary_search_by_key(&request.allowed_start_time, |stored_request| {
@@ -97,12 +192,10 @@ impl InnerRequestsTimeoutManager {
             })
             .map_or_else(|error_index| error_index, |ok_index| ok_index);
 
-        self.requests.insert(request_index, request.clone());
+        self.requests.insert(request_index, request);
 
         self.handle_all_decreasing_triggers()?;
-        self.handle_all_increasing_triggers()?;
-
-        Ok(request)
+        self.handle_all_increasing_triggers()
     }
 
     pub(super) fn handle_all_decreasing_triggers(&mut self) -> Result<()> {
@@ -208,7 +301,7 @@ impl InnerRequestsTimeoutManager {
                 continue;
             }
 
-            requests_count += 1;
+            requests_count += request.weight;
 
             match request.group_id {
                 None => continue,
@@ -220,9 +313,9 @@ impl InnerRequestsTimeoutManager {
                             continue;
                         }
                         Some(requests_count_tmp) => {
-                            requests_count_in_group += 1;
+                            requests_count_in_group += request.weight;
 
-                            requests_count_tmp.requests_count += 1;
+                            requests_count_tmp.requests_count += request.weight;
                         }
                     }
                 }
@@ -246,8 +339,8 @@ impl InnerRequestsTimeoutManager {
     }
 
     pub(super) fn get_all_available_requests_count(&self) -> usize {
-        let available_requests_number =
-            self.requests_per_period.saturating_sub(self.requests.len());
+        let reserved_weight: usize = self.requests.iter().map(|request| request.weight).sum();
+        let available_requests_number = self.requests_per_period.saturating_sub(reserved_weight);
 
         available_requests_number
     }
@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::sync::{Arc, Weak};
 
@@ -40,18 +41,23 @@ impl Display for RequestGroupId {
 
 pub struct RequestsTimeoutManager {
     inner: Mutex<InnerRequestsTimeoutManager>,
+    /// Limit applies to all request types if it isn't specified
+    request_types: Option<HashSet<RequestType>>,
 }
 
 impl RequestsTimeoutManager {
     pub fn new(
         requests_per_period: usize,
         period_duration: Duration,
+        request_weights: HashMap<RequestType, usize>,
+        request_types: Option<HashSet<RequestType>>,
         exchange_account_id: ExchangeAccountId,
         more_or_equals_available_requests_count_trigger_scheduler: MoreOrEqualsAvailableRequestsCountTriggerScheduler,
     ) -> Arc<Self> {
         let inner = InnerRequestsTimeoutManager {
             requests_per_period,
             period_duration,
+            request_weights,
             exchange_account_id,
             requests: Default::default(),
             pre_reserved_groups: Default::default(),
@@ -66,9 +72,18 @@ impl RequestsTimeoutManager {
 
         Arc::new(Self {
             inner: Mutex::new(inner),
+            request_types,
         })
     }
 
+    pub fn is_applicable(&self, request_type: RequestType) -> bool {
+        match &self.request_types {
+            Some(request_types) => request_types.contains(&request_type),
+            None => true,
+        }
+    }
+
+    /// Group is reserved by total weight of its requests
     pub fn try_reserve_group(
         &self,
         group_type: String,
@@ -76,6 +91,21 @@ impl RequestsTimeoutManager {
         requests_count: usize,
         // call_source: SourceInfo, // TODO not needed until DataRecorder is ready
     ) -> Result<Option<RequestGroupId>> {
+        let group_id = RequestGroupId::generate();
+        let is_reserved =
+            self.try_reserve_group_with_id(group_id, group_type, current_time, requests_count)?;
+
+        Ok(is_reserved.then_some(group_id))
+    }
+
+    /// Group with the same id can be reserved in several limits
+    pub fn try_reserve_group_with_id(
+        &self,
+        group_id: RequestGroupId,
+        group_type: String,
+        current_time: DateTime,
+        requests_count: usize,
+    ) -> Result<bool> {
         let mut inner = self.inner.lock();
 
         let current_time = inner.get_non_decreasing_time(current_time);
@@ -86,10 +116,9 @@ impl RequestsTimeoutManager {
 
         if available_requests_count < requests_count {
             // TODO save to DataRecorder
-            return Ok(None);
+            return Ok(false);
         }
 
-        let group_id = RequestGroupId::generate();
         let group = PreReservedGroup::new(group_id, group_type, requests_count);
         inner.pre_reserved_groups.push(group.clone());
 
@@ -104,7 +133,24 @@ impl RequestsTimeoutManager {
 
         (inner.group_was_reserved)(group)?;
 
-        Ok(Some(group_id))
+        Ok(true)
+    }
+
+    pub fn can_reserve_group(&self, requests_count: usize, current_time: DateTime) -> Result<bool> {
+        let mut inner = self.inner.lock();
+
+        let current_time = inner.get_non_decreasing_time(current_time);
+        inner.remove_outdated_requests(current_time)?;
+
+        Ok(inner.get_available_requests_count_at_present(current_time) >= requests_count)
+    }
+
+    pub fn has_group(&self, group_id: RequestGroupId) -> bool {
+        self.inner
+            .lock()
+            .pre_reserved_groups
+            .iter()
+            .any(|group| group.id == group_id)
     }
 
     pub fn remove_group(&self, group_id: RequestGroupId, _current_time: DateTime) -> Result<bool> {
@@ -142,6 +188,12 @@ impl RequestsTimeoutManager {
         }
     }
 
+    pub fn get_available_requests_count(&self, current_time: DateTime) -> usize {
+        self.inner
+            .lock()
+            .get_available_requests_count_at_present(current_time)
+    }
+
     pub fn try_reserve_instant(
         &self,
         request_type: RequestType,
@@ -187,19 +239,12 @@ impl RequestsTimeoutManager {
                 let all_available_requests_count = inner.get_all_available_requests_count();
                 let available_requests_count_without_group =
                     inner.get_available_requests_count_at_present(current_time);
-                let reserved_requests_count_for_group = inner
-                    .get_reserved_request_count_for_group_to_now(
-                        pre_reserved_group_id,
-                        current_time,
-                    );
-
-                let rest_requests_count_in_group = group
-                    .pre_reserved_requests_count
-                    .saturating_sub(reserved_requests_count_for_group);
+                let rest_requests_count_in_group =
+                    inner.get_rest_requests_count_in_group(&group, current_time);
                 let available_requests_count =
                     available_requests_count_without_group + rest_requests_count_in_group;
 
-                if available_requests_count == 0 {
+                if available_requests_count < inner.get_request_weight(request_type) {
                     // TODO save to DataRecorder
 
                     return Ok(false);
@@ -228,6 +273,60 @@ impl RequestsTimeoutManager {
         }
     }
 
+    pub fn period_duration(&self) -> Duration {
+        self.inner.lock().period_duration
+    }
+
+    /// Returns weight which was added to local usage to catch up with server reported usage
+    pub fn resync_used_weight(
+        &self,
+        request_type: RequestType,
+        server_used_weight: usize,
+        current_time: DateTime,
+    ) -> Result<usize> {
+        let mut inner = self.inner.lock();
+        let added_weight =
+            inner.resync_used_weight(request_type, server_used_weight, current_time)?;
+
+        if added_weight > 0 {
+            info!(
+                "Requests usage on {} resynchronized with server: added weight {} to reach {}",
+                inner.exchange_account_id, added_weight, server_used_weight
+            );
+        }
+
+        Ok(added_weight)
+    }
+
+    pub fn get_request_weight(&self, request_type: RequestType) -> usize {
+        self.inner.lock().get_request_weight(request_type)
+    }
+
+    /// Check without reservation, it is used to reserve request only if all limits have room
+    pub fn can_reserve_instant(
+        &self,
+        request_type: RequestType,
+        current_time: DateTime,
+        pre_reserved_group_id: Option<RequestGroupId>,
+    ) -> Result<bool> {
+        let mut inner = self.inner.lock();
+
+        let current_time = inner.get_non_decreasing_time(current_time);
+        inner.remove_outdated_requests(current_time)?;
+
+        let mut available_requests_count =
+            inner.get_available_requests_count_at_present(current_time);
+        let group = inner
+            .pre_reserved_groups
+            .iter()
+            .find(|group| Some(group.id) == pre_reserved_group_id);
+        if let Some(group) = group {
+            available_requests_count += inner.get_rest_requests_count_in_group(group, current_time);
+        }
+
+        Ok(available_requests_count >= inner.get_request_weight(request_type))
+    }
+
     pub fn try_reserve_request_instant(
         &self,
         request_type: RequestType,
@@ -244,13 +343,22 @@ impl RequestsTimeoutManager {
         current_time: DateTime,
         cancellation_token: CancellationToken,
     ) -> Result<(JoinHandle<FutureOutcome>, DateTime, Duration)> {
-        // Note: calculation doesnt' support request cancellation
-        // Note: suppose that exchange restriction work as your have n request on period and n request from beginning of next period and so on
-
-        // Algorithm:
-        // 1. We check: can we do request now
-        // 2. if not form schedule for request where put at start period by requestsPerPeriod requests
+        self.reserve_when_available_since(
+            request_type,
+            current_time,
+            current_time,
+            cancellation_token,
+        )
+    }
 
+    /// Request isn't started before `min_start_time`, so request can wait for the slowest of several limits
+    pub fn reserve_when_available_since(
+        self: Arc<Self>,
+        request_type: RequestType,
+        current_time: DateTime,
+        min_start_time: DateTime,
+        cancellation_token: CancellationToken,
+    ) -> Result<(JoinHandle<FutureOutcome>, DateTime, Duration)> {
         let mut inner = self.inner.lock();
 
         let current_time = inner.get_non_decreasing_time(current_time);
@@ -258,37 +366,19 @@ impl RequestsTimeoutManager {
 
         let _available_requests_count = inner.get_all_available_requests_count();
 
-        let mut request_start_time;
-        let delay;
-        let available_requests_count_for_period;
-        let request = if inner.requests.is_empty() {
-            request_start_time = current_time;
-            delay = Duration::zero();
-            available_requests_count_for_period = inner.requests_per_period;
-            inner.add_request(request_type, current_time, None)?
-        } else {
-            let last_request = inner.get_last_request()?;
-            let last_requests_start_time = last_request.allowed_start_time;
-
-            available_requests_count_for_period = inner.get_available_requests_in_last_period()?;
-            request_start_time = if available_requests_count_for_period == 0 {
-                last_requests_start_time + inner.period_duration + inner.delay_to_next_time_period
-            } else {
-                last_requests_start_time
-            };
-
-            request_start_time = std::cmp::max(request_start_time, current_time);
-            delay = request_start_time - current_time;
-            inner.add_request(request_type, request_start_time, None)?
-        };
+        let request_start_time = std::cmp::max(
+            inner.get_request_start_time(request_type, current_time)?,
+            min_start_time,
+        );
+        let delay = request_start_time - current_time;
+        let request = inner.add_request(request_type, request_start_time, None)?;
 
         info!(
             "Request {:?} reserved, available in request_start_time {}",
             request_type, request_start_time
         );
 
-        // TODO save to DataRecorder. Delete drop
-        drop(available_requests_count_for_period);
+        // TODO save to DataRecorder
 
         inner.last_time = Some(current_time);
 
@@ -309,6 +399,20 @@ impl RequestsTimeoutManager {
         Ok((request_availability, request_start_time, delay))
     }
 
+    /// Time when request can be started if it is reserved now
+    pub fn get_request_start_time(
+        &self,
+        request_type: RequestType,
+        current_time: DateTime,
+    ) -> Result<DateTime> {
+        let mut inner = self.inner.lock();
+
+        let current_time = inner.get_non_decreasing_time(current_time);
+        inner.remove_outdated_requests(current_time)?;
+
+        inner.get_request_start_time(request_type, current_time)
+    }
+
     async fn wait_for_request_availability(
         weak_self: Weak<Self>,
         request: Request,
@@ -2285,6 +2389,164 @@ mod test {
         }
     }
 
+    mod request_weights {
+        use super::*;
+
+        fn set_heavy_open_orders(timeout_manager: &RequestsTimeoutManager) {
+            timeout_manager
+                .inner
+                .lock()
+                .request_weights
+                .insert(RequestType::GetOpenOrders, 3);
+        }
+
+        #[rstest]
+        fn instant_reservation_accounts_weights(
+            timeout_manager: Arc<RequestsTimeoutManager>,
+        ) -> Result<()> {
+            // Arrange
+            set_heavy_open_orders(&timeout_manager);
+            let current_time = Utc::now();
+
+            // Act
+            let heavy_reserved = timeout_manager.try_reserve_instant(
+                RequestType::GetOpenOrders,
+                current_time,
+                None,
+            )?;
+            let second_heavy_reserved = timeout_manager.try_reserve_instant(
+                RequestType::GetOpenOrders,
+                current_time,
+                None,
+            )?;
+            let first_light_reserved = timeout_manager.try_reserve_instant(
+                RequestType::CreateOrder,
+                current_time,
+                None,
+            )?;
+            let second_light_reserved = timeout_manager.try_reserve_instant(
+                RequestType::CreateOrder,
+                current_time,
+                None,
+            )?;
+            let third_light_reserved = timeout_manager.try_reserve_instant(
+                RequestType::CreateOrder,
+                current_time,
+                None,
+            )?;
+
+            // Assert
+            assert!(heavy_reserved);
+            assert!(!second_heavy_reserved);
+            assert!(first_light_reserved);
+            assert!(second_light_reserved);
+            assert!(!third_light_reserved);
+            assert_eq!(
+                timeout_manager.get_available_requests_count(current_time),
+                0
+            );
+
+            let inner = timeout_manager.inner.lock();
+            assert_eq!(inner.requests.len(), 3);
+            let total_weight: usize = inner.requests.iter().map(|request| request.weight).sum();
+            assert_eq!(total_weight, 5);
+
+            Ok(())
+        }
+
+        #[rstest]
+        fn group_reservation_accounts_weights(
+            timeout_manager: Arc<RequestsTimeoutManager>,
+        ) -> Result<()> {
+            // Arrange
+            set_heavy_open_orders(&timeout_manager);
+            let current_time = Utc::now();
+            let group_id = timeout_manager
+                .try_reserve_group("GroupType".to_owned(), current_time, 5)?
+                .expect("in test");
+
+            // Act
+            let first_reserved = timeout_manager.try_reserve_instant(
+                RequestType::GetOpenOrders,
+                current_time,
+                Some(group_id),
+            )?;
+            let second_reserved = timeout_manager.try_reserve_instant(
+                RequestType::GetOpenOrders,
+                current_time,
+                Some(group_id),
+            )?;
+            let third_reserved = timeout_manager.try_reserve_instant(
+                RequestType::CreateOrder,
+                current_time,
+                Some(group_id),
+            )?;
+
+            // Assert
+            assert!(first_reserved);
+            assert!(!second_reserved);
+            assert!(third_reserved);
+
+            Ok(())
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn reserve_when_available_waits_for_enough_weight(
+            timeout_manager: Arc<RequestsTimeoutManager>,
+        ) -> Result<()> {
+            // Arrange
+            set_heavy_open_orders(&timeout_manager);
+            let current_time = Utc::now();
+            let _ = timeout_manager.clone().reserve_when_available(
+                RequestType::GetOpenOrders,
+                current_time,
+                CancellationToken::default(),
+            )?;
+
+            // Act
+            let (_, light_start_time, _) = timeout_manager.clone().reserve_when_available(
+                RequestType::CreateOrder,
+                current_time,
+                CancellationToken::default(),
+            )?;
+            let (_, heavy_start_time, _) = timeout_manager.clone().reserve_when_available(
+                RequestType::GetOpenOrders,
+                current_time,
+                CancellationToken::default(),
+            )?;
+
+            // Assert
+            assert_eq!(light_start_time, current_time);
+            let inner = timeout_manager.inner.lock();
+            assert_eq!(
+                heavy_start_time,
+                current_time + inner.period_duration + inner.delay_to_next_time_period
+            );
+
+            Ok(())
+        }
+
+        #[rstest]
+        fn reserve_when_available_fails_for_weight_over_limit(
+            timeout_manager: Arc<RequestsTimeoutManager>,
+        ) {
+            timeout_manager
+                .inner
+                .lock()
+                .request_weights
+                .insert(RequestType::GetOpenOrders, 40);
+
+            let result = timeout_manager.reserve_when_available(
+                RequestType::GetOpenOrders,
+                Utc::now(),
+                CancellationToken::default(),
+            );
+
+            assert!(result.is_err());
+        }
+    }
+
     mod triggers {
         use parking_lot::Mutex;
 
@@ -1,11 +1,14 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::{self, Display, Formatter},
     sync::Arc,
 };
 
 use chrono::{Duration, Utc};
 
-use crate::core::{exchanges::common::ExchangeAccountId, DateTime};
+use crate::core::{
+    exchanges::common::ExchangeAccountId, exchanges::general::request_type::RequestType, DateTime,
+};
 
 use super::{
     more_or_equals_available_requests_count_trigger_scheduler::MoreOrEqualsAvailableRequestsCountTriggerScheduler,
@@ -27,15 +30,33 @@ impl RequestsTimeoutManagerFactory {
         RequestsTimeoutManager::new(
             timeout_arguments.requests_per_period,
             timeout_arguments.period,
+            timeout_arguments.request_weights,
+            timeout_arguments.request_types,
             exchange_account_id,
             trigger_scheduler,
         )
     }
+
+    /// Separate limit for each timeout arguments
+    pub fn from_timeout_arguments(
+        timeout_arguments: Vec<RequestTimeoutArguments>,
+        exchange_account_id: ExchangeAccountId,
+    ) -> Vec<Arc<RequestsTimeoutManager>> {
+        timeout_arguments
+            .into_iter()
+            .map(|arguments| Self::from_requests_per_period(arguments, exchange_account_id.clone()))
+            .collect()
+    }
 }
 
 pub struct RequestTimeoutArguments {
+    /// Limit of total requests weight in period
     pub requests_per_period: usize,
     pub period: Duration,
+    /// Weight of request type which isn't specified is 1
+    pub request_weights: HashMap<RequestType, usize>,
+    /// Limit applies to all request types if it isn't specified
+    pub request_types: Option<HashSet<RequestType>>,
 }
 
 impl RequestTimeoutArguments {
@@ -43,9 +64,21 @@ impl RequestTimeoutArguments {
         Self {
             requests_per_period,
             period,
+            request_weights: HashMap::new(),
+            request_types: None,
         }
     }
 
+    pub fn for_request_types(mut self, request_types: &[RequestType]) -> Self {
+        self.request_types = Some(request_types.iter().copied().collect());
+        self
+    }
+
+    pub fn with_request_weights(mut self, request_weights: HashMap<RequestType, usize>) -> Self {
+        self.request_weights = request_weights;
+        self
+    }
+
     pub fn unlimited() -> RequestTimeoutArguments {
         Self::from_requests_per_second(usize::MAX)
     }
@@ -69,6 +102,11 @@ impl RequestTimeoutArguments {
         let period = Duration::hours(1);
         Self::new(requests_per_period, period)
     }
+
+    pub fn from_requests_per_day(requests_per_period: usize) -> RequestTimeoutArguments {
+        let period = Duration::days(1);
+        Self::new(requests_per_period, period)
+    }
 }
 
 impl Display for RequestTimeoutArguments {
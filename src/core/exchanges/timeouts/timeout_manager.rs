@@ -1,14 +1,15 @@
-use futures::future::ready;
-use futures::future::Either;
+use futures::future::{join_all, ready, Either};
 use futures::FutureExt;
+use itertools::Itertools;
+use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::future::Future;
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
-use anyhow::Result;
-use chrono::Utc;
+use anyhow::{bail, Result};
+use chrono::{Duration, Utc};
 use log::error;
 
 use crate::core::exchanges::common::ExchangeAccountId;
@@ -24,26 +25,103 @@ use crate::core::{
 
 pub type BoxFuture = Box<dyn Future<Output = Result<()>> + Sync + Send>;
 
+/// Weight used in the limit window of given period as reported by exchange server
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RequestsUsage {
+    pub period: Duration,
+    pub used_weight: usize,
+}
+
+impl RequestsUsage {
+    pub fn new(period: Duration, used_weight: usize) -> Self {
+        Self {
+            period,
+            used_weight,
+        }
+    }
+}
+
+/// Limits of exchange account are applied together: request is reserved only if all applicable limits have room
+struct ExchangeRequestsLimits {
+    limits: Vec<Arc<RequestsTimeoutManager>>,
+    // Check of all limits and reservation in them should be atomic
+    reservation_lock: Mutex<()>,
+}
+
+impl ExchangeRequestsLimits {
+    fn applicable(&self, request_type: RequestType) -> Vec<&Arc<RequestsTimeoutManager>> {
+        self.limits
+            .iter()
+            .filter(|limit| limit.is_applicable(request_type))
+            .collect()
+    }
+}
+
 pub struct TimeoutManager {
-    inner: HashMap<ExchangeAccountId, Arc<RequestsTimeoutManager>>,
+    inner: HashMap<ExchangeAccountId, ExchangeRequestsLimits>,
 }
 
 impl TimeoutManager {
     pub fn new(
-        timeout_managers: HashMap<ExchangeAccountId, Arc<RequestsTimeoutManager>>,
+        timeout_managers: HashMap<ExchangeAccountId, Vec<Arc<RequestsTimeoutManager>>>,
     ) -> Arc<Self> {
-        Arc::new(TimeoutManager {
-            inner: timeout_managers,
-        })
+        let inner = timeout_managers
+            .into_iter()
+            .map(|(exchange_account_id, limits)| {
+                let limits = ExchangeRequestsLimits {
+                    limits,
+                    reservation_lock: Mutex::new(()),
+                };
+                (exchange_account_id, limits)
+            })
+            .collect();
+
+        Arc::new(TimeoutManager { inner })
     }
 
+    /// Group is reserved in every limit by total weight of its applicable request types
     pub fn try_reserve_group(
         &self,
         exchange_account_id: &ExchangeAccountId,
-        requests_count: usize,
+        request_types: &[RequestType],
         group_type: String,
     ) -> Result<Option<RequestGroupId>> {
-        self.inner[exchange_account_id].try_reserve_group(group_type, now(), requests_count)
+        let exchange_limits = &self.inner[exchange_account_id];
+        let _reservation_guard = exchange_limits.reservation_lock.lock();
+
+        let group_weights = exchange_limits
+            .limits
+            .iter()
+            .map(|limit| {
+                let weight: usize = request_types
+                    .iter()
+                    .filter(|&&request_type| limit.is_applicable(request_type))
+                    .map(|&request_type| limit.get_request_weight(request_type))
+                    .sum();
+                (limit, weight)
+            })
+            .filter(|(_, weight)| *weight > 0)
+            .collect_vec();
+
+        let now = now();
+        for (limit, weight) in &group_weights {
+            if !limit.can_reserve_group(*weight, now)? {
+                return Ok(None);
+            }
+        }
+
+        let group_id = RequestGroupId::generate();
+        for (limit, weight) in group_weights {
+            if !limit.try_reserve_group_with_id(group_id, group_type.clone(), now, weight)? {
+                bail!(
+                    "Unable to reserve group {} on {} after successful check",
+                    group_id,
+                    exchange_account_id
+                );
+            }
+        }
+
+        Ok(Some(group_id))
     }
 
     pub fn remove_group(
@@ -51,7 +129,50 @@ impl TimeoutManager {
         exchange_account_id: &ExchangeAccountId,
         group_id: RequestGroupId,
     ) -> Result<bool> {
-        self.inner[exchange_account_id].remove_group(group_id, now())
+        let exchange_limits = &self.inner[exchange_account_id];
+        let _reservation_guard = exchange_limits.reservation_lock.lock();
+
+        let mut is_removed = false;
+        for limit in &exchange_limits.limits {
+            if limit.has_group(group_id) {
+                is_removed |= limit.remove_group(group_id, now())?;
+            }
+        }
+
+        Ok(is_removed)
+    }
+
+    /// Available requests count of the most loaded limit
+    pub fn get_available_requests_count(&self, exchange_account_id: &ExchangeAccountId) -> usize {
+        let now = now();
+        self.inner[exchange_account_id]
+            .limits
+            .iter()
+            .map(|limit| limit.get_available_requests_count(now))
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Usage is applied to limits with the same period which apply to the request type of response
+    pub fn resync(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+        request_type: RequestType,
+        usages: &[RequestsUsage],
+    ) -> Result<()> {
+        let exchange_limits = &self.inner[exchange_account_id];
+        let _reservation_guard = exchange_limits.reservation_lock.lock();
+
+        let now = now();
+        for usage in usages {
+            for limit in exchange_limits.applicable(request_type) {
+                if limit.period_duration() == usage.period {
+                    limit.resync_used_weight(request_type, usage.used_weight, now)?;
+                }
+            }
+        }
+
+        Ok(())
     }
 
     pub fn try_reserve_instant(
@@ -59,7 +180,7 @@ impl TimeoutManager {
         exchange_account_id: &ExchangeAccountId,
         request_type: RequestType,
     ) -> Result<bool> {
-        self.inner[exchange_account_id].try_reserve_instant(request_type, now(), None)
+        self.try_reserve_group_instant(exchange_account_id, request_type, None)
     }
 
     pub fn try_reserve_group_instant(
@@ -68,13 +189,51 @@ impl TimeoutManager {
         request_type: RequestType,
         pre_reserved_group_id: Option<RequestGroupId>,
     ) -> Result<bool> {
-        self.inner[exchange_account_id].try_reserve_instant(
+        let exchange_limits = &self.inner[exchange_account_id];
+        let _reservation_guard = exchange_limits.reservation_lock.lock();
+
+        Self::try_reserve_instant_in_limits(
+            exchange_limits,
             request_type,
-            now(),
             pre_reserved_group_id,
+            now(),
         )
     }
 
+    fn try_reserve_instant_in_limits(
+        exchange_limits: &ExchangeRequestsLimits,
+        request_type: RequestType,
+        pre_reserved_group_id: Option<RequestGroupId>,
+        now: DateTime,
+    ) -> Result<bool> {
+        // Group isn't reserved in limits which don't apply to its requests
+        let limits_with_group = exchange_limits
+            .applicable(request_type)
+            .into_iter()
+            .map(|limit| {
+                let group_id = pre_reserved_group_id.filter(|&group_id| limit.has_group(group_id));
+                (limit, group_id)
+            })
+            .collect_vec();
+
+        for (limit, group_id) in &limits_with_group {
+            if !limit.can_reserve_instant(request_type, now, *group_id)? {
+                return Ok(false);
+            }
+        }
+
+        for (limit, group_id) in limits_with_group {
+            if !limit.try_reserve_instant(request_type, now, group_id)? {
+                bail!(
+                    "Unable to reserve request {:?} after successful check",
+                    request_type
+                );
+            }
+        }
+
+        Ok(true)
+    }
+
     pub fn reserve_when_available(
         &self,
         exchange_account_id: &ExchangeAccountId,
@@ -82,43 +241,258 @@ impl TimeoutManager {
         pre_reservation_group_id: Option<RequestGroupId>,
         cancellation_token: CancellationToken,
     ) -> Result<impl Future<Output = FutureOutcome> + Send + Sync> {
-        let inner = (&self.inner[exchange_account_id]).clone();
-
-        const ERROR_MSG: &str = "Failed waiting in method TimeoutManager::reserve_when_available";
-        let convert = |handle: JoinHandle<FutureOutcome>| {
-            handle.map(|res| match res {
-                Ok(future_outcome) => future_outcome,
-                // Only panic can happen here and only in case if spawn_future() panicked itself
-                Err(error) => {
-                    error!("Future in reserve_when_available got error: {}", error);
-                    FutureOutcome::new(
-                        "spawn_future() for reserve_when_available".to_owned(),
-                        Uuid::new_v4(),
-                        CompletionReason::Panicked,
-                    )
-                }
-            })
+        let exchange_limits = &self.inner[exchange_account_id];
+        let _reservation_guard = exchange_limits.reservation_lock.lock();
+
+        let completed_instantly = |name: &str| {
+            Either::Right(ready(FutureOutcome::new(
+                name.to_owned(),
+                Uuid::new_v4(),
+                CompletionReason::CompletedSuccessfully,
+            )))
         };
 
         let now = now();
-        if pre_reservation_group_id.is_none() {
-            let result = inner.reserve_when_available(request_type, now, cancellation_token)?;
-            return Ok(Either::Left(convert(result.0)));
+        if pre_reservation_group_id.is_some()
+            && Self::try_reserve_instant_in_limits(
+                exchange_limits,
+                request_type,
+                pre_reservation_group_id,
+                now,
+            )?
+        {
+            return Ok(completed_instantly(
+                "spawn_future() for try_reserve_instant",
+            ));
         }
 
-        if inner.try_reserve_instant(request_type, now, pre_reservation_group_id)? {
-            return Ok(Either::Right(ready(FutureOutcome::new(
-                "spawn_future() for try_reserve_instant".to_owned(),
-                Uuid::new_v4(),
-                CompletionReason::CompletedSuccessfully,
-            ))));
+        let limits = exchange_limits.applicable(request_type);
+        if limits.is_empty() {
+            return Ok(completed_instantly(
+                "reserve_when_available() without limits",
+            ));
+        }
+
+        // Request waits for the slowest limit
+        let mut request_start_time = now;
+        for limit in &limits {
+            request_start_time =
+                request_start_time.max(limit.get_request_start_time(request_type, now)?);
         }
 
-        let result = inner.reserve_when_available(request_type, now, cancellation_token)?;
-        Ok(Either::Left(convert(result.0)))
+        let mut waiting_futures = Vec::with_capacity(limits.len());
+        for limit in limits {
+            let (handle, _, _) = limit.clone().reserve_when_available_since(
+                request_type,
+                now,
+                request_start_time,
+                cancellation_token.clone(),
+            )?;
+            waiting_futures.push(Self::convert_join_handle(handle));
+        }
+
+        let waiting = join_all(waiting_futures).map(|outcomes| {
+            let first_failed = outcomes
+                .iter()
+                .position(|outcome| outcome.into_result().is_err())
+                .unwrap_or(0);
+            outcomes
+                .into_iter()
+                .nth(first_failed)
+                .expect("There is at least one outcome because limits aren't empty")
+        });
+
+        Ok(Either::Left(waiting))
+    }
+
+    fn convert_join_handle(
+        handle: JoinHandle<FutureOutcome>,
+    ) -> impl Future<Output = FutureOutcome> + Send + Sync {
+        handle.map(|res| match res {
+            Ok(future_outcome) => future_outcome,
+            // Only panic can happen here and only in case if spawn_future() panicked itself
+            Err(error) => {
+                error!("Future in reserve_when_available got error: {}", error);
+                FutureOutcome::new(
+                    "spawn_future() for reserve_when_available".to_owned(),
+                    Uuid::new_v4(),
+                    CompletionReason::Panicked,
+                )
+            }
+        })
     }
 }
 
 pub fn now() -> DateTime {
     Utc::now()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exchanges::timeouts::requests_timeout_manager_factory::{
+        RequestTimeoutArguments, RequestsTimeoutManagerFactory,
+    };
+
+    fn exchange_account_id() -> ExchangeAccountId {
+        ExchangeAccountId::new("test_exchange_account_id".into(), 0)
+    }
+
+    fn timeout_manager(
+        timeout_arguments: Vec<RequestTimeoutArguments>,
+    ) -> (Arc<TimeoutManager>, Vec<Arc<RequestsTimeoutManager>>) {
+        let limits = RequestsTimeoutManagerFactory::from_timeout_arguments(
+            timeout_arguments,
+            exchange_account_id(),
+        );
+        let timeout_manager = TimeoutManager::new(crate::hashmap![
+            exchange_account_id() => limits.clone()
+        ]);
+
+        (timeout_manager, limits)
+    }
+
+    #[test]
+    fn reserve_instant_only_when_all_limits_have_room() -> Result<()> {
+        let (timeout_manager, limits) = timeout_manager(vec![
+            RequestTimeoutArguments::from_requests_per_minute(5),
+            RequestTimeoutArguments::from_requests_per_minute(2)
+                .for_request_types(&[RequestType::CreateOrder]),
+        ]);
+        let exchange_account_id = exchange_account_id();
+
+        assert!(
+            timeout_manager.try_reserve_instant(&exchange_account_id, RequestType::CreateOrder)?
+        );
+        assert!(
+            timeout_manager.try_reserve_instant(&exchange_account_id, RequestType::CreateOrder)?
+        );
+        assert!(
+            !timeout_manager.try_reserve_instant(&exchange_account_id, RequestType::CreateOrder)?
+        );
+        assert!(
+            timeout_manager.try_reserve_instant(&exchange_account_id, RequestType::CancelOrder)?
+        );
+
+        // Failed reservation doesn't take request from limits with room
+        let now = now();
+        assert_eq!(limits[0].get_available_requests_count(now), 2);
+        assert_eq!(limits[1].get_available_requests_count(now), 0);
+        assert_eq!(
+            timeout_manager.get_available_requests_count(&exchange_account_id),
+            0
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_reserved_only_in_applicable_limits() -> Result<()> {
+        let (timeout_manager, limits) = timeout_manager(vec![
+            RequestTimeoutArguments::from_requests_per_minute(5),
+            RequestTimeoutArguments::from_requests_per_minute(2)
+                .for_request_types(&[RequestType::CreateOrder]),
+        ]);
+        let exchange_account_id = exchange_account_id();
+
+        let group_id = timeout_manager
+            .try_reserve_group(
+                &exchange_account_id,
+                &[RequestType::CancelOrder, RequestType::CancelOrder],
+                "GroupType".to_owned(),
+            )?
+            .expect("in test");
+
+        assert!(limits[0].has_group(group_id));
+        assert!(!limits[1].has_group(group_id));
+        assert_eq!(limits[0].get_available_requests_count(now()), 3);
+
+        assert!(timeout_manager.try_reserve_group_instant(
+            &exchange_account_id,
+            RequestType::CancelOrder,
+            Some(group_id)
+        )?);
+        assert!(timeout_manager.remove_group(&exchange_account_id, group_id)?);
+        assert!(!limits[0].has_group(group_id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn resync_applies_server_usage_to_limits_with_same_period() -> Result<()> {
+        let (timeout_manager, limits) = timeout_manager(vec![
+            RequestTimeoutArguments::from_requests_per_minute(100),
+            RequestTimeoutArguments::new(10, Duration::seconds(10))
+                .for_request_types(&[RequestType::CreateOrder]),
+        ]);
+        let exchange_account_id = exchange_account_id();
+        assert!(
+            timeout_manager.try_reserve_instant(&exchange_account_id, RequestType::CreateOrder)?
+        );
+
+        timeout_manager.resync(
+            &exchange_account_id,
+            RequestType::CancelOrder,
+            &[
+                RequestsUsage::new(Duration::minutes(1), 40),
+                RequestsUsage::new(Duration::seconds(10), 5),
+            ],
+        )?;
+
+        // Order limit isn't applicable to CancelOrder response
+        assert_eq!(limits[0].get_available_requests_count(now()), 60);
+        assert_eq!(limits[1].get_available_requests_count(now()), 9);
+
+        // Server usage lower than local one doesn't free requests
+        timeout_manager.resync(
+            &exchange_account_id,
+            RequestType::CreateOrder,
+            &[
+                RequestsUsage::new(Duration::minutes(1), 10),
+                RequestsUsage::new(Duration::seconds(10), 5),
+            ],
+        )?;
+        assert_eq!(limits[0].get_available_requests_count(now()), 60);
+        assert_eq!(limits[1].get_available_requests_count(now()), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reserve_when_available_waits_for_the_slowest_limit() -> Result<()> {
+        let period = Duration::milliseconds(300);
+        let (timeout_manager, _) = timeout_manager(vec![
+            RequestTimeoutArguments::new(100, period),
+            RequestTimeoutArguments::new(1, period).for_request_types(&[RequestType::CreateOrder]),
+        ]);
+        let exchange_account_id = exchange_account_id();
+        assert!(
+            timeout_manager.try_reserve_instant(&exchange_account_id, RequestType::CreateOrder)?
+        );
+
+        let started = now();
+        timeout_manager
+            .reserve_when_available(
+                &exchange_account_id,
+                RequestType::CancelOrder,
+                None,
+                CancellationToken::default(),
+            )?
+            .await
+            .into_result()?;
+        assert!(now() - started < period);
+
+        timeout_manager
+            .reserve_when_available(
+                &exchange_account_id,
+                RequestType::CreateOrder,
+                None,
+                CancellationToken::default(),
+            )?
+            .await
+            .into_result()?;
+        assert!(now() - started >= period);
+
+        Ok(())
+    }
+}
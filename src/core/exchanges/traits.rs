@@ -1,9 +1,11 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use log::info;
+use rust_decimal::Decimal;
 use tokio::sync::broadcast;
 
 use super::{
@@ -15,9 +17,11 @@ use super::{
     general::currency_pair_metadata::CurrencyPairMetadata,
     general::handlers::handle_order_filled::FillEventData,
     timeouts::requests_timeout_manager_factory::RequestTimeoutArguments,
+    timeouts::timeout_manager::RequestsUsage,
 };
 use crate::core::connectivity::connectivity_manager::WebSocketRole;
-use crate::core::exchanges::events::ExchangeEvent;
+use crate::core::connectivity::network_connector::NetworkConnector;
+use crate::core::exchanges::events::{DerivativePosition, ExchangeEvent};
 use crate::core::exchanges::general::features::ExchangeFeatures;
 use crate::core::lifecycle::application_manager::ApplicationManager;
 use crate::core::orders::fill::EventSourceType;
@@ -25,6 +29,7 @@ use crate::core::orders::order::{
     ClientOrderId, ExchangeOrderId, OrderCancelling, OrderCreating, OrderInfo,
 };
 use crate::core::settings::ExchangeSettings;
+use crate::core::DateTime;
 use crate::core::{exchanges::general::exchange::BoxExchangeClient, orders::pool::OrderRef};
 use awc::http::Uri;
 
@@ -47,6 +52,26 @@ pub trait ExchangeClient: Support {
     ) -> Result<RestRequestOutcome>;
 
     async fn request_order_info(&self, order: &OrderRef) -> Result<RestRequestOutcome>;
+
+    async fn request_server_time(&self) -> Result<RestRequestOutcome>;
+
+    /// Only exchanges with derivatives support leverage and positions
+    async fn request_set_leverage(
+        &self,
+        _currency_pair: CurrencyPair,
+        _leverage: Decimal,
+    ) -> Result<RestRequestOutcome> {
+        Err(anyhow!("Leverage isn't supported by exchange"))
+    }
+
+    async fn request_active_positions(&self) -> Result<RestRequestOutcome> {
+        Err(anyhow!("Positions aren't supported by exchange"))
+    }
+
+    /// None if exchange doesn't have user data stream which expires without keepalive requests
+    async fn request_keepalive_user_data_stream(&self) -> Result<Option<RestRequestOutcome>> {
+        Ok(None)
+    }
 }
 
 #[async_trait]
@@ -72,12 +97,39 @@ pub trait Support: Send + Sync {
         callback: Box<dyn FnMut(FillEventData) + Send + Sync>,
     );
 
+    /// Callback is called when exchange reports changes of derivative positions
+    fn set_positions_changed_callback(&self, _callback: Box<dyn FnMut() + Send + Sync>) {}
+
+    /// Callback receives delay between message sending by exchange and its receiving
+    fn set_websocket_message_lag_callback(&self, callback: Box<dyn FnMut(Duration) + Send + Sync>);
+
     fn set_traded_specific_currencies(&self, currencies: Vec<SpecificCurrencyPair>);
 
+    /// Offset is added to local time in signed requests
+    fn set_server_time_offset(&self, offset: chrono::Duration);
+
     fn is_websocket_enabled(&self, role: WebSocketRole) -> bool;
 
     async fn create_ws_url(&self, role: WebSocketRole) -> Result<Uri>;
 
+    /// Messages sent right after websocket connection for exchanges which subscribe to channels by messages
+    async fn get_websocket_subscriptions(&self, _role: WebSocketRole) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Messages for subscription of connected main websocket to market data of added currency pairs
+    fn get_currency_pairs_subscriptions(
+        &self,
+        _currency_pairs: &[SpecificCurrencyPair],
+    ) -> Result<Vec<String>> {
+        bail!(
+            "Subscription of connected websocket to new currency pairs isn't supported by exchange"
+        )
+    }
+
+    /// Connection settings shared by REST client and websockets
+    fn get_network_connector(&self) -> &NetworkConnector;
+
     fn get_specific_currency_pair(&self, currency_pair: &CurrencyPair) -> SpecificCurrencyPair;
 
     fn get_supported_currencies(&self) -> &DashMap<CurrencyId, CurrencyCode>;
@@ -90,10 +142,23 @@ pub trait Support: Send + Sync {
 
     fn parse_open_orders(&self, response: &RestRequestOutcome) -> Result<Vec<OrderInfo>>;
     fn parse_order_info(&self, response: &RestRequestOutcome) -> Result<OrderInfo>;
+    fn parse_server_time(&self, response: &RestRequestOutcome) -> Result<DateTime>;
     fn parse_metadata(
         &self,
         response: &RestRequestOutcome,
     ) -> Result<Vec<Arc<CurrencyPairMetadata>>>;
+
+    fn parse_active_positions(
+        &self,
+        _response: &RestRequestOutcome,
+    ) -> Result<Vec<DerivativePosition>> {
+        bail!("Positions aren't supported by exchange")
+    }
+
+    /// Requests usage reported by exchange in response headers
+    fn get_requests_usages(&self, _response: &RestRequestOutcome) -> Vec<RequestsUsage> {
+        Vec::new()
+    }
 }
 
 pub struct ExchangeClientBuilderResult {
@@ -111,5 +176,7 @@ pub trait ExchangeClientBuilder {
 
     fn extend_settings(&self, settings: &mut ExchangeSettings);
 
-    fn get_timeout_argments(&self) -> RequestTimeoutArguments;
+    /// Each arguments are separate limit, request is reserved only if all applicable limits have room.
+    /// Limits can differ by market type of exchange account
+    fn get_timeout_argments(&self, settings: &ExchangeSettings) -> Vec<RequestTimeoutArguments>;
 }
@@ -39,7 +39,7 @@ impl Explanation {
         }
     }
 
-    fn reasons(self) -> Vec<String> {
+    pub(crate) fn reasons(self) -> Vec<String> {
         self.reasons
     }
 }
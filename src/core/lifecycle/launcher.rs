@@ -8,20 +8,29 @@ use crate::core::exchanges::traits::ExchangeClientBuilder;
 use crate::core::internal_events_loop::InternalEventsLoop;
 use crate::core::lifecycle::application_manager::ApplicationManager;
 use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::lifecycle::settings_updater::{SettingsUpdateRequest, SettingsUpdater};
 use crate::core::lifecycle::trading_engine::{EngineContext, TradingEngine};
-use crate::core::logger::init_logger;
+use crate::core::logger::init_logger_with_settings;
 use crate::core::order_book::local_snapshot_service::LocalSnapshotsService;
+use crate::core::position_service::PositionService;
 use crate::core::settings::{AppSettings, BaseStrategySettings, CoreSettings};
-use crate::core::{config::load_settings, statistic_service::StatisticEventHandler};
+use crate::core::{
+    config::load_combined_settings,
+    config_validation::{ensure_valid, validate_settings},
+    statistic_service::StatisticEventHandler,
+};
 use crate::core::{
     disposition_execution::executor::DispositionExecutorService,
     infrastructure::{keep_application_manager, spawn_future},
 };
 use crate::core::{
-    exchanges::binance::binance::BinanceBuilder, statistic_service::StatisticService,
+    exchanges::binance::binance::BinanceBuilder, exchanges::kraken::kraken::KrakenBuilder,
+    statistic_service::StatisticService,
 };
 use crate::hashmap;
 use crate::rest_api::control_panel::ControlPanel;
+use crate::rest_api::event_feed::EventFeed;
+use crate::rest_api::metrics::EventsChannelMetrics;
 use crate::strategies::disposition_strategy::DispositionStrategy;
 use anyhow::Result;
 use core::fmt::Debug;
@@ -32,7 +41,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::identity;
 use std::sync::Arc;
-use tokio::sync::{broadcast, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 pub struct EngineBuildConfig {
     pub supported_exchange_clients: HashMap<ExchangeId, Box<dyn ExchangeClientBuilder + 'static>>,
@@ -40,14 +49,19 @@ pub struct EngineBuildConfig {
 
 impl EngineBuildConfig {
     pub fn standard() -> Self {
-        let exchange_name = "Binance".into();
-        let supported_exchange_clients =
-            hashmap![exchange_name => Box::new(BinanceBuilder) as Box<dyn ExchangeClientBuilder>];
+        let supported_exchange_clients = hashmap![
+            "Binance".into() => Box::new(BinanceBuilder) as Box<dyn ExchangeClientBuilder>,
+            "Kraken".into() => Box::new(KrakenBuilder) as Box<dyn ExchangeClientBuilder>
+        ];
 
         EngineBuildConfig {
             supported_exchange_clients,
         }
     }
+
+    pub fn supported_exchange_ids(&self) -> Vec<ExchangeId> {
+        self.supported_exchange_clients.keys().cloned().collect()
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -65,20 +79,38 @@ pub async fn launch_trading_engine<'a, TStrategySettings>(
     build_strategy: impl Fn(&AppSettings<TStrategySettings>) -> Box<dyn DispositionStrategy + 'static>,
 ) -> Result<TradingEngine>
 where
-    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a> + Serialize,
+    TStrategySettings:
+        BaseStrategySettings + Clone + Debug + Deserialize<'a> + Serialize + Send + Sync + 'static,
 {
-    init_logger();
-
-    info!("*****************************");
-    info!("TradingEngine starting");
-
+    let supported_exchanges = build_settings.supported_exchange_ids();
     let settings = match init_user_settings {
-        InitSettings::Directly(v) => v,
+        InitSettings::Directly(v) => {
+            let serialized_settings = toml::Value::try_from(v.clone())?;
+            ensure_valid(validate_settings::<TStrategySettings>(
+                &serialized_settings,
+                &supported_exchanges,
+            ))?;
+            v
+        }
         InitSettings::Load(config_path, credentials_path) => {
-            load_settings::<TStrategySettings>(&config_path, &credentials_path)?
+            let settings = load_combined_settings(&config_path, &credentials_path)?;
+            ensure_valid(validate_settings::<TStrategySettings>(
+                &settings,
+                &supported_exchanges,
+            ))?;
+            settings.try_into()?
         }
     };
 
+    init_logger_with_settings(&settings.core.logging);
+
+    info!("*****************************");
+    info!("TradingEngine starting");
+
+    if settings.core.is_dry_run() {
+        info!("Dry run mode: orders won't be sent to exchanges");
+    }
+
     let application_manager = ApplicationManager::new(CancellationToken::new());
     keep_application_manager(application_manager.clone());
     let (events_sender, events_receiver) = broadcast::channel(CHANNEL_MAX_EVENTS_COUNT);
@@ -100,6 +132,9 @@ where
 
     let exchange_events = ExchangeEvents::new(events_sender.clone());
 
+    let position_service = PositionService::new(exchanges_map.clone());
+    position_service.clone().start(events_sender.subscribe());
+
     let (finish_graceful_shutdown_tx, finish_graceful_shutdown_rx) = oneshot::channel();
     let engine_context = EngineContext::new(
         settings.core.clone(),
@@ -108,6 +143,7 @@ where
         finish_graceful_shutdown_tx,
         timeout_manager,
         application_manager.clone(),
+        position_service.clone(),
     );
 
     let internal_events_loop = InternalEventsLoop::new();
@@ -116,11 +152,37 @@ where
     let statistic_service = StatisticService::new();
     let statistic_event_handler =
         create_statistic_event_handler(exchange_events, statistic_service.clone());
+    for exchange in exchanges_map.iter() {
+        let lag_statistic_service = statistic_service.clone();
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        exchange.set_websocket_message_lag_callback(Box::new(move |lag| {
+            lag_statistic_service.register_websocket_message_lag(&exchange_account_id, lag)
+        }));
+        let latency_statistic_service = statistic_service.clone();
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        exchange.set_rest_request_latency_callback(Box::new(move |latency| {
+            latency_statistic_service.register_rest_request_latency(&exchange_account_id, latency)
+        }));
+        exchange.set_exchange_blocker(engine_context.exchange_blocker.clone());
+    }
+    let event_feed = EventFeed::new();
+    event_feed
+        .clone()
+        .start(events_sender.subscribe(), &engine_context.exchange_blocker);
+    let events_channel_metrics = EventsChannelMetrics::new();
+    events_channel_metrics
+        .clone()
+        .start(events_sender.subscribe());
+    let (settings_updater, settings_updates_receiver) =
+        SettingsUpdater::new(&settings, supported_exchanges)?;
     let control_panel = ControlPanel::new(
-        "127.0.0.1:8080",
-        toml::Value::try_from(settings.clone())?.to_string(),
-        application_manager,
+        settings.core.control_panel.clone(),
+        Arc::new(settings_updater),
         statistic_service.clone(),
+        position_service,
+        engine_context.clone(),
+        event_feed,
+        events_channel_metrics,
     );
 
     {
@@ -142,6 +204,7 @@ where
         &settings.strategy,
         &engine_context,
         disposition_strategy,
+        settings_updates_receiver,
         &statistic_event_handler.stats,
     );
 
@@ -162,6 +225,7 @@ fn create_disposition_executor_service(
     base_settings: &dyn BaseStrategySettings,
     engine_context: &Arc<EngineContext>,
     disposition_strategy: Box<dyn DispositionStrategy>,
+    settings_updates_receiver: mpsc::Receiver<SettingsUpdateRequest>,
     statistics: &Arc<StatisticService>,
 ) -> Arc<DispositionExecutorService> {
     DispositionExecutorService::new(
@@ -172,6 +236,7 @@ fn create_disposition_executor_service(
         base_settings.currency_pair(),
         base_settings.max_amount(),
         disposition_strategy,
+        settings_updates_receiver,
         engine_context.application_manager.stop_token(),
         statistics.clone(),
     )
@@ -1,5 +1,6 @@
 pub mod application_manager;
 pub mod cancellation_token;
 pub mod launcher;
+pub mod settings_updater;
 pub mod shutdown;
 pub mod trading_engine;
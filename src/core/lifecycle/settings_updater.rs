@@ -0,0 +1,480 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+
+use anyhow::{anyhow, bail, Context, Result};
+use parking_lot::RwLock;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::core::config::{is_secret_field, redact_settings};
+use crate::core::config_validation::{validate_serialized_settings, ValidationError};
+use crate::core::exchanges::common::Amount;
+use crate::core::exchanges::common::{ExchangeAccountId, ExchangeId};
+use crate::core::secret::Secret;
+use crate::core::settings::{AppSettings, BaseStrategySettings, CurrencyPairSetting};
+
+const SETTINGS_UPDATES_CHANNEL_CAPACITY: usize = 10;
+
+/// Part of settings that can be applied to running DispositionExecutor without engine restart
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategySettingsUpdate {
+    pub max_amount: Amount,
+    /// Raw `strategy` table from config for passing to `DispositionStrategy::update_settings`
+    pub strategy_settings: toml::Value,
+    /// Currency pairs appended to `currency_pairs` of exchanges, they are added to running exchanges
+    pub added_currency_pairs: HashMap<ExchangeAccountId, Vec<CurrencyPairSetting>>,
+}
+
+pub struct SettingsUpdateRequest {
+    pub(crate) update: StrategySettingsUpdate,
+    pub(crate) result_sender: oneshot::Sender<Result<()>>,
+}
+
+/// Arguments are current and new serialized settings
+type ValidateSettingsFn = Box<dyn Fn(&str, &str) -> Result<StrategySettingsUpdate> + Send + Sync>;
+type ValidateConfigFn = Box<dyn Fn(&str) -> Vec<ValidationError> + Send + Sync>;
+
+/// Applies new settings to running engine. Settings are validated completely before
+/// anything is applied, so invalid settings never reach the strategy.
+pub struct SettingsUpdater {
+    /// Serialized settings contain credentials
+    current_settings: RwLock<Secret>,
+    validate_settings: ValidateSettingsFn,
+    validate_config: ValidateConfigFn,
+    updates_sender: mpsc::Sender<SettingsUpdateRequest>,
+}
+
+impl SettingsUpdater {
+    pub(crate) fn new<'a, TStrategySettings>(
+        current_settings: &AppSettings<TStrategySettings>,
+        supported_exchanges: Vec<ExchangeId>,
+    ) -> Result<(Self, mpsc::Receiver<SettingsUpdateRequest>)>
+    where
+        TStrategySettings: BaseStrategySettings
+            + Clone
+            + Debug
+            + Deserialize<'a>
+            + Serialize
+            + Send
+            + Sync
+            + 'static,
+    {
+        let serialized_settings = toml::Value::try_from(current_settings.clone())?.to_string();
+        let (updates_sender, updates_receiver) = mpsc::channel(SETTINGS_UPDATES_CHANNEL_CAPACITY);
+
+        let validate_settings = Box::new(|current_settings: &str, new_settings: &str| {
+            let current_settings: AppSettings<TStrategySettings> =
+                toml::from_str::<toml::Value>(current_settings)?.try_into()?;
+            validate_settings_update(&current_settings, new_settings)
+        });
+
+        let validate_config = Box::new(move |settings: &str| {
+            validate_serialized_settings::<TStrategySettings>(settings, &supported_exchanges)
+        });
+
+        let settings_updater = SettingsUpdater {
+            current_settings: RwLock::new(serialized_settings.into()),
+            validate_settings,
+            validate_config,
+            updates_sender,
+        };
+
+        Ok((settings_updater, updates_receiver))
+    }
+
+    /// Current settings with hidden credentials
+    pub fn redacted_settings(&self) -> Result<String> {
+        redact_settings(self.current_settings.read().expose())
+    }
+
+    /// Check complete settings with credentials, environment variables and secret files applied,
+    /// i.e. settings that will be used after engine restart
+    pub fn validate_config(&self, settings: &str) -> Vec<ValidationError> {
+        (self.validate_config)(settings)
+    }
+
+    /// Validate new settings and apply them to running strategy.
+    /// Returns error without changing anything if settings are invalid or strategy rejected them
+    pub async fn update_settings(&self, new_settings: &str) -> Result<()> {
+        let current_settings = self.current_settings.read().expose().to_owned();
+        let update = (self.validate_settings)(&current_settings, new_settings)
+            .context("Unable to validate settings update")?;
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.updates_sender
+            .send(SettingsUpdateRequest {
+                update,
+                result_sender,
+            })
+            .await
+            .map_err(|_| {
+                anyhow!("Unable to send settings update: DispositionExecutor is stopped")
+            })?;
+
+        result_receiver
+            .await
+            .context("DispositionExecutor dropped settings update without result")??;
+
+        *self.current_settings.write() = new_settings.into();
+
+        Ok(())
+    }
+}
+
+fn validate_settings_update<'a, TStrategySettings>(
+    current_settings: &AppSettings<TStrategySettings>,
+    new_settings: &str,
+) -> Result<StrategySettingsUpdate>
+where
+    TStrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a>,
+{
+    let new_settings: toml::Value =
+        toml::from_str(new_settings).context("Unable to parse new settings as toml")?;
+
+    let strategy_settings = new_settings
+        .get("strategy")
+        .cloned()
+        .ok_or(anyhow!("Unable to get strategy table from new settings"))?;
+
+    let mut new_settings: AppSettings<TStrategySettings> = new_settings
+        .try_into()
+        .context("Unable to parse new settings")?;
+
+    let added_currency_pairs = take_added_currency_pairs(current_settings, &mut new_settings);
+
+    // Changed credentials don't prevent update: they are saved and used after engine restart
+    let mut changed_core_settings = Vec::new();
+    collect_changed_settings(
+        &toml::Value::try_from(&current_settings.core)?,
+        &toml::Value::try_from(&new_settings.core)?,
+        "core",
+        &mut changed_core_settings,
+    );
+    if !changed_core_settings.is_empty() {
+        bail!(
+            "Core settings can't be changed without engine restart. Use POST /config instead. Changed settings: {}",
+            changed_core_settings.join(", ")
+        )
+    }
+
+    let current_strategy = &current_settings.strategy;
+    let new_strategy = &new_settings.strategy;
+    if new_strategy.exchange_account_id() != current_strategy.exchange_account_id() {
+        bail!(
+            "Strategy exchange account id can't be changed without engine restart: {} -> {}",
+            current_strategy.exchange_account_id(),
+            new_strategy.exchange_account_id()
+        )
+    }
+
+    if new_strategy.currency_pair() != current_strategy.currency_pair() {
+        bail!(
+            "Strategy currency pair can't be changed without engine restart: {} -> {}",
+            current_strategy.currency_pair(),
+            new_strategy.currency_pair()
+        )
+    }
+
+    let max_amount = new_strategy.max_amount();
+    if max_amount <= dec!(0) {
+        bail!(
+            "Strategy max amount should be positive, but got {}",
+            max_amount
+        )
+    }
+
+    Ok(StrategySettingsUpdate {
+        max_amount,
+        strategy_settings,
+        added_currency_pairs,
+    })
+}
+
+/// Currency pairs can only be appended to running exchanges. Appended pairs are collected
+/// and reverted in new settings, so any other change of currency pairs is detected as core change
+fn take_added_currency_pairs<TStrategySettings>(
+    current_settings: &AppSettings<TStrategySettings>,
+    new_settings: &mut AppSettings<TStrategySettings>,
+) -> HashMap<ExchangeAccountId, Vec<CurrencyPairSetting>>
+where
+    TStrategySettings: BaseStrategySettings + Clone,
+{
+    let mut added_currency_pairs = HashMap::new();
+    for new_exchange in &mut new_settings.core.exchanges {
+        let current_exchange = match current_settings
+            .core
+            .exchanges
+            .iter()
+            .find(|x| x.exchange_account_id == new_exchange.exchange_account_id)
+        {
+            Some(current_exchange) => current_exchange,
+            None => continue,
+        };
+
+        let current_pairs = current_exchange.currency_pairs.clone().unwrap_or_default();
+        let new_pairs = new_exchange.currency_pairs.clone().unwrap_or_default();
+        if new_pairs.len() <= current_pairs.len()
+            || new_pairs[..current_pairs.len()] != current_pairs[..]
+        {
+            continue;
+        }
+
+        added_currency_pairs.insert(
+            new_exchange.exchange_account_id.clone(),
+            new_pairs[current_pairs.len()..].to_vec(),
+        );
+        new_exchange.currency_pairs = current_exchange.currency_pairs.clone();
+    }
+
+    added_currency_pairs
+}
+
+/// Paths of settings with different values except credentials, e.g. `core.exchanges[0].is_margin_trading`
+fn collect_changed_settings(
+    current: &toml::Value,
+    new: &toml::Value,
+    path: &str,
+    changed_settings: &mut Vec<String>,
+) {
+    match (current, new) {
+        (toml::Value::Table(current), toml::Value::Table(new)) => {
+            let keys: BTreeSet<_> = current.keys().chain(new.keys()).collect();
+            for key in keys.into_iter().filter(|key| !is_secret_field(key)) {
+                let key_path = format!("{}.{}", path, key);
+                match (current.get(key), new.get(key)) {
+                    (Some(current), Some(new)) => {
+                        collect_changed_settings(current, new, &key_path, changed_settings)
+                    }
+                    _ => changed_settings.push(key_path),
+                }
+            }
+        }
+        (toml::Value::Array(current), toml::Value::Array(new)) if current.len() == new.len() => {
+            for (index, (current, new)) in current.iter().zip(new).enumerate() {
+                let item_path = format!("{}[{}]", path, index);
+                collect_changed_settings(current, new, &item_path, changed_settings);
+            }
+        }
+        _ if current != new => changed_settings.push(path.to_owned()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exchanges::common::{CurrencyPair, ExchangeAccountId};
+    use crate::core::settings::{CoreSettings, CurrencyPairSetting, ExchangeSettings};
+
+    #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+    struct TestStrategySettings {
+        exchange_account_id: ExchangeAccountId,
+        currency_pair: CurrencyPair,
+        max_amount: Amount,
+        spread: Amount,
+    }
+
+    impl BaseStrategySettings for TestStrategySettings {
+        fn exchange_account_id(&self) -> ExchangeAccountId {
+            self.exchange_account_id.clone()
+        }
+
+        fn currency_pair(&self) -> CurrencyPair {
+            self.currency_pair.clone()
+        }
+
+        fn max_amount(&self) -> Amount {
+            self.max_amount
+        }
+    }
+
+    fn current_settings() -> AppSettings<TestStrategySettings> {
+        let exchange_account_id: ExchangeAccountId = "Binance0".parse().expect("in test");
+        AppSettings {
+            strategy: TestStrategySettings {
+                exchange_account_id: exchange_account_id.clone(),
+                currency_pair: CurrencyPair::from_codes("eos".into(), "btc".into()),
+                max_amount: dec!(1),
+                spread: dec!(0.1),
+            },
+            core: CoreSettings {
+                self_trade_prevention: Default::default(),
+                dry_run: false,
+                forced_dry_run: false,
+                logging: Default::default(),
+                exchanges: vec![ExchangeSettings::new_short(
+                    exchange_account_id,
+                    "api_key".into(),
+                    "secret_key".into(),
+                    false,
+                )],
+                risk_manager: Default::default(),
+                control_panel: Default::default(),
+            },
+        }
+    }
+
+    fn serialize(settings: &AppSettings<TestStrategySettings>) -> String {
+        toml::Value::try_from(settings.clone())
+            .expect("in test")
+            .to_string()
+    }
+
+    #[test]
+    fn strategy_settings_changed() {
+        let current_settings = current_settings();
+        let mut new_settings = current_settings.clone();
+        new_settings.strategy.max_amount = dec!(2);
+        new_settings.strategy.spread = dec!(0.2);
+
+        let update = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect("in test");
+
+        assert_eq!(update.max_amount, dec!(2));
+        assert_eq!(
+            update.strategy_settings["spread"].as_str(),
+            Some("0.2"),
+            "strategy table should be passed as is"
+        );
+    }
+
+    #[test]
+    fn forced_dry_run_is_not_saved() {
+        let mut current_settings = current_settings();
+        current_settings.core.forced_dry_run = true;
+        let mut new_settings = current_settings.clone();
+        new_settings.strategy.max_amount = dec!(2);
+
+        let serialized_settings = serialize(&new_settings);
+        assert!(!serialized_settings.contains("forced_dry_run"));
+
+        let update =
+            validate_settings_update(&current_settings, &serialized_settings).expect("in test");
+
+        assert_eq!(update.max_amount, dec!(2));
+    }
+
+    #[test]
+    fn core_settings_changed() {
+        let current_settings = current_settings();
+        let mut new_settings = current_settings.clone();
+        new_settings.core.exchanges[0].is_margin_trading = true;
+
+        let error = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect_err("in test");
+
+        assert!(error.to_string().contains("Core settings"));
+        assert!(
+            error
+                .to_string()
+                .ends_with("Changed settings: core.exchanges[0].is_margin_trading"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn credentials_changed() {
+        let current_settings = current_settings();
+        let mut new_settings = current_settings.clone();
+        new_settings.core.exchanges[0].api_key = "new_api_key".into();
+        new_settings.core.exchanges[0].secret_key = "new_secret_key".into();
+        new_settings.strategy.max_amount = dec!(2);
+
+        let update = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect("in test");
+
+        assert_eq!(update.max_amount, dec!(2));
+    }
+
+    fn currency_pair_setting(base: &str, quote: &str) -> CurrencyPairSetting {
+        CurrencyPairSetting {
+            base: base.into(),
+            quote: quote.into(),
+            currency_pair: None,
+        }
+    }
+
+    #[test]
+    fn currency_pairs_added() {
+        let mut current_settings = current_settings();
+        current_settings.core.exchanges[0].currency_pairs =
+            Some(vec![currency_pair_setting("eos", "btc")]);
+        let mut new_settings = current_settings.clone();
+        new_settings.core.exchanges[0].currency_pairs = Some(vec![
+            currency_pair_setting("eos", "btc"),
+            currency_pair_setting("eth", "btc"),
+        ]);
+
+        let update = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect("in test");
+
+        let exchange_account_id = current_settings.core.exchanges[0]
+            .exchange_account_id
+            .clone();
+        assert_eq!(
+            update.added_currency_pairs,
+            HashMap::from([(
+                exchange_account_id,
+                vec![currency_pair_setting("eth", "btc")]
+            )])
+        );
+    }
+
+    #[test]
+    fn currency_pairs_removed() {
+        let mut current_settings = current_settings();
+        current_settings.core.exchanges[0].currency_pairs = Some(vec![
+            currency_pair_setting("eos", "btc"),
+            currency_pair_setting("eth", "btc"),
+        ]);
+        let mut new_settings = current_settings.clone();
+        new_settings.core.exchanges[0].currency_pairs =
+            Some(vec![currency_pair_setting("eos", "btc")]);
+        new_settings.core.dry_run = true;
+
+        let error = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect_err("in test");
+
+        assert!(
+            error
+                .to_string()
+                .ends_with("Changed settings: core.dry_run, core.exchanges[0].currency_pairs"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn currency_pair_changed() {
+        let current_settings = current_settings();
+        let mut new_settings = current_settings.clone();
+        new_settings.strategy.currency_pair = CurrencyPair::from_codes("eth".into(), "btc".into());
+
+        let error = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect_err("in test");
+
+        assert!(error.to_string().contains("currency pair"));
+    }
+
+    #[test]
+    fn not_positive_max_amount() {
+        let current_settings = current_settings();
+        let mut new_settings = current_settings.clone();
+        new_settings.strategy.max_amount = dec!(0);
+
+        let error = validate_settings_update(&current_settings, &serialize(&new_settings))
+            .expect_err("in test");
+
+        assert!(error.to_string().contains("max amount"));
+    }
+
+    #[test]
+    fn invalid_toml() {
+        let error =
+            validate_settings_update(&current_settings(), "strategy = ").expect_err("in test");
+
+        assert!(error.to_string().contains("toml"));
+    }
+}
@@ -18,7 +18,10 @@ use crate::core::exchanges::exchange_blocker::ExchangeBlocker;
 use crate::core::exchanges::general::exchange::Exchange;
 use crate::core::exchanges::timeouts::timeout_manager::TimeoutManager;
 use crate::core::lifecycle::shutdown::ShutdownService;
-use crate::core::settings::CoreSettings;
+use crate::core::position_service::PositionService;
+use crate::core::risk_manager::RiskManager;
+use crate::core::self_trade_guard::SelfTradeGuard;
+use crate::core::settings::{CoreSettings, SelfTradePrevention};
 use crate::core::{
     infrastructure::unset_application_manager, lifecycle::application_manager::ApplicationManager,
     lifecycle::cancellation_token::CancellationToken,
@@ -38,6 +41,8 @@ pub struct EngineContext {
     pub exchange_blocker: Arc<ExchangeBlocker>,
     pub application_manager: Arc<ApplicationManager>,
     pub timeout_manager: Arc<TimeoutManager>,
+    pub risk_manager: Arc<RiskManager>,
+    pub self_trade_guard: SelfTradeGuard,
     is_graceful_shutdown_started: AtomicBool,
     exchange_events: ExchangeEvents,
     finish_graceful_shutdown_sender: Mutex<Option<oneshot::Sender<()>>>,
@@ -51,6 +56,7 @@ impl EngineContext {
         finish_graceful_shutdown_sender: oneshot::Sender<()>,
         timeout_manager: Arc<TimeoutManager>,
         application_manager: Arc<ApplicationManager>,
+        position_service: Arc<PositionService>,
     ) -> Arc<Self> {
         let exchange_account_ids = app_settings
             .exchanges
@@ -58,13 +64,32 @@ impl EngineContext {
             .map(|x| x.exchange_account_id.clone())
             .collect_vec();
 
+        let exchange_blocker = ExchangeBlocker::new(exchange_account_ids);
+        let risk_manager = RiskManager::new(
+            app_settings.risk_manager.clone(),
+            exchange_blocker.clone(),
+            position_service,
+            exchanges.clone(),
+            app_settings.is_dry_run(),
+        );
+        risk_manager.clone().start(application_manager.stop_token());
+
+        // Real resting orders mustn't be cancelled in dry run mode
+        let self_trade_prevention = match app_settings.is_dry_run() {
+            true => SelfTradePrevention::RejectNew,
+            false => app_settings.self_trade_prevention,
+        };
+        let self_trade_guard = SelfTradeGuard::new(self_trade_prevention);
+
         let engine_context = Arc::new(EngineContext {
             app_settings,
             exchanges,
             shutdown_service: Default::default(),
-            exchange_blocker: ExchangeBlocker::new(exchange_account_ids),
+            exchange_blocker,
             application_manager: application_manager.clone(),
             timeout_manager,
+            risk_manager,
+            self_trade_guard,
             is_graceful_shutdown_started: Default::default(),
             exchange_events,
             finish_graceful_shutdown_sender: Mutex::new(Some(finish_graceful_shutdown_sender)),
@@ -99,17 +124,20 @@ impl EngineContext {
         self.shutdown_service.graceful_shutdown().await;
         self.exchange_blocker.stop_blocker().await;
 
-        let cancellation_token = CancellationToken::default();
-        const TIMEOUT: Duration = Duration::from_secs(5);
-
-        tokio::select! {
-            _ = cancel_opened_orders(&self.exchanges, cancellation_token.clone(), true) => (),
-            _ = tokio::time::sleep(TIMEOUT) => {
-                cancellation_token.cancel();
-                log::error!(
-                    "Timeout {} secs is exceeded: cancel open orders has been stopped",
-                    TIMEOUT.as_secs(),
-                );
+        // Orders aren't sent in dry run, so all opened orders are created by someone else
+        if !self.app_settings.is_dry_run() {
+            let cancellation_token = CancellationToken::default();
+            const TIMEOUT: Duration = Duration::from_secs(5);
+
+            tokio::select! {
+                _ = cancel_opened_orders(&self.exchanges, cancellation_token.clone(), true) => (),
+                _ = tokio::time::sleep(TIMEOUT) => {
+                    cancellation_token.cancel();
+                    log::error!(
+                        "Timeout {} secs is exceeded: cancel open orders has been stopped",
+                        TIMEOUT.as_secs(),
+                    );
+                }
             }
         }
 
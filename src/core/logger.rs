@@ -1,44 +1,460 @@
-use chrono::Utc;
-use log::LevelFilter;
+use crate::core::exchanges::common::{CurrencyPair, ExchangeAccountId, TradePlaceAccount};
+use crate::core::orders::order::ClientOrderId;
+use crate::core::settings::{LogFileSettings, LogFormat, LogRotation, LoggingSettings};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{LevelFilter, Metadata, Record};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Arguments;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Once;
 
+static LOG_LEVELS: Lazy<RwLock<LogLevels>> =
+    Lazy::new(|| RwLock::new(LogLevels::new(&LoggingSettings::default())));
+
+thread_local! {
+    static CURRENT_LOG_FIELDS: RefCell<Option<LogFields>> = const { RefCell::new(None) };
+}
+
+/// Logger with default settings, e.g. for tests
 pub fn init_logger() {
+    init_logger_with_settings(&LoggingSettings::default());
+}
+
+/// Only first call sets up logger, levels can be changed later by `update_log_levels`
+pub fn init_logger_with_settings(settings: &LoggingSettings) {
     static INIT_LOGGER: Once = Once::new();
 
     INIT_LOGGER.call_once(|| {
-        let _ = fern::Dispatch::new()
-            .format(|out, message, record| {
-                out.finish(format_args!(
-                    "[{}][{}][{}] {}",
-                    Utc::now().format("%Y-%m-%d %H:%M:%S,%3f"),
-                    record.level(),
-                    record.target(),
-                    message
-                ))
+        *LOG_LEVELS.write() = LogLevels::new(settings);
+
+        let format = settings.format;
+        let mut dispatch = fern::Dispatch::new()
+            .format(move |out, message, record| {
+                out.finish(format_args!("{}", format_record(format, message, record)))
             })
             .chain(
                 fern::Dispatch::new()
-                    .level(LevelFilter::Warn)
-                    .level_for("mmb", LevelFilter::Warn)
-                    .level_for("mmb_lib", LevelFilter::Warn)
+                    .filter(|metadata| LOG_LEVELS.read().console_enabled(metadata))
                     .chain(std::io::stdout()),
-            )
-            .chain(
+            );
+
+        if settings.file.level != LevelFilter::Off {
+            let file = RotatingFile::open(&settings.file).expect("Unable to open log file");
+            dispatch = dispatch.chain(
                 fern::Dispatch::new()
-                    .level(LevelFilter::Trace)
-                    .level_for("actix_tls", LevelFilter::Warn)
-                    .level_for("rustls", LevelFilter::Warn)
-                    .level_for("actix_codec", LevelFilter::Warn)
-                    .chain(
-                        std::fs::OpenOptions::new()
-                            .write(true)
-                            .create(true)
-                            .truncate(true)
-                            .open("log.txt")
-                            .expect("Unable to open log file"),
-                    ),
-            )
-            .apply()
-            .expect("Unable to set up logger");
+                    .filter(|metadata| LOG_LEVELS.read().file_enabled(metadata))
+                    .chain(fern::Output::writer(Box::new(file), "\n")),
+            );
+        }
+
+        dispatch.apply().expect("Unable to set up logger");
     })
 }
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LogLevels {
+    pub console_level: LevelFilter,
+    pub file_level: LevelFilter,
+    pub modules: HashMap<String, LevelFilter>,
+}
+
+impl LogLevels {
+    fn new(settings: &LoggingSettings) -> Self {
+        LogLevels {
+            console_level: settings.console_level,
+            file_level: settings.file.level,
+            modules: settings.modules.clone(),
+        }
+    }
+
+    fn console_enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target(), self.console_level)
+    }
+
+    fn file_enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target(), self.file_level)
+    }
+
+    /// Level of the most specific module matched by target or output level
+    fn level_for(&self, target: &str, output_level: LevelFilter) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || (target.starts_with(module.as_str())
+                        && target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(output_level)
+    }
+
+    fn apply(&mut self, update: LogLevelsUpdate) {
+        if let Some(console_level) = update.console_level {
+            self.console_level = console_level;
+        }
+        if let Some(file_level) = update.file_level {
+            self.file_level = file_level;
+        }
+        for (module, level) in update.modules {
+            match level {
+                Some(level) => self.modules.insert(module, level),
+                None => self.modules.remove(&module),
+            };
+        }
+    }
+}
+
+/// Change of log levels at runtime. Module with `None` level is removed
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct LogLevelsUpdate {
+    pub console_level: Option<LevelFilter>,
+    /// Has no effect if file output is disabled in settings
+    pub file_level: Option<LevelFilter>,
+    #[serde(default)]
+    pub modules: HashMap<String, Option<LevelFilter>>,
+}
+
+pub fn get_log_levels() -> LogLevels {
+    LOG_LEVELS.read().clone()
+}
+
+/// Changed levels aren't saved to config
+pub fn update_log_levels(update: LogLevelsUpdate) -> LogLevels {
+    let mut levels = LOG_LEVELS.write();
+    levels.apply(update);
+    levels.clone()
+}
+
+/// Structured fields of log record. They are separate keys in JSON format
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LogFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_account_id: Option<ExchangeAccountId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency_pair: Option<CurrencyPair>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<ClientOrderId>,
+}
+
+impl LogFields {
+    pub fn trade_place(trade_place: &TradePlaceAccount) -> Self {
+        LogFields {
+            exchange_account_id: Some(trade_place.exchange_account_id.clone()),
+            currency_pair: Some(trade_place.currency_pair.clone()),
+            client_order_id: None,
+        }
+    }
+
+    pub fn order(
+        exchange_account_id: &ExchangeAccountId,
+        currency_pair: &CurrencyPair,
+        client_order_id: &ClientOrderId,
+    ) -> Self {
+        LogFields {
+            exchange_account_id: Some(exchange_account_id.clone()),
+            currency_pair: Some(currency_pair.clone()),
+            client_order_id: Some(client_order_id.clone()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self == &LogFields::default()
+    }
+}
+
+/// Records logged inside `action` on current thread get `fields`. Prefer `log_with_fields!` macro
+pub fn with_log_fields<R>(fields: LogFields, action: impl FnOnce() -> R) -> R {
+    struct RestoreFields(Option<LogFields>);
+
+    impl Drop for RestoreFields {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT_LOG_FIELDS.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+
+    let previous = CURRENT_LOG_FIELDS.with(|current| current.borrow_mut().replace(fields));
+    let _restore = RestoreFields(previous);
+    action()
+}
+
+/// Log record with structured fields:
+/// `log_with_fields!(LogFields::trade_place(&trade_place), log::Level::Info, "Order {} created", id)`
+#[macro_export]
+macro_rules! log_with_fields {
+    ($fields:expr, $level:expr, $($arg:tt)+) => {
+        $crate::core::logger::with_log_fields($fields, || log::log!($level, $($arg)+))
+    };
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    timestamp: String,
+    level: &'a str,
+    target: &'a str,
+    message: String,
+    #[serde(flatten)]
+    fields: LogFields,
+}
+
+fn format_record(format: LogFormat, message: &Arguments, record: &Record) -> String {
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S,%3f");
+    let fields = CURRENT_LOG_FIELDS
+        .with(|current| current.borrow().clone())
+        .unwrap_or_default();
+
+    match format {
+        LogFormat::Text if fields.is_empty() => format!(
+            "[{}][{}][{}] {}",
+            timestamp,
+            record.level(),
+            record.target(),
+            message
+        ),
+        LogFormat::Text => {
+            let fields = serde_json::to_string(&fields).unwrap_or_default();
+            format!(
+                "[{}][{}][{}] {} {}",
+                timestamp,
+                record.level(),
+                record.target(),
+                message,
+                fields
+            )
+        }
+        LogFormat::Json => {
+            let json_record = JsonRecord {
+                timestamp: timestamp.to_string(),
+                level: record.level().as_str(),
+                target: record.target(),
+                message: message.to_string(),
+                fields,
+            };
+            serde_json::to_string(&json_record)
+                .unwrap_or_else(|error| format!("Unable to serialize log record: {}", error))
+        }
+    }
+}
+
+/// Log file that is appended on start and rotated by day or size. Rotated file gets suffix with time of rotation
+struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    max_file_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+    opened_date: NaiveDate,
+    // Rotation happens only between lines because line can be written by several calls
+    is_line_start: bool,
+}
+
+impl RotatingFile {
+    fn open(settings: &LogFileSettings) -> io::Result<Self> {
+        let path = PathBuf::from(&settings.path);
+        let file = Self::open_file(&path)?;
+        let metadata = file.metadata()?;
+        let opened_date = metadata
+            .modified()
+            .map(|modified| DateTime::<Utc>::from(modified).date().naive_utc())
+            .unwrap_or_else(|_| Utc::today().naive_utc());
+
+        let mut rotating_file = RotatingFile {
+            path,
+            rotation: settings.rotation,
+            max_file_size: settings.max_file_size_mb * 1024 * 1024,
+            max_files: settings.max_files,
+            file,
+            size: metadata.len(),
+            opened_date,
+            is_line_start: true,
+        };
+
+        // Log of previous day shouldn't be continued after restart
+        if rotating_file.should_rotate(0) {
+            rotating_file.rotate()?;
+        }
+
+        Ok(rotating_file)
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().append(true).create(true).open(path)
+    }
+
+    fn should_rotate(&self, incoming_len: usize) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        match self.rotation {
+            LogRotation::Never => false,
+            LogRotation::Daily => Utc::today().naive_utc() != self.opened_date,
+            LogRotation::Size => self.size + incoming_len as u64 > self.max_file_size,
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let suffix = Utc::now().format("%Y-%m-%d_%H-%M-%S%.3f");
+        let mut rotated_path = self.path.clone().into_os_string();
+        rotated_path.push(format!(".{}", suffix));
+        fs::rename(&self.path, rotated_path)?;
+
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+        self.opened_date = Utc::today().naive_utc();
+
+        self.remove_old_files()
+    }
+
+    fn remove_old_files(&self) -> io::Result<()> {
+        let file_name = match self.path.file_name().and_then(|x| x.to_str()) {
+            Some(file_name) => format!("{}.", file_name),
+            None => return Ok(()),
+        };
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let mut rotated_files = fs::read_dir(directory)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| matches!(entry.file_name().to_str(), Some(name) if name.starts_with(&file_name)))
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+
+        // Suffix is sortable time, so the oldest files are first
+        rotated_files.sort();
+        let excess_count = rotated_files.len().saturating_sub(self.max_files);
+        for path in &rotated_files[..excess_count] {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_line_start && self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.is_line_start = buf[written - 1] == b'\n';
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn metadata(level: Level, target: &str) -> Metadata {
+        Metadata::builder().level(level).target(target).build()
+    }
+
+    #[test]
+    fn module_level_overrides_output_level() {
+        let mut levels = LogLevels::new(&LoggingSettings::default());
+        levels
+            .modules
+            .insert("mmb_lib::core::exchanges".to_owned(), LevelFilter::Debug);
+
+        assert!(!levels.console_enabled(&metadata(Level::Info, "mmb_lib::core")));
+        assert!(
+            levels.console_enabled(&metadata(Level::Debug, "mmb_lib::core::exchanges::binance"))
+        );
+        assert!(!levels.console_enabled(&metadata(Level::Debug, "mmb_lib::core::exchanges_other")));
+        assert!(!levels.file_enabled(&metadata(Level::Info, "rustls::session")));
+        assert!(levels.file_enabled(&metadata(Level::Trace, "mmb_lib::core")));
+    }
+
+    #[test]
+    fn update_levels() {
+        let mut levels = LogLevels::new(&LoggingSettings::default());
+        let update: LogLevelsUpdate = serde_json::from_str(
+            r#"{"console_level": "info", "modules": {"rustls": null, "mmb_lib": "error"}}"#,
+        )
+        .expect("in test");
+
+        levels.apply(update);
+
+        assert_eq!(levels.console_level, LevelFilter::Info);
+        assert_eq!(levels.file_level, LevelFilter::Trace);
+        assert_eq!(levels.modules.get("rustls"), None);
+        assert_eq!(levels.modules.get("mmb_lib"), Some(&LevelFilter::Error));
+    }
+
+    #[test]
+    fn json_record_with_fields() {
+        let fields = LogFields::order(
+            &"Binance0".parse().expect("in test"),
+            &CurrencyPair::from_codes("eos".into(), "btc".into()),
+            &ClientOrderId::new("order1".into()),
+        );
+        let record = Record::builder()
+            .level(Level::Info)
+            .target("mmb_lib::test")
+            .build();
+
+        let line = with_log_fields(fields, || {
+            format_record(LogFormat::Json, &format_args!("Order created"), &record)
+        });
+
+        let json: serde_json::Value = serde_json::from_str(&line).expect("in test");
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["message"], "Order created");
+        assert_eq!(json["exchange_account_id"], "Binance0");
+        assert_eq!(json["currency_pair"], "eos/btc");
+        assert_eq!(json["client_order_id"], "order1");
+        assert!(CURRENT_LOG_FIELDS.with(|current| current.borrow().is_none()));
+    }
+
+    #[test]
+    fn file_rotated_by_size() {
+        let directory =
+            std::env::temp_dir().join(format!("mmb_log_test_{}", Utc::now().timestamp_nanos()));
+        fs::create_dir_all(&directory).expect("in test");
+        let settings = LogFileSettings {
+            level: LevelFilter::Trace,
+            path: directory.join("log.txt").to_string_lossy().to_string(),
+            rotation: LogRotation::Size,
+            max_file_size_mb: 0,
+            max_files: 1,
+        };
+
+        let mut file = RotatingFile::open(&settings).expect("in test");
+        for line in &["first\n", "second\n", "third\n"] {
+            file.write_all(line.as_bytes()).expect("in test");
+            // Rotated files have time suffix
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        file.flush().expect("in test");
+
+        let current = fs::read_to_string(&settings.path).expect("in test");
+        assert_eq!(current, "third\n");
+        let files_count = fs::read_dir(&directory).expect("in test").count();
+        assert_eq!(files_count, 2);
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
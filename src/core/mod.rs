@@ -1,14 +1,20 @@
 use chrono::Utc;
 
+pub mod backtest;
 pub mod connectivity;
 pub mod exchanges;
 pub mod infrastructure;
 pub mod logger;
 pub mod orders;
+pub mod position_service;
+pub mod risk_manager;
+pub mod secret;
+pub mod self_trade_guard;
 pub mod statistic_service;
 pub mod utils;
 
 pub mod config;
+pub mod config_validation;
 pub mod disposition_execution;
 pub(crate) mod events;
 pub mod explanation;
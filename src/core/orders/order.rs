@@ -368,6 +368,23 @@ pub struct OrderStatusHistory {
     status_changes: Vec<OrderStatusChange>,
 }
 
+impl OrderStatusHistory {
+    pub fn first_time_of(&self, status: OrderStatus) -> Option<DateTime> {
+        self.status_changes
+            .iter()
+            .find(|change| change.status == status)
+            .map(|change| change.time)
+    }
+
+    pub fn last_time_of(&self, status: OrderStatus) -> Option<DateTime> {
+        self.status_changes
+            .iter()
+            .rev()
+            .find(|change| change.status == status)
+            .map(|change| change.time)
+    }
+}
+
 /// Helping properties for trading engine internal use
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct SystemInternalOrderProps {
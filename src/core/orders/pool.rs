@@ -10,7 +10,9 @@ use super::{
     fill::OrderFill, order::OrderCancelling, order::OrderRole, order::OrderSide, order::OrderType,
     order::ReservationId,
 };
-use crate::core::exchanges::common::{Amount, CurrencyPair, ExchangeAccountId, TradePlaceAccount};
+use crate::core::exchanges::common::{
+    Amount, CurrencyPair, ExchangeAccountId, Price, TradePlaceAccount,
+};
 use crate::core::orders::order::{
     ClientOrderId, ExchangeOrderId, OrderHeader, OrderSimpleProps, OrderSnapshot, OrderStatus,
 };
@@ -157,4 +159,115 @@ impl OrdersPool {
             Some(order_ref) => order_ref.clone(),
         }
     }
+
+    /// Not finished orders on opposite side that would be matched by new order with specified price
+    pub fn find_crossing_orders(
+        &self,
+        currency_pair: &CurrencyPair,
+        side: OrderSide,
+        price: Price,
+    ) -> Vec<OrderRef> {
+        self.not_finished
+            .iter()
+            .filter(|order| {
+                order.fn_ref(|order| {
+                    // Only limit orders can rest in order book. Market orders are matched
+                    // immediately even though their price is stored too
+                    if order.header.currency_pair != *currency_pair
+                        || order.header.side == side
+                        || order.header.order_type != OrderType::Limit
+                        || order.props.is_finished()
+                    {
+                        return false;
+                    }
+
+                    match order.props.raw_price {
+                        None => false,
+                        Some(order_price) => match side {
+                            OrderSide::Buy => order_price <= price,
+                            OrderSide::Sell => order_price >= price,
+                        },
+                    }
+                })
+            })
+            .map(|order| order.value().clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::core::orders::order::OrderExecutionType;
+
+    fn add_order(
+        orders_pool: &OrdersPool,
+        currency_pair: &CurrencyPair,
+        side: OrderSide,
+        price: Price,
+    ) -> ClientOrderId {
+        add_order_with_type(orders_pool, currency_pair, OrderType::Limit, side, price)
+    }
+
+    fn add_order_with_type(
+        orders_pool: &OrdersPool,
+        currency_pair: &CurrencyPair,
+        order_type: OrderType,
+        side: OrderSide,
+        price: Price,
+    ) -> ClientOrderId {
+        let header = OrderHeader::new(
+            ClientOrderId::unique_id(),
+            Utc::now(),
+            "Binance0".parse().expect("in test"),
+            currency_pair.clone(),
+            order_type,
+            side,
+            dec!(1),
+            OrderExecutionType::None,
+            None,
+            None,
+            "test".to_owned(),
+        );
+        let client_order_id = header.client_order_id.clone();
+        let _ = orders_pool.add_simple_initial(header, Some(price));
+
+        client_order_id
+    }
+
+    #[test]
+    fn find_crossing_orders() {
+        let orders_pool = OrdersPool::new();
+        let currency_pair = CurrencyPair::from_codes("eos".into(), "btc".into());
+        let other_currency_pair = CurrencyPair::from_codes("eth".into(), "btc".into());
+
+        let crossing_sell = add_order(&orders_pool, &currency_pair, OrderSide::Sell, dec!(10));
+        let _not_crossing_sell = add_order(&orders_pool, &currency_pair, OrderSide::Sell, dec!(11));
+        let _buy = add_order(&orders_pool, &currency_pair, OrderSide::Buy, dec!(9));
+        let _other_pair_sell =
+            add_order(&orders_pool, &other_currency_pair, OrderSide::Sell, dec!(1));
+        let _market_sell = add_order_with_type(
+            &orders_pool,
+            &currency_pair,
+            OrderType::Market,
+            OrderSide::Sell,
+            dec!(10),
+        );
+
+        let crossing_orders =
+            orders_pool.find_crossing_orders(&currency_pair, OrderSide::Buy, dec!(10.5));
+
+        let crossing_ids: Vec<_> = crossing_orders
+            .iter()
+            .map(|order| order.client_order_id())
+            .collect();
+        assert_eq!(crossing_ids, vec![crossing_sell]);
+
+        let crossing_orders =
+            orders_pool.find_crossing_orders(&currency_pair, OrderSide::Sell, dec!(9.5));
+        assert!(crossing_orders.is_empty());
+    }
 }
@@ -0,0 +1,467 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use futures::FutureExt;
+use log::warn;
+use parking_lot::{Mutex, RwLock};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::exchanges::common::{Amount, ExchangeAccountId, Price, TradePlaceAccount};
+use super::exchanges::events::ExchangeEvent;
+use super::exchanges::general::exchange::Exchange;
+use super::infrastructure::spawn_future;
+use super::nothing_to_do;
+use super::orders::event::OrderEventType;
+use super::orders::fill::OrderFill;
+use super::orders::order::{OrderSide, OrderSnapshot};
+
+/// Position on trade place account calculated by average cost method.
+/// Amounts are in base currency, prices and PnL are in quote currency
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Position {
+    /// Positive for long position and negative for short one
+    amount: Amount,
+    average_entry_price: Price,
+    realized_pnl: Decimal,
+    commission: Amount,
+}
+
+impl Position {
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
+    pub fn average_entry_price(&self) -> Price {
+        self.average_entry_price
+    }
+
+    /// Realized PnL with deducted commissions
+    pub fn realized_pnl(&self) -> Decimal {
+        self.realized_pnl
+    }
+
+    pub fn commission(&self) -> Amount {
+        self.commission
+    }
+
+    pub(crate) fn apply_fill(
+        &mut self,
+        side: OrderSide,
+        price: Price,
+        amount: Amount,
+        commission_in_quote: Amount,
+    ) {
+        let signed_amount = match side {
+            OrderSide::Buy => amount,
+            OrderSide::Sell => -amount,
+        };
+
+        let is_increasing = self.amount.is_zero()
+            || self.amount.is_sign_positive() == signed_amount.is_sign_positive();
+        if is_increasing {
+            let new_abs_amount = self.amount.abs() + amount;
+            self.average_entry_price =
+                (self.amount.abs() * self.average_entry_price + amount * price) / new_abs_amount;
+        } else {
+            let closed_amount = amount.min(self.amount.abs());
+            let price_diff = if self.amount.is_sign_positive() {
+                price - self.average_entry_price
+            } else {
+                self.average_entry_price - price
+            };
+            self.realized_pnl += closed_amount * price_diff;
+
+            if amount > self.amount.abs() {
+                // Position is flipped, so rest of fill opens new position
+                self.average_entry_price = price;
+            }
+        }
+
+        self.amount += signed_amount;
+        if self.amount.is_zero() {
+            self.average_entry_price = dec!(0);
+        }
+
+        self.realized_pnl -= commission_in_quote;
+        self.commission += commission_in_quote;
+    }
+
+    /// Long position is marked by top bid and short one by top ask
+    pub(crate) fn unrealized_pnl(
+        &self,
+        top_bid: Option<Price>,
+        top_ask: Option<Price>,
+    ) -> Option<Decimal> {
+        if self.amount.is_zero() {
+            return Some(dec!(0));
+        }
+
+        let mark_price = match self.amount.is_sign_positive() {
+            true => top_bid?,
+            false => top_ask?,
+        };
+
+        Some((mark_price - self.average_entry_price) * self.amount)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PositionStatistic {
+    #[serde(flatten)]
+    position: Position,
+    /// None if there is no order book top for marking
+    unrealized_pnl: Option<Decimal>,
+}
+
+pub(crate) type PositionChangedCallback = Box<dyn FnMut(&TradePlaceAccount, Amount) + Send + Sync>;
+
+/// Tracks positions and PnL for every trade place account by `OrderFilled` events
+pub struct PositionService {
+    positions: RwLock<HashMap<TradePlaceAccount, Position>>,
+    // Fills can be received after order cancellation or completion, so applied fills are tracked
+    // by id for the whole engine lifetime like orders in pool
+    applied_fill_ids: Mutex<HashSet<Uuid>>,
+    exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>,
+    position_changed_callback: Mutex<PositionChangedCallback>,
+}
+
+impl PositionService {
+    pub fn new(exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>) -> Arc<Self> {
+        Arc::new(Self {
+            positions: Default::default(),
+            applied_fill_ids: Default::default(),
+            exchanges,
+            position_changed_callback: Mutex::new(Box::new(|_, _| {})),
+        })
+    }
+
+    /// Callback is called with new position amount after fills are applied
+    pub(crate) fn set_position_changed_callback(&self, callback: PositionChangedCallback) {
+        *self.position_changed_callback.lock() = callback;
+    }
+
+    pub fn start(self: Arc<Self>, events_receiver: broadcast::Receiver<ExchangeEvent>) {
+        let action = self.handle_events(events_receiver);
+        spawn_future("Start position service", true, action.boxed());
+    }
+
+    pub fn get_position(&self, trade_place_account: &TradePlaceAccount) -> Option<Position> {
+        self.positions.read().get(trade_place_account).cloned()
+    }
+
+    /// Positions with unrealized PnL marked against current order book top
+    pub fn get_statistics(&self) -> HashMap<TradePlaceAccount, PositionStatistic> {
+        self.positions
+            .read()
+            .iter()
+            .map(|(trade_place_account, position)| {
+                let (top_bid, top_ask) = self.get_top_prices(trade_place_account);
+                let statistic = PositionStatistic {
+                    position: position.clone(),
+                    unrealized_pnl: position.unrealized_pnl(top_bid, top_ask),
+                };
+
+                (trade_place_account.clone(), statistic)
+            })
+            .collect()
+    }
+
+    /// Realized and unrealized PnL of all positions on exchange account.
+    /// Positions without order book top are taken into account only by realized PnL
+    pub fn get_total_pnl(&self, exchange_account_id: &ExchangeAccountId) -> Decimal {
+        self.positions
+            .read()
+            .iter()
+            .filter(|(trade_place_account, _)| {
+                &trade_place_account.exchange_account_id == exchange_account_id
+            })
+            .map(|(trade_place_account, position)| {
+                let (top_bid, top_ask) = self.get_top_prices(trade_place_account);
+                position.realized_pnl()
+                    + position
+                        .unrealized_pnl(top_bid, top_ask)
+                        .unwrap_or_default()
+            })
+            .sum()
+    }
+
+    fn get_top_prices(
+        &self,
+        trade_place_account: &TradePlaceAccount,
+    ) -> (Option<Price>, Option<Price>) {
+        self.exchanges
+            .get(&trade_place_account.exchange_account_id)
+            .and_then(|exchange| {
+                exchange
+                    .order_book_top
+                    .get(&trade_place_account.currency_pair)
+                    .map(|top| {
+                        (
+                            top.bid.as_ref().map(|level| level.price),
+                            top.ask.as_ref().map(|level| level.price),
+                        )
+                    })
+            })
+            .unwrap_or((None, None))
+    }
+
+    async fn handle_events(
+        self: Arc<Self>,
+        mut events_receiver: broadcast::Receiver<ExchangeEvent>,
+    ) -> Result<()> {
+        loop {
+            let event = events_receiver
+                .recv()
+                .await
+                .context("Error during receiving event in PositionService::handle_events()")?;
+            // Like StatisticEventHandler it isn't stopped by CancellationToken
+            // to take into account fills occurred during graceful shutdown
+
+            if let ExchangeEvent::OrderEvent(order_event) = event {
+                match order_event.event_type {
+                    OrderEventType::OrderFilled { cloned_order } => {
+                        self.handle_order_filled(&cloned_order)
+                    }
+                    _ => nothing_to_do(),
+                }
+            }
+        }
+    }
+
+    fn handle_order_filled(&self, cloned_order: &OrderSnapshot) {
+        let header = &cloned_order.header;
+        let fills = &cloned_order.fills.fills;
+
+        // Snapshot contains all order fills, so apply only ones that weren't applied yet
+        let new_fills = {
+            let mut applied_fill_ids = self.applied_fill_ids.lock();
+            fills
+                .iter()
+                .filter(|fill| applied_fill_ids.insert(fill.id()))
+                .collect::<Vec<_>>()
+        };
+
+        if new_fills.is_empty() {
+            return;
+        }
+
+        let trade_place_account = TradePlaceAccount::new(
+            header.exchange_account_id.clone(),
+            header.currency_pair.clone(),
+        );
+
+        let position_amount = {
+            let mut positions = self.positions.write();
+            let position = positions.entry(trade_place_account.clone()).or_default();
+            for fill in new_fills {
+                let commission_in_quote = self.get_commission_in_quote(&trade_place_account, fill);
+                position.apply_fill(
+                    fill.side().unwrap_or(header.side),
+                    fill.price(),
+                    fill.amount(),
+                    commission_in_quote,
+                );
+            }
+            position.amount()
+        };
+
+        (self.position_changed_callback.lock())(&trade_place_account, position_amount);
+    }
+
+    fn get_commission_in_quote(
+        &self,
+        trade_place_account: &TradePlaceAccount,
+        fill: &OrderFill,
+    ) -> Amount {
+        let currency_pair_metadata = self
+            .exchanges
+            .get(&trade_place_account.exchange_account_id)
+            .and_then(|exchange| {
+                exchange
+                    .get_currency_pair_metadata(&trade_place_account.currency_pair)
+                    .ok()
+            });
+
+        let currency_pair_metadata = match currency_pair_metadata {
+            Some(currency_pair_metadata) => currency_pair_metadata,
+            None => {
+                warn!(
+                    "Unable to get currency pair metadata for {:?} so commission isn't taken into account in position",
+                    trade_place_account
+                );
+                return dec!(0);
+            }
+        };
+
+        let commission_currency_code = fill.converted_commission_currency_code();
+        if commission_currency_code == &currency_pair_metadata.quote_currency_code() {
+            fill.converted_commission_amount()
+        } else if commission_currency_code == &currency_pair_metadata.base_currency_code() {
+            fill.converted_commission_amount() * fill.price()
+        } else {
+            warn!(
+                "Commission in {} wasn't converted to {:?} currencies so it isn't taken into account in position",
+                commission_currency_code.as_str(),
+                trade_place_account
+            );
+            dec!(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exchanges::common::CurrencyPair;
+
+    #[test]
+    fn open_and_increase_long_position() {
+        let mut position = Position::default();
+
+        position.apply_fill(OrderSide::Buy, dec!(10), dec!(1), dec!(0.1));
+        position.apply_fill(OrderSide::Buy, dec!(13), dec!(2), dec!(0.2));
+
+        assert_eq!(position.amount(), dec!(3));
+        assert_eq!(position.average_entry_price(), dec!(12));
+        assert_eq!(position.realized_pnl(), dec!(-0.3));
+        assert_eq!(position.commission(), dec!(0.3));
+    }
+
+    #[test]
+    fn partially_close_long_position() {
+        let mut position = Position::default();
+
+        position.apply_fill(OrderSide::Buy, dec!(10), dec!(2), dec!(0));
+        position.apply_fill(OrderSide::Sell, dec!(15), dec!(1), dec!(0));
+
+        assert_eq!(position.amount(), dec!(1));
+        assert_eq!(position.average_entry_price(), dec!(10));
+        assert_eq!(position.realized_pnl(), dec!(5));
+    }
+
+    #[test]
+    fn close_short_position() {
+        let mut position = Position::default();
+
+        position.apply_fill(OrderSide::Sell, dec!(10), dec!(2), dec!(0));
+        position.apply_fill(OrderSide::Buy, dec!(8), dec!(2), dec!(0.5));
+
+        assert_eq!(position.amount(), dec!(0));
+        assert_eq!(position.average_entry_price(), dec!(0));
+        assert_eq!(position.realized_pnl(), dec!(3.5));
+    }
+
+    #[test]
+    fn flip_position() {
+        let mut position = Position::default();
+
+        position.apply_fill(OrderSide::Buy, dec!(10), dec!(1), dec!(0));
+        position.apply_fill(OrderSide::Sell, dec!(12), dec!(3), dec!(0));
+
+        assert_eq!(position.amount(), dec!(-2));
+        assert_eq!(position.average_entry_price(), dec!(12));
+        assert_eq!(position.realized_pnl(), dec!(2));
+    }
+
+    #[test]
+    fn unrealized_pnl() {
+        let mut long_position = Position::default();
+        long_position.apply_fill(OrderSide::Buy, dec!(10), dec!(2), dec!(0));
+        assert_eq!(
+            long_position.unrealized_pnl(Some(dec!(11)), Some(dec!(12))),
+            Some(dec!(2))
+        );
+        assert_eq!(long_position.unrealized_pnl(None, Some(dec!(12))), None);
+
+        let mut short_position = Position::default();
+        short_position.apply_fill(OrderSide::Sell, dec!(10), dec!(2), dec!(0));
+        assert_eq!(
+            short_position.unrealized_pnl(Some(dec!(11)), Some(dec!(12))),
+            Some(dec!(-4))
+        );
+
+        assert_eq!(
+            Position::default().unrealized_pnl(None, None),
+            Some(dec!(0))
+        );
+    }
+
+    mod events {
+        use tokio::time::{sleep, Duration};
+
+        use super::*;
+        use crate::core::exchanges::general::test_helper::{create_order_ref, create_test_fill};
+        use crate::core::orders::event::OrderEvent;
+        use crate::core::orders::order::ClientOrderId;
+        use crate::core::orders::pool::OrderRef;
+
+        fn send_order_event(
+            events_sender: &broadcast::Sender<ExchangeEvent>,
+            order_ref: &OrderRef,
+            event_type: OrderEventType,
+        ) {
+            let _ = events_sender
+                .send(ExchangeEvent::OrderEvent(OrderEvent::new(
+                    order_ref.clone(),
+                    event_type,
+                )))
+                .expect("in test");
+        }
+
+        fn order_filled(order_ref: &OrderRef) -> OrderEventType {
+            OrderEventType::OrderFilled {
+                cloned_order: Arc::new(order_ref.deep_clone()),
+            }
+        }
+
+        #[tokio::test]
+        async fn late_fill_after_cancel_is_applied_once() {
+            let position_service = PositionService::new(DashMap::new());
+            let (events_sender, events_receiver) = broadcast::channel(10);
+            position_service.clone().start(events_receiver);
+
+            let exchange_account_id = ExchangeAccountId::new("Binance".into(), 0);
+            let currency_pair = CurrencyPair::from_codes("btc".into(), "usdt".into());
+            let order_ref = create_order_ref(
+                &ClientOrderId::unique_id(),
+                None,
+                &exchange_account_id,
+                &currency_pair,
+                dec!(10),
+                dec!(3),
+                OrderSide::Buy,
+            );
+
+            order_ref.fn_mut(|order| order.add_fill(create_test_fill(dec!(10), dec!(1))));
+            send_order_event(&events_sender, &order_ref, order_filled(&order_ref));
+            send_order_event(
+                &events_sender,
+                &order_ref,
+                OrderEventType::CancelOrderSucceeded,
+            );
+            order_ref.fn_mut(|order| order.add_fill(create_test_fill(dec!(13), dec!(2))));
+            send_order_event(&events_sender, &order_ref, order_filled(&order_ref));
+            send_order_event(
+                &events_sender,
+                &order_ref,
+                OrderEventType::OrderCompleted {
+                    cloned_order: Arc::new(order_ref.deep_clone()),
+                },
+            );
+            send_order_event(&events_sender, &order_ref, order_filled(&order_ref));
+            sleep(Duration::from_millis(100)).await;
+
+            let position = position_service
+                .get_position(&TradePlaceAccount::new(exchange_account_id, currency_pair))
+                .expect("in test");
+            assert_eq!(position.amount(), dec!(3));
+            assert_eq!(position.average_entry_price(), dec!(12));
+        }
+    }
+}
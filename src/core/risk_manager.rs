@@ -0,0 +1,747 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use chrono::{NaiveDate, Utc};
+use dashmap::DashMap;
+use futures::FutureExt;
+use log::{error, info, warn};
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tokio::time::{sleep, Duration};
+
+use crate::core::exchanges::block_reasons;
+use crate::core::exchanges::common::{
+    Amount, CurrencyPair, ExchangeAccountId, Price, TradePlaceAccount,
+};
+use crate::core::exchanges::exchange_blocker::{BlockReason, BlockType, ExchangeBlocker};
+use crate::core::exchanges::general::exchange::Exchange;
+use crate::core::explanation::Explanation;
+use crate::core::infrastructure::spawn_future;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::orders::order::OrderSide;
+use crate::core::position_service::PositionService;
+use crate::core::settings::RiskManagerSettings;
+
+const DEFAULT_BLOCK_DURATION: Duration = Duration::from_secs(60);
+
+/// Order parameters needed for pre-trade checks
+#[derive(Debug, Clone)]
+pub struct RiskCheckOrder {
+    pub currency_pair: CurrencyPair,
+    pub side: OrderSide,
+    pub price: Price,
+    pub amount: Amount,
+}
+
+/// Market and account state needed for pre-trade checks
+#[derive(Debug, Clone, Default)]
+struct RiskCheckState {
+    position: Amount,
+    mid_price: Option<Price>,
+    open_orders_count: usize,
+    daily_loss: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskViolation {
+    MaxOrderNotional {
+        notional: Amount,
+        limit: Amount,
+    },
+    MaxPosition {
+        position: Amount,
+        limit: Amount,
+    },
+    PriceDeviation {
+        deviation_rate: Decimal,
+        limit: Decimal,
+    },
+    MaxOpenOrders {
+        count: usize,
+        limit: usize,
+    },
+    MaxDailyLoss {
+        loss: Decimal,
+        limit: Amount,
+    },
+}
+
+impl RiskViolation {
+    /// Reason to block exchange account. Violations of per-order limits are normal
+    /// during trading, so only the new order is rejected for them. Position limit breach
+    /// by filled orders is handled separately in `RiskManager::check_position`
+    fn block_reason(&self) -> Option<BlockReason> {
+        match self {
+            RiskViolation::MaxDailyLoss { .. } => Some(block_reasons::RISK_MAX_DAILY_LOSS),
+            RiskViolation::MaxOrderNotional { .. }
+            | RiskViolation::MaxPosition { .. }
+            | RiskViolation::PriceDeviation { .. }
+            | RiskViolation::MaxOpenOrders { .. } => None,
+        }
+    }
+}
+
+impl Display for RiskViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RiskViolation::MaxOrderNotional { notional, limit } => {
+                write!(f, "order notional {} > max {}", notional, limit)
+            }
+            RiskViolation::MaxPosition { position, limit } => {
+                write!(f, "resulting position {} exceeds max {}", position, limit)
+            }
+            RiskViolation::PriceDeviation {
+                deviation_rate,
+                limit,
+            } => write!(
+                f,
+                "price deviation from mid price {} > max {}",
+                deviation_rate, limit
+            ),
+            RiskViolation::MaxOpenOrders { count, limit } => {
+                write!(f, "open orders count {} >= max {}", count, limit)
+            }
+            RiskViolation::MaxDailyLoss { loss, limit } => {
+                write!(f, "daily loss {} >= max {}", loss, limit)
+            }
+        }
+    }
+}
+
+/// Pre-trade checks for every order before `Exchange::create_order`.
+/// On breach of position limit by filled orders or daily loss limit exchange account is blocked
+/// and its open orders are cancelled
+pub struct RiskManager {
+    settings: RiskManagerSettings,
+    exchange_blocker: Arc<ExchangeBlocker>,
+    positions: Arc<PositionService>,
+    exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>,
+    // Exchange account isn't blocked and orders aren't cancelled in dry run mode
+    is_dry_run: bool,
+    // PnL at the start of current UTC day for every exchange account
+    day_start_pnl: Mutex<HashMap<ExchangeAccountId, (NaiveDate, Decimal)>>,
+}
+
+impl RiskManager {
+    /// Daily loss is counted from PnL at creation time until the start of next UTC day
+    pub fn new(
+        settings: RiskManagerSettings,
+        exchange_blocker: Arc<ExchangeBlocker>,
+        positions: Arc<PositionService>,
+        exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>,
+        is_dry_run: bool,
+    ) -> Arc<Self> {
+        let risk_manager = Arc::new(Self {
+            settings,
+            exchange_blocker,
+            positions: positions.clone(),
+            exchanges,
+            is_dry_run,
+            day_start_pnl: Default::default(),
+        });
+        risk_manager.reset_day_start_pnl();
+
+        let risk_manager_weak = Arc::downgrade(&risk_manager);
+        positions.set_position_changed_callback(Box::new(move |trade_place_account, position| {
+            match risk_manager_weak.upgrade() {
+                Some(risk_manager) => risk_manager.check_position(trade_place_account, position),
+                None => info!("Unable to upgrade weak reference to RiskManager instance"),
+            }
+        }));
+
+        risk_manager
+    }
+
+    /// Resets daily loss baseline of every exchange account at the start of each UTC day
+    pub fn start(self: Arc<Self>, cancellation_token: CancellationToken) {
+        let action = async move {
+            loop {
+                let now = Utc::now();
+                let next_day_start = (now.date() + chrono::Duration::days(1)).and_hms(0, 0, 0);
+                let delay = (next_day_start - now).to_std().unwrap_or_default();
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = cancellation_token.when_cancelled() => return Ok(()),
+                }
+
+                self.reset_day_start_pnl();
+            }
+        };
+        spawn_future("Reset day start PnL", false, action.boxed());
+    }
+
+    fn reset_day_start_pnl(&self) {
+        let today = Utc::now().date().naive_utc();
+        let mut day_start_pnl = self.day_start_pnl.lock();
+        for exchange in self.exchanges.iter() {
+            let exchange_account_id = exchange.key();
+            let total_pnl = self.positions.get_total_pnl(exchange_account_id);
+            let _ = day_start_pnl.insert(exchange_account_id.clone(), (today, total_pnl));
+        }
+    }
+
+    /// Blocks exchange account and cancels its open orders if filled position exceeds limit
+    pub(crate) fn check_position(&self, trade_place_account: &TradePlaceAccount, position: Amount) {
+        let limit = match self.settings.max_position {
+            Some(limit) if position.abs() > limit => limit,
+            _ => return,
+        };
+
+        let exchange_account_id = &trade_place_account.exchange_account_id;
+        error!(
+            "Risk limit breached on {} {}: position {} exceeds max {}",
+            exchange_account_id, trade_place_account.currency_pair, position, limit
+        );
+
+        if self.is_dry_run
+            || self
+                .exchange_blocker
+                .is_blocked_by_reason(exchange_account_id, block_reasons::RISK_MAX_POSITION)
+        {
+            return;
+        }
+
+        match self.exchanges.get(exchange_account_id) {
+            Some(exchange) => self.block_exchange(
+                &exchange,
+                block_reasons::RISK_MAX_POSITION,
+                self.timed_block(),
+            ),
+            None => warn!(
+                "Unable to block unknown exchange account {} after risk limit breach",
+                exchange_account_id
+            ),
+        }
+    }
+
+    /// Returns true if order can be created. Otherwise reason is added to explanation.
+    /// On position or daily loss limit breach exchange account is blocked and its open orders are cancelled
+    pub fn check_order(
+        &self,
+        exchange: &Arc<Exchange>,
+        order: &RiskCheckOrder,
+        explanation: &mut Explanation,
+    ) -> bool {
+        let violation = match self.find_order_violation(exchange, order) {
+            None => return true,
+            Some(violation) => violation,
+        };
+
+        let exchange_account_id = &exchange.exchange_account_id;
+        let block_reason = match violation.block_reason() {
+            None => {
+                let msg = format!(
+                    "Order on {} {} is rejected by risk limit: {}",
+                    exchange_account_id, order.currency_pair, violation
+                );
+                warn!("{}", msg);
+                explanation.add_reason(msg);
+                return false;
+            }
+            Some(block_reason) => block_reason,
+        };
+
+        let msg = format!(
+            "Risk limit breached for order on {} {}: {}",
+            exchange_account_id, order.currency_pair, violation
+        );
+        error!("{}", msg);
+        explanation.add_reason(msg);
+
+        if self.is_dry_run {
+            return false;
+        }
+
+        let block_type = match violation {
+            RiskViolation::MaxDailyLoss { .. } => BlockType::Manual,
+            _ => self.timed_block(),
+        };
+        self.block_exchange(exchange, block_reason, block_type);
+
+        false
+    }
+
+    fn timed_block(&self) -> BlockType {
+        BlockType::Timed(
+            self.settings
+                .block_duration_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_BLOCK_DURATION),
+        )
+    }
+
+    fn block_exchange(
+        &self,
+        exchange: &Arc<Exchange>,
+        block_reason: BlockReason,
+        block_type: BlockType,
+    ) {
+        self.exchange_blocker
+            .block(&exchange.exchange_account_id, block_reason, block_type);
+
+        let action = exchange
+            .clone()
+            .cancel_opened_orders(CancellationToken::default(), true)
+            .map(|_| Ok(()));
+        spawn_future(
+            "Cancel opened orders after risk limit breach",
+            true,
+            action.boxed(),
+        );
+    }
+
+    /// Violated risk limit without blocking exchange account and cancelling its orders,
+    /// so it fits orders created by operator
+    pub fn find_order_violation(
+        &self,
+        exchange: &Exchange,
+        order: &RiskCheckOrder,
+    ) -> Option<RiskViolation> {
+        let state = self.get_state(exchange, &order.currency_pair);
+        find_violation(&self.settings, order, &state)
+    }
+
+    fn get_state(&self, exchange: &Exchange, currency_pair: &CurrencyPair) -> RiskCheckState {
+        let exchange_account_id = &exchange.exchange_account_id;
+
+        let position = self
+            .positions
+            .get_position(&TradePlaceAccount::new(
+                exchange_account_id.clone(),
+                currency_pair.clone(),
+            ))
+            .map(|position| position.amount())
+            .unwrap_or_default();
+
+        let mid_price = exchange.order_book_top.get(currency_pair).and_then(|top| {
+            match (top.bid.as_ref(), top.ask.as_ref()) {
+                (Some(bid), Some(ask)) => Some((bid.price + ask.price) / dec!(2)),
+                _ => None,
+            }
+        });
+
+        RiskCheckState {
+            position,
+            mid_price,
+            open_orders_count: exchange.orders.not_finished.len(),
+            daily_loss: self.get_daily_loss(exchange_account_id),
+        }
+    }
+
+    fn get_daily_loss(&self, exchange_account_id: &ExchangeAccountId) -> Decimal {
+        let total_pnl = self.positions.get_total_pnl(exchange_account_id);
+        let today = Utc::now().date().naive_utc();
+
+        // Baseline is reset at day start, so it's outdated only if reset is late
+        let mut day_start_pnl = self.day_start_pnl.lock();
+        let (day, start_pnl) = day_start_pnl
+            .entry(exchange_account_id.clone())
+            .or_insert((today, total_pnl));
+        if *day != today {
+            *day = today;
+            *start_pnl = total_pnl;
+        }
+
+        *start_pnl - total_pnl
+    }
+}
+
+fn find_violation(
+    settings: &RiskManagerSettings,
+    order: &RiskCheckOrder,
+    state: &RiskCheckState,
+) -> Option<RiskViolation> {
+    if let Some(limit) = settings.max_daily_loss {
+        if state.daily_loss >= limit {
+            return Some(RiskViolation::MaxDailyLoss {
+                loss: state.daily_loss,
+                limit,
+            });
+        }
+    }
+
+    if let Some(limit) = settings.max_order_notional {
+        let notional = order.price * order.amount;
+        if notional > limit {
+            return Some(RiskViolation::MaxOrderNotional { notional, limit });
+        }
+    }
+
+    // Orders which don't increase absolute position are allowed even if position exceeds limit
+    if let Some(limit) = settings.max_position {
+        let position = match order.side {
+            OrderSide::Buy => state.position + order.amount,
+            OrderSide::Sell => state.position - order.amount,
+        };
+        if position.abs() > limit && position.abs() > state.position.abs() {
+            return Some(RiskViolation::MaxPosition { position, limit });
+        }
+    }
+
+    // Price deviation isn't checked while there is no mid price
+    if let (Some(limit), Some(mid_price)) = (settings.max_price_deviation_rate, state.mid_price) {
+        if !mid_price.is_zero() {
+            let deviation_rate = ((order.price - mid_price) / mid_price).abs();
+            if deviation_rate > limit {
+                return Some(RiskViolation::PriceDeviation {
+                    deviation_rate,
+                    limit,
+                });
+            }
+        }
+    }
+
+    if let Some(limit) = settings.max_open_orders {
+        if state.open_orders_count >= limit {
+            return Some(RiskViolation::MaxOpenOrders {
+                count: state.open_orders_count,
+                limit,
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exchanges::general::test_helper::get_test_exchange;
+
+    fn order(side: OrderSide, price: Price, amount: Amount) -> RiskCheckOrder {
+        RiskCheckOrder {
+            currency_pair: CurrencyPair::from_codes("eos".into(), "btc".into()),
+            side,
+            price,
+            amount,
+        }
+    }
+
+    fn state() -> RiskCheckState {
+        RiskCheckState {
+            position: dec!(0),
+            mid_price: Some(dec!(10)),
+            open_orders_count: 0,
+            daily_loss: dec!(0),
+        }
+    }
+
+    #[test]
+    fn without_limits() {
+        let violation = find_violation(
+            &RiskManagerSettings::default(),
+            &order(OrderSide::Buy, dec!(1000), dec!(1000)),
+            &state(),
+        );
+
+        assert_eq!(violation, None);
+    }
+
+    #[test]
+    fn max_order_notional() {
+        let settings = RiskManagerSettings {
+            max_order_notional: Some(dec!(20)),
+            ..Default::default()
+        };
+
+        let allowed = find_violation(
+            &settings,
+            &order(OrderSide::Buy, dec!(10), dec!(2)),
+            &state(),
+        );
+        assert_eq!(allowed, None);
+
+        let violation = find_violation(
+            &settings,
+            &order(OrderSide::Buy, dec!(10), dec!(3)),
+            &state(),
+        );
+        assert_eq!(
+            violation,
+            Some(RiskViolation::MaxOrderNotional {
+                notional: dec!(30),
+                limit: dec!(20)
+            })
+        );
+    }
+
+    #[test]
+    fn max_position() {
+        let settings = RiskManagerSettings {
+            max_position: Some(dec!(5)),
+            ..Default::default()
+        };
+        let state = RiskCheckState {
+            position: dec!(4),
+            ..state()
+        };
+
+        let reducing = find_violation(
+            &settings,
+            &order(OrderSide::Sell, dec!(10), dec!(8)),
+            &state,
+        );
+        assert_eq!(reducing, None);
+
+        let violation =
+            find_violation(&settings, &order(OrderSide::Buy, dec!(10), dec!(2)), &state);
+        assert_eq!(
+            violation,
+            Some(RiskViolation::MaxPosition {
+                position: dec!(6),
+                limit: dec!(5)
+            })
+        );
+    }
+
+    #[test]
+    fn price_deviation() {
+        let settings = RiskManagerSettings {
+            max_price_deviation_rate: Some(dec!(0.1)),
+            ..Default::default()
+        };
+
+        let allowed = find_violation(
+            &settings,
+            &order(OrderSide::Sell, dec!(11), dec!(1)),
+            &state(),
+        );
+        assert_eq!(allowed, None);
+
+        let violation = find_violation(
+            &settings,
+            &order(OrderSide::Buy, dec!(8), dec!(1)),
+            &state(),
+        );
+        assert_eq!(
+            violation,
+            Some(RiskViolation::PriceDeviation {
+                deviation_rate: dec!(0.2),
+                limit: dec!(0.1)
+            })
+        );
+
+        let without_mid_price = RiskCheckState {
+            mid_price: None,
+            ..state()
+        };
+        let not_checked = find_violation(
+            &settings,
+            &order(OrderSide::Buy, dec!(8), dec!(1)),
+            &without_mid_price,
+        );
+        assert_eq!(not_checked, None);
+    }
+
+    #[test]
+    fn max_open_orders() {
+        let settings = RiskManagerSettings {
+            max_open_orders: Some(2),
+            ..Default::default()
+        };
+        let state = RiskCheckState {
+            open_orders_count: 2,
+            ..state()
+        };
+
+        let violation =
+            find_violation(&settings, &order(OrderSide::Buy, dec!(10), dec!(1)), &state);
+        assert_eq!(
+            violation,
+            Some(RiskViolation::MaxOpenOrders { count: 2, limit: 2 })
+        );
+    }
+
+    #[test]
+    fn max_daily_loss() {
+        let settings = RiskManagerSettings {
+            max_daily_loss: Some(dec!(100)),
+            ..Default::default()
+        };
+        let state = RiskCheckState {
+            daily_loss: dec!(100),
+            ..state()
+        };
+
+        let violation =
+            find_violation(&settings, &order(OrderSide::Buy, dec!(10), dec!(1)), &state);
+        assert_eq!(
+            violation.and_then(|violation| violation.block_reason()),
+            Some(block_reasons::RISK_MAX_DAILY_LOSS)
+        );
+    }
+
+    #[test]
+    fn max_position_reducing_order_is_allowed_over_limit() {
+        let settings = RiskManagerSettings {
+            max_position: Some(dec!(5)),
+            ..Default::default()
+        };
+        let state = RiskCheckState {
+            position: dec!(7),
+            ..state()
+        };
+
+        let reducing = find_violation(
+            &settings,
+            &order(OrderSide::Sell, dec!(10), dec!(1)),
+            &state,
+        );
+        assert_eq!(reducing, None);
+
+        let reversing = find_violation(
+            &settings,
+            &order(OrderSide::Sell, dec!(10), dec!(14)),
+            &state,
+        );
+        assert_eq!(reversing, None);
+
+        let increasing =
+            find_violation(&settings, &order(OrderSide::Buy, dec!(10), dec!(1)), &state);
+        assert_eq!(
+            increasing,
+            Some(RiskViolation::MaxPosition {
+                position: dec!(8),
+                limit: dec!(5)
+            })
+        );
+    }
+
+    #[test]
+    fn only_daily_loss_breach_blocks_exchange() {
+        let per_order_violations = [
+            RiskViolation::MaxOrderNotional {
+                notional: dec!(30),
+                limit: dec!(20),
+            },
+            RiskViolation::MaxPosition {
+                position: dec!(6),
+                limit: dec!(5),
+            },
+            RiskViolation::PriceDeviation {
+                deviation_rate: dec!(0.2),
+                limit: dec!(0.1),
+            },
+            RiskViolation::MaxOpenOrders { count: 2, limit: 2 },
+        ];
+        for violation in &per_order_violations {
+            assert_eq!(violation.block_reason(), None, "{}", violation);
+        }
+
+        let breach = RiskViolation::MaxDailyLoss {
+            loss: dec!(100),
+            limit: dec!(100),
+        };
+        assert_eq!(
+            breach.block_reason(),
+            Some(block_reasons::RISK_MAX_DAILY_LOSS)
+        );
+    }
+
+    struct TestContext {
+        exchange: Arc<Exchange>,
+        exchange_blocker: Arc<ExchangeBlocker>,
+        risk_manager: Arc<RiskManager>,
+    }
+
+    fn create_context(is_dry_run: bool) -> TestContext {
+        let (exchange, _rx) = get_test_exchange(false);
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        let exchange_blocker = ExchangeBlocker::new(vec![exchange_account_id.clone()]);
+        let settings = RiskManagerSettings {
+            max_position: Some(dec!(1)),
+            ..Default::default()
+        };
+        let exchanges = DashMap::new();
+        let _ = exchanges.insert(exchange_account_id, exchange.clone());
+        let risk_manager = RiskManager::new(
+            settings,
+            exchange_blocker.clone(),
+            PositionService::new(exchanges.clone()),
+            exchanges,
+            is_dry_run,
+        );
+
+        TestContext {
+            exchange,
+            exchange_blocker,
+            risk_manager,
+        }
+    }
+
+    fn check_position_breach(is_dry_run: bool) -> bool {
+        let context = create_context(is_dry_run);
+        let exchange_account_id = context.exchange.exchange_account_id.clone();
+
+        context.risk_manager.check_position(
+            &TradePlaceAccount::new(
+                exchange_account_id.clone(),
+                CurrencyPair::from_codes("eos".into(), "btc".into()),
+            ),
+            dec!(-2),
+        );
+
+        context.exchange_blocker.is_blocked(&exchange_account_id)
+    }
+
+    #[tokio::test]
+    async fn rejected_order_does_not_block() {
+        let context = create_context(false);
+
+        let mut explanation = Explanation::default();
+        let is_allowed = context.risk_manager.check_order(
+            &context.exchange,
+            &order(OrderSide::Buy, dec!(10), dec!(2)),
+            &mut explanation,
+        );
+
+        assert!(!is_allowed);
+        assert!(!context
+            .exchange_blocker
+            .is_blocked(&context.exchange.exchange_account_id));
+    }
+
+    #[tokio::test]
+    async fn find_order_violation_doesnt_block_exchange() {
+        let context = create_context(false);
+
+        let violation = context
+            .risk_manager
+            .find_order_violation(&context.exchange, &order(OrderSide::Buy, dec!(10), dec!(2)));
+
+        assert_eq!(
+            violation,
+            Some(RiskViolation::MaxPosition {
+                position: dec!(2),
+                limit: dec!(1)
+            })
+        );
+        assert!(!context
+            .exchange_blocker
+            .is_blocked(&context.exchange.exchange_account_id));
+    }
+
+    #[tokio::test]
+    async fn filled_position_breach_blocks_exchange() {
+        assert!(check_position_breach(false));
+    }
+
+    #[tokio::test]
+    async fn filled_position_breach_in_dry_run_doesnt_block_exchange() {
+        assert!(!check_position_breach(true));
+    }
+
+    #[tokio::test]
+    async fn daily_loss_baseline_is_set_on_creation() {
+        let context = create_context(false);
+        let exchange_account_id = &context.exchange.exchange_account_id;
+
+        let day_start_pnl = context.risk_manager.day_start_pnl.lock();
+        assert_eq!(
+            day_start_pnl.get(exchange_account_id).map(|(_, pnl)| *pnl),
+            Some(dec!(0))
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::{self, Debug, Formatter};
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Credential that is zeroed in memory on drop.
+/// It is printed as `***` by `Debug` and doesn't implement `Display`, so it can't be logged accidentally.
+/// Value is serialized as is because settings are saved to credentials file, use
+/// `config::redact_settings` for settings that are shown to user.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret::new(value.to_owned())
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_does_not_show_value() {
+        let secret = Secret::from("very_secret_value");
+
+        assert_eq!(format!("{:?}", secret), "Secret(***)");
+        assert_eq!(secret.expose(), "very_secret_value");
+    }
+
+    #[test]
+    fn serialized_transparently() {
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Credentials {
+            secret_key: Secret,
+        }
+
+        let credentials: Credentials = toml::from_str(r#"secret_key = "value""#).expect("in test");
+        assert_eq!(credentials.secret_key.expose(), "value");
+        assert_eq!(
+            toml::to_string(&credentials).expect("in test"),
+            "secret_key = \"value\"\n"
+        );
+    }
+}
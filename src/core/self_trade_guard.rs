@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use futures::FutureExt;
+use itertools::Itertools;
+use log::warn;
+
+use crate::core::exchanges::common::{CurrencyPair, Price};
+use crate::core::exchanges::general::exchange::Exchange;
+use crate::core::explanation::Explanation;
+use crate::core::infrastructure::spawn_future;
+use crate::core::lifecycle::cancellation_token::CancellationToken;
+use crate::core::orders::order::{OrderSide, OrderStatus};
+use crate::core::orders::pool::OrderRef;
+use crate::core::settings::SelfTradePrevention;
+
+/// Engine-wide check that new order wouldn't be matched by our own resting orders
+/// on the same exchange account and currency pair, whichever strategy created them
+pub struct SelfTradeGuard {
+    mode: SelfTradePrevention,
+}
+
+impl SelfTradeGuard {
+    pub fn new(mode: SelfTradePrevention) -> Self {
+        Self { mode }
+    }
+
+    /// Returns true if order can be created. Otherwise reason is added to explanation
+    /// and crossing resting orders are cancelled if it is needed by settings
+    pub fn check_order(
+        &self,
+        exchange: &Arc<Exchange>,
+        currency_pair: &CurrencyPair,
+        side: OrderSide,
+        price: Price,
+        explanation: &mut Explanation,
+    ) -> bool {
+        let crossing_orders = exchange
+            .orders
+            .find_crossing_orders(currency_pair, side, price);
+        if crossing_orders.is_empty() {
+            return true;
+        }
+
+        let msg = crossing_reason(exchange, currency_pair, side, price, &crossing_orders);
+        warn!("{}", msg);
+        explanation.add_reason(msg);
+
+        if self.mode == SelfTradePrevention::CancelResting {
+            for order in crossing_orders {
+                if order.status() == OrderStatus::Canceling {
+                    continue;
+                }
+
+                let exchange = exchange.clone();
+                let action = async move {
+                    exchange
+                        .wait_cancel_order(order, None, true, CancellationToken::default())
+                        .await
+                };
+                spawn_future(
+                    "Cancel resting order for self-trade prevention",
+                    true,
+                    action.boxed(),
+                );
+            }
+        }
+
+        false
+    }
+
+    /// Reason to reject order crossing own resting orders. Unlike `check_order` resting orders
+    /// aren't cancelled, so it fits orders created by operator
+    pub fn find_crossing_reason(
+        &self,
+        exchange: &Exchange,
+        currency_pair: &CurrencyPair,
+        side: OrderSide,
+        price: Price,
+    ) -> Option<String> {
+        let crossing_orders = exchange
+            .orders
+            .find_crossing_orders(currency_pair, side, price);
+        if crossing_orders.is_empty() {
+            return None;
+        }
+
+        Some(crossing_reason(
+            exchange,
+            currency_pair,
+            side,
+            price,
+            &crossing_orders,
+        ))
+    }
+}
+
+fn crossing_reason(
+    exchange: &Exchange,
+    currency_pair: &CurrencyPair,
+    side: OrderSide,
+    price: Price,
+    crossing_orders: &[OrderRef],
+) -> String {
+    format!(
+        "New {} order with price {} on {} {} would cross own orders: {}",
+        side,
+        price,
+        exchange.exchange_account_id,
+        currency_pair,
+        crossing_orders
+            .iter()
+            .map(|order| format!("{} ({})", order.client_order_id(), order.price()))
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::core::exchanges::general::test_helper::get_test_exchange;
+    use crate::core::orders::order::{ClientOrderId, OrderExecutionType, OrderHeader, OrderType};
+
+    fn add_resting_order(exchange: &Exchange, side: OrderSide, price: Price) -> OrderRef {
+        let header = OrderHeader::new(
+            ClientOrderId::unique_id(),
+            Utc::now(),
+            exchange.exchange_account_id.clone(),
+            currency_pair(),
+            OrderType::Limit,
+            side,
+            dec!(1),
+            OrderExecutionType::None,
+            None,
+            None,
+            "test".to_owned(),
+        );
+        let order = exchange.orders.add_simple_initial(header, Some(price));
+        order.fn_mut(|order| order.set_status(OrderStatus::Created, Utc::now()));
+
+        order
+    }
+
+    fn currency_pair() -> CurrencyPair {
+        CurrencyPair::from_codes("phb".into(), "btc".into())
+    }
+
+    fn is_cancellation_started(order: &OrderRef) -> bool {
+        order.fn_ref(|order| order.internal_props.is_canceling_from_wait_cancel_order)
+    }
+
+    #[tokio::test]
+    async fn not_crossing_order_is_allowed() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let _resting_sell = add_resting_order(&exchange, OrderSide::Sell, dec!(11));
+        let guard = SelfTradeGuard::new(SelfTradePrevention::CancelResting);
+
+        let mut explanation = Explanation::default();
+        let is_allowed = guard.check_order(
+            &exchange,
+            &currency_pair(),
+            OrderSide::Buy,
+            dec!(10),
+            &mut explanation,
+        );
+
+        assert!(is_allowed);
+        assert!(explanation.reasons().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reject_new_keeps_resting_orders() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let resting_sell = add_resting_order(&exchange, OrderSide::Sell, dec!(10));
+        let guard = SelfTradeGuard::new(SelfTradePrevention::RejectNew);
+
+        let mut explanation = Explanation::default();
+        let is_allowed = guard.check_order(
+            &exchange,
+            &currency_pair(),
+            OrderSide::Buy,
+            dec!(10.5),
+            &mut explanation,
+        );
+
+        assert!(!is_allowed);
+        assert_eq!(explanation.reasons().len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!is_cancellation_started(&resting_sell));
+    }
+
+    #[tokio::test]
+    async fn find_crossing_reason_keeps_resting_orders() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let resting_sell = add_resting_order(&exchange, OrderSide::Sell, dec!(10));
+        let guard = SelfTradeGuard::new(SelfTradePrevention::CancelResting);
+
+        let not_crossing =
+            guard.find_crossing_reason(&exchange, &currency_pair(), OrderSide::Buy, dec!(9));
+        assert_eq!(not_crossing, None);
+
+        let reason =
+            guard.find_crossing_reason(&exchange, &currency_pair(), OrderSide::Buy, dec!(10.5));
+        assert!(reason.is_some());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!is_cancellation_started(&resting_sell));
+    }
+
+    #[tokio::test]
+    async fn cancel_resting_cancels_crossing_orders() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let resting_sell = add_resting_order(&exchange, OrderSide::Sell, dec!(10));
+        let not_crossing_sell = add_resting_order(&exchange, OrderSide::Sell, dec!(11));
+        let guard = SelfTradeGuard::new(SelfTradePrevention::CancelResting);
+
+        let mut explanation = Explanation::default();
+        let is_allowed = guard.check_order(
+            &exchange,
+            &currency_pair(),
+            OrderSide::Buy,
+            dec!(10.5),
+            &mut explanation,
+        );
+
+        assert!(!is_allowed);
+        assert_eq!(explanation.reasons().len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(is_cancellation_started(&resting_sell));
+        assert!(!is_cancellation_started(&not_crossing_sell));
+    }
+}
@@ -1,35 +1,135 @@
 use super::{
     nothing_to_do,
-    orders::{event::OrderEventType, order::ClientOrderId},
+    orders::{
+        event::OrderEventType,
+        order::{ClientOrderId, OrderSnapshot},
+    },
 };
 use anyhow::{Context, Result};
 use futures::FutureExt;
 use log::error;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 
 use parking_lot::{Mutex, RwLock};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use tokio::sync::broadcast;
 
 use super::{
     exchanges::{
-        common::{Amount, Price, TradePlaceAccount},
+        common::{Amount, ExchangeAccountId, Price, TradePlaceAccount},
         events::ExchangeEvent,
     },
     infrastructure::spawn_future,
+    orders::{order::OrderStatus, pool::OrderRef},
+    DateTime,
 };
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+// Percentiles are calculated by last samples only
+const LATENCY_SAMPLES_MAX_COUNT: usize = 10_000;
+
+/// Last latency samples with percentiles calculation
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    samples_ms: VecDeque<u64>,
+    total_count: u64,
+    total_sum_ms: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    pub count: u64,
+    /// Sum of all registered latencies, not only of last samples
+    pub sum_ms: u64,
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn add(&mut self, latency: Duration) {
+        if self.samples_ms.len() == LATENCY_SAMPLES_MAX_COUNT {
+            let _ = self.samples_ms.pop_front();
+        }
+
+        let latency_ms = latency.as_millis() as u64;
+        self.samples_ms.push_back(latency_ms);
+        self.total_count += 1;
+        self.total_sum_ms += latency_ms;
+    }
+
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        let mut sorted_samples: Vec<_> = self.samples_ms.iter().copied().collect();
+        sorted_samples.sort_unstable();
+
+        // Nearest-rank method
+        let percentile = |rate: f64| {
+            let rank = (rate * sorted_samples.len() as f64).ceil() as usize;
+            sorted_samples.get(rank.max(1) - 1).copied()
+        };
+
+        LatencyPercentiles {
+            count: self.total_count,
+            sum_ms: self.total_sum_ms,
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: sorted_samples.last().copied(),
+        }
+    }
+}
+
+impl Serialize for LatencyHistogram {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.percentiles().serialize(serializer)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExchangeLatencyStatistic {
+    create_order_ack: LatencyHistogram,
+    create_order_first_fill: LatencyHistogram,
+    cancel_order_ack: LatencyHistogram,
+    websocket_message_lag: LatencyHistogram,
+    rest_request: LatencyHistogram,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ExchangeLatencyPercentiles {
+    pub create_order_ack: LatencyPercentiles,
+    pub create_order_first_fill: LatencyPercentiles,
+    pub cancel_order_ack: LatencyPercentiles,
+    pub websocket_message_lag: LatencyPercentiles,
+    pub rest_request: LatencyPercentiles,
+}
+
+impl ExchangeLatencyStatistic {
+    pub fn percentiles(&self) -> ExchangeLatencyPercentiles {
+        ExchangeLatencyPercentiles {
+            create_order_ack: self.create_order_ack.percentiles(),
+            create_order_first_fill: self.create_order_first_fill.percentiles(),
+            cancel_order_ack: self.cancel_order_ack.percentiles(),
+            websocket_message_lag: self.websocket_message_lag.percentiles(),
+            rest_request: self.rest_request.percentiles(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct TradePlaceAccountStatistic {
-    opened_orders_count: u64,
-    canceled_orders_count: u64,
-    partially_filled_orders_count: u64,
-    fully_filled_orders_count: u64,
+    pub(crate) opened_orders_count: u64,
+    pub(crate) canceled_orders_count: u64,
+    pub(crate) partially_filled_orders_count: u64,
+    pub(crate) fully_filled_orders_count: u64,
     // Calculated only for completely filled orders
-    summary_filled_amount: Amount,
+    pub(crate) summary_filled_amount: Amount,
     // Calculated only for completely filled orders
-    summary_commission: Amount,
+    pub(crate) summary_commission: Amount,
 }
 
 impl TradePlaceAccountStatistic {
@@ -79,10 +179,11 @@ impl DispositionExecutorStatistic {
     }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+#[derive(Default, Debug, Serialize)]
 pub(crate) struct StatisticServiceState {
     trade_place_stats: RwLock<HashMap<TradePlaceAccount, TradePlaceAccountStatistic>>,
     disposition_executor_stats: Mutex<DispositionExecutorStatistic>,
+    latency_stats: RwLock<HashMap<ExchangeAccountId, ExchangeLatencyStatistic>>,
 }
 
 impl StatisticServiceState {
@@ -90,9 +191,23 @@ impl StatisticServiceState {
         Self {
             trade_place_stats: Default::default(),
             disposition_executor_stats: Default::default(),
+            latency_stats: Default::default(),
         }
     }
 
+    fn register_latency(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+        latency: Duration,
+        get_histogram: impl FnOnce(&mut ExchangeLatencyStatistic) -> &mut LatencyHistogram,
+    ) {
+        let mut latency_stats = self.latency_stats.write();
+        let exchange_latency_stats = latency_stats
+            .entry(exchange_account_id.clone())
+            .or_default();
+        get_histogram(exchange_latency_stats).add(latency);
+    }
+
     pub(crate) fn register_created_order(&self, trade_place_account: &TradePlaceAccount) {
         self.trade_place_stats
             .write()
@@ -242,6 +357,130 @@ impl StatisticService {
     pub(crate) fn register_skipped_event(&self) {
         self.statistic_service_state.register_skipped_event();
     }
+
+    pub fn get_trade_place_statistics(
+        &self,
+    ) -> HashMap<TradePlaceAccount, TradePlaceAccountStatistic> {
+        self.statistic_service_state
+            .trade_place_stats
+            .read()
+            .clone()
+    }
+
+    pub fn get_skipped_events_amount(&self) -> u64 {
+        self.statistic_service_state
+            .disposition_executor_stats
+            .lock()
+            .skipped_events_amount
+    }
+
+    pub fn get_all_latency_percentiles(
+        &self,
+    ) -> HashMap<ExchangeAccountId, ExchangeLatencyPercentiles> {
+        self.statistic_service_state
+            .latency_stats
+            .read()
+            .iter()
+            .map(|(exchange_account_id, x)| (exchange_account_id.clone(), x.percentiles()))
+            .collect()
+    }
+
+    pub fn get_latency_percentiles(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+    ) -> Option<ExchangeLatencyPercentiles> {
+        self.statistic_service_state
+            .latency_stats
+            .read()
+            .get(exchange_account_id)
+            .map(|x| x.percentiles())
+    }
+
+    pub(crate) fn register_websocket_message_lag(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+        lag: Duration,
+    ) {
+        self.statistic_service_state
+            .register_latency(exchange_account_id, lag, |x| &mut x.websocket_message_lag);
+    }
+
+    pub(crate) fn register_rest_request_latency(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+        latency: Duration,
+    ) {
+        self.statistic_service_state
+            .register_latency(exchange_account_id, latency, |x| &mut x.rest_request);
+    }
+
+    fn register_order_latency(
+        &self,
+        exchange_account_id: &ExchangeAccountId,
+        start_time: Option<DateTime>,
+        end_time: Option<DateTime>,
+        get_histogram: impl FnOnce(&mut ExchangeLatencyStatistic) -> &mut LatencyHistogram,
+    ) {
+        if let (Some(start_time), Some(end_time)) = (start_time, end_time) {
+            // Negative latency is possible only because of clock adjustment
+            if let Ok(latency) = (end_time - start_time).to_std() {
+                self.statistic_service_state.register_latency(
+                    exchange_account_id,
+                    latency,
+                    get_histogram,
+                );
+            }
+        }
+    }
+
+    /// Latency between `Exchange::create_order` call and order creation acknowledgement
+    fn register_create_order_latency(&self, order: &OrderRef) {
+        let (creating_time, created_time) = order.fn_ref(|order| {
+            (
+                order.status_history.first_time_of(OrderStatus::Creating),
+                order.status_history.first_time_of(OrderStatus::Created),
+            )
+        });
+
+        self.register_order_latency(
+            &order.exchange_account_id(),
+            creating_time,
+            created_time,
+            |x| &mut x.create_order_ack,
+        );
+    }
+
+    fn register_first_fill_latency(&self, cloned_order: &OrderSnapshot) {
+        if cloned_order.fills.fills.len() != 1 {
+            return;
+        }
+
+        self.register_order_latency(
+            &cloned_order.header.exchange_account_id,
+            cloned_order
+                .status_history
+                .first_time_of(OrderStatus::Creating),
+            cloned_order.fills.last_fill_received_time(),
+            |x| &mut x.create_order_first_fill,
+        );
+    }
+
+    /// Latency between last cancellation request and cancellation acknowledgement
+    fn register_cancel_order_latency(&self, order: &OrderRef) {
+        let (canceling_time, canceled_time) = order.fn_ref(|order| {
+            (
+                order.status_history.last_time_of(OrderStatus::Canceling),
+                order.status_history.last_time_of(OrderStatus::Canceled),
+            )
+        });
+
+        self.register_order_latency(
+            &order.exchange_account_id(),
+            canceling_time,
+            canceled_time,
+            |x| &mut x.cancel_order_ack,
+        );
+    }
 }
 
 pub struct StatisticEventHandler {
@@ -288,13 +527,16 @@ impl StatisticEventHandler {
                 match order_event.event_type {
                     OrderEventType::CreateOrderSucceeded => {
                         self.stats.register_created_order(&trade_place_account);
+                        self.stats.register_create_order_latency(&order_event.order);
                     }
                     OrderEventType::CancelOrderSucceeded => {
                         let client_order_id = order_event.order.client_order_id();
                         self.stats
                             .register_canceled_order(&trade_place_account, &client_order_id);
+                        self.stats.register_cancel_order_latency(&order_event.order);
                     }
                     OrderEventType::OrderFilled { cloned_order } => {
+                        self.stats.register_first_fill_latency(&cloned_order);
                         self.stats.register_partially_filled_order(
                             &trade_place_account,
                             &cloned_order.header.client_order_id,
@@ -326,3 +568,58 @@ impl StatisticEventHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(samples_ms: impl IntoIterator<Item = u64>) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::default();
+        for sample in samples_ms {
+            histogram.add(Duration::from_millis(sample));
+        }
+        histogram
+    }
+
+    #[test]
+    fn empty_histogram_percentiles() {
+        assert_eq!(
+            LatencyHistogram::default().percentiles(),
+            LatencyPercentiles::default()
+        );
+    }
+
+    #[test]
+    fn histogram_percentiles() {
+        let percentiles = histogram((1..=100).rev()).percentiles();
+
+        assert_eq!(
+            percentiles,
+            LatencyPercentiles {
+                count: 100,
+                sum_ms: 5050,
+                p50_ms: Some(50),
+                p90_ms: Some(90),
+                p99_ms: Some(99),
+                max_ms: Some(100),
+            }
+        );
+    }
+
+    #[test]
+    fn histogram_keeps_only_last_samples() {
+        let histogram = histogram(
+            std::iter::repeat(1_000)
+                .take(10)
+                .chain(std::iter::repeat(1).take(LATENCY_SAMPLES_MAX_COUNT)),
+        );
+
+        let percentiles = histogram.percentiles();
+        assert_eq!(percentiles.count, LATENCY_SAMPLES_MAX_COUNT as u64 + 10);
+        assert_eq!(
+            percentiles.sum_ms,
+            10 * 1_000 + LATENCY_SAMPLES_MAX_COUNT as u64
+        );
+        assert_eq!(percentiles.max_ms, Some(1));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod cli;
 #[allow(dead_code)]
 pub mod core;
 pub mod rest_api;
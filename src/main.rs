@@ -1,61 +1,60 @@
 use anyhow::Result;
+use mmb_lib::cli::{execute_command, parse_args, USAGE};
 use mmb_lib::core::settings::BaseStrategySettings;
 use mmb_lib::core::{
-    config::CONFIG_PATH,
-    config::CREDENTIALS_PATH,
     exchanges::common::{Amount, CurrencyPair, ExchangeAccountId},
-    lifecycle::launcher::{launch_trading_engine, EngineBuildConfig, InitSettings},
+    lifecycle::launcher::EngineBuildConfig,
 };
 use mmb_lib::strategies::disposition_strategy::ExampleStrategy;
-use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
-pub struct ExampleStrategySettings {}
+pub struct ExampleStrategySettings {
+    pub exchange_account_id: ExchangeAccountId,
+    pub currency_pair: CurrencyPair,
+    pub max_amount: Amount,
+    #[serde(default)]
+    pub spread: Amount,
+}
 
 impl BaseStrategySettings for ExampleStrategySettings {
     fn exchange_account_id(&self) -> ExchangeAccountId {
-        "Binance0"
-            .parse()
-            .expect("Binance should be specified for example strategy")
+        self.exchange_account_id.clone()
     }
 
     fn currency_pair(&self) -> CurrencyPair {
-        CurrencyPair::from_codes("eos".into(), "btc".into())
+        self.currency_pair.clone()
     }
 
     fn max_amount(&self) -> Amount {
-        dec!(1)
+        self.max_amount
     }
 }
 
-#[allow(dead_code)]
 #[actix_web::main]
 async fn main() -> Result<()> {
-    let engine_config = EngineBuildConfig::standard();
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    let init_settings = InitSettings::<ExampleStrategySettings>::Load(
-        CONFIG_PATH.to_owned(),
-        CREDENTIALS_PATH.to_owned(),
-    );
-
-    let engine = launch_trading_engine(&engine_config, init_settings, |settings| {
-        Box::new(ExampleStrategy::new(
-            settings.strategy.exchange_account_id().clone(),
-            settings.strategy.currency_pair().clone(),
-        ))
-    })
-    .await?;
-
-    // let ctx = engine.context();
-    // let _ = tokio::spawn(async move {
-    //     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-    //     ctx.application_manager
-    //         .clone()
-    //         .spawn_graceful_shutdown("test".to_owned());
-    // });
-
-    engine.run().await;
+    let engine_config = EngineBuildConfig::standard();
+    let is_succeeded =
+        execute_command::<ExampleStrategySettings>(command, &engine_config, |settings| {
+            Box::new(ExampleStrategy::new(
+                settings.strategy.exchange_account_id(),
+                settings.strategy.currency_pair(),
+                settings.strategy.spread,
+            ))
+        })
+        .await?;
+
+    if !is_succeeded {
+        std::process::exit(1);
+    }
 
     Ok(())
 }
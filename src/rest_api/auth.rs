@@ -0,0 +1,461 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, HeaderMap, Method};
+use actix_web::{error, Error, HttpMessage};
+use anyhow::{bail, Context, Result};
+use bytes::{Bytes, BytesMut};
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::core::settings::{ControlPanelApiKey, ControlPanelRole};
+
+pub const KEY_ID_HEADER: &str = "X-MMB-Key";
+pub const TIMESTAMP_HEADER: &str = "X-MMB-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-MMB-Signature";
+
+/// Max difference between signed request timestamp and server time
+const MAX_TIMESTAMP_DIFF_MS: i64 = 30_000;
+const MAX_SIGNED_BODY_SIZE: usize = 1024 * 1024;
+
+/// Authenticates control panel requests with bearer token or HMAC signature and checks role of api key.
+/// Bearer token is api key secret: `Authorization: Bearer <secret>`.
+/// Signed request has headers `X-MMB-Key` with key id, `X-MMB-Timestamp` with unix time in milliseconds
+/// and `X-MMB-Signature` with hex encoded HMAC-SHA256 of `{timestamp}{method}{path_and_query}{body}`.
+/// Body of GET, HEAD and websocket upgrade requests isn't read, so it's signed as empty.
+/// Without api keys requests get read-only role unless authentication is disabled explicitly.
+pub(super) struct Authentication {
+    api_keys: Arc<Vec<ControlPanelApiKey>>,
+    allow_unauthenticated: bool,
+}
+
+impl Authentication {
+    pub(super) fn new(api_keys: Arc<Vec<ControlPanelApiKey>>, allow_unauthenticated: bool) -> Self {
+        Self {
+            api_keys,
+            allow_unauthenticated,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = AuthenticationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware {
+            service: Rc::new(service),
+            api_keys: self.api_keys.clone(),
+            allow_unauthenticated: self.allow_unauthenticated,
+        }))
+    }
+}
+
+pub(super) struct AuthenticationMiddleware<S> {
+    service: Rc<S>,
+    api_keys: Arc<Vec<ControlPanelApiKey>>,
+    allow_unauthenticated: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let api_keys = self.api_keys.clone();
+        let allow_unauthenticated = self.allow_unauthenticated;
+
+        Box::pin(async move {
+            if api_keys.is_empty() {
+                if !allow_unauthenticated
+                    && required_role(req.method(), req.path()) > ControlPanelRole::ReadOnly
+                {
+                    return Err(error::ErrorUnauthorized(
+                        "Control panel api keys aren't configured",
+                    ));
+                }
+
+                return service.call(req).await;
+            }
+
+            let role = match bearer_token(req.headers()) {
+                Some(token) => authenticate_by_token(&api_keys, token),
+                None if !is_body_signed(&req) => authenticate_by_signature(
+                    &api_keys,
+                    req.headers(),
+                    req.method(),
+                    &path_and_query(&req),
+                    b"",
+                    Utc::now().timestamp_millis(),
+                ),
+                None => {
+                    let body = read_body(&mut req).await?;
+                    let role = authenticate_by_signature(
+                        &api_keys,
+                        req.headers(),
+                        req.method(),
+                        &path_and_query(&req),
+                        &body,
+                        Utc::now().timestamp_millis(),
+                    );
+                    req.set_payload(Payload::Stream(Box::pin(stream::once(async { Ok(body) }))));
+                    role
+                }
+            }
+            .map_err(error::ErrorUnauthorized)?;
+
+            let required_role = required_role(req.method(), req.path());
+            if role < required_role {
+                return Err(error::ErrorForbidden(format!(
+                    "Role {:?} is required",
+                    required_role
+                )));
+            }
+
+            service.call(req).await
+        })
+    }
+}
+
+fn required_role(method: &Method, path: &str) -> ControlPanelRole {
+    match *method {
+        // Config contains credentials
+        Method::GET if path == "/config" => ControlPanelRole::Admin,
+        Method::GET | Method::HEAD => ControlPanelRole::ReadOnly,
+        _ => ControlPanelRole::Admin,
+    }
+}
+
+/// Payload of websocket connection is a stream of frames which never ends,
+/// so it can't be read before passing request to handler
+fn is_body_signed(req: &ServiceRequest) -> bool {
+    let is_upgrade = req.headers().contains_key(header::UPGRADE);
+    !is_upgrade && !matches!(*req.method(), Method::GET | Method::HEAD)
+}
+
+fn path_and_query(req: &ServiceRequest) -> String {
+    match req.query_string() {
+        "" => req.path().to_owned(),
+        query => format!("{}?{}", req.path(), query),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+async fn read_body(req: &mut ServiceRequest) -> Result<Bytes, Error> {
+    let mut payload = req.take_payload();
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > MAX_SIGNED_BODY_SIZE {
+            return Err(error::ErrorPayloadTooLarge("Request body is too large"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+fn authenticate_by_token(api_keys: &[ControlPanelApiKey], token: &str) -> Result<ControlPanelRole> {
+    api_keys
+        .iter()
+        .find(|api_key| constant_time_eq(api_key.secret.expose().as_bytes(), token.as_bytes()))
+        .map(|api_key| api_key.role)
+        .context("Invalid bearer token")
+}
+
+fn authenticate_by_signature(
+    api_keys: &[ControlPanelApiKey],
+    headers: &HeaderMap,
+    method: &Method,
+    path_and_query: &str,
+    body: &[u8],
+    now_ms: i64,
+) -> Result<ControlPanelRole> {
+    let get_header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .with_context(|| format!("Missing bearer token or {} header", name))
+    };
+
+    let key_id = get_header(KEY_ID_HEADER)?;
+    let timestamp = get_header(TIMESTAMP_HEADER)?;
+    let signature = get_header(SIGNATURE_HEADER)?;
+
+    let timestamp_ms: i64 = timestamp
+        .parse()
+        .with_context(|| format!("Unable to parse {} header", TIMESTAMP_HEADER))?;
+    if (now_ms - timestamp_ms).abs() > MAX_TIMESTAMP_DIFF_MS {
+        bail!("Request timestamp {} is expired", timestamp_ms);
+    }
+
+    let api_key = api_keys
+        .iter()
+        .find(|api_key| api_key.key_id == key_id)
+        .with_context(|| format!("Unknown api key {}", key_id))?;
+
+    let signature = hex::decode(signature).context("Unable to decode signature")?;
+    sign(
+        api_key.secret.expose(),
+        timestamp,
+        method,
+        path_and_query,
+        body,
+    )?
+    .verify(&signature)
+    .context("Invalid signature")?;
+
+    Ok(api_key.role)
+}
+
+fn sign(
+    secret: &str,
+    timestamp: &str,
+    method: &Method,
+    path_and_query: &str,
+    body: &[u8],
+) -> Result<Hmac<Sha256>> {
+    let mut hmac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).context("Unable to calculate hmac")?;
+    hmac.update(timestamp.as_bytes());
+    hmac.update(method.as_str().as_bytes());
+    hmac.update(path_and_query.as_bytes());
+    hmac.update(body);
+
+    Ok(hmac)
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0, |acc, (left, right)| acc | (left ^ right))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{HeaderValue, StatusCode};
+    use actix_web::{test, web, App};
+
+    use crate::rest_api::endpoints;
+    use crate::rest_api::event_feed::EventFeed;
+
+    const NOW_MS: i64 = 1_600_000_000_000;
+
+    fn api_keys() -> Vec<ControlPanelApiKey> {
+        vec![
+            ControlPanelApiKey {
+                key_id: "monitoring".to_owned(),
+                secret: "read_secret".into(),
+                role: ControlPanelRole::ReadOnly,
+            },
+            ControlPanelApiKey {
+                key_id: "operator".to_owned(),
+                secret: "admin_secret".into(),
+                role: ControlPanelRole::Admin,
+            },
+        ]
+    }
+
+    fn signed_headers(key_id: &str, secret: &str, timestamp_ms: i64, body: &[u8]) -> HeaderMap {
+        signed_request_headers(key_id, secret, timestamp_ms, &Method::POST, "/stop", body)
+    }
+
+    fn signed_request_headers(
+        key_id: &str,
+        secret: &str,
+        timestamp_ms: i64,
+        method: &Method,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> HeaderMap {
+        let timestamp = timestamp_ms.to_string();
+        let signature = sign(secret, &timestamp, method, path_and_query, body)
+            .expect("in test")
+            .finalize()
+            .into_bytes();
+
+        let mut headers = HeaderMap::new();
+        let mut insert = |name, value: String| {
+            headers.insert(
+                actix_web::http::HeaderName::from_static(name),
+                HeaderValue::from_str(&value).expect("in test"),
+            )
+        };
+        insert("x-mmb-key", key_id.to_owned());
+        insert("x-mmb-timestamp", timestamp);
+        insert("x-mmb-signature", hex::encode(signature));
+
+        headers
+    }
+
+    #[test]
+    fn token_authentication() {
+        let api_keys = api_keys();
+
+        assert_eq!(
+            authenticate_by_token(&api_keys, "admin_secret").expect("in test"),
+            ControlPanelRole::Admin
+        );
+        assert_eq!(
+            authenticate_by_token(&api_keys, "read_secret").expect("in test"),
+            ControlPanelRole::ReadOnly
+        );
+        assert!(authenticate_by_token(&api_keys, "admin_secre").is_err());
+    }
+
+    #[test]
+    fn signature_authentication() {
+        let api_keys = api_keys();
+        let headers = signed_headers("operator", "admin_secret", NOW_MS, b"body");
+
+        let role = authenticate_by_signature(
+            &api_keys,
+            &headers,
+            &Method::POST,
+            "/stop",
+            b"body",
+            NOW_MS + 1000,
+        )
+        .expect("in test");
+
+        assert_eq!(role, ControlPanelRole::Admin);
+    }
+
+    #[test]
+    fn signature_authentication_fails_for_changed_body() {
+        let api_keys = api_keys();
+        let headers = signed_headers("operator", "admin_secret", NOW_MS, b"body");
+
+        let result = authenticate_by_signature(
+            &api_keys,
+            &headers,
+            &Method::POST,
+            "/stop",
+            b"other body",
+            NOW_MS,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn signature_authentication_fails_for_expired_timestamp() {
+        let api_keys = api_keys();
+        let headers = signed_headers("operator", "admin_secret", NOW_MS, b"");
+
+        let result = authenticate_by_signature(
+            &api_keys,
+            &headers,
+            &Method::POST,
+            "/stop",
+            b"",
+            NOW_MS + MAX_TIMESTAMP_DIFF_MS + 1,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn required_roles() {
+        assert_eq!(
+            required_role(&Method::GET, "/stats"),
+            ControlPanelRole::ReadOnly
+        );
+        assert_eq!(
+            required_role(&Method::GET, "/config"),
+            ControlPanelRole::Admin
+        );
+        assert_eq!(
+            required_role(&Method::POST, "/stop"),
+            ControlPanelRole::Admin
+        );
+    }
+
+    #[actix_rt::test]
+    async fn signed_events_handshake() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication::new(Arc::new(api_keys()), false))
+                .app_data(web::Data::new(EventFeed::new()))
+                .service(endpoints::events),
+        )
+        .await;
+
+        let headers = signed_request_headers(
+            "monitoring",
+            "read_secret",
+            Utc::now().timestamp_millis(),
+            &Method::GET,
+            "/events?event_types=order",
+            b"",
+        );
+        let mut request = test::TestRequest::get()
+            .uri("/events?event_types=order")
+            .insert_header((header::CONNECTION, "upgrade"))
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+            .insert_header((header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="));
+        for (name, value) in headers.iter() {
+            request = request.insert_header((name.clone(), value.clone()));
+        }
+        // Websocket frames sent by client mustn't be read as signed body
+        let request = request.set_payload("frames").to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+    }
+
+    #[actix_rt::test]
+    async fn admin_requests_are_rejected_without_api_keys() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication::new(Arc::new(Vec::new()), false))
+                .service(endpoints::health)
+                .service(endpoints::stop),
+        )
+        .await;
+
+        let request = test::TestRequest::post().uri("/stop").to_request();
+        let error = app.call(request).await.err().expect("in test");
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let request = test::TestRequest::get().uri("/health").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
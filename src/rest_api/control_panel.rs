@@ -1,45 +1,63 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use futures::executor;
-use log::error;
+use log::{error, warn};
 use parking_lot::Mutex;
-use std::{sync::mpsc, sync::mpsc::Sender, sync::Arc, thread};
+use rustls::internal::pemfile;
+use std::{fs::File, io::BufReader, sync::mpsc, sync::mpsc::Sender, sync::Arc, thread};
 
+use super::auth::Authentication;
 use super::endpoints;
+use super::event_feed::EventFeed;
+use super::metrics::EventsChannelMetrics;
 use actix_web::{dev::Server, rt, App, HttpServer};
 use tokio::sync::oneshot;
 
 use crate::core::{
-    lifecycle::{application_manager::ApplicationManager, trading_engine::Service},
+    lifecycle::{
+        settings_updater::SettingsUpdater,
+        trading_engine::{EngineContext, Service},
+    },
+    position_service::PositionService,
+    settings::{ControlPanelSettings, TlsSettings},
     statistic_service::StatisticService,
 };
 use actix_web::web::Data;
 
 pub(crate) struct ControlPanel {
-    address: String,
-    engine_settings: String,
-    application_manager: Arc<ApplicationManager>,
+    settings: ControlPanelSettings,
+    settings_updater: Arc<SettingsUpdater>,
     server_stopper_tx: Arc<Mutex<Option<Sender<()>>>>,
     work_finished_sender: Arc<Mutex<Option<oneshot::Sender<Result<()>>>>>,
     work_finished_receiver: Arc<Mutex<Option<oneshot::Receiver<Result<()>>>>>,
     statistics: Arc<StatisticService>,
+    positions: Arc<PositionService>,
+    engine_context: Arc<EngineContext>,
+    event_feed: Arc<EventFeed>,
+    events_channel_metrics: Arc<EventsChannelMetrics>,
 }
 
 impl ControlPanel {
     pub(crate) fn new(
-        address: &str,
-        engine_settings: String,
-        application_manager: Arc<ApplicationManager>,
+        settings: ControlPanelSettings,
+        settings_updater: Arc<SettingsUpdater>,
         statistics: Arc<StatisticService>,
+        positions: Arc<PositionService>,
+        engine_context: Arc<EngineContext>,
+        event_feed: Arc<EventFeed>,
+        events_channel_metrics: Arc<EventsChannelMetrics>,
     ) -> Arc<Self> {
         let (work_finished_sender, work_finished_receiver) = oneshot::channel();
         Arc::new(Self {
-            address: address.to_owned(),
-            engine_settings,
-            application_manager,
+            settings,
+            settings_updater,
             server_stopper_tx: Arc::new(Mutex::new(None)),
             work_finished_sender: Arc::new(Mutex::new(Some(work_finished_sender))),
             work_finished_receiver: Arc::new(Mutex::new(Some(work_finished_receiver))),
             statistics,
+            positions,
+            engine_context,
+            event_feed,
+            events_channel_metrics,
         })
     }
 
@@ -59,22 +77,60 @@ impl ControlPanel {
     pub(crate) fn start(self: Arc<Self>) -> Result<()> {
         let (server_stopper_tx, server_stopper_rx) = mpsc::channel::<()>();
         *self.server_stopper_tx.lock() = Some(server_stopper_tx.clone());
-        let engine_settings = self.engine_settings.clone();
-        let application_manager = self.application_manager.clone();
+        let settings_updater = self.settings_updater.clone();
+        let application_manager = self.engine_context.application_manager.clone();
         let statistics = self.statistics.clone();
+        let positions = self.positions.clone();
+        let engine_context = self.engine_context.clone();
+        let event_feed = self.event_feed.clone();
+        let events_channel_metrics = self.events_channel_metrics.clone();
+        let api_keys = Arc::new(self.settings.api_keys.clone());
+        let allow_unauthenticated = self.settings.allow_unauthenticated;
+        if api_keys.is_empty() {
+            match allow_unauthenticated {
+                true => warn!("Control panel api keys aren't specified so requests are not authenticated"),
+                false => warn!("Control panel api keys aren't specified so only read-only requests are allowed"),
+            }
+        }
+
         let server = HttpServer::new(move || {
             App::new()
+                .wrap(Authentication::new(api_keys.clone(), allow_unauthenticated))
                 .app_data(Data::new(server_stopper_tx.clone()))
-                .app_data(Data::new(engine_settings.clone()))
+                .app_data(Data::new(settings_updater.clone()))
                 .app_data(Data::new(application_manager.clone()))
                 .app_data(Data::new(statistics.clone()))
+                .app_data(Data::new(positions.clone()))
+                .app_data(Data::new(engine_context.clone()))
+                .app_data(Data::new(engine_context.exchanges.clone()))
+                .app_data(Data::new(event_feed.clone()))
+                .app_data(Data::new(events_channel_metrics.clone()))
                 .service(endpoints::health)
                 .service(endpoints::stop)
                 .service(endpoints::stats)
                 .service(endpoints::get_config)
                 .service(endpoints::set_config)
-        })
-        .bind(&self.address)?
+                .service(endpoints::validate_config)
+                .service(endpoints::update_config)
+                .service(endpoints::get_orders)
+                .service(endpoints::create_order)
+                .service(endpoints::cancel_all_orders)
+                .service(endpoints::get_order)
+                .service(endpoints::cancel_order)
+                .service(endpoints::get_blockers)
+                .service(endpoints::block_exchange)
+                .service(endpoints::unblock_exchange)
+                .service(endpoints::events)
+                .service(endpoints::metrics)
+                .service(endpoints::get_logging_levels)
+                .service(endpoints::update_logging_levels)
+        });
+        let server = match &self.settings.tls {
+            Some(tls_settings) => {
+                server.bind_rustls(&self.settings.address, load_tls_config(tls_settings)?)?
+            }
+            None => server.bind(&self.settings.address)?,
+        }
         .shutdown_timeout(1)
         .workers(1)
         .run();
@@ -118,6 +174,35 @@ impl ControlPanel {
     }
 }
 
+fn load_tls_config(settings: &TlsSettings) -> Result<rustls::ServerConfig> {
+    let open = |path: &str| {
+        File::open(path)
+            .map(BufReader::new)
+            .with_context(|| format!("Unable to open {}", path))
+    };
+
+    let certs = pemfile::certs(&mut open(&settings.cert_path)?)
+        .map_err(|_| anyhow!("Unable to parse certificates from {}", settings.cert_path))?;
+
+    let mut keys = pemfile::pkcs8_private_keys(&mut open(&settings.key_path)?)
+        .map_err(|_| anyhow!("Unable to parse PKCS8 keys from {}", settings.key_path))?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut open(&settings.key_path)?)
+            .map_err(|_| anyhow!("Unable to parse RSA keys from {}", settings.key_path))?;
+    }
+    let key = keys
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Private key not found in {}", settings.key_path))?;
+
+    let mut config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .context("Unable to set TLS certificate")?;
+
+    Ok(config)
+}
+
 impl Service for ControlPanel {
     fn name(&self) -> &str {
         "ControlPanel"
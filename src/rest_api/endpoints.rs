@@ -1,12 +1,44 @@
-use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
-use log::{error, warn};
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse, Responder};
+use actix_web_actors::ws;
+use chrono::Utc;
+use dashmap::DashMap;
+use log::{error, info, warn};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use std::cmp::Reverse;
 use std::sync::{mpsc::Sender, Arc};
+use std::time::Duration;
+
+use super::event_feed::{EventFeed, EventFeedSession, FeedFilter};
+use super::metrics::{render_metrics, EventsChannelMetrics};
 
 use crate::core::{
-    config::save_settings, config::CONFIG_PATH, config::CREDENTIALS_PATH,
-    lifecycle::application_manager::ApplicationManager, statistic_service::StatisticService,
+    config::CONFIG_PATH,
+    config::CREDENTIALS_PATH,
+    config::{prepare_received_settings, save_settings, ReceivedSettings},
+    exchanges::common::{Amount, Price},
+    exchanges::common::{CurrencyPair, ExchangeAccountId},
+    exchanges::exchange_blocker::{BlockReason, BlockType},
+    exchanges::general::currency_pair_metadata::Round,
+    exchanges::general::exchange::Exchange,
+    lifecycle::application_manager::ApplicationManager,
+    lifecycle::cancellation_token::CancellationToken,
+    lifecycle::settings_updater::SettingsUpdater,
+    lifecycle::trading_engine::EngineContext,
+    logger::{get_log_levels, update_log_levels, LogLevelsUpdate},
+    orders::order::{
+        ClientOrderId, OrderCreating, OrderExecutionType, OrderHeader, OrderSide, OrderSnapshot,
+        OrderStatus, OrderType,
+    },
+    position_service::PositionService,
+    risk_manager::RiskCheckOrder,
+    statistic_service::StatisticService,
 };
 
+const DEFAULT_ORDERS_LIMIT: usize = 100;
+const MANUAL_ORDER_STRATEGY_NAME: &str = "manual";
+
 // New endpoints have to be added as a service for actix server. Look at super::control_panel::start_server()
 
 #[get("/health")]
@@ -23,19 +55,43 @@ pub(super) async fn stop(server_stopper_tx: web::Data<Sender<()>>) -> impl Respo
     HttpResponse::Ok().body("ControlPanel turned off")
 }
 
+// Credentials are replaced with `***`
 #[get("/config")]
-pub(super) async fn get_config(engine_settings: web::Data<String>) -> impl Responder {
-    HttpResponse::Ok().body(engine_settings.get_ref())
+pub(super) async fn get_config(
+    settings_updater: web::Data<Arc<SettingsUpdater>>,
+) -> Result<HttpResponse, Error> {
+    let settings = settings_updater.redacted_settings().map_err(|err| {
+        let error_message = format!("Unable to redact settings: {:?}", err);
+        error!("{}", error_message);
+
+        error::ErrorInternalServerError(error_message)
+    })?;
+
+    Ok(HttpResponse::Ok().body(settings))
+}
+
+/// Settings from request body. Credentials redacted by GET /config are replaced with saved ones
+fn get_settings_from_body(body: &web::Bytes) -> Result<ReceivedSettings, Error> {
+    let settings = std::str::from_utf8(body)?;
+
+    prepare_received_settings(settings, CONFIG_PATH, CREDENTIALS_PATH)
+        .map_err(|err| error::ErrorBadRequest(format!("{:#}", err)))
 }
 
 #[post("/config")]
 pub(super) async fn set_config(
     body: web::Bytes,
     application_manager: web::Data<Arc<ApplicationManager>>,
+    settings_updater: web::Data<Arc<SettingsUpdater>>,
 ) -> Result<HttpResponse, Error> {
-    let settings = std::str::from_utf8(&body)?;
+    let settings = get_settings_from_body(&body)?;
 
-    save_settings(settings, CONFIG_PATH, CREDENTIALS_PATH).map_err(|err| {
+    let validation_errors = settings_updater.validate_config(&settings.effective);
+    if !validation_errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({ "errors": validation_errors })));
+    }
+
+    save_settings(&settings.saved, CONFIG_PATH, CREDENTIALS_PATH).map_err(|err| {
         let error_message = format!(
             "Error while trying save new config in set_config endpoint: {}",
             err.to_string()
@@ -53,11 +109,826 @@ pub(super) async fn set_config(
     Ok(HttpResponse::Ok().body("Config was successfully updated. Trading engine stopped"))
 }
 
+// Check settings for POST /config without saving them
+#[post("/config/validate")]
+pub(super) async fn validate_config(
+    body: web::Bytes,
+    settings_updater: web::Data<Arc<SettingsUpdater>>,
+) -> Result<HttpResponse, Error> {
+    let settings = get_settings_from_body(&body)?;
+
+    let errors = settings_updater.validate_config(&settings.effective);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "is_valid": errors.is_empty(),
+        "errors": errors,
+    })))
+}
+
+// Apply new settings to running strategy without engine restart and save them
+#[post("/config/update")]
+pub(super) async fn update_config(
+    body: web::Bytes,
+    settings_updater: web::Data<Arc<SettingsUpdater>>,
+) -> Result<HttpResponse, Error> {
+    let settings = get_settings_from_body(&body)?;
+
+    settings_updater
+        .update_settings(&settings.effective)
+        .await
+        .map_err(|err| {
+            let error_message = format!(
+                "Error while trying apply new config in update_config endpoint: {:?}",
+                err
+            );
+            warn!("{}", error_message);
+
+            error::ErrorBadRequest(error_message)
+        })?;
+
+    save_settings(&settings.saved, CONFIG_PATH, CREDENTIALS_PATH).map_err(|err| {
+        let error_message = format!(
+            "Config was applied but not saved in update_config endpoint: {:?}",
+            err
+        );
+        warn!("{}", error_message);
+
+        error::ErrorInternalServerError(error_message)
+    })?;
+
+    Ok(HttpResponse::Ok().body("Config was successfully applied without engine restart"))
+}
+
 #[get("/stats")]
 pub(super) async fn stats(
     statistics: web::Data<Arc<StatisticService>>,
+    positions: web::Data<Arc<PositionService>>,
 ) -> Result<HttpResponse, Error> {
-    let json_statistic = serde_json::to_string(&statistics.statistic_service_state)?;
+    let mut json_statistic = serde_json::to_value(&statistics.statistic_service_state)?;
+    json_statistic["positions"] = serde_json::to_value(positions.get_statistics())?;
+    let json_statistic = json_statistic.to_string();
 
     Ok(HttpResponse::Ok().body(&json_statistic))
 }
+
+#[derive(Debug, Deserialize)]
+pub(super) struct OrdersFilter {
+    exchange_account_id: Option<ExchangeAccountId>,
+    #[serde(default, deserialize_with = "deserialize_optional_currency_pair")]
+    currency_pair: Option<CurrencyPair>,
+    status: Option<OrderStatus>,
+    strategy_name: Option<String>,
+    /// Return only not finished orders. Otherwise recent finished orders are returned too
+    #[serde(default)]
+    open_only: bool,
+    limit: Option<usize>,
+}
+
+impl OrdersFilter {
+    fn is_matched(&self, order: &OrderSnapshot) -> bool {
+        let header = &order.header;
+        if let Some(currency_pair) = &self.currency_pair {
+            if currency_pair != &header.currency_pair {
+                return false;
+            }
+        }
+
+        if let Some(status) = self.status {
+            if status != order.props.status {
+                return false;
+            }
+        }
+
+        if let Some(strategy_name) = &self.strategy_name {
+            if strategy_name != &header.strategy_name {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Currency pairs are stored in lowercase, but users often type them in uppercase in query string
+fn deserialize_optional_currency_pair<'de, D>(
+    deserializer: D,
+) -> Result<Option<CurrencyPair>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|currency_pair| {
+            CurrencyPair::deserialize(currency_pair.to_lowercase().into_deserializer())
+        })
+        .transpose()
+}
+
+fn get_exchange(
+    exchanges: &DashMap<ExchangeAccountId, Arc<Exchange>>,
+    exchange_account_id: &ExchangeAccountId,
+) -> Result<Arc<Exchange>, Error> {
+    exchanges
+        .get(exchange_account_id)
+        .map(|exchange| exchange.clone())
+        .ok_or_else(|| error::ErrorNotFound(format!("Exchange {} not found", exchange_account_id)))
+}
+
+// Orders from all exchanges sorted from newest to oldest
+#[get("/orders")]
+pub(super) async fn get_orders(
+    filter: web::Query<OrdersFilter>,
+    exchanges: web::Data<DashMap<ExchangeAccountId, Arc<Exchange>>>,
+) -> Result<HttpResponse, Error> {
+    let mut orders = Vec::new();
+    for exchange in exchanges.iter() {
+        if let Some(exchange_account_id) = &filter.exchange_account_id {
+            if &exchange.exchange_account_id != exchange_account_id {
+                continue;
+            }
+        }
+
+        let orders_source = match filter.open_only {
+            true => &exchange.orders.not_finished,
+            false => &exchange.orders.cache_by_client_id,
+        };
+
+        // Only matched orders are cloned because there can be a lot of finished orders in cache
+        orders.extend(
+            orders_source
+                .iter()
+                .filter(|order| order.fn_ref(|order| filter.is_matched(order)))
+                .map(|order| order.deep_clone()),
+        );
+    }
+
+    orders.sort_by_key(|order| Reverse(order.header.init_time));
+    orders.truncate(filter.limit.unwrap_or(DEFAULT_ORDERS_LIMIT));
+
+    Ok(HttpResponse::Ok().json(orders))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ManualOrder {
+    exchange_account_id: ExchangeAccountId,
+    currency_pair: CurrencyPair,
+    side: OrderSide,
+    order_type: OrderType,
+    /// Required for market orders too because it is used for amount and risk checks
+    price: Price,
+    amount: Amount,
+    #[serde(default = "default_execution_type")]
+    execution_type: OrderExecutionType,
+}
+
+fn default_execution_type() -> OrderExecutionType {
+    OrderExecutionType::None
+}
+
+// Create order by operator for example to flatten position by hand.
+// Order passes the same self-trade and risk checks as orders created by strategy,
+// but violations only reject it without cancelling resting orders or blocking exchange
+#[post("/orders")]
+pub(super) async fn create_order(
+    manual_order: web::Json<ManualOrder>,
+    engine_context: web::Data<Arc<EngineContext>>,
+) -> Result<HttpResponse, Error> {
+    if engine_context.app_settings.is_dry_run() {
+        return Err(error::ErrorConflict(
+            "Orders can't be created because engine is started in dry run mode",
+        ));
+    }
+
+    let manual_order = manual_order.into_inner();
+    if !matches!(
+        manual_order.order_type,
+        OrderType::Limit | OrderType::Market
+    ) {
+        return Err(error::ErrorBadRequest(format!(
+            "Only Limit and Market orders can be created manually, but got {:?}",
+            manual_order.order_type
+        )));
+    }
+
+    let exchange = get_exchange(&engine_context.exchanges, &manual_order.exchange_account_id)?;
+    if engine_context
+        .exchange_blocker
+        .is_blocked(&manual_order.exchange_account_id)
+    {
+        return Err(error::ErrorConflict(format!(
+            "Orders can't be created because exchange {} is blocked",
+            manual_order.exchange_account_id
+        )));
+    }
+
+    let currency_pair_metadata = exchange
+        .get_currency_pair_metadata(&manual_order.currency_pair)
+        .map_err(|err| error::ErrorBadRequest(format!("{:?}", err)))?;
+
+    let price = currency_pair_metadata
+        .price_round(manual_order.price, Round::ToNearest)
+        .map_err(|err| error::ErrorBadRequest(format!("Unable to round price: {:?}", err)))?;
+    let amount = currency_pair_metadata
+        .amount_round(manual_order.amount, Round::Floor)
+        .map_err(|err| error::ErrorBadRequest(format!("Unable to round amount: {:?}", err)))?;
+
+    let min_amount = currency_pair_metadata
+        .get_min_amount(price)
+        .map_err(|err| error::ErrorBadRequest(format!("{:?}", err)))?;
+    if amount < min_amount {
+        return Err(error::ErrorBadRequest(format!(
+            "Order amount {} is less than min amount {} for price {}",
+            amount, min_amount, price
+        )));
+    }
+
+    // Checks have no side effects: operator decides what to do with resting orders and blocking
+    if let Some(reason) = engine_context.self_trade_guard.find_crossing_reason(
+        &exchange,
+        &manual_order.currency_pair,
+        manual_order.side,
+        price,
+    ) {
+        return Err(error::ErrorBadRequest(format!(
+            "Order was rejected: {}",
+            reason
+        )));
+    }
+
+    let risk_check_order = RiskCheckOrder {
+        currency_pair: manual_order.currency_pair.clone(),
+        side: manual_order.side,
+        price,
+        amount,
+    };
+    if let Some(violation) = engine_context
+        .risk_manager
+        .find_order_violation(&exchange, &risk_check_order)
+    {
+        return Err(error::ErrorBadRequest(format!(
+            "Order was rejected by risk limit: {}",
+            violation
+        )));
+    }
+
+    let client_order_id = ClientOrderId::unique_id();
+    let order_header = OrderHeader::new(
+        client_order_id.clone(),
+        Utc::now(),
+        manual_order.exchange_account_id.clone(),
+        manual_order.currency_pair.clone(),
+        manual_order.order_type,
+        manual_order.side,
+        amount,
+        manual_order.execution_type,
+        None,
+        None,
+        MANUAL_ORDER_STRATEGY_NAME.to_owned(),
+    );
+    let order_creating = OrderCreating {
+        header: order_header,
+        price,
+    };
+
+    info!("Creating manual order {:?}", order_creating);
+    match exchange
+        .create_order(&order_creating, CancellationToken::default())
+        .await
+    {
+        Ok(order) => Ok(HttpResponse::Ok().json(json!({
+            "client_order_id": client_order_id,
+            "exchange_order_id": order.exchange_order_id(),
+            "status": order.status(),
+        }))),
+        Err(err) => {
+            warn!(
+                "Unable to create manual order {}: {:?}",
+                client_order_id, err
+            );
+
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "client_order_id": client_order_id,
+                "status": OrderStatus::FailedToCreate,
+                "error": format!("{:?}", err),
+            })))
+        }
+    }
+}
+
+#[get("/orders/{exchange_account_id}/{client_order_id}")]
+pub(super) async fn get_order(
+    path: web::Path<(ExchangeAccountId, String)>,
+    exchanges: web::Data<DashMap<ExchangeAccountId, Arc<Exchange>>>,
+) -> Result<HttpResponse, Error> {
+    let (exchange_account_id, client_order_id) = path.into_inner();
+    let exchange = get_exchange(&exchanges, &exchange_account_id)?;
+
+    let order = exchange
+        .orders
+        .cache_by_client_id
+        .get(&ClientOrderId::new(client_order_id.as_str().into()))
+        .map(|order| order.deep_clone())
+        .ok_or_else(|| error::ErrorNotFound(format!("Order {} not found", client_order_id)))?;
+
+    Ok(HttpResponse::Ok().json(order))
+}
+
+#[post("/orders/{exchange_account_id}/{client_order_id}/cancel")]
+pub(super) async fn cancel_order(
+    path: web::Path<(ExchangeAccountId, String)>,
+    exchanges: web::Data<DashMap<ExchangeAccountId, Arc<Exchange>>>,
+) -> Result<HttpResponse, Error> {
+    let (exchange_account_id, client_order_id) = path.into_inner();
+    let exchange = get_exchange(&exchanges, &exchange_account_id)?;
+
+    let order = exchange
+        .orders
+        .cache_by_client_id
+        .get(&ClientOrderId::new(client_order_id.as_str().into()))
+        .map(|order| order.clone())
+        .ok_or_else(|| error::ErrorNotFound(format!("Order {} not found", client_order_id)))?;
+
+    if order.is_finished() {
+        return Err(error::ErrorBadRequest(format!(
+            "Order {} is already finished with status {:?}",
+            client_order_id,
+            order.status()
+        )));
+    }
+
+    exchange
+        .wait_cancel_order(order.clone(), None, true, CancellationToken::default())
+        .await
+        .map_err(|err| {
+            let error_message = format!(
+                "Error while trying cancel order {} in cancel_order endpoint: {:?}",
+                client_order_id, err
+            );
+            warn!("{}", error_message);
+
+            error::ErrorInternalServerError(error_message)
+        })?;
+
+    Ok(HttpResponse::Ok().json(order.deep_clone()))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct CancelAllOrdersParams {
+    currency_pair: CurrencyPair,
+}
+
+#[post("/orders/{exchange_account_id}/cancel_all")]
+pub(super) async fn cancel_all_orders(
+    path: web::Path<ExchangeAccountId>,
+    params: web::Query<CancelAllOrdersParams>,
+    exchanges: web::Data<DashMap<ExchangeAccountId, Arc<Exchange>>>,
+) -> Result<HttpResponse, Error> {
+    let exchange_account_id = path.into_inner();
+    let exchange = get_exchange(&exchanges, &exchange_account_id)?;
+    let currency_pair = params.into_inner().currency_pair;
+
+    exchange
+        .cancel_all_orders(currency_pair.clone())
+        .await
+        .map_err(|err| {
+            let error_message = format!(
+                "Error while trying cancel all orders for {} on {} in cancel_all_orders endpoint: {:?}",
+                currency_pair, exchange_account_id, err
+            );
+            warn!("{}", error_message);
+
+            error::ErrorInternalServerError(error_message)
+        })?;
+
+    Ok(HttpResponse::Ok().body(format!(
+        "All orders for {} on {} were cancelled",
+        currency_pair, exchange_account_id
+    )))
+}
+
+#[get("/logging/levels")]
+pub(super) async fn get_logging_levels() -> impl Responder {
+    HttpResponse::Ok().json(get_log_levels())
+}
+
+// Levels are changed until restart, config isn't updated
+#[post("/logging/levels")]
+pub(super) async fn update_logging_levels(update: web::Json<LogLevelsUpdate>) -> impl Responder {
+    let levels = update_log_levels(update.into_inner());
+    info!("Log levels are changed: {:?}", levels);
+
+    HttpResponse::Ok().json(levels)
+}
+
+#[get("/blockers")]
+pub(super) async fn get_blockers(
+    engine_context: web::Data<Arc<EngineContext>>,
+) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().json(engine_context.exchange_blocker.get_blockers_info()))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct BlockParams {
+    reason: String,
+    /// Exchange is blocked until manual unblock if duration isn't specified
+    duration_secs: Option<u64>,
+}
+
+// Pause trading on exchange account without engine stopping
+#[post("/blockers/{exchange_account_id}/block")]
+pub(super) async fn block_exchange(
+    path: web::Path<ExchangeAccountId>,
+    params: web::Json<BlockParams>,
+    engine_context: web::Data<Arc<EngineContext>>,
+) -> Result<HttpResponse, Error> {
+    let exchange_account_id = path.into_inner();
+    let _ = get_exchange(&engine_context.exchanges, &exchange_account_id)?;
+
+    let block_type = match params.duration_secs {
+        None => BlockType::Manual,
+        Some(duration_secs) => BlockType::Timed(Duration::from_secs(duration_secs)),
+    };
+
+    info!(
+        "Blocking {} by reason {} with {:?} via control panel",
+        exchange_account_id, params.reason, block_type
+    );
+    engine_context.exchange_blocker.block(
+        &exchange_account_id,
+        BlockReason::custom(&params.reason),
+        block_type,
+    );
+
+    Ok(HttpResponse::Ok().body(format!(
+        "{} was blocked by reason {}",
+        exchange_account_id, params.reason
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct UnblockParams {
+    reason: String,
+}
+
+// Any blocker can be removed this way, including ones created by engine itself
+#[post("/blockers/{exchange_account_id}/unblock")]
+pub(super) async fn unblock_exchange(
+    path: web::Path<ExchangeAccountId>,
+    params: web::Json<UnblockParams>,
+    engine_context: web::Data<Arc<EngineContext>>,
+) -> Result<HttpResponse, Error> {
+    let exchange_account_id = path.into_inner();
+    let _ = get_exchange(&engine_context.exchanges, &exchange_account_id)?;
+
+    // Reason is searched among active blockers, so unknown reasons don't get interned
+    let reason = engine_context
+        .exchange_blocker
+        .find_reason(&exchange_account_id, &params.reason)
+        .ok_or_else(|| {
+            error::ErrorNotFound(format!(
+                "{} isn't blocked by reason {}",
+                exchange_account_id, params.reason
+            ))
+        })?;
+
+    info!(
+        "Unblocking {} by reason {} via control panel",
+        exchange_account_id, params.reason
+    );
+    engine_context
+        .exchange_blocker
+        .unblock(&exchange_account_id, reason);
+
+    Ok(HttpResponse::Ok().body(format!(
+        "Unblock of {} by reason {} was requested",
+        exchange_account_id, params.reason
+    )))
+}
+
+// Websocket with JSON events, e.g. `ws://127.0.0.1:8080/events?event_types=order,fill`
+#[get("/events")]
+pub(super) async fn events(
+    req: HttpRequest,
+    payload: web::Payload,
+    filter: web::Query<FeedFilter>,
+    event_feed: web::Data<Arc<EventFeed>>,
+) -> Result<HttpResponse, Error> {
+    let session = EventFeedSession::new(event_feed.subscribe(), filter.into_inner())
+        .map_err(|err| error::ErrorBadRequest(err.to_string()))?;
+
+    ws::start(session, &req, payload)
+}
+
+// Metrics in Prometheus text exposition format
+#[get("/metrics")]
+pub(super) async fn metrics(
+    engine_context: web::Data<Arc<EngineContext>>,
+    statistics: web::Data<Arc<StatisticService>>,
+    events_channel_metrics: web::Data<Arc<EventsChannelMetrics>>,
+) -> impl Responder {
+    let body = render_metrics(&engine_context, &statistics, &events_channel_metrics);
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use rust_decimal_macros::dec;
+    use tokio::sync::{broadcast, oneshot};
+
+    use crate::core::exchanges::block_reasons;
+    use crate::core::exchanges::events::{ExchangeEvent, ExchangeEvents};
+    use crate::core::exchanges::general::currency_pair_metadata::{
+        CurrencyPairMetadata, Precision,
+    };
+    use crate::core::exchanges::general::test_helper::{
+        create_test_fill, get_test_exchange_with_id, set_test_symbol,
+    };
+    use crate::core::orders::event::{OrderEvent, OrderEventType};
+    use crate::core::orders::pool::OrderRef;
+    use crate::core::settings::{
+        CoreSettings, ExchangeSettings, RiskManagerSettings, SelfTradePrevention,
+    };
+
+    struct TestContext {
+        engine_context: Arc<EngineContext>,
+        exchange: Arc<Exchange>,
+        _events_receiver: broadcast::Receiver<ExchangeEvent>,
+    }
+
+    fn create_context(
+        self_trade_prevention: SelfTradePrevention,
+        risk_manager: RiskManagerSettings,
+    ) -> TestContext {
+        let (exchange, events_receiver) =
+            get_test_exchange_with_id("Binance0".parse().expect("in test"), false);
+        set_test_symbol(
+            &exchange,
+            CurrencyPairMetadata::new(
+                true,
+                false,
+                "phb".into(),
+                "phb".into(),
+                "btc".into(),
+                "btc".into(),
+                None,
+                None,
+                "phb".into(),
+                Some(dec!(0.1)),
+                None,
+                None,
+                None,
+                Precision::ByTick { tick: dec!(0.1) },
+                Precision::ByTick { tick: dec!(0.1) },
+            ),
+        );
+
+        let exchange_account_id = exchange.exchange_account_id.clone();
+        let settings = CoreSettings {
+            self_trade_prevention,
+            exchanges: vec![ExchangeSettings::new_short(
+                exchange_account_id.clone(),
+                "test_api_key".into(),
+                "test_secret_key".into(),
+                false,
+            )],
+            risk_manager,
+            ..Default::default()
+        };
+        let exchanges: DashMap<_, _> = [(exchange_account_id, exchange.clone())]
+            .iter()
+            .cloned()
+            .collect();
+        let (events_sender, _) = broadcast::channel(10);
+        let (finish_graceful_shutdown_sender, _) = oneshot::channel();
+        let engine_context = EngineContext::new(
+            settings,
+            exchanges.clone(),
+            ExchangeEvents::new(events_sender),
+            finish_graceful_shutdown_sender,
+            exchange.timeout_manager.clone(),
+            ApplicationManager::new(CancellationToken::default()),
+            PositionService::new(exchanges),
+        );
+
+        TestContext {
+            engine_context,
+            exchange,
+            _events_receiver: events_receiver,
+        }
+    }
+
+    fn add_resting_order(exchange: &Exchange, side: OrderSide, price: Price) -> OrderRef {
+        add_order(
+            exchange,
+            CurrencyPair::from_codes("phb".into(), "btc".into()),
+            side,
+            price,
+        )
+    }
+
+    fn add_order(
+        exchange: &Exchange,
+        currency_pair: CurrencyPair,
+        side: OrderSide,
+        price: Price,
+    ) -> OrderRef {
+        let header = OrderHeader::new(
+            ClientOrderId::unique_id(),
+            Utc::now(),
+            exchange.exchange_account_id.clone(),
+            currency_pair,
+            OrderType::Limit,
+            side,
+            dec!(1),
+            OrderExecutionType::None,
+            None,
+            None,
+            "test".to_owned(),
+        );
+        let order = exchange.orders.add_simple_initial(header, Some(price));
+        order.fn_mut(|order| order.set_status(OrderStatus::Created, Utc::now()));
+
+        order
+    }
+
+    async fn post_order(
+        context: &TestContext,
+        side: OrderSide,
+        price: Price,
+    ) -> (StatusCode, String) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(context.engine_context.clone()))
+                .service(create_order),
+        )
+        .await;
+        let request = test::TestRequest::post()
+            .uri("/orders")
+            .set_json(&json!({
+                "exchange_account_id": context.exchange.exchange_account_id,
+                "currency_pair": "phb/btc",
+                "side": side,
+                "order_type": OrderType::Limit,
+                "price": price,
+                "amount": dec!(1),
+            }))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+        let status = response.status();
+        let body = test::read_body(response).await;
+
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn get_orders_by_query(context: &TestContext, query: &str) -> Vec<serde_json::Value> {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(context.engine_context.exchanges.clone()))
+                .service(get_orders),
+        )
+        .await;
+        let request = test::TestRequest::get()
+            .uri(&format!("/orders?{}", query))
+            .to_request();
+
+        test::read_response_json(&app, request).await
+    }
+
+    #[actix_rt::test]
+    async fn filter_orders_by_currency_pair_in_any_case() {
+        let context = create_context(SelfTradePrevention::RejectNew, Default::default());
+        let phb_order = add_resting_order(&context.exchange, OrderSide::Buy, dec!(10));
+        let _ = add_order(
+            &context.exchange,
+            CurrencyPair::from_codes("eth".into(), "btc".into()),
+            OrderSide::Buy,
+            dec!(10),
+        );
+
+        for currency_pair in ["phb/btc", "PHB/BTC", "Phb/Btc"].iter() {
+            let orders =
+                get_orders_by_query(&context, &format!("currency_pair={}", currency_pair)).await;
+
+            assert_eq!(orders.len(), 1, "{}", currency_pair);
+            assert_eq!(
+                orders[0]["header"]["client_order_id"],
+                json!(phb_order.client_order_id())
+            );
+        }
+        assert_eq!(get_orders_by_query(&context, "").await.len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn filter_orders_by_status_and_limit() {
+        let context = create_context(SelfTradePrevention::RejectNew, Default::default());
+        let canceled_order = add_resting_order(&context.exchange, OrderSide::Buy, dec!(9));
+        canceled_order.fn_mut(|order| order.set_status(OrderStatus::Canceled, Utc::now()));
+        for price in [dec!(10), dec!(11)].iter() {
+            let _ = add_resting_order(&context.exchange, OrderSide::Buy, *price);
+        }
+
+        let canceled_orders = get_orders_by_query(&context, "status=Canceled").await;
+        assert_eq!(canceled_orders.len(), 1);
+        assert_eq!(
+            canceled_orders[0]["header"]["client_order_id"],
+            json!(canceled_order.client_order_id())
+        );
+
+        let created_orders = get_orders_by_query(&context, "status=Created&limit=1").await;
+        assert_eq!(created_orders.len(), 1);
+        assert_eq!(
+            created_orders[0]["props"]["status"],
+            json!(OrderStatus::Created)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn reject_order_while_exchange_is_blocked() {
+        let context = create_context(SelfTradePrevention::RejectNew, Default::default());
+        context.engine_context.exchange_blocker.block(
+            &context.exchange.exchange_account_id,
+            block_reasons::EXCHANGE_UNAVAILABLE,
+            BlockType::Manual,
+        );
+
+        let (status, body) = post_order(&context, OrderSide::Buy, dec!(10)).await;
+
+        assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+        assert!(context.exchange.orders.cache_by_client_id.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn reject_self_trade_without_cancelling_resting_orders() {
+        let context = create_context(SelfTradePrevention::CancelResting, Default::default());
+        let resting_sell = add_resting_order(&context.exchange, OrderSide::Sell, dec!(10));
+
+        let (status, body) = post_order(&context, OrderSide::Buy, dec!(10.5)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("would cross own orders"), "{}", body);
+        assert_eq!(context.exchange.orders.cache_by_client_id.len(), 1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(
+            !resting_sell.fn_ref(|order| order.internal_props.is_canceling_from_wait_cancel_order)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn reject_risk_violation_without_blocking_exchange() {
+        let risk_manager = RiskManagerSettings {
+            max_position: Some(dec!(0.5)),
+            ..Default::default()
+        };
+        let context = create_context(SelfTradePrevention::RejectNew, risk_manager);
+
+        let (status, body) = post_order(&context, OrderSide::Buy, dec!(10)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("rejected by risk limit"), "{}", body);
+        assert!(context.exchange.orders.cache_by_client_id.is_empty());
+        assert!(!context
+            .engine_context
+            .exchange_blocker
+            .is_blocked(&context.exchange.exchange_account_id));
+    }
+
+    #[actix_rt::test]
+    async fn stats_contain_positions() {
+        let (exchange, _events_receiver) =
+            get_test_exchange_with_id("Binance0".parse().expect("in test"), false);
+        let position_service = PositionService::new(DashMap::new());
+        let (events_sender, events_receiver) = broadcast::channel(10);
+        position_service.clone().start(events_receiver);
+
+        let order = add_resting_order(&exchange, OrderSide::Sell, dec!(10));
+        order.fn_mut(|order| order.add_fill(create_test_fill(dec!(10), dec!(1))));
+        let _ = events_sender
+            .send(ExchangeEvent::OrderEvent(OrderEvent::new(
+                order.clone(),
+                OrderEventType::OrderFilled {
+                    cloned_order: Arc::new(order.deep_clone()),
+                },
+            )))
+            .expect("in test");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(StatisticService::new()))
+                .app_data(web::Data::new(position_service))
+                .service(stats),
+        )
+        .await;
+        let request = test::TestRequest::get().uri("/stats").to_request();
+        let statistic: serde_json::Value = test::read_response_json(&app, request).await;
+
+        let position = &statistic["positions"]["Binance0|phb/btc"];
+        assert_eq!(position["amount"], json!(dec!(-1)));
+        assert_eq!(position["average_entry_price"], json!(dec!(10)));
+    }
+}
@@ -0,0 +1,583 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web_actors::ws;
+use anyhow::{bail, Result};
+use chrono::Utc;
+use futures::stream::BoxStream;
+use futures::{stream, FutureExt, Stream, StreamExt};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::core::exchanges::common::{CurrencyPair, ExchangeAccountId};
+use crate::core::exchanges::events::ExchangeEvent;
+use crate::core::exchanges::exchange_blocker::{ExchangeBlocker, ExchangeBlockerEvent};
+use crate::core::infrastructure::spawn_future;
+use crate::core::order_book::local_snapshot_service::LocalSnapshotsService;
+use crate::core::orders::event::{OrderEvent, OrderEventType};
+use crate::core::orders::order::OrderSnapshot;
+
+// Subscriber that is behind by more events skips them and receives `lagged` message
+const FEED_CHANNEL_CAPACITY: usize = 10_000;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// Time without pongs from subscriber after which it's disconnected
+const HEARTBEAT_FAIL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedEventType {
+    Order,
+    Fill,
+    Balance,
+    OrderBookTop,
+    Blocker,
+}
+
+impl FromStr for FeedEventType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Ok(match value {
+            "order" => FeedEventType::Order,
+            "fill" => FeedEventType::Fill,
+            "balance" => FeedEventType::Balance,
+            "order_book_top" => FeedEventType::OrderBookTop,
+            "blocker" => FeedEventType::Blocker,
+            _ => bail!("Unknown event type {}", value),
+        })
+    }
+}
+
+/// JSON projection of engine event. It is serialized once for all subscribers
+#[derive(Debug)]
+pub struct FeedEvent {
+    event_type: FeedEventType,
+    exchange_account_id: ExchangeAccountId,
+    /// None for events related to whole exchange account like balances or blockers
+    currency_pair: Option<CurrencyPair>,
+    json: String,
+}
+
+impl FeedEvent {
+    fn new(
+        event_type: FeedEventType,
+        exchange_account_id: ExchangeAccountId,
+        currency_pair: Option<CurrencyPair>,
+        data: Value,
+    ) -> Self {
+        let json = json!({
+            "type": event_type,
+            "time": Utc::now(),
+            "exchange_account_id": exchange_account_id,
+            "currency_pair": currency_pair,
+            "data": data,
+        })
+        .to_string();
+
+        FeedEvent {
+            event_type,
+            exchange_account_id,
+            currency_pair,
+            json,
+        }
+    }
+}
+
+/// Filter is specified by websocket url query, e.g. `/events?event_types=order,fill&currency_pair=eos/btc`
+#[derive(Debug, Default, Deserialize)]
+pub struct FeedFilter {
+    /// Comma separated event types. All types are streamed if it isn't specified
+    event_types: Option<String>,
+    exchange_account_id: Option<ExchangeAccountId>,
+    currency_pair: Option<CurrencyPair>,
+}
+
+struct ParsedFeedFilter {
+    event_types: Option<HashSet<FeedEventType>>,
+    exchange_account_id: Option<ExchangeAccountId>,
+    currency_pair: Option<CurrencyPair>,
+}
+
+impl FeedFilter {
+    fn parse(self) -> Result<ParsedFeedFilter> {
+        let event_types = match self.event_types {
+            None => None,
+            Some(event_types) => Some(
+                event_types
+                    .split(',')
+                    .map(|x| x.trim().parse())
+                    .collect::<Result<_>>()?,
+            ),
+        };
+
+        Ok(ParsedFeedFilter {
+            event_types,
+            exchange_account_id: self.exchange_account_id,
+            currency_pair: self.currency_pair,
+        })
+    }
+}
+
+impl ParsedFeedFilter {
+    fn is_matched(&self, event: &FeedEvent) -> bool {
+        if let Some(event_types) = &self.event_types {
+            if !event_types.contains(&event.event_type) {
+                return false;
+            }
+        }
+
+        if let Some(exchange_account_id) = &self.exchange_account_id {
+            if exchange_account_id != &event.exchange_account_id {
+                return false;
+            }
+        }
+
+        // Events without currency pair are related to all pairs of exchange account
+        match (&self.currency_pair, &event.currency_pair) {
+            (Some(currency_pair), Some(event_currency_pair)) => {
+                currency_pair == event_currency_pair
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Projects `ExchangeEvent`s and blocker events to JSON for control panel subscribers
+pub struct EventFeed {
+    feed_sender: broadcast::Sender<Arc<FeedEvent>>,
+}
+
+impl EventFeed {
+    pub fn new() -> Arc<Self> {
+        let (feed_sender, _) = broadcast::channel(FEED_CHANNEL_CAPACITY);
+        Arc::new(EventFeed { feed_sender })
+    }
+
+    pub fn start(
+        self: Arc<Self>,
+        events_receiver: broadcast::Receiver<ExchangeEvent>,
+        exchange_blocker: &ExchangeBlocker,
+    ) {
+        let feed_sender = self.feed_sender.clone();
+        exchange_blocker.register_handler(Box::new(move |event, _| {
+            if feed_sender.receiver_count() > 0 {
+                let _ = feed_sender.send(Arc::new(project_blocker_event(&event)));
+            }
+
+            async {}.boxed()
+        }));
+
+        let action = self.handle_events(events_receiver);
+        spawn_future("Start event feed", false, action.boxed());
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<FeedEvent>> {
+        self.feed_sender.subscribe()
+    }
+
+    async fn handle_events(
+        self: Arc<Self>,
+        mut events_receiver: broadcast::Receiver<ExchangeEvent>,
+    ) -> anyhow::Result<()> {
+        // Own snapshots are needed to get order book top at the moment of event
+        let mut local_snapshots_service = LocalSnapshotsService::default();
+
+        loop {
+            let event = match events_receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped_count)) => {
+                    warn!("EventFeed skipped {} events", skipped_count);
+                    continue;
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            };
+
+            let feed_events = match event {
+                ExchangeEvent::OrderBookEvent(order_book_event) => {
+                    let trade_place_account = local_snapshots_service.update(order_book_event);
+                    match trade_place_account {
+                        None => continue,
+                        Some(trade_place_account) => {
+                            let snapshot = local_snapshots_service
+                                .get_snapshot(trade_place_account.trade_place())
+                                .expect("snapshot should exists because we just added one");
+                            let price_level =
+                                |(price, amount)| json!({ "price": price, "amount": amount });
+                            let data = json!({
+                                "bid": snapshot.get_top_bid().map(price_level),
+                                "ask": snapshot.get_top_ask().map(price_level),
+                            });
+
+                            vec![FeedEvent::new(
+                                FeedEventType::OrderBookTop,
+                                trade_place_account.exchange_account_id,
+                                Some(trade_place_account.currency_pair),
+                                data,
+                            )]
+                        }
+                    }
+                }
+                _ if self.feed_sender.receiver_count() == 0 => continue,
+                ExchangeEvent::OrderEvent(order_event) => project_order_event(&order_event),
+                ExchangeEvent::BalanceUpdate(balance_update) => {
+                    let balances = balance_update
+                        .balances_and_positions
+                        .balances
+                        .iter()
+                        .map(|x| json!({ "currency_code": x.currency_code, "balance": x.balance }))
+                        .collect::<Vec<_>>();
+
+                    vec![FeedEvent::new(
+                        FeedEventType::Balance,
+                        balance_update.exchange_account_id,
+                        None,
+                        json!({ "balances": balances }),
+                    )]
+                }
+                ExchangeEvent::LiquidationPrice(_) | ExchangeEvent::Trades(_) => continue,
+            };
+
+            for feed_event in feed_events {
+                // Error means there are no subscribers now
+                let _ = self.feed_sender.send(Arc::new(feed_event));
+            }
+        }
+    }
+}
+
+fn project_order_event(order_event: &OrderEvent) -> Vec<FeedEvent> {
+    let (event_name, order): (_, Arc<OrderSnapshot>) = match &order_event.event_type {
+        OrderEventType::CreateOrderSucceeded => (
+            "CreateOrderSucceeded",
+            Arc::new(order_event.order.deep_clone()),
+        ),
+        OrderEventType::CreateOrderFailed => (
+            "CreateOrderFailed",
+            Arc::new(order_event.order.deep_clone()),
+        ),
+        OrderEventType::OrderFilled { cloned_order } => ("OrderFilled", cloned_order.clone()),
+        OrderEventType::OrderCompleted { cloned_order } => ("OrderCompleted", cloned_order.clone()),
+        OrderEventType::CancelOrderSucceeded => (
+            "CancelOrderSucceeded",
+            Arc::new(order_event.order.deep_clone()),
+        ),
+        OrderEventType::CancelOrderFailed => (
+            "CancelOrderFailed",
+            Arc::new(order_event.order.deep_clone()),
+        ),
+    };
+
+    let exchange_account_id = order.header.exchange_account_id.clone();
+    let currency_pair = order.header.currency_pair.clone();
+
+    let mut feed_events = Vec::new();
+    if let OrderEventType::OrderFilled { .. } = order_event.event_type {
+        if let Some(fill) = order.fills.fills.last() {
+            feed_events.push(FeedEvent::new(
+                FeedEventType::Fill,
+                exchange_account_id.clone(),
+                Some(currency_pair.clone()),
+                json!({
+                    "client_order_id": order.header.client_order_id,
+                    "side": order.header.side,
+                    "fill": fill,
+                }),
+            ));
+        }
+    }
+
+    feed_events.push(FeedEvent::new(
+        FeedEventType::Order,
+        exchange_account_id,
+        Some(currency_pair),
+        json!({ "event": event_name, "order": order }),
+    ));
+
+    feed_events
+}
+
+fn project_blocker_event(event: &ExchangeBlockerEvent) -> FeedEvent {
+    FeedEvent::new(
+        FeedEventType::Blocker,
+        event.exchange_account_id.clone(),
+        None,
+        json!({
+            "reason": event.reason.to_string(),
+            "moment": format!("{:?}", event.moment),
+        }),
+    )
+}
+
+/// Text of message for subscriber
+struct FeedMessage(String);
+
+/// Stream of messages for subscriber with events matched by filter. If subscriber is too slow,
+/// it receives `lagged` message with count of skipped events instead of them.
+fn feed_messages(
+    feed_receiver: broadcast::Receiver<Arc<FeedEvent>>,
+    filter: ParsedFeedFilter,
+) -> impl Stream<Item = FeedMessage> {
+    let filter = Arc::new(filter);
+    stream::unfold(feed_receiver, move |mut feed_receiver| {
+        let filter = filter.clone();
+        async move {
+            loop {
+                let text = match feed_receiver.recv().await {
+                    Ok(event) if filter.is_matched(&event) => event.json.clone(),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped_count)) => {
+                        json!({ "type": "lagged", "skipped_events": skipped_count }).to_string()
+                    }
+                    Err(RecvError::Closed) => return None,
+                };
+
+                return Some((FeedMessage(text), feed_receiver));
+            }
+        }
+    })
+}
+
+/// Websocket connection of event feed subscriber. Feed events are forwarded to subscriber,
+/// pings are answered and subscriber which doesn't answer our pings is disconnected
+pub(super) struct EventFeedSession {
+    feed_messages: Option<BoxStream<'static, FeedMessage>>,
+    last_heartbeat_time: Instant,
+}
+
+impl EventFeedSession {
+    pub(super) fn new(
+        feed_receiver: broadcast::Receiver<Arc<FeedEvent>>,
+        filter: FeedFilter,
+    ) -> Result<Self> {
+        let filter = filter.parse()?;
+
+        Ok(EventFeedSession {
+            feed_messages: Some(feed_messages(feed_receiver, filter).boxed()),
+            last_heartbeat_time: Instant::now(),
+        })
+    }
+
+    fn heartbeat(&self, ctx: &mut <Self as Actor>::Context) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |session, ctx| {
+            if session.last_heartbeat_time.elapsed() > HEARTBEAT_FAIL_TIMEOUT {
+                info!("Event feed subscriber doesn't answer pings, disconnecting");
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"");
+        });
+    }
+}
+
+impl Actor for EventFeedSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(feed_messages) = self.feed_messages.take() {
+            let _ = ctx.add_stream(feed_messages);
+        }
+        self.heartbeat(ctx);
+    }
+}
+
+impl StreamHandler<FeedMessage> for EventFeedSession {
+    fn handle(&mut self, message: FeedMessage, ctx: &mut Self::Context) {
+        ctx.text(message.0);
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        // Event feed is stopped with trading engine
+        ctx.close(Some(ws::CloseCode::Away.into()));
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for EventFeedSession {
+    fn handle(&mut self, message: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match message {
+            Ok(ws::Message::Ping(message)) => {
+                self.last_heartbeat_time = Instant::now();
+                ctx.pong(&message);
+            }
+            Ok(ws::Message::Pong(_)) => self.last_heartbeat_time = Instant::now(),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            // Filter is specified by url, so messages from subscriber are ignored
+            Ok(_) => {}
+            Err(error) => {
+                warn!("Event feed subscriber websocket error: {}", error);
+                ctx.stop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_codec::{Decoder, Encoder};
+    use actix_web::error::PayloadError;
+    use awc::ws::{Codec, Frame};
+    use bytes::{Bytes, BytesMut};
+    use futures::channel::mpsc;
+
+    fn exchange_account_id() -> ExchangeAccountId {
+        ExchangeAccountId::new("Binance".into(), 0)
+    }
+
+    fn currency_pair() -> CurrencyPair {
+        CurrencyPair::from_codes("eos".into(), "btc".into())
+    }
+
+    fn filter(event_types: Option<&str>, currency_pair: Option<CurrencyPair>) -> ParsedFeedFilter {
+        FeedFilter {
+            event_types: event_types.map(|x| x.to_owned()),
+            exchange_account_id: Some(exchange_account_id()),
+            currency_pair,
+        }
+        .parse()
+        .expect("in test")
+    }
+
+    #[test]
+    fn filter_by_event_type() {
+        let filter = filter(Some("order, fill"), None);
+
+        let fill = FeedEvent::new(
+            FeedEventType::Fill,
+            exchange_account_id(),
+            Some(currency_pair()),
+            Value::Null,
+        );
+        let order_book_top = FeedEvent::new(
+            FeedEventType::OrderBookTop,
+            exchange_account_id(),
+            Some(currency_pair()),
+            Value::Null,
+        );
+
+        assert!(filter.is_matched(&fill));
+        assert!(!filter.is_matched(&order_book_top));
+    }
+
+    #[test]
+    fn filter_by_trade_place_account() {
+        let filter = filter(None, Some(currency_pair()));
+
+        let other_pair_order = FeedEvent::new(
+            FeedEventType::Order,
+            exchange_account_id(),
+            Some(CurrencyPair::from_codes("eth".into(), "btc".into())),
+            Value::Null,
+        );
+        let other_exchange_order = FeedEvent::new(
+            FeedEventType::Order,
+            ExchangeAccountId::new("Binance".into(), 1),
+            Some(currency_pair()),
+            Value::Null,
+        );
+        let balance = FeedEvent::new(
+            FeedEventType::Balance,
+            exchange_account_id(),
+            None,
+            Value::Null,
+        );
+
+        assert!(!filter.is_matched(&other_pair_order));
+        assert!(!filter.is_matched(&other_exchange_order));
+        assert!(
+            filter.is_matched(&balance),
+            "event without currency pair is related to all pairs"
+        );
+    }
+
+    #[test]
+    fn unknown_event_type() {
+        let result = FeedFilter {
+            event_types: Some("order,trade".to_owned()),
+            ..Default::default()
+        }
+        .parse();
+
+        assert!(result.is_err());
+    }
+
+    async fn next_frame(
+        server_frames: &mut (impl Stream<Item = Result<Bytes, actix_web::Error>> + Unpin),
+        buffer: &mut BytesMut,
+    ) -> Option<Frame> {
+        let mut codec = Codec::new().client_mode();
+        loop {
+            if let Some(frame) = codec.decode(buffer).expect("in test") {
+                return Some(frame);
+            }
+
+            let bytes = server_frames.next().await?.expect("in test");
+            buffer.extend_from_slice(&bytes);
+        }
+    }
+
+    fn client_frame(message: ws::Message) -> Result<Bytes, PayloadError> {
+        let mut buffer = BytesMut::new();
+        Codec::new()
+            .client_mode()
+            .encode(message, &mut buffer)
+            .expect("in test");
+
+        Ok(buffer.freeze())
+    }
+
+    #[actix_rt::test]
+    async fn session_forwards_matched_events_and_answers_ping() {
+        let event_feed = EventFeed::new();
+        let session = EventFeedSession::new(
+            event_feed.subscribe(),
+            FeedFilter {
+                event_types: Some("blocker".to_owned()),
+                ..Default::default()
+            },
+        )
+        .expect("in test");
+        let (client_sender, client_frames) = mpsc::unbounded();
+        let mut server_frames = ws::WebsocketContext::create(session, client_frames);
+        let mut buffer = BytesMut::new();
+
+        client_sender
+            .unbounded_send(client_frame(ws::Message::Ping("test".into())))
+            .expect("in test");
+        let frame = next_frame(&mut server_frames, &mut buffer).await;
+        assert_eq!(frame, Some(Frame::Pong("test".into())));
+
+        for event_type in &[FeedEventType::Order, FeedEventType::Blocker] {
+            let _ = event_feed.feed_sender.send(Arc::new(FeedEvent::new(
+                *event_type,
+                exchange_account_id(),
+                None,
+                Value::Null,
+            )));
+        }
+        match next_frame(&mut server_frames, &mut buffer).await {
+            Some(Frame::Text(text)) => {
+                let text = String::from_utf8_lossy(&text);
+                assert!(text.contains(r#""type":"blocker""#), "{}", text);
+            }
+            frame => panic!("Unexpected frame {:?}", frame),
+        }
+
+        client_sender
+            .unbounded_send(client_frame(ws::Message::Close(None)))
+            .expect("in test");
+        let frame = next_frame(&mut server_frames, &mut buffer).await;
+        assert_eq!(frame, Some(Frame::Close(None)));
+        assert!(next_frame(&mut server_frames, &mut buffer).await.is_none());
+    }
+}
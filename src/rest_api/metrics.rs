@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures::FutureExt;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::core::connectivity::connectivity_manager::WebSocketRole;
+use crate::core::exchanges::events::ExchangeEvent;
+use crate::core::infrastructure::spawn_future;
+use crate::core::lifecycle::trading_engine::EngineContext;
+use crate::core::statistic_service::{
+    LatencyPercentiles, StatisticService, TradePlaceAccountStatistic,
+};
+
+/// Observes delivery of events through engine events channel
+#[derive(Default)]
+pub struct EventsChannelMetrics {
+    received_events_count: AtomicU64,
+    /// Events skipped because receiver fell behind channel capacity
+    lagged_events_count: AtomicU64,
+    /// Time between creation of last order book event and its receiving from channel
+    last_order_book_event_lag_ms: AtomicU64,
+}
+
+impl EventsChannelMetrics {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn start(self: Arc<Self>, events_receiver: broadcast::Receiver<ExchangeEvent>) {
+        let action = self.handle_events(events_receiver);
+        spawn_future("Start events channel metrics", false, action.boxed());
+    }
+
+    async fn handle_events(
+        self: Arc<Self>,
+        mut events_receiver: broadcast::Receiver<ExchangeEvent>,
+    ) -> anyhow::Result<()> {
+        loop {
+            match events_receiver.recv().await {
+                Ok(ExchangeEvent::OrderBookEvent(order_book_event)) => {
+                    let _ = self.received_events_count.fetch_add(1, Ordering::Relaxed);
+                    let lag = Utc::now() - order_book_event.creation_time;
+                    self.last_order_book_event_lag_ms
+                        .store(lag.num_milliseconds().max(0) as u64, Ordering::Relaxed);
+                }
+                Ok(_) => {
+                    let _ = self.received_events_count.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(RecvError::Lagged(skipped_count)) => {
+                    let _ = self
+                        .lagged_events_count
+                        .fetch_add(skipped_count, Ordering::Relaxed);
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+}
+
+type Labels<'a> = &'a [(&'a str, &'a dyn Display)];
+type StatisticCounter = (
+    &'static str,
+    &'static str,
+    fn(&TradePlaceAccountStatistic) -> u64,
+);
+
+/// Writer of Prometheus text exposition format
+#[derive(Default)]
+struct PrometheusWriter {
+    output: String,
+}
+
+impl PrometheusWriter {
+    fn header(&mut self, name: &str, metric_type: &str, help: &str) {
+        let _ = writeln!(self.output, "# HELP {} {}", name, help);
+        let _ = writeln!(self.output, "# TYPE {} {}", name, metric_type);
+    }
+
+    fn sample(&mut self, name: &str, labels: Labels, value: impl Display) {
+        self.output.push_str(name);
+        if !labels.is_empty() {
+            self.output.push('{');
+            for (index, (label, label_value)) in labels.iter().enumerate() {
+                if index > 0 {
+                    self.output.push(',');
+                }
+
+                let label_value = label_value
+                    .to_string()
+                    .replace('\\', r"\\")
+                    .replace('"', r#"\""#)
+                    .replace('\n', r"\n");
+                let _ = write!(self.output, "{}=\"{}\"", label, label_value);
+            }
+            self.output.push('}');
+        }
+
+        let _ = writeln!(self.output, " {}", value);
+    }
+
+    fn summary(&mut self, name: &str, labels: Labels, percentiles: &LatencyPercentiles) {
+        let quantiles = [
+            ("0.5", percentiles.p50_ms),
+            ("0.9", percentiles.p90_ms),
+            ("0.99", percentiles.p99_ms),
+        ];
+        for (quantile, value_ms) in &quantiles {
+            if let Some(value_ms) = value_ms {
+                let mut quantile_labels = labels.to_vec();
+                quantile_labels.push(("quantile", quantile));
+                self.sample(name, &quantile_labels, *value_ms as f64 / 1000.0);
+            }
+        }
+
+        self.sample(
+            &format!("{}_sum", name),
+            labels,
+            percentiles.sum_ms as f64 / 1000.0,
+        );
+        self.sample(&format!("{}_count", name), labels, percentiles.count);
+    }
+}
+
+fn bool_gauge(value: bool) -> u8 {
+    match value {
+        true => 1,
+        false => 0,
+    }
+}
+
+pub(super) fn render_metrics(
+    engine_context: &EngineContext,
+    statistics: &StatisticService,
+    events_channel_metrics: &EventsChannelMetrics,
+) -> String {
+    let mut writer = PrometheusWriter::default();
+
+    let trade_place_statistics = statistics.get_trade_place_statistics();
+    let counters: [StatisticCounter; 4] = [
+        ("mmb_opened_orders_total", "Count of created orders", |x| {
+            x.opened_orders_count
+        }),
+        (
+            "mmb_canceled_orders_total",
+            "Count of canceled orders",
+            |x| x.canceled_orders_count,
+        ),
+        (
+            "mmb_partially_filled_orders",
+            "Count of orders that are filled partially now",
+            |x| x.partially_filled_orders_count,
+        ),
+        (
+            "mmb_fully_filled_orders_total",
+            "Count of completely filled orders",
+            |x| x.fully_filled_orders_count,
+        ),
+    ];
+    for (name, help, get_value) in &counters {
+        let metric_type = match name.ends_with("_total") {
+            true => "counter",
+            false => "gauge",
+        };
+        writer.header(name, metric_type, help);
+        for (trade_place_account, statistic) in &trade_place_statistics {
+            writer.sample(
+                name,
+                &[
+                    (
+                        "exchange_account_id",
+                        &trade_place_account.exchange_account_id,
+                    ),
+                    ("currency_pair", &trade_place_account.currency_pair),
+                ],
+                get_value(statistic),
+            );
+        }
+    }
+
+    writer.header(
+        "mmb_filled_amount_total",
+        "counter",
+        "Filled amount of completely filled orders",
+    );
+    for (trade_place_account, statistic) in &trade_place_statistics {
+        writer.sample(
+            "mmb_filled_amount_total",
+            &[
+                (
+                    "exchange_account_id",
+                    &trade_place_account.exchange_account_id,
+                ),
+                ("currency_pair", &trade_place_account.currency_pair),
+            ],
+            statistic.summary_filled_amount,
+        );
+    }
+
+    writer.header(
+        "mmb_commission_total",
+        "counter",
+        "Commission of completely filled orders",
+    );
+    for (trade_place_account, statistic) in &trade_place_statistics {
+        writer.sample(
+            "mmb_commission_total",
+            &[
+                (
+                    "exchange_account_id",
+                    &trade_place_account.exchange_account_id,
+                ),
+                ("currency_pair", &trade_place_account.currency_pair),
+            ],
+            statistic.summary_commission,
+        );
+    }
+
+    writer.header(
+        "mmb_disposition_executor_skipped_events_total",
+        "counter",
+        "Count of events skipped by disposition executor",
+    );
+    writer.sample(
+        "mmb_disposition_executor_skipped_events_total",
+        &[],
+        statistics.get_skipped_events_amount(),
+    );
+
+    writer.header(
+        "mmb_open_orders",
+        "gauge",
+        "Count of not finished orders in local orders pool",
+    );
+    for exchange in engine_context.exchanges.iter() {
+        let mut open_orders_by_pair = HashMap::new();
+        for order in exchange.orders.not_finished.iter() {
+            *open_orders_by_pair
+                .entry(order.currency_pair())
+                .or_insert(0) += 1;
+        }
+
+        for (currency_pair, open_orders_count) in open_orders_by_pair {
+            writer.sample(
+                "mmb_open_orders",
+                &[
+                    ("exchange_account_id", &exchange.exchange_account_id),
+                    ("currency_pair", &currency_pair),
+                ],
+                open_orders_count,
+            );
+        }
+    }
+
+    writer.header(
+        "mmb_available_requests",
+        "gauge",
+        "Count of requests available in current rate limit period",
+    );
+    for exchange in engine_context.exchanges.iter() {
+        writer.sample(
+            "mmb_available_requests",
+            &[("exchange_account_id", &exchange.exchange_account_id)],
+            engine_context
+                .timeout_manager
+                .get_available_requests_count(&exchange.exchange_account_id),
+        );
+    }
+
+    writer.header(
+        "mmb_exchange_blocked",
+        "gauge",
+        "1 if exchange account is blocked by any reason",
+    );
+    for exchange in engine_context.exchanges.iter() {
+        writer.sample(
+            "mmb_exchange_blocked",
+            &[("exchange_account_id", &exchange.exchange_account_id)],
+            bool_gauge(
+                engine_context
+                    .exchange_blocker
+                    .is_blocked(&exchange.exchange_account_id),
+            ),
+        );
+    }
+
+    writer.header(
+        "mmb_websocket_connected",
+        "gauge",
+        "1 if exchange websocket is connected",
+    );
+    for exchange in engine_context.exchanges.iter() {
+        for (role, role_name) in &[
+            (WebSocketRole::Main, "main"),
+            (WebSocketRole::Secondary, "secondary"),
+        ] {
+            if let Some(is_connected) = exchange.is_websocket_connected(*role) {
+                writer.sample(
+                    "mmb_websocket_connected",
+                    &[
+                        ("exchange_account_id", &exchange.exchange_account_id),
+                        ("role", role_name),
+                    ],
+                    bool_gauge(is_connected),
+                );
+            }
+        }
+    }
+
+    writer.header(
+        "mmb_events_channel_received_total",
+        "counter",
+        "Count of events received from engine events channel",
+    );
+    writer.sample(
+        "mmb_events_channel_received_total",
+        &[],
+        events_channel_metrics
+            .received_events_count
+            .load(Ordering::Relaxed),
+    );
+
+    writer.header(
+        "mmb_events_channel_lagged_total",
+        "counter",
+        "Count of events skipped because receiver fell behind events channel",
+    );
+    writer.sample(
+        "mmb_events_channel_lagged_total",
+        &[],
+        events_channel_metrics
+            .lagged_events_count
+            .load(Ordering::Relaxed),
+    );
+
+    writer.header(
+        "mmb_events_channel_lag_seconds",
+        "gauge",
+        "Delay between creation of last order book event and its receiving from events channel",
+    );
+    writer.sample(
+        "mmb_events_channel_lag_seconds",
+        &[],
+        events_channel_metrics
+            .last_order_book_event_lag_ms
+            .load(Ordering::Relaxed) as f64
+            / 1000.0,
+    );
+
+    writer.header(
+        "mmb_latency_seconds",
+        "summary",
+        "Latency of order requests, REST requests and websocket messages",
+    );
+    for (exchange_account_id, percentiles) in statistics.get_all_latency_percentiles() {
+        let latencies = [
+            ("create_order_ack", &percentiles.create_order_ack),
+            (
+                "create_order_first_fill",
+                &percentiles.create_order_first_fill,
+            ),
+            ("cancel_order_ack", &percentiles.cancel_order_ack),
+            ("websocket_message_lag", &percentiles.websocket_message_lag),
+            ("rest_request", &percentiles.rest_request),
+        ];
+        for (kind, percentiles) in &latencies {
+            writer.summary(
+                "mmb_latency_seconds",
+                &[
+                    ("exchange_account_id", &exchange_account_id),
+                    ("kind", kind),
+                ],
+                percentiles,
+            );
+        }
+    }
+
+    writer.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_with_escaped_labels() {
+        let mut writer = PrometheusWriter::default();
+
+        writer.header("test_metric", "gauge", "Test metric");
+        writer.sample("test_metric", &[("label", &r#"a"b\c"#), ("other", &1)], 2.5);
+        writer.sample("test_metric", &[], 3);
+
+        assert_eq!(
+            writer.output,
+            "# HELP test_metric Test metric\n\
+             # TYPE test_metric gauge\n\
+             test_metric{label=\"a\\\"b\\\\c\",other=\"1\"} 2.5\n\
+             test_metric 3\n"
+        );
+    }
+
+    #[test]
+    fn summary_skips_empty_quantiles() {
+        let mut writer = PrometheusWriter::default();
+        let percentiles = LatencyPercentiles {
+            count: 2,
+            sum_ms: 3500,
+            p50_ms: Some(1500),
+            p90_ms: None,
+            p99_ms: None,
+            max_ms: Some(2000),
+        };
+
+        writer.summary("latency", &[("kind", &"test")], &percentiles);
+
+        assert_eq!(
+            writer.output,
+            "latency{kind=\"test\",quantile=\"0.5\"} 1.5\n\
+             latency_sum{kind=\"test\"} 3.5\n\
+             latency_count{kind=\"test\"} 2\n"
+        );
+    }
+}
@@ -1,2 +1,5 @@
+pub mod auth;
 pub mod control_panel;
 pub mod endpoints;
+pub mod event_feed;
+pub mod metrics;
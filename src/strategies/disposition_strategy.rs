@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
-use anyhow::Result;
-use rust_decimal::Decimal;
+use anyhow::{bail, Context, Result};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal_macros::dec;
+use serde::Deserialize;
 
 use crate::core::disposition_execution::{
     PriceSlot, TradeCycle, TradeDisposition, TradingContext, TradingContextBySide,
@@ -31,18 +33,38 @@ pub trait DispositionStrategy: Send + Sync + 'static {
         target_eai: &ExchangeAccountId,
         cancellation_token: CancellationToken,
     ) -> Result<()>;
+
+    /// Apply new strategy settings (the `strategy` table from config) without engine restart.
+    /// Implementation should validate settings completely and change nothing if it returns error
+    fn update_settings(&mut self, _strategy_settings: &toml::Value) -> Result<()> {
+        bail!("Strategy doesn't support settings update without engine restart")
+    }
+}
+
+/// Part of `strategy` settings table that can be changed without engine restart
+#[derive(Deserialize)]
+struct ExampleStrategyUpdatableSettings {
+    #[serde(default)]
+    spread: Decimal,
 }
 
 pub struct ExampleStrategy {
     target_eai: ExchangeAccountId,
     currency_pair: CurrencyPair,
+    /// Relative distance of order price from top price of order book, e.g. 0.01 is 1%
+    spread: Decimal,
 }
 
 impl ExampleStrategy {
-    pub fn new(target_eai: ExchangeAccountId, currency_pair: CurrencyPair) -> Self {
+    pub fn new(
+        target_eai: ExchangeAccountId,
+        currency_pair: CurrencyPair,
+        spread: Decimal,
+    ) -> Self {
         ExampleStrategy {
             target_eai,
             currency_pair,
+            spread,
         }
     }
 
@@ -58,6 +80,22 @@ impl ExampleStrategy {
         self.trade_place_account().trade_place()
     }
 
+    /// Price is moved away from top price by spread and rounded outward to top price precision
+    fn calc_price(&self, side: OrderSide, top_price: Decimal) -> Decimal {
+        let (price, rounding_strategy) = match side {
+            OrderSide::Buy => (
+                top_price * (dec!(1) - self.spread),
+                RoundingStrategy::ToNegativeInfinity,
+            ),
+            OrderSide::Sell => (
+                top_price * (dec!(1) + self.spread),
+                RoundingStrategy::ToPositiveInfinity,
+            ),
+        };
+
+        price.round_dp_with_strategy(top_price.scale(), rounding_strategy)
+    }
+
     fn calc_trading_context_by_side(
         &mut self,
         side: OrderSide,
@@ -67,7 +105,8 @@ impl ExampleStrategy {
         explanation: Explanation,
     ) -> Option<TradingContextBySide> {
         let snapshot = local_snapshots_service.get_snapshot(self.trade_place())?;
-        let price = snapshot.get_top(side)?.0;
+        let top_price = snapshot.get_top(side)?.0;
+        let price = self.calc_price(side, top_price);
 
         Some(TradingContextBySide {
             max_amount,
@@ -125,4 +164,78 @@ impl DispositionStrategy for ExampleStrategy {
         // TODO save order fill info in Database
         Ok(())
     }
+
+    fn update_settings(&mut self, strategy_settings: &toml::Value) -> Result<()> {
+        let settings: ExampleStrategyUpdatableSettings = strategy_settings
+            .clone()
+            .try_into()
+            .context("Unable to parse ExampleStrategy settings")?;
+
+        if settings.spread < dec!(0) || settings.spread >= dec!(1) {
+            bail!(
+                "ExampleStrategy spread should be in range [0, 1), but got {}",
+                settings.spread
+            )
+        }
+
+        self.spread = settings.spread;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_strategy() -> ExampleStrategy {
+        ExampleStrategy::new(
+            "Binance0".parse().expect("in test"),
+            CurrencyPair::from_codes("eos".into(), "btc".into()),
+            dec!(0),
+        )
+    }
+
+    fn strategy_settings(spread: &str) -> toml::Value {
+        toml::from_str(&format!(
+            "exchange_account_id = \"Binance0\"\nmax_amount = 1\nspread = {}",
+            spread
+        ))
+        .expect("in test")
+    }
+
+    #[test]
+    fn spread_updated() {
+        let mut strategy = create_strategy();
+        assert_eq!(
+            strategy.calc_price(OrderSide::Buy, dec!(0.500)),
+            dec!(0.500)
+        );
+
+        strategy
+            .update_settings(&strategy_settings("\"0.1\""))
+            .expect("in test");
+
+        assert_eq!(strategy.spread, dec!(0.1));
+        assert_eq!(
+            strategy.calc_price(OrderSide::Buy, dec!(0.555)),
+            dec!(0.499)
+        );
+        assert_eq!(
+            strategy.calc_price(OrderSide::Sell, dec!(0.555)),
+            dec!(0.611)
+        );
+    }
+
+    #[test]
+    fn invalid_spread_is_not_applied() {
+        let mut strategy = create_strategy();
+
+        let error = strategy
+            .update_settings(&strategy_settings("1"))
+            .expect_err("in test");
+
+        assert!(error.to_string().contains("spread"), "{}", error);
+        assert_eq!(strategy.spread, dec!(0));
+    }
 }
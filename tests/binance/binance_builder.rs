@@ -74,7 +74,7 @@ impl BinanceBuilder {
             application_manager.clone(),
         ));
 
-        let timeout_manager = get_timeout_manager(&exchange_account_id);
+        let timeout_manager = get_timeout_manager(&settings);
         let exchange = Exchange::new(
             exchange_account_id.clone(),
             binance,
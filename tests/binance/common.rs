@@ -5,11 +5,11 @@ use anyhow::Result;
 use mmb_lib::{
     core::exchanges::common::ExchangeId,
     core::exchanges::{
-        common::ExchangeAccountId,
         timeouts::requests_timeout_manager_factory::RequestsTimeoutManagerFactory,
         timeouts::timeout_manager::TimeoutManager,
     },
     core::lifecycle::launcher::EngineBuildConfig,
+    core::settings::ExchangeSettings,
     hashmap,
 };
 
@@ -51,15 +51,16 @@ macro_rules! get_binance_credentials_or_exit {
     }};
 }
 
-pub(crate) fn get_timeout_manager(exchange_account_id: &ExchangeAccountId) -> Arc<TimeoutManager> {
+pub(crate) fn get_timeout_manager(settings: &ExchangeSettings) -> Arc<TimeoutManager> {
     let engine_build_config = EngineBuildConfig::standard();
     let timeout_arguments = engine_build_config.supported_exchange_clients
         [&ExchangeId::new("Binance".into())]
-        .get_timeout_argments();
-    let request_timeout_manager = RequestsTimeoutManagerFactory::from_requests_per_period(
+        .get_timeout_argments(settings);
+    let exchange_account_id = settings.exchange_account_id.clone();
+    let request_timeout_managers = RequestsTimeoutManagerFactory::from_timeout_arguments(
         timeout_arguments,
         exchange_account_id.clone(),
     );
 
-    TimeoutManager::new(hashmap![exchange_account_id.clone() => request_timeout_manager])
+    TimeoutManager::new(hashmap![exchange_account_id => request_timeout_managers])
 }
@@ -2,6 +2,7 @@
 use futures::FutureExt;
 use hyper::Uri;
 use mmb_lib::core::config::parse_settings;
+use mmb_lib::core::connectivity::network_connector::NetworkConnector;
 use mmb_lib::core::disposition_execution::{PriceSlot, TradingContext};
 use mmb_lib::core::explanation::Explanation;
 use mmb_lib::core::lifecycle::cancellation_token::CancellationToken;
@@ -82,8 +83,8 @@ async fn orders_cancelled() {
     )
     .expect("in test");
     let mut exchange_settings = &mut settings.core.exchanges[0];
-    exchange_settings.api_key = api_key.clone();
-    exchange_settings.secret_key = secret_key;
+    exchange_settings.api_key = api_key.clone().into();
+    exchange_settings.secret_key = secret_key.into();
     let exchange_account_id = exchange_settings.exchange_account_id.clone();
 
     let init_settings = InitSettings::Directly(settings.clone());
@@ -125,14 +126,13 @@ async fn orders_cancelled() {
         .cancel_order_or_fail(&created_order, exchange.clone())
         .await;
 
-    let rest_client = RestClient::new();
+    let rest_client = RestClient::new(NetworkConnector::default()).expect("in test");
     let statistics: Value = serde_json::from_str(
         &rest_client
             .get(
                 "http://127.0.0.1:8080/stats"
                     .parse::<Uri>()
                     .expect("in test"),
-                &api_key,
             )
             .await
             .expect("in test")
@@ -0,0 +1,38 @@
+use mmb_lib::core::exchanges::common::*;
+use mmb_lib::core::exchanges::events::ExchangeEvent;
+use mmb_lib::core::exchanges::kraken::kraken::*;
+use mmb_lib::core::exchanges::traits::{ExchangeClientBuilder, Support};
+use mmb_lib::core::lifecycle::application_manager::ApplicationManager;
+use mmb_lib::core::lifecycle::cancellation_token::CancellationToken;
+use mmb_lib::core::settings::ExchangeSettings;
+
+use awc::http::StatusCode;
+use tokio::sync::broadcast;
+
+pub(crate) const ASSET_PAIRS: &str = include_str!("fixtures/asset_pairs.json");
+
+pub(crate) fn response(content: &str) -> RestRequestOutcome {
+    RestRequestOutcome::new(content.to_owned(), StatusCode::OK)
+}
+
+/// Kraken client with parsed metadata of fixture asset pairs
+pub(crate) fn create_kraken() -> (Kraken, broadcast::Receiver<ExchangeEvent>) {
+    let exchange_account_id: ExchangeAccountId = "Kraken0".parse().expect("in test");
+    let mut settings = ExchangeSettings::new_short(
+        exchange_account_id.clone(),
+        "api_key".to_owned(),
+        "c2VjcmV0X2tleQ==".to_owned(),
+        false,
+    );
+    KrakenBuilder.extend_settings(&mut settings);
+
+    let (tx, rx) = broadcast::channel(10);
+    let application_manager = ApplicationManager::new(CancellationToken::default());
+    let kraken = Kraken::new(exchange_account_id, settings, tx, application_manager);
+
+    let _ = kraken
+        .parse_metadata(&response(ASSET_PAIRS))
+        .expect("in test");
+
+    (kraken, rx)
+}
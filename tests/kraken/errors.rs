@@ -0,0 +1,68 @@
+use mmb_lib::core::exchanges::common::*;
+use mmb_lib::core::exchanges::traits::Support;
+
+use crate::kraken::common::{create_kraken, response};
+
+#[test]
+fn successful_response_is_not_error() {
+    let (kraken, _rx) = create_kraken();
+
+    let result = kraken.is_rest_error_code(&response(include_str!("fixtures/add_order.json")));
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn error_response_is_clarified() {
+    let (kraken, _rx) = create_kraken();
+    let cases = [
+        (
+            r#"{"error":["EOrder:Insufficient funds"]}"#,
+            ExchangeErrorType::InsufficientFunds,
+        ),
+        (
+            r#"{"error":["EOrder:Unknown order"]}"#,
+            ExchangeErrorType::OrderNotFound,
+        ),
+        (
+            r#"{"error":["EOrder:Order minimum not met"]}"#,
+            ExchangeErrorType::InvalidOrder,
+        ),
+        (
+            r#"{"error":["EAPI:Invalid key"]}"#,
+            ExchangeErrorType::Authentication,
+        ),
+        (
+            r#"{"error":["EAPI:Rate limit exceeded"]}"#,
+            ExchangeErrorType::RateLimit,
+        ),
+        (
+            r#"{"error":["EService:Unavailable"]}"#,
+            ExchangeErrorType::ServiceUnavailable,
+        ),
+        (
+            r#"{"error":["EGeneral:Internal error"]}"#,
+            ExchangeErrorType::Unknown,
+        ),
+    ];
+
+    for (content, expected_error_type) in cases.iter() {
+        let mut error = kraken
+            .is_rest_error_code(&response(content))
+            .expect_err("in test");
+        kraken.clarify_error_type(&mut error);
+
+        assert_eq!(error.error_type, *expected_error_type, "{}", content);
+    }
+}
+
+#[test]
+fn unparsable_response_is_parsing_error() {
+    let (kraken, _rx) = create_kraken();
+
+    let error = kraken
+        .is_rest_error_code(&response(r#"{"result":{}}"#))
+        .expect_err("in test");
+
+    assert_eq!(error.error_type, ExchangeErrorType::ParsingError);
+}
@@ -0,0 +1,60 @@
+use mmb_lib::core::exchanges::common::*;
+use mmb_lib::core::exchanges::general::currency_pair_metadata::Precision;
+use mmb_lib::core::exchanges::traits::Support;
+use rust_decimal_macros::dec;
+
+use crate::kraken::common::{create_kraken, response, ASSET_PAIRS};
+
+#[test]
+fn parse_metadata() {
+    let (kraken, _rx) = create_kraken();
+
+    let mut metadata = kraken
+        .parse_metadata(&response(ASSET_PAIRS))
+        .expect("in test");
+    metadata.sort_by_key(|metadata| metadata.base_currency_code.to_string());
+
+    // Dark pool pair has no websocket name, so it is skipped
+    assert_eq!(metadata.len(), 2);
+
+    let eth_xbt = &metadata[0];
+    assert!(!eth_xbt.is_active);
+    assert_eq!(eth_xbt.base_currency_id, "XETH".into());
+    assert_eq!(eth_xbt.quote_currency_code, "xbt".into());
+    assert!(matches!(eth_xbt.price_precision, Precision::ByTick { tick } if tick == dec!(0.00001)));
+
+    let xbt_usd = &metadata[1];
+    assert!(xbt_usd.is_active);
+    assert!(!xbt_usd.is_derivative);
+    assert_eq!(xbt_usd.base_currency_id, "XXBT".into());
+    assert_eq!(xbt_usd.base_currency_code, "xbt".into());
+    assert_eq!(xbt_usd.quote_currency_id, "ZUSD".into());
+    assert_eq!(xbt_usd.quote_currency_code, "usd".into());
+    assert_eq!(xbt_usd.amount_currency_code, "xbt".into());
+    assert_eq!(xbt_usd.min_amount, Some(dec!(0.0001)));
+    assert_eq!(xbt_usd.min_cost, Some(dec!(0.5)));
+    assert!(matches!(xbt_usd.price_precision, Precision::ByTick { tick } if tick == dec!(0.1)));
+    assert!(
+        matches!(xbt_usd.amount_precision, Precision::ByTick { tick } if tick == dec!(0.00000001))
+    );
+}
+
+#[test]
+fn currency_pairs_mapping() {
+    let (kraken, _rx) = create_kraken();
+    let currency_pair = CurrencyPair::from_codes("xbt".into(), "usd".into());
+
+    let specific_currency_pair = kraken.get_specific_currency_pair(&currency_pair);
+
+    assert_eq!(specific_currency_pair, "XBTUSD".into());
+    assert_eq!(
+        kraken
+            .get_unified_currency_pair(&specific_currency_pair)
+            .expect("in test"),
+        currency_pair
+    );
+    assert_eq!(
+        kraken.specific_to_websocket_pair.read()[&specific_currency_pair],
+        "XBT/USD"
+    );
+}
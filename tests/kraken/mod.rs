@@ -0,0 +1,6 @@
+pub mod common;
+pub mod errors;
+pub mod metadata;
+pub mod orders;
+pub mod server_time;
+pub mod websocket;
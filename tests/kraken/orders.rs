@@ -0,0 +1,80 @@
+use mmb_lib::core::exchanges::common::*;
+use mmb_lib::core::exchanges::traits::Support;
+use mmb_lib::core::orders::order::*;
+use rust_decimal_macros::dec;
+
+use crate::kraken::common::{create_kraken, response};
+
+#[test]
+fn get_order_id() {
+    let (kraken, _rx) = create_kraken();
+
+    let exchange_order_id = kraken
+        .get_order_id(&response(include_str!("fixtures/add_order.json")))
+        .expect("in test");
+
+    assert_eq!(exchange_order_id, "OU22CG-KLAF2-FWUDD7".into());
+}
+
+#[test]
+fn parse_open_orders() {
+    let (kraken, _rx) = create_kraken();
+    let client_order_id = ClientOrderId::unique_id();
+    let _ = kraken
+        .client_order_ids
+        .insert("OQCLML-BW3P3-BUCMWZ".into(), client_order_id.clone());
+
+    let mut orders = kraken
+        .parse_open_orders(&response(include_str!("fixtures/open_orders.json")))
+        .expect("in test");
+    orders.sort_by_key(|order| order.exchange_order_id.clone());
+
+    assert_eq!(orders.len(), 2);
+
+    let eth_xbt_order = &orders[0];
+    assert_eq!(
+        eth_xbt_order.exchange_order_id,
+        "OB5VMB-B4U2U-DK2WRW".into()
+    );
+    // Order is created outside of this client, so client order id is unknown
+    assert_eq!(eth_xbt_order.client_order_id.as_str(), "");
+    assert_eq!(
+        eth_xbt_order.currency_pair,
+        CurrencyPair::from_codes("eth".into(), "xbt".into())
+    );
+    assert_eq!(eth_xbt_order.order_side, OrderSide::Sell);
+    assert_eq!(eth_xbt_order.price, dec!(0.065));
+
+    let xbt_usd_order = &orders[1];
+    assert_eq!(
+        xbt_usd_order.exchange_order_id,
+        "OQCLML-BW3P3-BUCMWZ".into()
+    );
+    assert_eq!(xbt_usd_order.client_order_id, client_order_id);
+    assert_eq!(
+        xbt_usd_order.currency_pair,
+        CurrencyPair::from_codes("xbt".into(), "usd".into())
+    );
+    assert_eq!(xbt_usd_order.order_side, OrderSide::Buy);
+    assert_eq!(xbt_usd_order.order_status, OrderStatus::Created);
+    assert_eq!(xbt_usd_order.price, dec!(30010));
+    assert_eq!(xbt_usd_order.amount, dec!(1.25));
+    assert_eq!(xbt_usd_order.filled_amount, dec!(0.375));
+}
+
+#[test]
+fn parse_order_info() {
+    let (kraken, _rx) = create_kraken();
+
+    let order_info = kraken
+        .parse_order_info(&response(include_str!("fixtures/query_orders.json")))
+        .expect("in test");
+
+    assert_eq!(order_info.exchange_order_id, "OBCMZD-JIEE7-77TH3F".into());
+    assert_eq!(order_info.order_side, OrderSide::Sell);
+    assert_eq!(order_info.order_status, OrderStatus::Completed);
+    assert_eq!(order_info.price, dec!(27500));
+    assert_eq!(order_info.average_fill_price, dec!(27554.9));
+    assert_eq!(order_info.filled_amount, dec!(1.25));
+    assert_eq!(order_info.commission_amount, Some(dec!(89.55362)));
+}
@@ -0,0 +1,15 @@
+use chrono::{TimeZone, Utc};
+use mmb_lib::core::exchanges::traits::Support;
+
+use crate::kraken::common::{create_kraken, response};
+
+#[test]
+fn parse_server_time() {
+    let (kraken, _rx) = create_kraken();
+
+    let server_time = kraken
+        .parse_server_time(&response(include_str!("fixtures/server_time.json")))
+        .expect("in test");
+
+    assert_eq!(server_time, Utc.timestamp(1688669448, 0));
+}
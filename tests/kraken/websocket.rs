@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use mmb_lib::core::exchanges::common::*;
+use mmb_lib::core::exchanges::events::ExchangeEvent;
+use mmb_lib::core::exchanges::general::handlers::handle_order_filled::FillEventData;
+use mmb_lib::core::exchanges::traits::Support;
+use mmb_lib::core::order_book::event::EventType;
+use mmb_lib::core::orders::order::*;
+use parking_lot::Mutex;
+use rust_decimal_macros::dec;
+
+use crate::kraken::common::create_kraken;
+
+const SUBSCRIPTION_STATUS: &str = include_str!("fixtures/ws_subscription_status.json");
+
+#[test]
+fn subscription_status_registers_channel() {
+    let (kraken, _rx) = create_kraken();
+
+    kraken
+        .on_websocket_message(SUBSCRIPTION_STATUS)
+        .expect("in test");
+
+    let channel = kraken.subscribed_channels.read()[&336].clone();
+    assert_eq!(channel.name, "book-10");
+    assert_eq!(
+        channel.currency_pair,
+        CurrencyPair::from_codes("xbt".into(), "usd".into())
+    );
+}
+
+#[test]
+fn data_of_unknown_channel_is_error() {
+    let (kraken, _rx) = create_kraken();
+
+    let result = kraken.on_websocket_message(include_str!("fixtures/ws_book_snapshot.json"));
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn order_book_snapshot_and_update() {
+    let (kraken, mut rx) = create_kraken();
+    kraken
+        .on_websocket_message(SUBSCRIPTION_STATUS)
+        .expect("in test");
+
+    kraken
+        .on_websocket_message(include_str!("fixtures/ws_book_snapshot.json"))
+        .expect("in test");
+    kraken
+        .on_websocket_message(include_str!("fixtures/ws_book_update.json"))
+        .expect("in test");
+
+    let mut order_book_events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            ExchangeEvent::OrderBookEvent(order_book_event) => {
+                order_book_events.push(order_book_event.dissolve())
+            }
+            _ => panic!("Unexpected exchange event"),
+        }
+    }
+
+    // Update contains separate asks and bids payloads
+    assert_eq!(order_book_events.len(), 3);
+
+    let (_, _, _, currency_pair, _, event_type, data) = order_book_events.remove(0);
+    assert_eq!(
+        currency_pair,
+        CurrencyPair::from_codes("xbt".into(), "usd".into())
+    );
+    assert!(matches!(event_type, EventType::Snapshot));
+    assert_eq!(data.asks.len(), 2);
+    assert_eq!(data.asks[&dec!(30010.1)], dec!(0.5));
+    assert_eq!(data.bids[&dec!(30009.5)], dec!(0.015));
+
+    let (_, _, _, _, _, event_type, data) = order_book_events.remove(0);
+    assert!(matches!(event_type, EventType::Update));
+    assert_eq!(data.asks[&dec!(30010.1)], dec!(0));
+    assert!(data.bids.is_empty());
+
+    let (_, _, _, _, event_id, event_type, data) = order_book_events.remove(0);
+    assert!(matches!(event_type, EventType::Update));
+    assert_eq!(event_id, "1987456320");
+    assert_eq!(data.bids[&dec!(30010)], dec!(2.5));
+}
+
+#[test]
+fn open_orders_update() {
+    let (kraken, _rx) = create_kraken();
+    let created_client_order_id = ClientOrderId::unique_id();
+    let cancelled_client_order_id = ClientOrderId::unique_id();
+    let _ = kraken.client_order_ids.insert(
+        "OU22CG-KLAF2-FWUDD7".into(),
+        created_client_order_id.clone(),
+    );
+    let _ = kraken.client_order_ids.insert(
+        "OQCLML-BW3P3-BUCMWZ".into(),
+        cancelled_client_order_id.clone(),
+    );
+
+    let created_orders = Arc::new(Mutex::new(Vec::new()));
+    let created_orders_clone = created_orders.clone();
+    kraken.set_order_created_callback(Box::new(move |client_order_id, exchange_order_id, _| {
+        created_orders_clone
+            .lock()
+            .push((client_order_id, exchange_order_id))
+    }));
+    let cancelled_orders = Arc::new(Mutex::new(Vec::new()));
+    let cancelled_orders_clone = cancelled_orders.clone();
+    kraken.set_order_cancelled_callback(Box::new(move |client_order_id, exchange_order_id, _| {
+        cancelled_orders_clone
+            .lock()
+            .push((client_order_id, exchange_order_id))
+    }));
+
+    kraken
+        .on_websocket_message(include_str!("fixtures/ws_open_orders.json"))
+        .expect("in test");
+
+    assert_eq!(
+        *created_orders.lock(),
+        vec![(created_client_order_id, "OU22CG-KLAF2-FWUDD7".into())]
+    );
+    assert_eq!(
+        *cancelled_orders.lock(),
+        vec![(cancelled_client_order_id, "OQCLML-BW3P3-BUCMWZ".into())]
+    );
+    assert!(!kraken
+        .client_order_ids
+        .contains_key(&"OQCLML-BW3P3-BUCMWZ".into()));
+}
+
+#[test]
+fn own_trades() {
+    let (kraken, _rx) = create_kraken();
+    let client_order_id = ClientOrderId::unique_id();
+    let _ = kraken
+        .client_order_ids
+        .insert("OU22CG-KLAF2-FWUDD7".into(), client_order_id.clone());
+
+    let fills: Arc<Mutex<Vec<FillEventData>>> = Arc::new(Mutex::new(Vec::new()));
+    let fills_clone = fills.clone();
+    kraken.set_handle_order_filled_callback(Box::new(move |event_data| {
+        fills_clone.lock().push(event_data)
+    }));
+
+    kraken
+        .on_websocket_message(include_str!("fixtures/ws_own_trades.json"))
+        .expect("in test");
+
+    let fills = fills.lock();
+    assert_eq!(fills.len(), 1);
+    let fill = &fills[0];
+    assert_eq!(fill.trade_id, "TDLH43-DVQXD-2KHVYY");
+    assert_eq!(fill.client_order_id, Some(client_order_id));
+    assert_eq!(fill.exchange_order_id, "OU22CG-KLAF2-FWUDD7".into());
+    assert_eq!(fill.fill_price, dec!(27500));
+    assert_eq!(fill.fill_amount, dec!(0.5));
+    assert_eq!(fill.commission_amount, Some(dec!(35.75)));
+    assert_eq!(fill.commission_currency_code, Some("usd".into()));
+    assert_eq!(fill.order_side, Some(OrderSide::Buy));
+}
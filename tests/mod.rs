@@ -1,4 +1,5 @@
 pub mod binance;
 pub mod control_panel;
 pub mod core;
+pub mod kraken;
 pub mod lifecycle;